@@ -0,0 +1,230 @@
+//! Benchmark of `parse_path` on a large synthetic log.
+//!
+//! The log is generated deterministically, so timings are comparable across commits:
+//!
+//!     cargo bench --bench parse -- [--frames N] [--runs N] [--html]
+//!
+//! By default graphs and code are written as plain text, which keeps the numbers about the
+//! parse loop itself; `--html` includes highlighting.
+
+use md5::{Digest, Md5};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const PREFIX: &str = "V1206 15:18:20.254000 1500233";
+
+struct LogWriter {
+    log: String,
+    envelopes: usize,
+    // Microseconds, advanced by every envelope so chromium events stay ordered
+    ts: u64,
+}
+
+impl LogWriter {
+    fn envelope(&mut self, pathname: &str, metadata: &str, payload: Option<&str>) {
+        self.envelopes += 1;
+        self.ts += 137;
+        match payload {
+            Some(payload) => {
+                // tlparse joins payload lines with \n, so a trailing newline isn't hashed
+                let payload = payload.strip_suffix('\n').unwrap_or(payload);
+                let mut buf = [0u8; 32];
+                let hash = base16ct::lower::encode_str(&Md5::digest(payload), &mut buf).unwrap();
+                writeln!(
+                    self.log,
+                    "{PREFIX} {pathname}] {{{metadata}, \"has_payload\": \"{hash}\"}}"
+                )
+                .unwrap();
+                for line in payload.lines() {
+                    writeln!(self.log, "\t{line}").unwrap();
+                }
+            }
+            None => writeln!(self.log, "{PREFIX} {pathname}] {{{metadata}}}").unwrap(),
+        }
+    }
+
+    fn chromium_event(&mut self, name: &str, ph: &str, compile_id: &str) {
+        let payload = format!(
+            "{{\n\"name\": \"{name}\",\n\"ts\": {},\n\"args\": {{\n\"compile_id\": \"{compile_id}\"\n}},\n\"ph\": \"{ph}\",\n\"cat\": \"dynamo_timed\",\n\"tid\": 0,\n\"pid\": 0\n}}",
+            self.ts
+        );
+        self.envelope(
+            "torch/_dynamo/utils.py:1288",
+            "\"chromium_event\": {}",
+            Some(&payload),
+        );
+    }
+}
+
+fn fx_graph(frame: usize, nodes: usize) -> String {
+    let mut graph = String::from("class GraphModule(torch.nn.Module):\n");
+    writeln!(
+        graph,
+        "    def forward(self, L_x_: \"f32[8, 16][16, 1]cpu\", L_w_: \"f32[16, 16][16, 1]cpu\"):"
+    )
+    .unwrap();
+    let mut prev = "L_x_".to_string();
+    for i in 0..nodes {
+        let op = ["mm", "add", "relu", "mul"][(i + frame) % 4];
+        writeln!(
+            graph,
+            "        # File: /workspace/model.py:{}, code: x = torch.{op}(x, w)",
+            100 + i
+        )
+        .unwrap();
+        writeln!(
+            graph,
+            "        {op}_{i}: \"f32[8, 16][16, 1]cpu\" = torch.{op}({prev}, L_w_);  {prev} = None"
+        )
+        .unwrap();
+        prev = format!("{op}_{i}");
+    }
+    writeln!(graph, "        return ({prev},)").unwrap();
+    graph
+}
+
+fn output_code(frame: usize, kernels: usize) -> String {
+    let mut code = String::from("import torch\nimport triton\nimport triton.language as tl\n\n");
+    for k in 0..kernels {
+        writeln!(
+            code,
+            "@triton.jit\ndef triton_poi_fused_add_{frame}_{k}(in_ptr0, out_ptr0, xnumel, XBLOCK: tl.constexpr):\n    xnumel = 128\n    xoffset = tl.program_id(0) * XBLOCK\n    xindex = xoffset + tl.arange(0, XBLOCK)[:]\n    xmask = xindex < xnumel\n    tmp0 = tl.load(in_ptr0 + (xindex), xmask)\n    tmp1 = tmp0 + tmp0\n    tl.store(out_ptr0 + (xindex), tmp1, xmask)\n"
+        )
+        .unwrap();
+    }
+    code.push_str("def call(args):\n    buf0 = empty_strided_cuda((8, 16), (16, 1), torch.float32)\n    return (buf0, )\n");
+    code
+}
+
+/// A log with `frames` compiled frames, each with the envelopes a typical inductor
+/// compile produces
+fn synthetic_log(frames: usize) -> LogWriter {
+    let mut w = LogWriter {
+        log: String::new(),
+        envelopes: 0,
+        ts: 1733527100000000,
+    };
+    for (i, filename) in ["/workspace/model.py", "/workspace/train.py"]
+        .iter()
+        .enumerate()
+    {
+        w.envelope(
+            "torch/_logging/structured.py:22",
+            &format!("\"str\": [\"{filename}\", {i}]"),
+            None,
+        );
+    }
+    for frame in 0..frames {
+        let cid = format!("\"frame_id\": {frame}, \"frame_compile_id\": 0, \"attempt\": 0");
+        let display_cid = format!("{frame}/0");
+        w.chromium_event("dynamo", "B", &display_cid);
+        w.envelope(
+            "torch/_dynamo/convert_frame.py:961",
+            &format!(
+                "\"dynamo_start\": {{\"stack\": [{{\"line\": 20, \"name\": \"main\", \"filename\": 1}}, {{\"line\": {}, \"name\": \"forward\", \"filename\": 0}}]}}, {cid}",
+                10 + frame % 50
+            ),
+            None,
+        );
+        w.envelope(
+            "torch/_dynamo/guards.py:2338",
+            &format!("\"artifact\": {{\"name\": \"dynamo_graph_break_reason\", \"encoding\": \"string\"}}, {cid}"),
+            Some("Graph break: call_function print in skip_files\n  File \"/workspace/model.py\", line 12"),
+        );
+        w.envelope(
+            "torch/_dynamo/output_graph.py:1347",
+            &format!("\"dynamo_output_graph\": {{\"sizes\": {{\"l_x_\": [8, 16]}}}}, {cid}"),
+            Some(&fx_graph(frame, 40)),
+        );
+        for graph in [
+            "aot_forward_graph",
+            "aot_backward_graph",
+            "inductor_post_grad_graph",
+        ] {
+            w.envelope(
+                "torch/_functorch/_aot_autograd/dispatch_and_compile_graph.py:214",
+                &format!("\"{graph}\": {{}}, {cid}"),
+                Some(&fx_graph(frame, 60)),
+            );
+        }
+        w.envelope(
+            "torch/_inductor/graph.py:1985",
+            &format!("\"inductor_output_code\": {{\"filename\": \"/tmp/torchinductor/c{frame}.py\"}}, {cid}"),
+            Some(&output_code(frame, 8)),
+        );
+        w.envelope(
+            "torch/_inductor/codecache.py:1209",
+            &format!("\"artifact\": {{\"name\": \"fx_graph_cache_miss\", \"encoding\": \"json\"}}, {cid}"),
+            Some(&format!("{{\"key\": \"f{frame:032x}\", \"components\": [\"[abc] gm: GraphModule()\"], \"cache_event_time\": {frame}}}")),
+        );
+        let guards: Vec<String> = (0..20)
+            .map(|g| format!("{{\"code\": \"L['x'].size()[{g}] == 16\", \"stack\": null, \"user_stack\": null}}"))
+            .collect();
+        w.envelope(
+            "torch/_dynamo/guards.py:2338",
+            &format!("\"dynamo_guards\": {{}}, {cid}"),
+            Some(&format!("[{}]", guards.join(", "))),
+        );
+        w.envelope(
+            "torch/_dynamo/utils.py:839",
+            &format!(
+                "\"compilation_metrics\": {{\"co_name\": \"forward\", \"co_filename\": \"/workspace/model.py\", \"co_firstlineno\": 10, \"cache_size\": 0, \"accumulated_cache_size\": 0, \"guard_count\": 20, \"shape_env_guard_count\": 0, \"graph_op_count\": 40, \"graph_node_count\": 42, \"graph_input_count\": 2, \"start_time\": {}, \"entire_frame_compile_time_s\": 1.5, \"backend_compile_time_s\": 1.2, \"inductor_compile_time_s\": 1.0, \"code_gen_time_s\": 0.3, \"fail_type\": null, \"fail_reason\": null, \"fail_user_frame_filename\": null, \"fail_user_frame_lineno\": null, \"non_compliant_ops\": [], \"compliant_custom_ops\": [], \"restart_reasons\": [], \"dynamo_time_before_restart_s\": 0.0, \"has_guarded_code\": true}}, {cid}",
+                frame as f64 * 2.0
+            ),
+            None,
+        );
+        w.chromium_event("dynamo", "E", &display_cid);
+    }
+    w
+}
+
+fn arg_value(args: &[String], name: &str, default: usize) -> usize {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.parse().expect("expected a number"))
+        .unwrap_or(default)
+}
+
+fn main() {
+    // cargo bench passes --bench; anything we don't recognize is ignored
+    let args: Vec<String> = std::env::args().collect();
+    let frames = arg_value(&args, "--frames", 2000);
+    let runs = arg_value(&args, "--runs", 5).max(1);
+    let html = args.iter().any(|a| a == "--html");
+
+    let writer = synthetic_log(frames);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("synthetic.log");
+    std::fs::write(&path, &writer.log).unwrap();
+    let megabytes = writer.log.len() as f64 / 1e6;
+    eprintln!(
+        "synthetic log: {} frames, {} envelopes, {:.1} MB",
+        frames, writer.envelopes, megabytes
+    );
+
+    let mut times: Vec<Duration> = (0..runs).map(|_| time_parse(&path, html)).collect();
+    times.sort();
+    let median = times[times.len() / 2];
+    println!(
+        "parse_path: min {:.3}s, median {:.3}s over {} runs ({:.0} envelopes/s, {:.1} MB/s)",
+        times[0].as_secs_f64(),
+        median.as_secs_f64(),
+        runs,
+        writer.envelopes as f64 / median.as_secs_f64(),
+        megabytes / median.as_secs_f64(),
+    );
+}
+
+fn time_parse(path: &Path, html: bool) -> Duration {
+    let config = tlparse::ParseConfig {
+        plain_text: !html,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let output = tlparse::parse_path(&path.to_path_buf(), &config).unwrap();
+    let elapsed = start.elapsed();
+    assert!(!output.is_empty());
+    elapsed
+}
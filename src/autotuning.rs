@@ -0,0 +1,388 @@
+//! Autotuning report.
+//!
+//! Inductor logs what it knows about autotuning each Triton kernel in `triton_kernel_info`
+//! artifacts, and times benchmarking its configs with `dynamo_timed` chromium events. This
+//! totals both per compile id and kernel, so the kernels that autotuning time goes to stand out.
+
+use serde_json::Value;
+
+use crate::compile_time::{format_ms, timed_spans};
+use crate::types::*;
+
+/// How many kernels the run-wide table of the slowest kernels to autotune lists
+const SLOWEST_KERNELS_LIMIT: usize = 20;
+
+const BENCHMARK_ALL_CONFIGS: &str = "CachingAutotuner.benchmark_all_configs";
+
+#[derive(Default)]
+struct KernelAutotuning {
+    name: String,
+    cache_state: Option<String>,
+    num_configs: Option<u64>,
+    only_config: Option<String>,
+    // The config autotuning settled on, logged on autotune cache hits
+    best_config: Option<String>,
+    compile_time_us: Option<u64>,
+    benchmark_us: f64,
+    num_benchmarks: usize,
+}
+
+impl KernelAutotuning {
+    fn total_us(&self) -> f64 {
+        self.compile_time_us.unwrap_or(0) as f64 + self.benchmark_us
+    }
+}
+
+#[derive(Default)]
+struct CompileAutotuning {
+    compile_id: Option<CompileId>,
+    kernels: Vec<KernelAutotuning>,
+    // Benchmarking that isn't part of autotuning a Triton kernel's configs, e.g. choosing
+    // between mm implementations
+    other_benchmark_us: f64,
+    // Totals reported by the compile's compilation metrics
+    compile_time_autotune_us: Option<u64>,
+    runtime_autotune_us: Option<u64>,
+}
+
+impl CompileAutotuning {
+    // The latest kernel called `name`; forward and backward graphs can reuse kernel names
+    fn kernel(&mut self, name: &str) -> &mut KernelAutotuning {
+        match self.kernels.iter().rposition(|k| k.name == name) {
+            Some(i) => &mut self.kernels[i],
+            None => {
+                self.kernels.push(KernelAutotuning {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.kernels.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AutotuneReport {
+    // Keyed by the bracketed compile id, e.g. "[0/0]"
+    compiles: FxIndexMap<String, CompileAutotuning>,
+}
+
+// Configs are logged as [name, value] pairs, e.g. [["XBLOCK", 128], ["num_warps", 4]]
+fn format_config(config: &Value) -> String {
+    match config {
+        Value::Array(pairs) => pairs
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([Value::String(name), value]) => format!("{}={}", name, value),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+impl AutotuneReport {
+    pub fn is_empty(&self) -> bool {
+        self.compiles.is_empty()
+    }
+
+    fn compile(&mut self, compile_id: &Option<CompileId>) -> &mut CompileAutotuning {
+        let key = compile_id
+            .as_ref()
+            .map_or("(unknown)".to_string(), |c| c.to_string());
+        let compile = self.compiles.entry(key).or_default();
+        compile.compile_id = compile.compile_id.take().or_else(|| compile_id.clone());
+        compile
+    }
+
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str) {
+        if let Some(m) = &e.compilation_metrics {
+            if let Some(us) = m.compile_time_autotune_time_us {
+                self.compile(&e.compile_id).compile_time_autotune_us = Some(us);
+            }
+        }
+        // Runtime metrics aren't parsed into the envelope
+        if let Some(m) = e._other.get("compilation_metrics_runtime") {
+            if let Some(us) = m
+                .get("runtime_triton_autotune_time_us")
+                .and_then(Value::as_u64)
+            {
+                self.compile(&e.compile_id).runtime_autotune_us = Some(us);
+            }
+        }
+        if e.artifact.as_ref().map(|a| a.name.as_str()) != Some("triton_kernel_info") {
+            return;
+        }
+        let Ok(Value::Object(by_kernel)) = serde_json::from_str::<Value>(payload) else {
+            return;
+        };
+        let compile = self.compile(&e.compile_id);
+        for (name, info) in by_kernel {
+            let kernel = match compile
+                .kernels
+                .iter()
+                .rposition(|k| k.name == name && k.cache_state.is_none())
+            {
+                Some(i) => &mut compile.kernels[i],
+                None => {
+                    compile.kernels.push(KernelAutotuning {
+                        name: name.clone(),
+                        ..Default::default()
+                    });
+                    compile.kernels.last_mut().unwrap()
+                }
+            };
+            kernel.cache_state = info
+                .get("autotune_cache_state")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            kernel.num_configs = info.get("num_configs").and_then(Value::as_u64);
+            kernel.only_config = info.get("only_config").map(format_config);
+            kernel.best_config = info.get("best_config").map(format_config);
+            kernel.compile_time_us = info.get("compile_time_us").and_then(Value::as_u64);
+        }
+    }
+
+    /// Total the benchmarking `dynamo_timed` events. Benchmarks run lazily, often outside of
+    /// any compile, in which case they're logged with compile id "None" and attributed to the
+    /// event they ran in, e.g. the first run of the compiled graph.
+    pub fn add_benchmarks(&mut self, chromium_events: &[Value]) {
+        fn compile_id(event: &Value) -> Option<&str> {
+            event
+                .pointer("/args/compile_id")
+                .and_then(Value::as_str)
+                .filter(|c| *c != "None")
+        }
+        for span in timed_spans(chromium_events) {
+            let is_benchmark_all_configs = span.name == BENCHMARK_ALL_CONFIGS;
+            // benchmark_gpu is also what benchmark_all_configs runs for each config
+            let is_other_benchmark = span.name.ends_with(".benchmark_gpu")
+                && !span.parents.iter().any(|parent| {
+                    parent.get("name").and_then(Value::as_str) == Some(BENCHMARK_ALL_CONFIGS)
+                });
+            if !is_benchmark_all_configs && !is_other_benchmark {
+                continue;
+            }
+            let key = compile_id(span.begin)
+                .or_else(|| span.parents.iter().rev().find_map(|e| compile_id(e)))
+                .map_or("(unknown)".to_string(), |c| format!("[{}]", c));
+            let compile = self.compiles.entry(key).or_default();
+            match span
+                .begin
+                .pointer("/args/kernel_name")
+                .and_then(Value::as_str)
+            {
+                Some(kernel_name) if is_benchmark_all_configs => {
+                    let kernel = compile.kernel(kernel_name);
+                    kernel.benchmark_us += span.duration_us;
+                    kernel.num_benchmarks += 1;
+                }
+                _ => compile.other_benchmark_us += span.duration_us,
+            }
+        }
+    }
+
+    /// `kernels_page` links a compile id to its kernel table
+    pub fn finish(
+        &self,
+        kernels_page: impl Fn(&Option<CompileId>) -> Option<String>,
+        css: &'static str,
+        qps: &'static str,
+    ) -> AutotuningContext {
+        let kernel_context = |compile_id: &str, k: &KernelAutotuning| AutotuneKernelContext {
+            compile_id: compile_id.to_string(),
+            name: k.name.clone(),
+            cache_state: k.cache_state.clone().unwrap_or_default(),
+            candidates: k
+                .num_configs
+                .or(k.only_config.as_ref().map(|_| 1))
+                .map_or(String::new(), |n| n.to_string()),
+            chosen_config: k
+                .best_config
+                .as_ref()
+                .or(k.only_config.as_ref())
+                .cloned()
+                .unwrap_or_default(),
+            compile_ms: k
+                .compile_time_us
+                .map_or(String::new(), |us| format_ms(us as f64)),
+            benchmark_ms: if k.num_benchmarks > 0 {
+                format_ms(k.benchmark_us)
+            } else {
+                String::new()
+            },
+            num_benchmarks: k.num_benchmarks,
+            total_ms: format_ms(k.total_us()),
+        };
+
+        let mut cache_states: FxIndexMap<String, usize> = FxIndexMap::default();
+        let mut slowest: Vec<(&str, &KernelAutotuning)> = Vec::new();
+        let mut compiles = Vec::new();
+        for (compile_id, compile) in &self.compiles {
+            for kernel in &compile.kernels {
+                if let Some(state) = &kernel.cache_state {
+                    *cache_states.entry(state.clone()).or_default() += 1;
+                }
+                slowest.push((compile_id, kernel));
+            }
+            let mut kernels: Vec<&KernelAutotuning> = compile.kernels.iter().collect();
+            kernels.sort_by(|a, b| b.total_us().total_cmp(&a.total_us()));
+            compiles.push(CompileAutotuningContext {
+                compile_id: compile_id.clone(),
+                kernels_page: kernels_page(&compile.compile_id).unwrap_or_default(),
+                compile_time_autotune_ms: compile
+                    .compile_time_autotune_us
+                    .map_or(String::new(), |us| format_ms(us as f64)),
+                runtime_autotune_ms: compile
+                    .runtime_autotune_us
+                    .map_or(String::new(), |us| format_ms(us as f64)),
+                other_benchmark_ms: format_ms(compile.other_benchmark_us),
+                kernels: kernels
+                    .into_iter()
+                    .map(|k| kernel_context(compile_id, k))
+                    .collect(),
+            });
+        }
+        slowest.sort_by(|(_, a), (_, b)| b.total_us().total_cmp(&a.total_us()));
+        slowest.truncate(SLOWEST_KERNELS_LIMIT);
+
+        let kernels = self.compiles.values().flat_map(|c| &c.kernels);
+        // Summing no floats gives -0.0
+        let compile_us = kernels
+            .clone()
+            .filter_map(|k| k.compile_time_us)
+            .fold(0.0, |total, us| total + us as f64);
+        let benchmark_us = kernels.clone().fold(0.0, |total, k| total + k.benchmark_us);
+        let other_benchmark_us = self
+            .compiles
+            .values()
+            .fold(0.0, |total, c| total + c.other_benchmark_us);
+        let metrics_total = |us: fn(&CompileAutotuning) -> Option<u64>| {
+            let totals: Vec<u64> = self.compiles.values().filter_map(us).collect();
+            if totals.is_empty() {
+                String::new()
+            } else {
+                format_ms(totals.iter().sum::<u64>() as f64)
+            }
+        };
+        AutotuningContext {
+            css,
+            qps,
+            num_kernels: kernels.count(),
+            cache_states: cache_states
+                .into_iter()
+                .map(|(state, count)| AutotuneCacheStateContext { state, count })
+                .collect(),
+            compile_ms: format_ms(compile_us),
+            benchmark_ms: format_ms(benchmark_us),
+            other_benchmark_ms: format_ms(other_benchmark_us),
+            total_ms: format_ms(compile_us + benchmark_us + other_benchmark_us),
+            compile_time_autotune_ms: metrics_total(|c| c.compile_time_autotune_us),
+            runtime_autotune_ms: metrics_total(|c| c.runtime_autotune_us),
+            slowest_limit: SLOWEST_KERNELS_LIMIT,
+            slowest: slowest
+                .into_iter()
+                .map(|(compile_id, k)| kernel_context(compile_id, k))
+                .collect(),
+            compiles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_autotuning() {
+        let mut report = AutotuneReport::default();
+        let envelope: Envelope = serde_json::from_value(json!({
+            "artifact": {"name": "triton_kernel_info", "encoding": "json"},
+            "frame_id": 0, "frame_compile_id": 0, "attempt": 0
+        }))
+        .unwrap();
+        report.add_envelope(
+            &envelope,
+            r#"{"triton_poi_a": {"autotune_cache_state": "miss", "num_configs": 2, "compile_time_us": 3000},
+                "triton_poi_b": {"autotune_cache_state": "only 1 config", "only_config": [["XBLOCK", 128], ["num_warps", 4]], "compile_time_us": 1000},
+                "triton_poi_c": {"autotune_cache_state": "hit", "best_config": [["XBLOCK", 512], ["num_warps", 8]], "compile_time_us": 500}}"#,
+        );
+        let event = |name: &str, ph: &str, ts: f64, args: Value| json!({"name": name, "ph": ph, "ts": ts, "cat": "dynamo_timed", "pid": 0, "tid": 0, "args": args});
+        let events = vec![
+            event(
+                "CUDAGraphTreeManager.run_eager",
+                "B",
+                0.0,
+                json!({"compile_id": "0/0"}),
+            ),
+            event(
+                BENCHMARK_ALL_CONFIGS,
+                "B",
+                0.0,
+                json!({"kernel_name": "triton_poi_a", "compile_id": "0/0"}),
+            ),
+            // Benchmarks of the kernel's configs
+            event(
+                "TritonBenchmarker.benchmark_gpu",
+                "B",
+                0.0,
+                json!({"compile_id": "None"}),
+            ),
+            event(
+                "TritonBenchmarker.benchmark_gpu",
+                "E",
+                1000.0,
+                json!({"compile_id": "None"}),
+            ),
+            event(
+                BENCHMARK_ALL_CONFIGS,
+                "E",
+                5000.0,
+                json!({"kernel_name": "triton_poi_a", "compile_id": "0/0"}),
+            ),
+            event(
+                "InductorBenchmarker.benchmark_gpu",
+                "B",
+                5000.0,
+                json!({"compile_id": "None"}),
+            ),
+            event(
+                "InductorBenchmarker.benchmark_gpu",
+                "E",
+                7000.0,
+                json!({"compile_id": "None"}),
+            ),
+            event(
+                "CUDAGraphTreeManager.run_eager",
+                "E",
+                8000.0,
+                json!({"compile_id": "0/0"}),
+            ),
+        ];
+        report.add_benchmarks(&events);
+        let context = report.finish(|_| None, "", "");
+        assert_eq!(context.num_kernels, 3);
+        assert_eq!(context.compile_ms, "4.5");
+        assert_eq!(context.benchmark_ms, "5.0");
+        assert_eq!(context.other_benchmark_ms, "2.0");
+        assert_eq!(context.total_ms, "11.5");
+
+        let compile = &context.compiles[0];
+        assert_eq!(compile.compile_id, "[0/0]");
+        assert_eq!(compile.other_benchmark_ms, "2.0");
+        let a = &compile.kernels[0];
+        assert_eq!(
+            (a.name.as_str(), a.candidates.as_str(), a.total_ms.as_str()),
+            ("triton_poi_a", "2", "8.0")
+        );
+        let b = &compile.kernels[1];
+        assert_eq!(b.candidates, "1");
+        assert_eq!(b.chosen_config, "XBLOCK=128, num_warps=4");
+        assert_eq!(a.chosen_config, "");
+        let c = &compile.kernels[2];
+        assert_eq!(c.chosen_config, "XBLOCK=512, num_warps=8");
+    }
+}
@@ -1,24 +1,32 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use anyhow::{bail, Context};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use tlparse::{
     // New reusable library API for multi-rank landing generation
     generate_intermediate_files,
     generate_multi_rank_landing,
+    intermediate::validate::validate_intermediate_dir,
     parse_path,
+    query::{run_query, CompileIdPattern, Predicate, QueryFilter, QueryFormat, TimeBound},
     // Context used to pass rank list; other fields are recomputed inside the API
     MultiRankContext,
     ParseConfig,
+    DEFAULT_HIGHLIGHT_SIZE_LIMIT,
+    DEFAULT_STRAGGLER_PERCENTILE,
 };
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Option<Commands>,
+    #[arg(required = true)]
+    path: Option<PathBuf>,
     /// Parse most recent log
     #[arg(long)]
     latest: bool,
@@ -52,7 +60,8 @@ pub struct Cli {
     /// For export specific logs
     #[arg(short, long)]
     export: bool,
-    /// For inductor provenance tracking highlighter
+    /// Deprecated, has no effect: the inductor provenance tracking highlighter is generated
+    /// whenever the log has node mappings
     #[arg(short, long)]
     inductor_provenance: bool,
     /// Parse all ranks and create a unified multi-rank report
@@ -61,18 +70,108 @@ pub struct Cli {
     /// Generate intermediate JSON files only (no HTML rendering)
     #[arg(long)]
     intermediate_only: Option<PathBuf>,
+    /// Load build products per compile id on demand and collapse the stack trie, keeping
+    /// index.html small. Always on for logs with more than 1000 compile ids
+    #[arg(long)]
+    lazy_index: bool,
+    /// Print the time spent parsing each envelope type to stderr
+    #[arg(long)]
+    profile: bool,
+    /// Graphs and code larger than this many bytes are written as plain text, with a link
+    /// that highlights them in the browser
+    #[arg(long, default_value_t = DEFAULT_HIGHLIGHT_SIZE_LIMIT)]
+    highlight_size_limit: usize,
+    /// With --all-ranks-html, flag ranks whose total compile time is above this percentile
+    /// (0-100) of all ranks and more than 10% above the median
+    #[arg(long, default_value_t = DEFAULT_STRAGGLER_PERCENTILE)]
+    straggler_percentile: f64,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Check a directory written by --intermediate-only against the intermediate file schemas
+    Validate {
+        /// Directory containing manifest.json and the intermediate JSONL files
+        intermediate_dir: PathBuf,
+    },
+    /// Print the envelopes of a log or intermediate directory that match the given filters
+    Query {
+        /// Log file, or directory written by --intermediate-only
+        input: PathBuf,
+        /// Compile id in the usual display format, e.g. [0/1], [0/1_1] or [!0/1/0]; `*` matches
+        /// any id. May be repeated
+        #[arg(short, long = "compile-id", value_name = "ID")]
+        compile_id: Vec<CompileIdPattern>,
+        /// Envelope type, e.g. compilation_metrics or artifact. May be repeated
+        #[arg(short = 't', long = "type", value_name = "TYPE")]
+        types: Vec<String>,
+        /// Artifact name (the `name` of artifacts, dump files, graph dumps and links). May be
+        /// repeated
+        #[arg(short, long, value_name = "NAME")]
+        artifact: Vec<String>,
+        /// Only entries logged by this rank. A log holding several ranks is read for this rank
+        /// rather than for the first one.
+        #[arg(long)]
+        rank: Option<u32>,
+        /// Only entries logged at or after this time (e.g. 2024-11-28T12:00:00 or 12:00:00). In
+        /// UTC if the log's timezone can be inferred from its chromium events, otherwise in the
+        /// log's local time
+        #[arg(long, value_name = "TIME")]
+        since: Option<TimeBound>,
+        /// Only entries logged at or before this time
+        #[arg(long, value_name = "TIME")]
+        until: Option<TimeBound>,
+        /// Predicate on the metadata: PATH [OP VALUE], e.g. 'fail_type', 'cache_size>=2' or
+        /// 'restart_reasons ~= graph break'. OP is one of == != < <= > >= ~=. May be repeated
+        #[arg(short = 'w', long = "where", value_name = "PREDICATE")]
+        predicates: Vec<Predicate>,
+        /// Output format: jsonl, table or payload
+        #[arg(short, long, default_value = "table")]
+        format: QueryFormat,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Commands::Validate { intermediate_dir }) => return handle_validate(&intermediate_dir),
+        Some(Commands::Query {
+            input,
+            compile_id,
+            types,
+            artifact,
+            rank,
+            since,
+            until,
+            predicates,
+            format,
+        }) => {
+            let filter = QueryFilter {
+                compile_ids: compile_id,
+                types,
+                names: artifact,
+                rank,
+                since,
+                until,
+                predicates,
+            };
+            return handle_query(&input, &filter, format);
+        }
+        None => {}
+    }
+    let cli_path = cli.path.context("a log path is required")?;
+
     // Early validation of incompatible flags
     if cli.all_ranks_html && cli.latest {
         bail!("--latest cannot be used with --all-ranks-html");
     }
+    if !(0.0..=100.0).contains(&cli.straggler_percentile) {
+        bail!("--straggler-percentile must be between 0 and 100");
+    }
 
     let path = if cli.latest {
-        let input_path = cli.path;
+        let input_path = cli_path;
         // Path should be a directory
         if !input_path.is_dir() {
             bail!(
@@ -92,9 +191,13 @@ fn main() -> anyhow::Result<()> {
         };
         last_modified_file.path()
     } else {
-        cli.path
+        cli_path
     };
 
+    if cli.inductor_provenance {
+        eprintln!("warning: --inductor-provenance is deprecated and has no effect");
+    }
+
     let config = ParseConfig {
         strict: cli.strict,
         strict_compile_id: cli.strict_compile_id,
@@ -105,6 +208,10 @@ fn main() -> anyhow::Result<()> {
         export: cli.export,
         inductor_provenance: cli.inductor_provenance,
         intermediate_output: cli.intermediate_only.clone(),
+        lazy_index: cli.lazy_index,
+        profile: cli.profile,
+        highlight_size_limit: cli.highlight_size_limit,
+        straggler_percentile: cli.straggler_percentile,
     };
 
     // Handle intermediate-only mode
@@ -135,6 +242,54 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Validate an intermediate directory, printing every problem found
+fn handle_validate(intermediate_dir: &Path) -> anyhow::Result<()> {
+    let report = validate_intermediate_dir(intermediate_dir)?;
+    for warning in &report.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &report.errors {
+        eprintln!("error: {}", error);
+    }
+    if !report.is_ok() {
+        bail!(
+            "{} is not valid: {} error(s)",
+            intermediate_dir.display(),
+            report.errors.len()
+        );
+    }
+    println!(
+        "{} is valid ({} entries checked)",
+        intermediate_dir.display(),
+        report.entries_checked
+    );
+    Ok(())
+}
+
+/// Run a query, printing matches to stdout and the match count to stderr
+fn handle_query(input: &Path, filter: &QueryFilter, format: QueryFormat) -> anyhow::Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    let result = run_query(input, filter, format, &mut out).and_then(|n| {
+        std::io::Write::flush(&mut out)?;
+        Ok(n)
+    });
+    match result {
+        Ok(n) => {
+            eprintln!("{} matching entries", n);
+            Ok(())
+        }
+        // Output piped into e.g. `head` was closed early; that's not an error
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
 /// Create the output directory
 fn setup_output_directory(out_path: &PathBuf, overwrite: bool) -> anyhow::Result<()> {
     if out_path.exists() {
@@ -0,0 +1,337 @@
+//! Compile time stragglers across ranks.
+//!
+//! A rank that compiles slower than the others holds every other rank up at its next
+//! collective, which is how compile time turns into NCCL timeouts. This totals the
+//! `compilation_metrics` of every rank's output, spreads each compile id's time across the
+//! ranks, and flags ranks whose total compile time is beyond a percentile of all ranks and
+//! meaningfully above the median.
+//!
+//! The time of a compile is its `entire_frame_compile_time_s`, plus the Inductor compile time of
+//! its backward, which is compiled lazily on the first backward pass.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::templates::*;
+use crate::types::*;
+
+/// The most compile ids the heatmap shows
+const HEATMAP_LIMIT: usize = 500;
+
+/// How far above the median, as a fraction of it, a rank's total must be to be flagged. The
+/// percentile alone always falls below the slowest rank, however close the ranks are.
+const MIN_OVER_MEDIAN: f64 = 0.1;
+
+#[derive(Deserialize)]
+struct MetricsEnvelope {
+    #[serde(flatten)]
+    compile_id: Option<CompileId>,
+    compilation_metrics: Option<CompilationMetricsMetadata>,
+    bwd_compilation_metrics: Option<BwdCompilationMetricsMetadata>,
+    artifact: Option<ArtifactMetadata>,
+}
+
+/// Compile time in seconds per compile id, in the order the rank compiled them, and the hits
+/// and lookups of the rank's cache hit and miss artifacts. Bypasses never looked the cache up.
+fn read_compile_times(out_path: &Path, rank: u32) -> (FxIndexMap<String, f64>, (usize, usize)) {
+    let mut times: FxIndexMap<String, f64> = FxIndexMap::default();
+    let (mut hits, mut lookups) = (0, 0);
+    let Ok(file) = File::open(out_path.join(format!("rank_{rank}")).join("raw.jsonl")) else {
+        return (times, (hits, lookups));
+    };
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if !line.contains("compilation_metrics\"") && !line.contains("_cache_") {
+            continue;
+        }
+        let Ok(envelope) = serde_json::from_str::<MetricsEnvelope>(&line) else {
+            continue;
+        };
+        if let Some(artifact) = &envelope.artifact {
+            if artifact.name.ends_with("_cache_hit") {
+                hits += 1;
+                lookups += 1;
+            } else if artifact.name.ends_with("_cache_miss") {
+                lookups += 1;
+            }
+            continue;
+        }
+        let seconds = match (
+            envelope.compilation_metrics,
+            envelope.bwd_compilation_metrics,
+        ) {
+            (Some(m), _) => m.entire_frame_compile_time_s.or(m
+                .dynamo_cumulative_compile_time_us
+                .map(|us| us as f64 / 1e6)),
+            (_, Some(m)) => m.inductor_compile_time_s,
+            _ => None,
+        };
+        let Some(seconds) = seconds else {
+            continue;
+        };
+        let compile_id = envelope
+            .compile_id
+            .map_or("(unknown)".to_string(), |c| c.to_string());
+        *times.entry(compile_id).or_default() += seconds;
+    }
+    (times, (hits, lookups))
+}
+
+/// The value below which `percentile` percent of `sorted` lies, interpolating between
+/// neighbours
+fn percentile_of(sorted: &[f64], percentile: f64) -> f64 {
+    let position = (percentile.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+fn format_s(s: f64) -> String {
+    format!("{:.3}", s)
+}
+
+/// Background of a heatmap cell, from white for the fastest rank of a compile id to red for
+/// the slowest
+fn heat(time: f64, min: f64, max: f64) -> String {
+    let t = if max > min {
+        (time - min) / (max - min)
+    } else {
+        0.0
+    };
+    format!("background-color: hsl(0, 100%, {:.0}%)", 100.0 - 40.0 * t)
+}
+
+pub struct CompileStragglers {
+    ranks: Vec<u32>,
+    times: Vec<FxIndexMap<String, f64>>,
+    /// Cache hits and lookups per rank
+    cache: Vec<(usize, usize)>,
+    percentile: f64,
+}
+
+impl CompileStragglers {
+    /// Read the compile times and cache lookups of each rank
+    pub fn new(out_path: &Path, rank_nums: &[u32], percentile: f64) -> Self {
+        let (times, cache) = rank_nums
+            .iter()
+            .map(|&rank| read_compile_times(out_path, rank))
+            .unzip();
+        CompileStragglers {
+            ranks: rank_nums.to_vec(),
+            times,
+            cache,
+            percentile,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.iter().all(|times| times.is_empty())
+    }
+
+    pub fn finish(&self) -> (CompileStragglersContext, CompileTimeSummary) {
+        let totals: Vec<f64> = self
+            .times
+            .iter()
+            .map(|times| times.values().sum())
+            .collect();
+        let mut sorted = totals.clone();
+        sorted.sort_by(f64::total_cmp);
+        let threshold = percentile_of(&sorted, self.percentile);
+        let median = percentile_of(&sorted, 50.0);
+
+        let ranks: Vec<RankCompileTimeContext> = self
+            .ranks
+            .iter()
+            .zip(&self.times)
+            .zip(totals.iter().zip(&self.cache))
+            .map(|((rank, times), (&total, &(hits, lookups)))| {
+                let slowest = times
+                    .iter()
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+                    .map_or(String::new(), |(compile_id, _)| compile_id.clone());
+                RankCompileTimeContext {
+                    rank: *rank,
+                    total_s: format_s(total),
+                    over_median_s: format_s(total - median),
+                    num_compiles: times.len(),
+                    cache_hits: hits,
+                    cache_lookups: lookups,
+                    cache_hit_ratio: if lookups > 0 {
+                        format!("{:.0}%", 100.0 * hits as f64 / lookups as f64)
+                    } else {
+                        "-".to_string()
+                    },
+                    slowest_compile_id: slowest,
+                    flagged: total > threshold && total - median > MIN_OVER_MEDIAN * median,
+                }
+            })
+            .collect();
+
+        // Compile ids in the order the lowest rank that compiled them did
+        let mut compile_ids: FxIndexMap<&str, ()> = FxIndexMap::default();
+        for times in &self.times {
+            for compile_id in times.keys() {
+                compile_ids.entry(compile_id).or_default();
+            }
+        }
+        let mut compiles = Vec::new();
+        let mut heatmap = Vec::new();
+        for (order, &compile_id) in compile_ids.keys().enumerate() {
+            let by_rank: Vec<Option<f64>> = self
+                .times
+                .iter()
+                .map(|times| times.get(compile_id).copied())
+                .collect();
+            let mut sorted: Vec<f64> = by_rank.iter().flatten().copied().collect();
+            sorted.sort_by(f64::total_cmp);
+            let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+            let slowest_rank = self
+                .ranks
+                .iter()
+                .zip(&by_rank)
+                .find(|(_, time)| **time == Some(max))
+                .map_or(0, |(rank, _)| *rank);
+            compiles.push(CompileIdSpreadContext {
+                order,
+                compile_id: compile_id.to_string(),
+                num_ranks: sorted.len(),
+                min_s: format_s(min),
+                median_s: format_s(percentile_of(&sorted, 50.0)),
+                max_s: format_s(max),
+                spread_s: format_s(max - min),
+                slowest_rank,
+            });
+            if heatmap.len() < HEATMAP_LIMIT {
+                heatmap.push(HeatmapRowContext {
+                    compile_id: compile_id.to_string(),
+                    cells: by_rank
+                        .iter()
+                        .map(|time| match time {
+                            Some(time) => HeatmapCellContext {
+                                time_s: format_s(*time),
+                                style: heat(*time, min, max),
+                            },
+                            None => HeatmapCellContext {
+                                time_s: "-".to_string(),
+                                style: String::new(),
+                            },
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        let flagged_ranks = ranks
+            .iter()
+            .filter(|r| r.flagged)
+            .map(|r| r.rank.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let summary = CompileTimeSummary {
+            percentile: format!("{}", self.percentile),
+            threshold_s: format_s(threshold),
+            has_flagged: !flagged_ranks.is_empty(),
+            flagged_ranks: flagged_ranks.clone(),
+        };
+        let context = CompileStragglersContext {
+            css: CSS,
+            qps: TEMPLATE_QUERY_PARAM_SCRIPT,
+            javascript: SORTABLE_TABLE_JAVASCRIPT,
+            percentile: summary.percentile.clone(),
+            threshold_s: summary.threshold_s.clone(),
+            median_s: format_s(median),
+            flagged_ranks,
+            rank_headers: self.ranks.clone(),
+            num_heatmap_compiles: heatmap.len(),
+            num_compiles: compiles.len(),
+            ranks,
+            compiles,
+            heatmap,
+        };
+        (context, summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_of() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 10.0];
+        assert_eq!(percentile_of(&sorted, 50.0), 3.0);
+        assert_eq!(percentile_of(&sorted, 100.0), 10.0);
+        assert_eq!(percentile_of(&sorted, 0.0), 1.0);
+        assert!((percentile_of(&sorted, 90.0) - 7.6).abs() < 1e-9);
+        assert_eq!(percentile_of(&[5.0], 90.0), 5.0);
+    }
+
+    #[test]
+    fn test_read_compile_times() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("rank_0")).unwrap();
+        let lines = [
+            r#"{"artifact":{"encoding":"json","name":"fx_graph_cache_hit"},"frame_id":0,"frame_compile_id":0,"attempt":0}"#,
+            r#"{"artifact":{"encoding":"json","name":"aotautograd_cache_miss"},"frame_id":0,"frame_compile_id":0,"attempt":0}"#,
+            r#"{"artifact":{"encoding":"json","name":"fx_graph_cache_bypass"},"frame_id":0,"frame_compile_id":1,"attempt":0}"#,
+            r#"{"compilation_metrics":{"entire_frame_compile_time_s":1.5},"frame_id":0,"frame_compile_id":0,"attempt":0}"#,
+        ];
+        std::fs::write(dir.path().join("rank_0/raw.jsonl"), lines.join("\n")).unwrap();
+        let (times, cache) = read_compile_times(dir.path(), 0);
+        assert_eq!(times.get("[0/0]"), Some(&1.5));
+        assert_eq!(cache, (1, 2));
+    }
+
+    #[test]
+    fn test_flagged_ranks() {
+        let times = |pairs: &[(&str, f64)]| -> FxIndexMap<String, f64> {
+            pairs.iter().map(|(c, t)| (c.to_string(), *t)).collect()
+        };
+        let stragglers = CompileStragglers {
+            ranks: vec![0, 1, 2, 3],
+            times: vec![
+                times(&[("[0/0]", 1.0), ("[0/1]", 0.5)]),
+                times(&[("[0/0]", 1.1), ("[0/1]", 0.4)]),
+                times(&[("[0/0]", 4.0), ("[0/1]", 0.6)]),
+                times(&[("[0/0]", 1.2)]),
+            ],
+            cache: vec![(1, 2), (2, 2), (0, 2), (0, 0)],
+            percentile: 90.0,
+        };
+        let (context, summary) = stragglers.finish();
+        assert_eq!(summary.flagged_ranks, "2");
+        assert_eq!(context.ranks[2].total_s, "4.600");
+        assert_eq!(context.ranks[0].cache_hit_ratio, "50%");
+        assert_eq!(context.ranks[3].cache_hit_ratio, "-");
+
+        let first = &context.compiles[0];
+        assert_eq!(
+            (
+                first.min_s.as_str(),
+                first.median_s.as_str(),
+                first.max_s.as_str()
+            ),
+            ("1.000", "1.150", "4.000")
+        );
+        assert_eq!(first.slowest_rank, 2);
+        assert_eq!(context.compiles[1].num_ranks, 3);
+        assert_eq!(context.heatmap[1].cells[3].time_s, "-");
+    }
+
+    #[test]
+    fn test_near_equal_ranks_not_flagged() {
+        let stragglers = CompileStragglers {
+            ranks: vec![0, 1],
+            times: vec![
+                [("[0/0]".to_string(), 10.0)].into_iter().collect(),
+                [("[0/0]".to_string(), 10.001)].into_iter().collect(),
+            ],
+            cache: vec![(0, 0), (0, 0)],
+            percentile: 90.0,
+        };
+        let (context, summary) = stragglers.finish();
+        assert!(!context.ranks.iter().any(|r| r.flagged));
+        assert!(!summary.has_flagged);
+    }
+}
@@ -0,0 +1,494 @@
+//! Compile time profile.
+//!
+//! Totals the phase timings of every compilation_metrics envelope, ranks the slowest compiles,
+//! and breaks the `dynamo_timed` chromium events down per compile id, so the time spent in
+//! cache lookups can be compared with the time spent compiling.
+
+use fxhash::FxHashMap;
+use serde_json::Value;
+
+use crate::types::*;
+
+/// How many compile ids the slowest compiles table lists
+const SLOWEST_COMPILES_LIMIT: usize = 20;
+
+#[derive(Default)]
+struct CompileTimes {
+    compile_id: String,
+    compile_id_html: Option<String>,
+    entire_frame_compile_time_s: Option<f64>,
+    backend_compile_time_s: Option<f64>,
+    inductor_compile_time_s: Option<f64>,
+    code_gen_time_s: Option<f64>,
+    dynamo_time_before_restart_s: Option<f64>,
+    bwd_inductor_compile_time_s: Option<f64>,
+    bwd_code_gen_time_s: Option<f64>,
+    aot_backward_elapsed_time_s: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct CompileTimeProfile {
+    // One per compilation_metrics envelope; a log can hold several runs that reuse compile ids
+    compiles: Vec<CompileTimes>,
+    // Latest entry of each compile id, which backward metrics are attributed to
+    latest: FxHashMap<Option<CompileId>, usize>,
+}
+
+#[derive(Default)]
+struct EventTimes {
+    count: usize,
+    total_us: f64,
+    self_us: f64,
+}
+
+#[derive(Default)]
+struct CompileEvents {
+    events: FxIndexMap<String, EventTimes>,
+    // Events with nothing open below them on their thread
+    root_us: f64,
+    // Self time of cache events; what they spend in nested events (e.g. compiling on a miss)
+    // isn't cache lookup
+    cache_us: f64,
+}
+
+/// A `dynamo_timed` chromium event, from its B to its E event
+pub struct TimedSpan<'a> {
+    pub name: &'a str,
+    pub begin: &'a Value,
+    pub end: &'a Value,
+    pub duration_us: f64,
+    /// The duration less that of the spans directly nested in it
+    pub self_us: f64,
+    /// The B events of the spans open around it on its thread, outermost first
+    pub parents: Vec<&'a Value>,
+}
+
+struct OpenSpan<'a> {
+    name: &'a str,
+    begin: &'a Value,
+    ts: f64,
+    children_us: f64,
+}
+
+fn is_cache_event(name: &str) -> bool {
+    name.to_lowercase().contains("cache")
+}
+
+fn is_cache_outcome(name: &str) -> bool {
+    ["_cache_hit", "_cache_miss", "_cache_bypass"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+fn format_s(s: Option<f64>) -> String {
+    s.map_or(String::new(), |s| format!("{:.3}", s))
+}
+
+pub fn format_ms(us: f64) -> String {
+    format!("{:.1}", us / 1000.0)
+}
+
+/// Pair up the B and E `dynamo_timed` events of each thread, in the order the spans end
+pub fn timed_spans(chromium_events: &[Value]) -> Vec<TimedSpan<'_>> {
+    let mut spans = Vec::new();
+    let mut open: FxHashMap<(String, String), Vec<OpenSpan>> = FxHashMap::default();
+    for event in chromium_events {
+        let cat = event.get("cat").and_then(Value::as_str);
+        if cat.is_some_and(|cat| cat != "dynamo_timed") {
+            continue;
+        }
+        let (Some(name), Some(ph), Some(ts)) = (
+            event.get("name").and_then(Value::as_str),
+            event.get("ph").and_then(Value::as_str),
+            event.get("ts").and_then(Value::as_f64),
+        ) else {
+            continue;
+        };
+        let thread = (
+            event.get("pid").map_or(String::new(), Value::to_string),
+            event.get("tid").map_or(String::new(), Value::to_string),
+        );
+        let stack = open.entry(thread).or_default();
+        match ph {
+            "B" => {
+                stack.push(OpenSpan {
+                    name,
+                    begin: event,
+                    ts,
+                    children_us: 0.0,
+                });
+            }
+            "E" => {
+                // Events that never ended (e.g. because of an exception) are dropped along with
+                // everything that was open above them
+                let Some(position) = stack.iter().rposition(|e| e.name == name) else {
+                    continue;
+                };
+                stack.truncate(position + 1);
+                let begin = stack.pop().unwrap();
+                let duration_us = (ts - begin.ts).max(0.0);
+                if let Some(parent) = stack.last_mut() {
+                    parent.children_us += duration_us;
+                }
+                spans.push(TimedSpan {
+                    name: begin.name,
+                    begin: begin.begin,
+                    end: event,
+                    duration_us,
+                    self_us: (duration_us - begin.children_us).max(0.0),
+                    parents: stack.iter().map(|open| open.begin).collect(),
+                });
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// Total the `dynamo_timed` spans per compile id
+fn match_events(chromium_events: &[Value]) -> FxIndexMap<String, CompileEvents> {
+    let mut compiles: FxIndexMap<String, CompileEvents> = FxIndexMap::default();
+    for span in timed_spans(chromium_events) {
+        let compile_id = [span.begin, span.end]
+            .into_iter()
+            .find_map(|event| event.pointer("/args/compile_id").and_then(Value::as_str))
+            .unwrap_or("(unknown)");
+        let compile = compiles.entry(compile_id.to_string()).or_default();
+        if span.parents.is_empty() {
+            compile.root_us += span.duration_us;
+        }
+        if is_cache_event(span.name) {
+            compile.cache_us += span.self_us;
+        }
+        let times = compile.events.entry(span.name.to_string()).or_default();
+        times.count += 1;
+        times.total_us += span.duration_us;
+        times.self_us += span.self_us;
+    }
+    compiles
+}
+
+/// Count the cache hits, misses and bypasses logged as instant events, with the time the hits
+/// report saving
+fn cache_outcomes(chromium_events: &[Value]) -> Vec<CacheOutcomeContext> {
+    let mut outcomes: FxIndexMap<&str, (usize, f64)> = FxIndexMap::default();
+    for event in chromium_events {
+        let Some(name) = event.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        if event.get("ph").and_then(Value::as_str) != Some("i") || !is_cache_outcome(name) {
+            continue;
+        }
+        let outcome = outcomes.entry(name).or_default();
+        outcome.0 += 1;
+        outcome.1 += event
+            .pointer("/args/time_saved_ms")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0);
+    }
+    outcomes.sort_keys();
+    outcomes
+        .into_iter()
+        .map(|(name, (count, time_saved_ms))| CacheOutcomeContext {
+            name: name.to_string(),
+            count,
+            time_saved_ms: if time_saved_ms > 0.0 {
+                format!("{:.1}", time_saved_ms)
+            } else {
+                String::new()
+            },
+        })
+        .collect()
+}
+
+impl CompileTimeProfile {
+    pub fn is_empty(&self) -> bool {
+        self.compiles.is_empty()
+    }
+
+    fn push(&mut self, compile_id: &Option<CompileId>) -> &mut CompileTimes {
+        self.latest.insert(compile_id.clone(), self.compiles.len());
+        self.compiles.push(CompileTimes {
+            compile_id: compile_id
+                .as_ref()
+                .map_or("(unknown)".to_string(), |c| c.to_string()),
+            ..Default::default()
+        });
+        self.compiles.last_mut().unwrap()
+    }
+
+    fn latest(&mut self, compile_id: &Option<CompileId>) -> &mut CompileTimes {
+        match self.latest.get(compile_id) {
+            Some(&index) => &mut self.compiles[index],
+            None => self.push(compile_id),
+        }
+    }
+
+    /// Record the phase timings of a compilation_metrics envelope. `compile_id_html` links to
+    /// its metrics page.
+    pub fn add_metrics(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        compile_id_html: &str,
+        m: &CompilationMetricsMetadata,
+    ) {
+        let or_us = |s: Option<f64>, us: Option<u64>| s.or(us.map(|us| us as f64 / 1e6));
+        let compile = self.push(compile_id);
+        compile.compile_id_html = Some(compile_id_html.to_string());
+        compile.entire_frame_compile_time_s = or_us(
+            m.entire_frame_compile_time_s,
+            m.dynamo_cumulative_compile_time_us,
+        );
+        compile.backend_compile_time_s = or_us(
+            m.backend_compile_time_s,
+            m.aot_autograd_cumulative_compile_time_us,
+        );
+        compile.inductor_compile_time_s = or_us(
+            m.inductor_compile_time_s,
+            m.inductor_cumulative_compile_time_us,
+        );
+        compile.code_gen_time_s = or_us(
+            m.code_gen_time_s,
+            m.inductor_code_gen_cumulative_compile_time_us,
+        );
+        compile.dynamo_time_before_restart_s = m.dynamo_time_before_restart_s;
+    }
+
+    pub fn add_bwd_metrics(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        m: &BwdCompilationMetricsMetadata,
+    ) {
+        let compile = self.latest(compile_id);
+        compile.bwd_inductor_compile_time_s = m.inductor_compile_time_s;
+        compile.bwd_code_gen_time_s = m.code_gen_time_s;
+    }
+
+    pub fn add_aot_backward_metrics(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        m: &AOTAutogradBackwardCompilationMetricsMetadata,
+    ) {
+        self.latest(compile_id).aot_backward_elapsed_time_s = m.elapsed_time;
+    }
+
+    pub fn finish(
+        &self,
+        chromium_events: &[Value],
+        css: &'static str,
+        qps: &'static str,
+    ) -> CompileTimeContext {
+        let phase = |name: &'static str, time: fn(&CompileTimes) -> Option<f64>| {
+            let times: Vec<f64> = self.compiles.iter().filter_map(time).collect();
+            CompileTimePhaseContext {
+                name,
+                total_s: format!("{:.3}", times.iter().sum::<f64>()),
+                num_compiles: times.len(),
+            }
+        };
+        let phases = vec![
+            phase("entire_frame_compile_time_s", |c| {
+                c.entire_frame_compile_time_s
+            }),
+            phase("backend_compile_time_s", |c| c.backend_compile_time_s),
+            phase("inductor_compile_time_s", |c| c.inductor_compile_time_s),
+            phase("code_gen_time_s", |c| c.code_gen_time_s),
+            phase("dynamo_time_before_restart_s", |c| {
+                c.dynamo_time_before_restart_s
+            }),
+            phase("bwd inductor_compile_time_s", |c| {
+                c.bwd_inductor_compile_time_s
+            }),
+            phase("bwd code_gen_time_s", |c| c.bwd_code_gen_time_s),
+            phase("aot_autograd_backward elapsed_time", |c| {
+                c.aot_backward_elapsed_time_s
+            }),
+        ]
+        .into_iter()
+        .filter(|p| p.num_compiles > 0)
+        .collect();
+
+        let mut slowest: Vec<&CompileTimes> = self
+            .compiles
+            .iter()
+            .filter(|c| c.entire_frame_compile_time_s.is_some())
+            .collect();
+        slowest.sort_by(|a, b| {
+            b.entire_frame_compile_time_s
+                .partial_cmp(&a.entire_frame_compile_time_s)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let compile_id_html = |c: &CompileTimes| {
+            c.compile_id_html
+                .clone()
+                .unwrap_or_else(|| c.compile_id.clone())
+        };
+        let slowest = slowest
+            .into_iter()
+            .take(SLOWEST_COMPILES_LIMIT)
+            .map(|c| SlowCompileContext {
+                compile_id: compile_id_html(c),
+                entire_frame_compile_time_s: format_s(c.entire_frame_compile_time_s),
+                backend_compile_time_s: format_s(c.backend_compile_time_s),
+                inductor_compile_time_s: format_s(c.inductor_compile_time_s),
+                code_gen_time_s: format_s(c.code_gen_time_s),
+                bwd_inductor_compile_time_s: format_s(c.bwd_inductor_compile_time_s),
+            })
+            .collect();
+
+        // chromium events carry the compile id without brackets, e.g. "0/0"
+        let links: FxHashMap<String, String> = self
+            .compiles
+            .iter()
+            .map(|c| (c.compile_id.clone(), compile_id_html(c)))
+            .collect();
+        let event_compiles = match_events(chromium_events);
+        let root_us: f64 = event_compiles.values().map(|c| c.root_us).sum();
+        let cache_us: f64 = event_compiles.values().map(|c| c.cache_us).sum();
+        let events = event_compiles
+            .into_iter()
+            .map(|(compile_id, compile)| {
+                let mut events: Vec<(String, EventTimes)> = compile.events.into_iter().collect();
+                events.sort_by(|(_, a), (_, b)| {
+                    b.total_us
+                        .partial_cmp(&a.total_us)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                let bracketed = format!("[{}]", compile_id);
+                CompileEventsContext {
+                    compile_id: links.get(&bracketed).cloned().unwrap_or(bracketed),
+                    total_ms: format_ms(compile.root_us),
+                    cache_ms: format_ms(compile.cache_us),
+                    events: events
+                        .into_iter()
+                        .map(|(name, times)| TimedEventContext {
+                            name,
+                            count: times.count,
+                            total_ms: format_ms(times.total_us),
+                            self_ms: format_ms(times.self_us),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        CompileTimeContext {
+            css,
+            qps,
+            num_compiles: self.compiles.len(),
+            phases,
+            slowest_limit: SLOWEST_COMPILES_LIMIT,
+            slowest,
+            total_ms: format_ms(root_us),
+            cache_ms: format_ms(cache_us),
+            cache_percent: if root_us > 0.0 {
+                format!("{:.1}", 100.0 * cache_us / root_us)
+            } else {
+                "0.0".to_string()
+            },
+            cache_outcomes: cache_outcomes(chromium_events),
+            compiles: events,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_match_events() {
+        let event = |name: &str, ph: &str, ts: f64, tid: u32| {
+            json!({"name": name, "ph": ph, "ts": ts, "cat": "dynamo_timed", "pid": 0, "tid": tid,
+                   "args": {"compile_id": "0/0"}})
+        };
+        let events = vec![
+            event("entire_frame_compile", "B", 0.0, 0),
+            event("backend_compile", "B", 1000.0, 0),
+            // Another thread doesn't nest into this one
+            event("async_compile.wait", "B", 1500.0, 1),
+            event("FxGraphCache.load", "B", 2000.0, 0),
+            event("PyCodeCache.load_by_key_path", "B", 2500.0, 0),
+            event("PyCodeCache.load_by_key_path", "E", 3000.0, 0),
+            event("FxGraphCache.load", "E", 4000.0, 0),
+            event("async_compile.wait", "E", 6500.0, 1),
+            event("backend_compile", "E", 9000.0, 0),
+            event("entire_frame_compile", "E", 10000.0, 0),
+        ];
+        let compiles = match_events(&events);
+        let compile = &compiles["0/0"];
+        let backend = &compile.events["backend_compile"];
+        assert_eq!(backend.count, 1);
+        assert_eq!(backend.total_us, 8000.0);
+        assert_eq!(backend.self_us, 6000.0);
+        assert_eq!(compile.events["entire_frame_compile"].self_us, 2000.0);
+        assert_eq!(compile.root_us, 15000.0);
+        // Self time of both cache events
+        assert_eq!(compile.cache_us, 2000.0);
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut profile = CompileTimeProfile::default();
+        let compile_id = |frame_id| {
+            Some(CompileId {
+                compiled_autograd_id: None,
+                frame_id: Some(frame_id),
+                frame_compile_id: Some(0),
+                attempt: Some(0),
+            })
+        };
+        for (frame_id, time) in [(0, 1.0), (1, 3.0), (2, 2.0)] {
+            profile.add_metrics(
+                &compile_id(frame_id),
+                &format!("<a>{}</a>", frame_id),
+                &CompilationMetricsMetadata {
+                    entire_frame_compile_time_s: Some(time),
+                    backend_compile_time_s: Some(time / 2.0),
+                    ..Default::default()
+                },
+            );
+        }
+        profile.add_bwd_metrics(
+            &compile_id(1),
+            &BwdCompilationMetricsMetadata {
+                inductor_compile_time_s: Some(0.5),
+                code_gen_time_s: None,
+                fail_type: None,
+                fail_reason: None,
+            },
+        );
+        let events = vec![
+            json!({"name": "dynamo", "ph": "B", "ts": 0.0, "pid": 0, "tid": 0, "args": {"compile_id": "1/0"}}),
+            json!({"name": "dynamo", "ph": "E", "ts": 4000.0, "pid": 0, "tid": 0, "args": {"compile_id": "1/0"}}),
+            json!({"name": "fx_graph_cache_hit", "ph": "i", "ts": 0.0, "pid": 0, "tid": 0, "args": {"time_saved_ms": 12}}),
+            json!({"name": "fx_graph_cache_miss", "ph": "i", "ts": 0.0, "pid": 0, "tid": 0, "args": {}}),
+        ];
+        let context = profile.finish(&events, "", "");
+        let phases: Vec<(&str, &str, usize)> = context
+            .phases
+            .iter()
+            .map(|p| (p.name, p.total_s.as_str(), p.num_compiles))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                ("entire_frame_compile_time_s", "6.000", 3),
+                ("backend_compile_time_s", "3.000", 3),
+                ("bwd inductor_compile_time_s", "0.500", 1),
+            ]
+        );
+        let slowest: Vec<&str> = context
+            .slowest
+            .iter()
+            .map(|c| c.compile_id.as_str())
+            .collect();
+        assert_eq!(slowest, vec!["<a>1</a>", "<a>2</a>", "<a>0</a>"]);
+        assert_eq!(context.compiles.len(), 1);
+        assert_eq!(context.compiles[0].compile_id, "<a>1</a>");
+        assert_eq!(context.total_ms, "4.0");
+        assert_eq!(context.cache_outcomes.len(), 2);
+        assert_eq!(context.cache_outcomes[0].time_saved_ms, "12.0");
+    }
+}
@@ -0,0 +1,398 @@
+//! Dynamic shapes report.
+//!
+//! Collects the symbolic shape envelopes of each compile id (symbols created, specializations
+//! and shape guards) and, for frames that recompiled because the size of an input changed,
+//! suggests marking the dimension dynamic or static up front.
+
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::graph_breaks::frame_location;
+use crate::parsers::format_stack;
+use crate::recompiles::frame_key;
+use crate::types::*;
+
+static SIZE_MISMATCH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"tensor '(?<source>.+?)' size mismatch at index (?<dim>\d+)\. expected (?<expected>\d+), actual (?<actual>\d+)",
+    )
+    .unwrap()
+});
+static LOCAL_SOURCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[LG]\['([^']+)'\]").unwrap());
+
+struct Symbol {
+    unbacked: bool,
+    value: Option<String>,
+    range: Option<String>,
+    sources: Vec<String>,
+    location: Option<String>,
+}
+
+#[derive(Default)]
+struct CompileShapes {
+    compile_id: String,
+    symbols: FxIndexMap<String, Symbol>,
+    specializations: Vec<SpecializationContext>,
+    // Guard expression, whether it came from guard_added_fast, and the user code it came from
+    guards: Vec<(String, bool, Option<String>)>,
+    size_mismatches: Vec<SizeMismatch>,
+}
+
+struct SizeMismatch {
+    source: String,
+    dim: u32,
+    expected: String,
+    actual: String,
+}
+
+// A frame, and the source and dimension of one of its input tensors
+type FrameDim = (Option<(Option<u32>, u32)>, String, u32);
+
+#[derive(Default)]
+pub struct DynamicShapes {
+    compiles: FxIndexMap<Option<CompileId>, CompileShapes>,
+}
+
+fn user_location(user_stack: &Option<StackSummary>) -> Option<String> {
+    user_stack.as_ref()?.last().map(frame_location)
+}
+
+/// The Python expression for a guard source, e.g. `L['self'].weight` -> `self.weight`
+fn source_expression(source: &str) -> String {
+    LOCAL_SOURCE.replace_all(source, "$1").into_owned()
+}
+
+impl DynamicShapes {
+    pub fn is_empty(&self) -> bool {
+        self.compiles.is_empty()
+    }
+
+    fn compile(&mut self, compile_id: &Option<CompileId>) -> &mut CompileShapes {
+        self.compiles
+            .entry(compile_id.clone())
+            .or_insert_with(|| CompileShapes {
+                compile_id: compile_id
+                    .as_ref()
+                    .map_or("(unknown)".to_string(), |c| c.to_string()),
+                ..Default::default()
+            })
+    }
+
+    fn symbol(&mut self, compile_id: &Option<CompileId>, name: &str) -> &mut Symbol {
+        self.compile(compile_id)
+            .symbols
+            .entry(name.to_string())
+            .or_insert(Symbol {
+                unbacked: false,
+                value: None,
+                range: None,
+                sources: Vec::new(),
+                location: None,
+            })
+    }
+
+    fn add_sources(&mut self, compile_id: &Option<CompileId>, name: &str, sources: &[String]) {
+        let symbol = self.symbol(compile_id, name);
+        for source in sources {
+            if !symbol.sources.contains(source) {
+                symbol.sources.push(source.clone());
+            }
+        }
+    }
+
+    /// Record the symbolic shape envelopes of a compile, and the recompile reasons that
+    /// mention a size change
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str) {
+        let compile_id = &e.compile_id;
+        if let Some(m) = &e.create_symbol {
+            let name = m.symbol.clone().unwrap_or_default();
+            let sources: Vec<String> = m.source.iter().cloned().collect();
+            self.add_sources(compile_id, &name, &sources);
+            let symbol = self.symbol(compile_id, &name);
+            symbol.value = m.val.clone();
+            symbol.range = m.vr.clone();
+            symbol.location = user_location(&m.user_stack);
+        }
+        if let Some(m) = &e.create_unbacked_symbol {
+            let name = m.symbol.clone().unwrap_or_default();
+            let symbol = self.symbol(compile_id, &name);
+            symbol.unbacked = true;
+            symbol.range = m.vr.clone();
+            symbol.location = user_location(&m.user_stack);
+        }
+        if let Some(m) = &e.symbolic_shape_specialization {
+            let sources = m.sources.clone().unwrap_or_default();
+            if let Some(name) = &m.symbol {
+                self.add_sources(compile_id, name, &sources);
+            }
+            self.compile(compile_id)
+                .specializations
+                .push(SpecializationContext {
+                    compile_id: String::new(),
+                    symbol: m.symbol.clone().unwrap_or_default(),
+                    sources: sources.join(", "),
+                    value: m.value.clone().unwrap_or_default(),
+                    reason: m.reason.clone().unwrap_or_default(),
+                    location: user_location(&m.user_stack).unwrap_or_default(),
+                    user_stack_html: m
+                        .user_stack
+                        .as_ref()
+                        .map_or(String::new(), |s| format_stack(s, "User Stack", false)),
+                });
+        }
+        if let Some(m) = &e.guard_added_fast {
+            self.compile(compile_id).guards.push((
+                m.expr.clone().unwrap_or_default(),
+                true,
+                user_location(&m.user_stack),
+            ));
+        }
+        if let Some(m) = &e.guard_added {
+            for (name, source) in m.symbol_to_sources.iter().flatten() {
+                self.add_sources(compile_id, name, std::slice::from_ref(source));
+            }
+            self.compile(compile_id).guards.push((
+                m.expr.clone().unwrap_or_default(),
+                false,
+                user_location(&m.user_stack),
+            ));
+        }
+        if e.artifact
+            .as_ref()
+            .is_some_and(|a| a.name == "recompile_reasons")
+        {
+            let reasons = serde_json::from_str::<Vec<String>>(payload).unwrap_or_default();
+            let mismatches: Vec<SizeMismatch> = reasons
+                .iter()
+                .flat_map(|reason| SIZE_MISMATCH.captures_iter(reason))
+                .filter_map(|caps| {
+                    Some(SizeMismatch {
+                        source: caps["source"].to_string(),
+                        dim: caps["dim"].parse().ok()?,
+                        expected: caps["expected"].to_string(),
+                        actual: caps["actual"].to_string(),
+                    })
+                })
+                .collect();
+            if !mismatches.is_empty() {
+                self.compile(compile_id).size_mismatches.extend(mismatches);
+            }
+        }
+    }
+
+    /// For every input dimension whose size change made a frame recompile, suggest
+    /// `mark_dynamic`, or `mark_static` if a later compile of the frame specialized it anyway
+    fn suggestions(&self) -> Vec<ShapeSuggestionContext> {
+        let mut suggestions: FxIndexMap<FrameDim, Vec<String>> = FxIndexMap::default();
+        let mut first_compile: FxHashMap<FrameDim, String> = FxHashMap::default();
+        for (compile_id, compile) in &self.compiles {
+            let frame = frame_key(compile_id).map(|(frame, _)| frame);
+            for m in &compile.size_mismatches {
+                let key = (frame, m.source.clone(), m.dim);
+                first_compile
+                    .entry(key.clone())
+                    .or_insert_with(|| compile.compile_id.clone());
+                let sizes = suggestions.entry(key).or_default();
+                for size in [&m.expected, &m.actual] {
+                    if !sizes.contains(size) {
+                        sizes.push(size.clone());
+                    }
+                }
+            }
+        }
+        suggestions
+            .into_iter()
+            .map(|(key, sizes)| {
+                let (frame, source, dim) = &key;
+                let size_source = format!("{}.size()[{}]", source, dim);
+                let specialized = self
+                    .compiles
+                    .iter()
+                    .filter(|(c, _)| frame_key(c).map(|(f, _)| f) == *frame)
+                    .flat_map(|(_, compile)| &compile.specializations)
+                    .any(|s| s.sources.split(", ").any(|s| s == size_source));
+                let tensor = source_expression(source);
+                let (action, why) = if specialized {
+                    (
+                        format!("torch._dynamo.mark_static({}, {})", tensor, dim),
+                        "The dimension was compiled dynamically after the size changed, but was specialized anyway, so the dynamic compile only adds a recompile.",
+                    )
+                } else {
+                    (
+                        format!("torch._dynamo.mark_dynamic({}, {})", tensor, dim),
+                        "Marking the dimension dynamic before the first call compiles it dynamically from the start, instead of recompiling when the size first changes.",
+                    )
+                };
+                ShapeSuggestionContext {
+                    compile_id: first_compile[&key].clone(),
+                    source: source.clone(),
+                    dim: *dim,
+                    sizes: sizes.join(", "),
+                    action,
+                    why,
+                }
+            })
+            .collect()
+    }
+
+    fn compile_context(&self, compile: &CompileShapes, page: &str) -> CompileShapesContext {
+        CompileShapesContext {
+            compile_id: compile.compile_id.clone(),
+            page: page.to_string(),
+            num_symbols: compile.symbols.len(),
+            num_specializations: compile.specializations.len(),
+            num_guards: compile.guards.len(),
+            symbols: compile
+                .symbols
+                .iter()
+                .map(|(name, s)| SymbolContext {
+                    symbol: name.clone(),
+                    kind: if s.unbacked { "unbacked" } else { "backed" },
+                    value: s.value.clone().unwrap_or_default(),
+                    range: s.range.clone().unwrap_or_default(),
+                    sources: s.sources.join(", "),
+                    location: s.location.clone().unwrap_or_default(),
+                })
+                .collect(),
+            specializations: compile.specializations.clone(),
+            guards: compile
+                .guards
+                .iter()
+                .map(|(expr, fast, location)| ShapeGuardContext {
+                    expr: expr.clone(),
+                    kind: if *fast {
+                        "guard_added_fast"
+                    } else {
+                        "guard_added"
+                    },
+                    location: location.clone().unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The dynamic shapes page of a compile id, if it has any symbolic shape envelopes
+    pub fn page_name(&self, compile_id: &Option<CompileId>) -> Option<String> {
+        let cid = compile_id.as_ref()?;
+        self.compiles
+            .get(compile_id)
+            .filter(|c| {
+                !c.symbols.is_empty() || !c.specializations.is_empty() || !c.guards.is_empty()
+            })
+            .map(|_| format!("{}/dynamic_shapes.html", cid.as_directory_name()))
+    }
+
+    /// Build a page context for each compile id with a page, keyed by the page's filename
+    pub fn compile_pages(
+        &self,
+        css: &'static str,
+        qps: &'static str,
+    ) -> Vec<(String, DynamicShapesContext)> {
+        self.compiles
+            .iter()
+            .filter_map(|(compile_id, compile)| {
+                let page = self.page_name(compile_id)?;
+                let context = DynamicShapesContext {
+                    css,
+                    qps,
+                    title: format!("Dynamic shapes of {}", compile.compile_id),
+                    run_wide: false,
+                    compiles: vec![self.compile_context(compile, &page)],
+                    suggestions: Vec::new(),
+                    specializations: Vec::new(),
+                };
+                Some((page, context))
+            })
+            .collect()
+    }
+
+    /// Build the page context for the whole run
+    pub fn finish(&self, css: &'static str, qps: &'static str) -> DynamicShapesContext {
+        DynamicShapesContext {
+            css,
+            qps,
+            title: "Dynamic shapes".to_string(),
+            run_wide: true,
+            compiles: self
+                .compiles
+                .iter()
+                .filter_map(|(compile_id, compile)| {
+                    Some(self.compile_context(compile, &self.page_name(compile_id)?))
+                })
+                .collect(),
+            suggestions: self.suggestions(),
+            specializations: self
+                .compiles
+                .values()
+                .flat_map(|compile| {
+                    compile
+                        .specializations
+                        .iter()
+                        .map(|s| SpecializationContext {
+                            compile_id: compile.compile_id.clone(),
+                            ..s.clone()
+                        })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_expression() {
+        assert_eq!(source_expression("L['self'].weight"), "self.weight");
+        assert_eq!(source_expression("L['x']"), "x");
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let reasons = r#"["tensor 'L['x']' size mismatch at index 0. expected 8, actual 16", "tensor 'L['y']' size mismatch at index 1. expected 3, actual 4"]"#;
+        let mut shapes = DynamicShapes::default();
+        shapes.add_envelope(
+            &Envelope::for_test(
+                1,
+                r#"{"artifact": {"name": "recompile_reasons", "encoding": "json"}}"#,
+            ),
+            reasons,
+        );
+        shapes.add_envelope(
+            &Envelope::for_test(
+                1,
+                r#"{"create_symbol": {"symbol": "s0", "val": "16", "vr": "[2, int_oo]", "source": "L['x'].size()[0]"}}"#,
+            ),
+            "",
+        );
+        shapes.add_envelope(
+            &Envelope::for_test(
+                1,
+                r#"{"symbolic_shape_specialization": {"symbol": "s1", "sources": ["L['y'].size()[1]"], "value": "4", "reason": "Eq(s1, 4)"}}"#,
+            ),
+            "",
+        );
+        let suggestions = shapes.suggestions();
+        let actions: Vec<(&str, &str)> = suggestions
+            .iter()
+            .map(|s| (s.action.as_str(), s.sizes.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("torch._dynamo.mark_dynamic(x, 0)", "8, 16"),
+                ("torch._dynamo.mark_static(y, 1)", "3, 4"),
+            ]
+        );
+
+        let pages = shapes.compile_pages("", "");
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].0, "-_0_1_0/dynamic_shapes.html");
+        let symbols = &pages[0].1.compiles[0].symbols;
+        assert_eq!(symbols[0].sources, "L['x'].size()[0]");
+        assert_eq!(symbols[0].range, "[2, int_oo]");
+        assert_eq!(symbols[1].sources, "L['y'].size()[1]");
+    }
+}
@@ -0,0 +1,301 @@
+//! First divergence between ranks.
+//!
+//! The divergence groups of the landing page say that ranks disagree, not where they started
+//! to. This lines up the compile events of every rank in the order the rank logged them (the
+//! start of each compile id, cache hits and misses, recompile reasons and collective
+//! schedules) and finds the first event the ranks disagree on. For a representative of each
+//! group of ranks there, it collects what is needed to root-cause a hang: the stack of the
+//! compile, its recompile reasons and guards, and its cache status.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use fxhash::FxHashMap;
+use serde::Deserialize;
+
+use crate::rank_comparison::{comparison_page, mask_per_process};
+use crate::types::*;
+
+/// Events shown before and after the first divergence
+const CONTEXT_EVENTS: usize = 3;
+
+struct Event {
+    compile_id: String,
+    /// What is compared across ranks
+    key: String,
+    description: String,
+}
+
+/// What a rank logged for one compile id
+#[derive(Default)]
+struct Compile {
+    recompile_reasons: Vec<String>,
+    cache_status: Vec<String>,
+    guards_url: Option<String>,
+}
+
+#[derive(Default)]
+struct RankEvents {
+    events: Vec<Event>,
+    compiles: FxHashMap<String, Compile>,
+}
+
+/// The kind of an artifact from its name, e.g. `fx_graph_cache_miss` for
+/// `fx_graph_cache_miss_15.json`
+fn artifact_kind(name: &str) -> &str {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    match stem.rsplit_once('_') {
+        Some((kind, number)) if number.chars().all(|c| c.is_ascii_digit()) => kind,
+        _ => stem,
+    }
+}
+
+fn read_json_strings(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<String>>(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The events of a rank, from the artifacts of its compile_directory.json in the order they
+/// were written
+fn read_events(out_path: &Path, rank: u32) -> RankEvents {
+    let rank_dir = out_path.join(format!("rank_{rank}"));
+    let directory: serde_json::Map<String, serde_json::Value> =
+        std::fs::read_to_string(rank_dir.join("compile_directory.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+    let mut artifacts: Vec<(u64, &str, &str, &str)> = directory
+        .iter()
+        // Artifacts logged outside of a compile, e.g. the `[-/-]` graph execution records,
+        // have no frame id
+        .filter(|(compile_id, _)| {
+            !compile_id.starts_with("unknown") && compile_id.chars().any(|c| c.is_ascii_digit())
+        })
+        .flat_map(|(compile_id, entry)| {
+            entry
+                .get("artifacts")
+                .and_then(|a| a.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(move |a| {
+                    Some((
+                        a.get("number")?.as_u64()?,
+                        compile_id.as_str(),
+                        a.get("name")?.as_str()?,
+                        a.get("url")?.as_str()?,
+                    ))
+                })
+        })
+        .collect();
+    artifacts.sort_unstable();
+
+    let mut rank_events = RankEvents::default();
+    for (_, compile_id, name, url) in artifacts {
+        let RankEvents { events, compiles } = &mut rank_events;
+        let event = |key: String, description: String| Event {
+            compile_id: compile_id.to_string(),
+            key,
+            description,
+        };
+        if !compiles.contains_key(compile_id) {
+            compiles.insert(compile_id.to_string(), Compile::default());
+            events.push(event(
+                format!("compile {compile_id}"),
+                format!("Compile {compile_id} started"),
+            ));
+        }
+        let compile = compiles.get_mut(compile_id).unwrap();
+        let kind = artifact_kind(name);
+        if kind.ends_with("_cache_hit")
+            || kind.ends_with("_cache_miss")
+            || kind.ends_with("_cache_bypass")
+        {
+            compile.cache_status.push(kind.to_string());
+            events.push(event(
+                format!("{compile_id} {kind}"),
+                format!("{kind} in {compile_id}"),
+            ));
+        } else if kind == "recompile_reasons" {
+            let reasons: Vec<String> = read_json_strings(&rank_dir.join(url))
+                .iter()
+                .map(|reason| mask_per_process(reason.trim()))
+                .collect();
+            events.push(event(
+                format!("{compile_id} recompile {}", reasons.join("\n")),
+                format!("Recompile {compile_id}: {}", reasons.join("; ")),
+            ));
+            compile.recompile_reasons.extend(reasons);
+        } else if kind == "inductor_collective_schedule" {
+            let ops = read_json_strings(&rank_dir.join(url));
+            events.push(event(
+                format!("{compile_id} collectives {}", ops.join(",")),
+                format!("Collectives of {compile_id}: {}", ops.join(", ")),
+            ));
+        } else if kind == "dynamo_guards"
+            || (kind == "dynamo_cpp_guards_str" && compile.guards_url.is_none())
+        {
+            compile.guards_url = Some(format!("rank_{rank}/{url}"));
+        }
+    }
+    rank_events
+}
+
+#[derive(Deserialize)]
+struct StartEnvelope {
+    #[serde(flatten)]
+    compile_id: Option<CompileId>,
+    dynamo_start: Option<DynamoStartMetadata>,
+}
+
+/// The stack of each compile id of a rank, one frame per line, from the `dynamo_start`
+/// envelopes in its raw.jsonl
+fn read_stacks(out_path: &Path, rank: u32) -> FxHashMap<String, String> {
+    let mut stacks = FxHashMap::default();
+    let Ok(file) = File::open(out_path.join(format!("rank_{rank}")).join("raw.jsonl")) else {
+        return stacks;
+    };
+    let mut string_table: Vec<Option<String>> = Vec::new();
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if line.starts_with("{\"string_table\"") {
+            #[derive(Deserialize)]
+            struct StringTable {
+                string_table: Vec<Option<String>>,
+            }
+            if let Ok(table) = serde_json::from_str::<StringTable>(&line) {
+                string_table = table.string_table;
+            }
+            continue;
+        }
+        if !line.contains("\"dynamo_start\"") {
+            continue;
+        }
+        let Ok(StartEnvelope {
+            compile_id: Some(compile_id),
+            dynamo_start: Some(DynamoStartMetadata { stack: Some(stack) }),
+        }) = serde_json::from_str::<StartEnvelope>(&line)
+        else {
+            continue;
+        };
+        let stack = stack
+            .iter()
+            .map(|frame| {
+                let filename = frame.uninterned_filename.as_deref().or_else(|| {
+                    string_table
+                        .get(frame.filename as usize)
+                        .and_then(|s| s.as_deref())
+                });
+                format!(
+                    "{}:{} in {}{}",
+                    simplify_filename(filename.unwrap_or("(unknown)")),
+                    frame.line,
+                    frame.name,
+                    frame
+                        .loc
+                        .as_ref()
+                        .map_or(String::new(), |loc| format!("\n    {loc}"))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        stacks.insert(compile_id.to_string(), stack);
+    }
+    stacks
+}
+
+/// Find the first event the ranks disagree on. `has_rank_comparison` links each group to
+/// the rank comparison page of its compile id.
+pub fn find_first_divergence(
+    out_path: &Path,
+    rank_nums: &[u32],
+    has_rank_comparison: bool,
+) -> Option<FirstDivergence> {
+    if rank_nums.len() < 2 {
+        return None;
+    }
+    let events: Vec<RankEvents> = rank_nums
+        .iter()
+        .map(|&rank| read_events(out_path, rank))
+        .collect();
+    let longest = events.iter().map(|e| e.events.len()).max().unwrap_or(0);
+    fn key(e: &RankEvents, i: usize) -> Option<&str> {
+        e.events.get(i).map(|event| event.key.as_str())
+    }
+    let index = (0..longest).find(|&i| events.iter().any(|e| key(e, i) != key(&events[0], i)))?;
+
+    // Group ranks by their event at the divergence, None once a rank has no more events
+    let mut groups: FxIndexMap<Option<&str>, Vec<usize>> = FxIndexMap::default();
+    for (i, e) in events.iter().enumerate() {
+        groups.entry(key(e, index)).or_default().push(i);
+    }
+
+    let groups = groups
+        .into_values()
+        .map(|members| {
+            let (rank, rank_events) = (rank_nums[members[0]], &events[members[0]]);
+            let event = rank_events.events.get(index);
+            // Once a rank has no more events, describe the compile it ended with
+            let compile_id = event
+                .or(rank_events.events.last())
+                .map_or(String::new(), |e| e.compile_id.clone());
+            let compile = rank_events.compiles.get(&compile_id);
+            DivergenceGroupContext {
+                ranks: members
+                    .iter()
+                    .map(|&i| rank_nums[i].to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                rank,
+                event: event.map_or("No more compile events".to_string(), |e| {
+                    e.description.clone()
+                }),
+                stack: read_stacks(out_path, rank)
+                    .remove(&compile_id)
+                    .unwrap_or_default(),
+                recompile_reasons: compile.map_or(Vec::new(), |c| c.recompile_reasons.clone()),
+                cache_status: compile.map_or(String::new(), |c| c.cache_status.join(", ")),
+                guards_url: compile.and_then(|c| c.guards_url.clone()),
+                comparison_url: (has_rank_comparison && !compile_id.is_empty())
+                    .then(|| comparison_page(&compile_id)),
+                context: (index.saturating_sub(CONTEXT_EVENTS)
+                    ..(index + CONTEXT_EVENTS + 1).min(rank_events.events.len()))
+                    .map(|i| DivergenceEventContext {
+                        number: i + 1,
+                        description: rank_events.events[i].description.clone(),
+                        divergent: i == index,
+                    })
+                    .collect(),
+                compile_id,
+            }
+        })
+        .collect();
+    Some(FirstDivergence {
+        number: index + 1,
+        groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_kind() {
+        assert_eq!(
+            artifact_kind("fx_graph_cache_miss_15.json"),
+            "fx_graph_cache_miss"
+        );
+        assert_eq!(
+            artifact_kind("recompile_reasons_19.json"),
+            "recompile_reasons"
+        );
+        assert_eq!(
+            artifact_kind("inductor_output_code_cvklj7mq_11.html"),
+            "inductor_output_code_cvklj7mq"
+        );
+        assert_eq!(artifact_kind("recompiles_-_0.html"), "recompiles_-");
+    }
+}
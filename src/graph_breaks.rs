@@ -0,0 +1,379 @@
+//! Graph break leaderboard.
+//!
+//! `failures_and_restarts.html` lists every restart and compilation failure separately. This
+//! groups them by normalised reason and by the user source location they happened at, so the
+//! breaks that cost the most stand out, and marks those locations in the stack trie.
+
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::types::*;
+
+static HEX_ADDRESS: Lazy<Regex> = Lazy::new(|| Regex::new(r"0x[0-9a-fA-F]+").unwrap());
+static NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d+\b").unwrap());
+static WHITESPACE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
+/// Strip the parts of a break reason that differ between otherwise identical breaks (object
+/// addresses, line numbers, sizes, ...) so that they group together
+pub fn normalize_reason(reason: &str) -> String {
+    let reason = HEX_ADDRESS.replace_all(reason, "0x…");
+    let reason = NUMBER.replace_all(&reason, "N");
+    WHITESPACE.replace_all(reason.trim(), " ").into_owned()
+}
+
+/// Index of the innermost frame of `stack` that is user code rather than PyTorch itself,
+/// falling back to the innermost frame
+fn user_frame_index(stack: &StackSummary) -> Option<usize> {
+    let is_torch = |frame: &FrameSummary| {
+        let filename = frame
+            .uninterned_filename
+            .clone()
+            .unwrap_or_else(|| unintern_str(frame.filename));
+        filename.contains("/torch/")
+    };
+    stack
+        .iter()
+        .rposition(|frame| !is_torch(frame))
+        .or(stack.len().checked_sub(1))
+}
+
+pub fn frame_location(frame: &FrameSummary) -> String {
+    let filename = frame
+        .uninterned_filename
+        .clone()
+        .unwrap_or_else(|| unintern_str(frame.filename));
+    format!("{}:{}", simplify_filename(&filename), frame.line)
+}
+
+struct GraphBreak {
+    // "Restart", or the failure type
+    kind: String,
+    reason: String,
+    location: Option<String>,
+    compile_id: String,
+    compile_id_html: String,
+    // Where to mark the break in the stack trie: the compile's stack, up to and including
+    // the break's frame
+    trie_path: Option<StackSummary>,
+    // The wasted time of a compilation_metrics is charged to its first break only
+    wasted_time_s: f64,
+}
+
+#[derive(Default)]
+pub struct GraphBreakLeaderboard {
+    breaks: Vec<GraphBreak>,
+}
+
+impl GraphBreakLeaderboard {
+    pub fn is_empty(&self) -> bool {
+        self.breaks.is_empty()
+    }
+
+    /// Record the restarts and failure of one compilation_metrics envelope. `compile_id_html`
+    /// links to its metrics page.
+    pub fn add(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        compile_id_html: &str,
+        m: &CompilationMetricsMetadata,
+        stack_index: &StackIndex,
+    ) {
+        // Stacks are indexed under attempt 0, see CompilationMetricsParser
+        let mut cid = compile_id.clone();
+        if let Some(c) = cid.as_mut() {
+            if c.frame_compile_id.is_some() {
+                c.attempt = Some(0);
+            }
+        }
+        let stack = stack_index.get(&cid);
+        let user_frame = stack.and_then(|s| user_frame_index(s).map(|i| (s, i)));
+        let mut wasted_time_s = m.dynamo_time_before_restart_s.unwrap_or(0.0);
+        let compile_id = compile_id
+            .as_ref()
+            .map_or("(unknown)".to_string(), |c| c.to_string());
+
+        for reason in m.restart_reasons.iter().flatten() {
+            self.breaks.push(GraphBreak {
+                kind: "Restart".to_string(),
+                reason: reason.clone(),
+                location: user_frame.map(|(s, i)| frame_location(&s[i])),
+                compile_id: compile_id.clone(),
+                compile_id_html: compile_id_html.to_string(),
+                trie_path: user_frame.map(|(s, i)| s[..=i].to_vec()),
+                wasted_time_s: std::mem::take(&mut wasted_time_s),
+            });
+        }
+        if let Some(fail_type) = &m.fail_type {
+            let reported = m
+                .fail_user_frame_filename
+                .as_ref()
+                .zip(m.fail_user_frame_lineno)
+                .map(|(filename, lineno)| (simplify_filename(filename).to_string(), lineno));
+            let location = match &reported {
+                Some((filename, lineno)) => Some(format!("{}:{}", filename, lineno)),
+                None => user_frame.map(|(s, i)| frame_location(&s[i])),
+            };
+            // Mark the reported frame if it is on the compile's stack, otherwise the frame
+            // that was being compiled
+            let trie_path = stack.filter(|s| !s.is_empty()).map(|s| {
+                let end = location
+                    .as_ref()
+                    .and_then(|l| s.iter().rposition(|frame| &frame_location(frame) == l))
+                    .unwrap_or(s.len() - 1);
+                s[..=end].to_vec()
+            });
+            self.breaks.push(GraphBreak {
+                kind: fail_type.clone(),
+                reason: m.fail_reason.clone().unwrap_or_default(),
+                location,
+                compile_id: compile_id.clone(),
+                compile_id_html: compile_id_html.to_string(),
+                trie_path,
+                wasted_time_s,
+            });
+        }
+    }
+
+    /// The breaks with a user source location, as (location, compile id, compile id link, kind,
+    /// reason)
+    pub fn located(&self) -> impl Iterator<Item = (&str, &str, &str, &str, &str)> {
+        self.breaks.iter().filter_map(|b| {
+            Some((
+                b.location.as_deref()?,
+                b.compile_id.as_str(),
+                b.compile_id_html.as_str(),
+                b.kind.as_str(),
+                b.reason.as_str(),
+            ))
+        })
+    }
+
+    /// Group the breaks by kind, normalised reason and location. Groups are sorted by number
+    /// of breaks, then by wasted time, and ranked from 1 in that order. Also returns the rank
+    /// of each break's group.
+    fn group(&self) -> (Vec<GraphBreakGroup>, Vec<usize>) {
+        let mut groups: Vec<GraphBreakGroup> = Vec::new();
+        let mut lookup: FxHashMap<(&str, String, Option<&str>), usize> = FxHashMap::default();
+        let mut group_of = Vec::with_capacity(self.breaks.len());
+        for b in &self.breaks {
+            let normalized = normalize_reason(&b.reason);
+            let key = (b.kind.as_str(), normalized.clone(), b.location.as_deref());
+            let g = *lookup.entry(key).or_insert_with(|| {
+                groups.push(GraphBreakGroup {
+                    rank: 0,
+                    kind: b.kind.clone(),
+                    reason: normalized,
+                    example: b.reason.clone(),
+                    location: b.location.clone().unwrap_or("(unknown)".to_string()),
+                    compile_ids: Vec::new(),
+                    count: 0,
+                    wasted_time_s: 0.0,
+                });
+                groups.len() - 1
+            });
+            let group = &mut groups[g];
+            group.count += 1;
+            if !group.compile_ids.contains(&b.compile_id_html) {
+                group.compile_ids.push(b.compile_id_html.clone());
+            }
+            group.wasted_time_s += b.wasted_time_s;
+            group_of.push(g);
+        }
+
+        let mut order: Vec<usize> = (0..groups.len()).collect();
+        order.sort_by(|&a, &b| {
+            by_cost(
+                (groups[a].count, groups[a].wasted_time_s),
+                (groups[b].count, groups[b].wasted_time_s),
+            )
+        });
+        let mut rank_of = vec![0; groups.len()];
+        for (i, &g) in order.iter().enumerate() {
+            rank_of[g] = i + 1;
+        }
+        for (g, group) in groups.iter_mut().enumerate() {
+            group.rank = rank_of[g];
+        }
+        groups.sort_by_key(|group| group.rank);
+        (groups, group_of.into_iter().map(|g| rank_of[g]).collect())
+    }
+
+    /// Build the leaderboard page context and mark each group's location in `stack_trie`
+    pub fn finish(
+        &self,
+        stack_trie: &mut StackTrieNode,
+        css: &'static str,
+        qps: &'static str,
+    ) -> GraphBreaksContext {
+        let (groups, rank_of_break) = self.group();
+
+        for (b, &rank) in self.breaks.iter().zip(&rank_of_break) {
+            if let Some(path) = &b.trie_path {
+                let group = &groups[rank - 1];
+                stack_trie.mark(
+                    path,
+                    format!(
+                        "<a href='graph_breaks.html#break-{rank}' class='break-marker' title='#{rank}: {count} × {kind}: {reason}'>⚠{rank}</a>",
+                        count = group.count,
+                        kind = html_escape::encode_single_quoted_attribute(&group.kind),
+                        reason = html_escape::encode_single_quoted_attribute(&group.reason),
+                    ),
+                );
+            }
+        }
+
+        let mut locations: Vec<(GraphBreakLocationContext, f64)> = Vec::new();
+        let mut location_lookup: FxHashMap<&str, usize> = FxHashMap::default();
+        for group in &groups {
+            let i = *location_lookup
+                .entry(group.location.as_str())
+                .or_insert_with(|| {
+                    locations.push((
+                        GraphBreakLocationContext {
+                            location: group.location.clone(),
+                            count: 0,
+                            groups: Vec::new(),
+                            wasted_time_s: String::new(),
+                        },
+                        0.0,
+                    ));
+                    locations.len() - 1
+                });
+            let (location, wasted_time_s) = &mut locations[i];
+            location.count += group.count;
+            location.groups.push(group.rank);
+            *wasted_time_s += group.wasted_time_s;
+        }
+        locations
+            .sort_by(|(a, a_time), (b, b_time)| by_cost((a.count, *a_time), (b.count, *b_time)));
+
+        GraphBreaksContext {
+            css,
+            qps,
+            num_breaks: self.breaks.len(),
+            groups: groups
+                .into_iter()
+                .map(|g| GraphBreakGroupContext {
+                    rank: g.rank,
+                    kind: g.kind,
+                    reason: g.reason,
+                    example: g.example,
+                    location: g.location,
+                    count: g.count,
+                    compile_ids: g.compile_ids,
+                    wasted_time_s: format_seconds(g.wasted_time_s),
+                })
+                .collect(),
+            locations: locations
+                .into_iter()
+                .map(|(mut location, wasted_time_s)| {
+                    location.wasted_time_s = format_seconds(wasted_time_s);
+                    location
+                })
+                .collect(),
+        }
+    }
+}
+
+struct GraphBreakGroup {
+    rank: usize,
+    kind: String,
+    reason: String,
+    example: String,
+    location: String,
+    compile_ids: Vec<String>,
+    count: usize,
+    wasted_time_s: f64,
+}
+
+// Orders (count, wasted time) pairs most costly first: most breaks, then most wasted time
+fn by_cost(a: (usize, f64), b: (usize, f64)) -> std::cmp::Ordering {
+    b.0.cmp(&a.0).then(b.1.total_cmp(&a.1))
+}
+
+fn format_seconds(s: f64) -> String {
+    format!("{:.3}", s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_reason() {
+        assert_eq!(
+            normalize_reason("call_function <object at 0x7f3a2b> on line 12\n  size [3, 4]"),
+            "call_function <object at 0x…> on line N size [N, N]"
+        );
+    }
+
+    #[test]
+    fn test_grouping() {
+        let mut leaderboard = GraphBreakLeaderboard::default();
+        let restart = |reason: &str, time| CompilationMetricsMetadata {
+            restart_reasons: Some(vec![reason.to_string()]),
+            dynamo_time_before_restart_s: Some(time),
+            ..Default::default()
+        };
+        let stack_index = StackIndex::default();
+        leaderboard.add(&None, "a", &restart("break at line 1", 1.0), &stack_index);
+        leaderboard.add(&None, "b", &restart("break at line 2", 2.0), &stack_index);
+        leaderboard.add(&None, "c", &restart("other", 5.0), &stack_index);
+        leaderboard.add(
+            &None,
+            "d",
+            &CompilationMetricsMetadata {
+                fail_type: Some("BackendCompilerFailed".to_string()),
+                fail_reason: Some("boom".to_string()),
+                fail_user_frame_filename: Some("/workspace/model.py".to_string()),
+                fail_user_frame_lineno: Some(7),
+                ..Default::default()
+            },
+            &stack_index,
+        );
+
+        let mut trie = StackTrieNode::default();
+        let ctx = leaderboard.finish(&mut trie, "", "");
+        assert_eq!(ctx.num_breaks, 4);
+        assert_eq!(ctx.groups[0].reason, "break at line N");
+        assert_eq!(ctx.groups[0].count, 2);
+        assert_eq!(ctx.groups[0].compile_ids, vec!["a", "b"]);
+        assert_eq!(ctx.groups[0].wasted_time_s, "3.000");
+        // Ties on count are broken by wasted time
+        assert_eq!(ctx.groups[1].reason, "other");
+        assert_eq!(ctx.groups[2].kind, "BackendCompilerFailed");
+        assert_eq!(ctx.groups[2].location, "/workspace/model.py:7");
+        assert_eq!(ctx.locations[0].location, "(unknown)");
+        assert_eq!(ctx.locations[0].count, 3);
+    }
+
+    #[test]
+    fn test_wasted_time_charged_once() {
+        let mut leaderboard = GraphBreakLeaderboard::default();
+        leaderboard.add(
+            &None,
+            "a",
+            &CompilationMetricsMetadata {
+                restart_reasons: Some(vec!["first".to_string(), "second".to_string()]),
+                dynamo_time_before_restart_s: Some(4.0),
+                fail_type: Some("BackendCompilerFailed".to_string()),
+                fail_reason: Some("boom".to_string()),
+                ..Default::default()
+            },
+            &StackIndex::default(),
+        );
+
+        let mut trie = StackTrieNode::default();
+        let ctx = leaderboard.finish(&mut trie, "", "");
+        assert_eq!(ctx.num_breaks, 3);
+        let wasted: Vec<&str> = ctx
+            .groups
+            .iter()
+            .map(|g| g.wasted_time_s.as_str())
+            .collect();
+        assert_eq!(wasted, vec!["4.000", "0.000", "0.000"]);
+        assert_eq!(ctx.groups[0].reason, "first");
+        assert_eq!(ctx.locations[0].wasted_time_s, "4.000");
+    }
+}
@@ -0,0 +1,494 @@
+//! Graph size metrics.
+//!
+//! FX graphs are logged as the code `GraphModule.print_readable` generates, one statement per
+//! node. This reads the nodes back out of that code to count them by opcode and call target,
+//! for each graph of each compile id and across the run.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::types::*;
+
+/// How many call targets are listed for a single graph, and for the whole run
+const GRAPH_TOP_TARGETS: usize = 5;
+const RUN_TOP_TARGETS: usize = 50;
+
+const OPCODES: &[&str] = &[
+    "placeholder",
+    "get_attr",
+    "call_function",
+    "call_method",
+    "call_module",
+    "output",
+];
+
+// The graphs logged for a compile id, in the order they are compiled
+const GRAPH_KINDS: &[&str] = &[
+    "dynamo_output_graph",
+    "inductor_pre_grad_graph",
+    "aot_joint_graph",
+    "aot_forward_graph",
+    "aot_backward_graph",
+    "aot_inference_graph",
+    "inductor_post_grad_graph",
+];
+
+/// The kind of FX graph an envelope logs, if it logs one
+pub fn graph_kind(e: &Envelope) -> Option<&'static str> {
+    [
+        e.dynamo_output_graph.is_some(),
+        e.inductor_pre_grad_graph.is_some(),
+        e.aot_joint_graph.is_some(),
+        e.aot_forward_graph.is_some(),
+        e.aot_backward_graph.is_some(),
+        e.aot_inference_graph.is_some(),
+        e.inductor_post_grad_graph.is_some(),
+    ]
+    .iter()
+    .position(|&is_kind| is_kind)
+    .map(|i| GRAPH_KINDS[i])
+}
+
+// `name: "f32[4]" = rhs;  a = b = None`
+static ASSIGNMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(?<name>\w+)(?:: "[^"]*")? = (?<rhs>.*?)(?:;\s+(?:\w+ = )+None)?$"#).unwrap()
+});
+static CALL: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?<callee>[\w.]+)\(").unwrap());
+static SUBSCRIPT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\w+\[").unwrap());
+static BINARY_OP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[\w.]+ (?<op>\*\*|//|<<|>>|==|!=|<=|>=|[-+*/%@&|^<>]) [\w.]+$").unwrap()
+});
+static UNARY_OP: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?<op>[-~])\w+$").unwrap());
+
+fn operator_name(op: &str) -> &'static str {
+    match op {
+        "+" => "add",
+        "-" => "sub",
+        "*" => "mul",
+        "/" => "truediv",
+        "//" => "floordiv",
+        "%" => "mod",
+        "**" => "pow",
+        "@" => "matmul",
+        "&" => "and_",
+        "|" => "or_",
+        "^" => "xor",
+        "<<" => "lshift",
+        ">>" => "rshift",
+        "==" => "eq",
+        "!=" => "ne",
+        "<" => "lt",
+        "<=" => "le",
+        ">" => "gt",
+        ">=" => "ge",
+        _ => "unknown",
+    }
+}
+
+/// Split `s` at the commas that aren't nested in brackets or strings
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// Node counts of a graph
+#[derive(Debug, Default, PartialEq)]
+struct GraphSummary {
+    // By opcode, in the order of OPCODES
+    opcodes: [usize; 6],
+    // Call target -> count, for call_function, call_method and call_module nodes
+    targets: FxIndexMap<String, usize>,
+    outputs: usize,
+    // Nested graphs, e.g. the bodies of higher order ops
+    subgraphs: usize,
+}
+
+impl GraphSummary {
+    fn num_nodes(&self) -> usize {
+        self.opcodes.iter().sum()
+    }
+
+    fn add_node(&mut self, opcode: &str, target: Option<String>) {
+        if let Some(i) = OPCODES.iter().position(|&o| o == opcode) {
+            self.opcodes[i] += 1;
+        }
+        if let Some(target) = target {
+            *self.targets.entry(target).or_default() += 1;
+        }
+    }
+}
+
+/// Count the nodes of the outermost `forward` of a graph printed by `print_readable`
+fn summarize_graph(payload: &str) -> GraphSummary {
+    let mut summary = GraphSummary::default();
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = payload.lines();
+    let Some(forward_indent) = lines.by_ref().find_map(|line| {
+        let trimmed = line.trim_start();
+        trimmed.starts_with("def forward(").then(|| {
+            let params = trimmed
+                .strip_prefix("def forward(")
+                .and_then(|p| p.rsplit_once(')'))
+                .map_or("", |(p, _)| p);
+            let mut locals: Vec<String> = Vec::new();
+            for param in split_top_level(params).into_iter().skip(1) {
+                let name = param.split(':').next().unwrap_or("").trim();
+                summary.add_node("placeholder", None);
+                locals.push(name.trim_start_matches('*').to_string());
+            }
+            (indent(line), locals)
+        })
+    }) else {
+        return summary;
+    };
+    let (forward_indent, mut locals) = forward_indent;
+
+    let mut in_forward = true;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if indent(line) <= forward_indent {
+            in_forward = false;
+        }
+        if trimmed.starts_with("class ") && trimmed.ends_with("(torch.nn.Module):") {
+            summary.subgraphs += 1;
+        }
+        if !in_forward || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(returned) = trimmed.strip_prefix("return") {
+            let returned = returned.trim();
+            summary.add_node("output", None);
+            summary.outputs = match returned.strip_prefix('(') {
+                Some(tuple) => split_top_level(tuple.strip_suffix(')').unwrap_or(tuple)).len(),
+                None if returned.is_empty() || returned == "None" => 0,
+                None => 1,
+            };
+            continue;
+        }
+        let Some(caps) = ASSIGNMENT.captures(trimmed) else {
+            continue;
+        };
+        let rhs = &caps["rhs"];
+        let is_local = |name: &str| locals.iter().any(|l| l == name);
+        if rhs.starts_with("self.") && !rhs.contains('(') {
+            summary.add_node("get_attr", None);
+        } else if let Some(call) = CALL.captures(rhs) {
+            let callee = &call["callee"];
+            match callee.split_once('.') {
+                Some(("self", module)) => {
+                    summary.add_node("call_module", Some(format!("self.{}", module)))
+                }
+                Some((receiver, method)) if is_local(receiver) => {
+                    summary.add_node("call_method", Some(format!("Tensor.{}", method)))
+                }
+                _ => summary.add_node(
+                    "call_function",
+                    Some(
+                        callee
+                            .strip_prefix("torch.ops.")
+                            .unwrap_or(callee)
+                            .to_string(),
+                    ),
+                ),
+            }
+        } else if SUBSCRIPT.is_match(rhs) {
+            summary.add_node("call_function", Some("operator.getitem".to_string()));
+        } else if let Some(op) = BINARY_OP.captures(rhs) {
+            summary.add_node(
+                "call_function",
+                Some(format!("operator.{}", operator_name(&op["op"]))),
+            );
+        } else if let Some(op) = UNARY_OP.captures(rhs) {
+            let name = if &op["op"] == "-" { "neg" } else { "invert" };
+            summary.add_node("call_function", Some(format!("operator.{}", name)));
+        } else if is_local(rhs) || rhs.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            // Renames of placeholders, e.g. `l_x_ = L_x_`, aren't nodes
+        } else {
+            summary.add_node("call_function", Some("(other)".to_string()));
+        }
+        locals.push(caps["name"].to_string());
+    }
+    summary
+}
+
+fn format_size(size: &Value) -> String {
+    match size {
+        Value::Array(dims) => format!(
+            "[{}]",
+            dims.iter().map(format_size).collect::<Vec<_>>().join(", ")
+        ),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+struct Graph {
+    kind: &'static str,
+    summary: GraphSummary,
+    sizes: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct CompileGraphs {
+    compile_id: String,
+    graphs: Vec<Graph>,
+}
+
+#[derive(Default)]
+pub struct GraphStats {
+    compiles: FxIndexMap<Option<CompileId>, CompileGraphs>,
+}
+
+/// The most common call targets of `graphs`, with the share of their call nodes
+fn op_counts<'a>(graphs: impl Iterator<Item = &'a Graph>, limit: usize) -> Vec<OpCountContext> {
+    let mut counts: FxIndexMap<&str, (usize, usize)> = FxIndexMap::default();
+    for graph in graphs {
+        for (target, count) in &graph.summary.targets {
+            let (total, num_graphs) = counts.entry(target).or_default();
+            *total += count;
+            *num_graphs += 1;
+        }
+    }
+    let num_calls = counts
+        .values()
+        .fold(0, |sum, (count, _)| sum + count)
+        .max(1);
+    let mut counts: Vec<(&str, (usize, usize))> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .take(limit)
+        .map(|(target, (count, num_graphs))| OpCountContext {
+            target: target.to_string(),
+            count,
+            num_graphs,
+            share: format!("{:.1}%", 100.0 * count as f64 / num_calls as f64),
+        })
+        .collect()
+}
+
+impl GraphStats {
+    pub fn is_empty(&self) -> bool {
+        self.compiles.is_empty()
+    }
+
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str) {
+        let Some(kind) = graph_kind(e) else {
+            return;
+        };
+        let sizes = e
+            .dynamo_output_graph
+            .as_ref()
+            .and_then(|m| m.sizes.as_ref())
+            .map_or(Vec::new(), |sizes| {
+                sizes
+                    .iter()
+                    .map(|(name, size)| (name.clone(), format_size(size)))
+                    .collect()
+            });
+        self.compiles
+            .entry(e.compile_id.clone())
+            .or_insert_with(|| CompileGraphs {
+                compile_id: e
+                    .compile_id
+                    .as_ref()
+                    .map_or("(unknown)".to_string(), |c| c.to_string()),
+                ..Default::default()
+            })
+            .graphs
+            .push(Graph {
+                kind,
+                summary: summarize_graph(payload),
+                sizes,
+            });
+    }
+
+    /// The graph stats page of a compile id
+    pub fn page_name(&self, compile_id: &Option<CompileId>) -> Option<String> {
+        let cid = compile_id.as_ref()?;
+        self.compiles
+            .contains_key(compile_id)
+            .then(|| format!("{}/graph_stats.html", cid.as_directory_name()))
+    }
+
+    fn graph_contexts(&self, compile: &CompileGraphs) -> Vec<GraphSummaryContext> {
+        compile
+            .graphs
+            .iter()
+            .map(|g| GraphSummaryContext {
+                kind: g.kind,
+                opcodes: g.summary.opcodes.to_vec(),
+                num_nodes: g.summary.num_nodes(),
+                inputs: g.summary.opcodes[0],
+                outputs: g.summary.outputs,
+                subgraphs: g.summary.subgraphs,
+                top_targets: op_counts(std::iter::once(g), GRAPH_TOP_TARGETS)
+                    .into_iter()
+                    .map(|op| format!("{} ({})", op.target, op.count))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                targets: op_counts(std::iter::once(g), usize::MAX),
+                sizes: g
+                    .sizes
+                    .iter()
+                    .map(|(name, size)| format!("{}: {}", name, size))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Build a page context for each compile id, keyed by the page's filename
+    pub fn compile_pages(
+        &self,
+        css: &'static str,
+        qps: &'static str,
+    ) -> Vec<(String, CompileGraphStatsContext)> {
+        self.compiles
+            .iter()
+            .filter_map(|(compile_id, compile)| {
+                let context = CompileGraphStatsContext {
+                    css,
+                    qps,
+                    compile_id: compile.compile_id.clone(),
+                    opcodes: OPCODES,
+                    graphs: self.graph_contexts(compile),
+                };
+                Some((self.page_name(compile_id)?, context))
+            })
+            .collect()
+    }
+
+    /// Build the summary of the whole run
+    pub fn finish(&self, css: &'static str, qps: &'static str) -> GraphStatsContext {
+        let graphs_of = |kind: &'static str| {
+            self.compiles
+                .values()
+                .flat_map(|c| &c.graphs)
+                .filter(move |g| g.kind == kind)
+        };
+        let kinds = GRAPH_KINDS
+            .iter()
+            .filter(|&&kind| graphs_of(kind).next().is_some())
+            .map(|&kind| {
+                let num_graphs = graphs_of(kind).count();
+                let num_nodes = graphs_of(kind).fold(0, |sum, g| sum + g.summary.num_nodes());
+                GraphKindContext {
+                    kind,
+                    num_graphs,
+                    num_nodes,
+                    mean_nodes: format!("{:.1}", num_nodes as f64 / num_graphs as f64),
+                    max_nodes: graphs_of(kind)
+                        .map(|g| g.summary.num_nodes())
+                        .max()
+                        .unwrap_or(0),
+                    targets: op_counts(graphs_of(kind), RUN_TOP_TARGETS),
+                }
+            })
+            .collect();
+        let compiles = self
+            .compiles
+            .iter()
+            .filter_map(|(compile_id, compile)| {
+                Some(CompileGraphSizesContext {
+                    compile_id: compile.compile_id.clone(),
+                    page: self.page_name(compile_id)?,
+                    // Nodes per graph kind, in the order of GRAPH_KINDS
+                    num_nodes: GRAPH_KINDS
+                        .iter()
+                        .map(|kind| {
+                            compile
+                                .graphs
+                                .iter()
+                                .filter(|g| g.kind == *kind)
+                                .map(|g| g.summary.num_nodes().to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        GraphStatsContext {
+            css,
+            qps,
+            graph_kinds: GRAPH_KINDS,
+            run_top_targets: RUN_TOP_TARGETS,
+            kinds,
+            compiles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_graph() {
+        let payload = r#"class GraphModule(torch.nn.Module):
+    def forward(self, L_x_: "f32[8, 4][4, 1]cpu", L_y_: "f32[4][1]cpu"):
+        l_x_ = L_x_
+        l_y_ = L_y_
+
+         # File: model.py:3 in forward, code: z = torch.sin(x) + y
+        sin: "f32[8, 4][4, 1]cpu" = torch.sin(l_x_);  l_x_ = None
+        add: "f32[8, 4][4, 1]cpu" = sin + l_y_;  sin = l_y_ = None
+        weight = self.weight
+        linear = self.linear(add)
+        sum_1: "f32[][]cpu" = add.sum()
+        neg = -sum_1
+        mm = torch.ops.aten.mm.default(add, weight)
+        getitem = mm[0]
+        return (add, sum_1)
+
+    class body_0(torch.nn.Module):
+        def forward(self, child: "f32[][]cpu"):
+            sin: "f32[][]cpu" = torch.sin(child)
+            return sin
+"#;
+        let summary = summarize_graph(payload);
+        assert_eq!(summary.opcodes, [2, 1, 5, 1, 1, 1]);
+        assert_eq!(summary.outputs, 2);
+        assert_eq!(summary.subgraphs, 1);
+        let targets: Vec<(&str, usize)> = summary
+            .targets
+            .iter()
+            .map(|(t, n)| (t.as_str(), *n))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("torch.sin", 1),
+                ("operator.add", 1),
+                ("self.linear", 1),
+                ("Tensor.sum", 1),
+                ("operator.neg", 1),
+                ("aten.mm.default", 1),
+                ("operator.getitem", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! Guard analytics.
+//!
+//! Sorts guard code into categories and aggregates guards across the run: counts per category
+//! for each compile id, compile ids with unusually many guards, guards that are installed by
+//! many compiles, and the user code the guards come from. Also renders the C++ guard tree of
+//! `dynamo_cpp_guards_str` as a collapsible, searchable page.
+
+use fxhash::FxHashMap;
+use html_escape::encode_text;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::graph_breaks::frame_location;
+use crate::types::*;
+
+static SHAPE_EXPR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\.(size|stride)\(\)\[\d+\]|\.storage_offset\(\)|\b[su]\d+\b").unwrap()
+});
+
+/// Compiles with at least this many guards (or shape env guards) are always flagged
+const LARGE_GUARD_COUNT: u64 = 1000;
+const LARGE_SHAPE_ENV_GUARD_COUNT: u64 = 100;
+/// Otherwise counts are flagged when they are more than twice the run's median and at least
+/// this large
+const MIN_UNUSUAL_GUARD_COUNT: u64 = 100;
+const MIN_UNUSUAL_SHAPE_ENV_GUARD_COUNT: u64 = 20;
+/// The most shared guards listed on the analytics page
+const IDENTICAL_GUARDS_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardCategory {
+    TensorMatch,
+    IdMatch,
+    TypeMatch,
+    ShapeEnv,
+    GlobalState,
+    DictKeys,
+    Constant,
+    Length,
+    Attribute,
+    Aliasing,
+    Other,
+}
+
+const NUM_CATEGORIES: usize = GuardCategory::ALL.len();
+
+impl GuardCategory {
+    pub const ALL: [GuardCategory; 11] = [
+        GuardCategory::TensorMatch,
+        GuardCategory::IdMatch,
+        GuardCategory::TypeMatch,
+        GuardCategory::ShapeEnv,
+        GuardCategory::GlobalState,
+        GuardCategory::DictKeys,
+        GuardCategory::Constant,
+        GuardCategory::Length,
+        GuardCategory::Attribute,
+        GuardCategory::Aliasing,
+        GuardCategory::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GuardCategory::TensorMatch => "Tensor match",
+            GuardCategory::IdMatch => "Id match",
+            GuardCategory::TypeMatch => "Type match",
+            GuardCategory::ShapeEnv => "Shape env",
+            GuardCategory::GlobalState => "Global state",
+            GuardCategory::DictKeys => "Dict keys",
+            GuardCategory::Constant => "Constant",
+            GuardCategory::Length => "Length",
+            GuardCategory::Attribute => "Attribute",
+            GuardCategory::Aliasing => "Tensor aliasing",
+            GuardCategory::Other => "Other",
+        }
+    }
+
+    fn index(self) -> usize {
+        GuardCategory::ALL.iter().position(|&c| c == self).unwrap()
+    }
+}
+
+/// Categorise a guard, given either its Python code from `dynamo_guards` or a leaf of the
+/// `dynamo_cpp_guards_str` tree such as `TENSOR_MATCH: check_tensor(...)`
+pub fn categorize(code: &str) -> GuardCategory {
+    let code = code.trim();
+    let (kind, rest) = code.split_once(": ").unwrap_or((code, ""));
+    if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+        match kind {
+            "TENSOR_MATCH" => return GuardCategory::TensorMatch,
+            "ID_MATCH" => return GuardCategory::IdMatch,
+            "TYPE_MATCH" => return GuardCategory::TypeMatch,
+            "DICT_CONTAINS" | "DICT_KEYS_MATCH" | "DICT_VERSION" => return GuardCategory::DictKeys,
+            "GLOBAL_STATE"
+            | "DEFAULT_DEVICE"
+            | "TORCH_FUNCTION_MODE_STACK"
+            | "GRAD_MODE"
+            | "DETERMINISTIC_ALGORITHMS"
+            | "FUNCTORCH_STACK_MATCH"
+            | "DUAL_LEVEL" => return GuardCategory::GlobalState,
+            "EQUALS_MATCH" => return GuardCategory::Constant,
+            "LENGTH_CHECK" | "DICT_LENGTH" => return GuardCategory::Length,
+            "HASATTR" | "NO_HASATTR" => return GuardCategory::Attribute,
+            "NO_TENSOR_ALIASING" => return GuardCategory::Aliasing,
+            // e.g. LAMBDA_GUARD, which wraps arbitrary Python code
+            _ if !rest.is_empty() => return categorize(rest),
+            _ => {}
+        }
+    }
+
+    if code.contains("check_tensor(") || code.contains("___check_tensors") {
+        GuardCategory::TensorMatch
+    } else if code.contains("check_no_aliasing") {
+        GuardCategory::Aliasing
+    } else if code.contains("___check_obj_id(") {
+        GuardCategory::IdMatch
+    } else if code.contains("___check_type_id(") || code.contains("isinstance(") {
+        GuardCategory::TypeMatch
+    } else if code.contains("___dict_contains(")
+        || code.contains(".keys()")
+        || code.contains("___dict_version")
+        || code.contains("___key_to_id")
+    {
+        GuardCategory::DictKeys
+    } else if code.contains("___check_global_state")
+        || code.contains("___check_torch_function_mode_stack")
+        || code.contains("___check_current_backend")
+        || code.contains("___is_grad_enabled")
+        || code.contains("CURRENT_DEVICE")
+        || code.starts_with("torch._")
+    {
+        GuardCategory::GlobalState
+    } else if code.contains("hasattr(") {
+        GuardCategory::Attribute
+    } else if code.starts_with("len(") {
+        GuardCategory::Length
+    } else if SHAPE_EXPR.is_match(code) {
+        GuardCategory::ShapeEnv
+    } else if code.contains(" == ") || code.contains(" is ") {
+        GuardCategory::Constant
+    } else {
+        GuardCategory::Other
+    }
+}
+
+/// The guards of one compile, grouped by category in `GuardCategory::ALL` order
+pub fn group_by_category(guards: &[DynamoGuard]) -> Vec<GuardCategoryContext> {
+    let mut groups: Vec<GuardCategoryContext> = GuardCategory::ALL
+        .iter()
+        .map(|c| GuardCategoryContext {
+            name: c.name(),
+            id: c.name().to_lowercase().replace(' ', "-"),
+            count: 0,
+            guards: Vec::new(),
+        })
+        .collect();
+    for guard in guards {
+        let group = &mut groups[categorize(&guard.code).index()];
+        group.count += 1;
+        group.guards.push(guard.code.clone());
+    }
+    groups.retain(|g| g.count > 0);
+    groups
+}
+
+/// A node of the `dynamo_cpp_guards_str` tree: a guard manager, or a leaf guard
+#[derive(Debug)]
+pub struct CppGuardNode {
+    pub text: String,
+    // The trailing `# ...` comment, usually the user code that installed the guard
+    pub comment: Option<String>,
+    pub children: Vec<CppGuardNode>,
+}
+
+impl CppGuardNode {
+    /// The kind of a guard manager: the last of the leading `...Manager` segments, e.g.
+    /// `GuardManager` for `ValueManager: GuardManager: source=...` and `KeyValueManager` for
+    /// `KeyValueManager pair at index=1`. None for a leaf guard.
+    pub fn manager_kind(&self) -> Option<&str> {
+        self.text
+            .split(':')
+            .map_while(|segment| {
+                segment
+                    .split_whitespace()
+                    .next()
+                    .filter(|word| word.ends_with("Manager"))
+            })
+            .last()
+    }
+
+    pub fn is_manager(&self) -> bool {
+        !self.children.is_empty() || self.manager_kind().is_some()
+    }
+
+    /// Number of leaf guards in this subtree
+    pub fn num_guards(&self) -> usize {
+        if self.is_manager() {
+            self.children.iter().map(CppGuardNode::num_guards).sum()
+        } else {
+            1
+        }
+    }
+
+    /// The source expression a guard manager guards, e.g. `L['x']`
+    pub fn source(&self) -> Option<&str> {
+        let source = self.text.split_once("source=")?.1;
+        Some(source.split(", accessed_by=").next().unwrap_or(source))
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a CppGuardNode>) {
+        if self.is_manager() {
+            for child in &self.children {
+                child.collect_leaves(leaves);
+            }
+        } else {
+            leaves.push(self);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CppGuardTree {
+    // Lines before the tree, e.g. `TREE_GUARD_MANAGER:`
+    pub header: Vec<String>,
+    pub roots: Vec<CppGuardNode>,
+    // Lines after the tree, e.g. `Guard latency = 15.88 us`
+    pub footer: Vec<String>,
+}
+
+impl CppGuardTree {
+    pub fn leaves(&self) -> Vec<&CppGuardNode> {
+        let mut leaves = Vec::new();
+        for root in &self.roots {
+            root.collect_leaves(&mut leaves);
+        }
+        leaves
+    }
+}
+
+/// Parse the tree drawn by `str(guard_manager)`, where each node is a `+- ` line indented by
+/// one `| ` per level
+pub fn parse_cpp_guard_tree(payload: &str) -> CppGuardTree {
+    fn attach(open: &mut Vec<(usize, CppGuardNode)>, roots: &mut Vec<CppGuardNode>, depth: usize) {
+        while open.last().is_some_and(|(d, _)| *d >= depth) {
+            let (_, node) = open.pop().unwrap();
+            match open.last_mut() {
+                Some((_, parent)) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+    }
+
+    let mut tree = CppGuardTree::default();
+    // Nodes whose children are still being read, with their depth
+    let mut open: Vec<(usize, CppGuardNode)> = Vec::new();
+    for line in payload.lines() {
+        let rest = line.trim_start_matches(['|', ' ']);
+        if let Some(node) = rest.strip_prefix("+- ") {
+            let depth = line.len() - rest.len();
+            attach(&mut open, &mut tree.roots, depth);
+            let (text, comment) = match node.split_once("  # ") {
+                Some((text, comment)) => (text, Some(comment.trim().to_string())),
+                None => (node, None),
+            };
+            open.push((
+                depth,
+                CppGuardNode {
+                    text: text.trim_end().to_string(),
+                    comment,
+                    children: Vec::new(),
+                },
+            ));
+        } else if line.starts_with('|') && !open.is_empty() {
+            // A guard that spans several lines
+            let (_, node) = open.last_mut().unwrap();
+            node.text.push('\n');
+            node.text.push_str(rest.trim_end());
+        } else if !line.trim().is_empty() {
+            if open.is_empty() && tree.roots.is_empty() {
+                tree.header.push(line.to_string());
+            } else {
+                tree.footer.push(line.to_string());
+            }
+        }
+    }
+    attach(&mut open, &mut tree.roots, 0);
+    tree
+}
+
+/// Render the tree as nested lists with collapsible guard managers. `user_stacks` maps the code
+/// of a guard to the anchor of its user stack on the page.
+pub fn cpp_guard_tree_html(tree: &CppGuardTree, user_stacks: &FxHashMap<&str, String>) -> String {
+    fn node_html(
+        node: &CppGuardNode,
+        source: Option<&str>,
+        depth: usize,
+        user_stacks: &FxHashMap<&str, String>,
+        html: &mut String,
+    ) {
+        let source = node.source().or(source);
+        html.push_str(&format!(
+            "<li data-source=\"{}\">",
+            html_escape::encode_double_quoted_attribute(source.unwrap_or(""))
+        ));
+        let comment = node.comment.as_ref().map_or(String::new(), |c| {
+            format!(" <span class='guard-comment'># {}</span>", encode_text(c))
+        });
+        if node.is_manager() {
+            html.push_str(&format!(
+                "<details{}><summary><code class='guard-text'>{}</code> <span class='guard-count'>({} guard(s))</span>{}</summary><ul>",
+                if depth < 2 { " open" } else { "" },
+                encode_text(&node.text),
+                node.num_guards(),
+                comment,
+            ));
+            for child in &node.children {
+                node_html(child, source, depth + 1, user_stacks, html);
+            }
+            html.push_str("</ul></details>");
+        } else {
+            let category = categorize(&node.text);
+            html.push_str(&format!(
+                "<code class='guard-text' title='{}'>{}</code>{}",
+                category.name(),
+                encode_text(&node.text),
+                comment,
+            ));
+            let code = node
+                .text
+                .split_once(": ")
+                .map_or(node.text.as_str(), |(_, c)| c);
+            if let Some(anchor) = user_stacks.get(code) {
+                html.push_str(&format!(" <a href='#{}'>user stack</a>", anchor));
+            }
+        }
+        html.push_str("</li>\n");
+    }
+
+    let mut html = String::from("<ul id='guard-tree'>\n");
+    for root in &tree.roots {
+        node_html(root, None, 0, user_stacks, &mut html);
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+struct CompileGuards {
+    compile_id: String,
+    // Links to the metrics page, once compilation_metrics has been seen
+    compile_id_html: Option<String>,
+    counts: [usize; NUM_CATEGORIES],
+    num_guards: usize,
+    guard_count: Option<u64>,
+    shape_env_guard_count: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct GuardAnalytics {
+    compiles: FxIndexMap<Option<CompileId>, CompileGuards>,
+    // Guard code -> indices into `compiles`
+    identical: FxIndexMap<String, Vec<usize>>,
+    // Innermost frame of the guard's user stack -> counts per category
+    locations: FxIndexMap<String, [usize; NUM_CATEGORIES]>,
+}
+
+fn median(mut values: Vec<u64>) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+fn is_unusual(value: u64, median: u64, min: u64, large: u64) -> bool {
+    value >= large || (value >= min && value > 2 * median)
+}
+
+fn format_counts(counts: &[usize; NUM_CATEGORIES]) -> String {
+    GuardCategory::ALL
+        .iter()
+        .zip(counts)
+        .filter(|(_, &n)| n > 0)
+        .map(|(c, n)| format!("{} {}", c.name(), n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl GuardAnalytics {
+    pub fn is_empty(&self) -> bool {
+        self.compiles.is_empty()
+    }
+
+    fn compile(&mut self, compile_id: &Option<CompileId>) -> (usize, &mut CompileGuards) {
+        let entry = self.compiles.entry(compile_id.clone());
+        let index = entry.index();
+        let compile = entry.or_insert_with(|| CompileGuards {
+            compile_id: compile_id
+                .as_ref()
+                .map_or("(unknown)".to_string(), |c| c.to_string()),
+            compile_id_html: None,
+            counts: [0; NUM_CATEGORIES],
+            num_guards: 0,
+            guard_count: None,
+            shape_env_guard_count: None,
+        });
+        (index, compile)
+    }
+
+    /// Record the parsed `dynamo_guards` payload of a compile
+    pub fn add_guards(&mut self, compile_id: &Option<CompileId>, guards: &[DynamoGuard]) {
+        let (index, _) = self.compile(compile_id);
+        for guard in guards {
+            let category = categorize(&guard.code).index();
+            let compile = &mut self.compiles[index];
+            compile.counts[category] += 1;
+            compile.num_guards += 1;
+            let compiles = self.identical.entry(guard.code.clone()).or_default();
+            if compiles.last() != Some(&index) {
+                compiles.push(index);
+            }
+            if let Some(frame) = guard.user_stack.as_ref().and_then(|s| s.last()) {
+                self.locations
+                    .entry(frame_location(frame))
+                    .or_insert([0; NUM_CATEGORIES])[category] += 1;
+            }
+        }
+    }
+
+    /// Record the guard counts of a compile's compilation_metrics. `compile_id_html` links to its
+    /// metrics page.
+    pub fn add_metrics(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        compile_id_html: &str,
+        m: &CompilationMetricsMetadata,
+    ) {
+        if m.guard_count.is_none() && !self.compiles.contains_key(compile_id) {
+            return;
+        }
+        let (_, compile) = self.compile(compile_id);
+        compile.compile_id_html = Some(compile_id_html.to_string());
+        compile.guard_count = m.guard_count;
+        compile.shape_env_guard_count = m.shape_env_guard_count;
+    }
+
+    pub fn finish(&self, css: &'static str, qps: &'static str) -> GuardAnalyticsContext {
+        let guard_count = |c: &CompileGuards| c.guard_count.unwrap_or(c.num_guards as u64);
+        let median_guards = median(self.compiles.values().map(guard_count).collect());
+        let median_shape_env = median(
+            self.compiles
+                .values()
+                .filter_map(|c| c.shape_env_guard_count)
+                .collect(),
+        );
+
+        let mut totals = [0; NUM_CATEGORIES];
+        let compiles: Vec<CompileGuardsContext> = self
+            .compiles
+            .values()
+            .map(|c| {
+                for (total, n) in totals.iter_mut().zip(c.counts) {
+                    *total += n;
+                }
+                let mut unusual = Vec::new();
+                if is_unusual(
+                    guard_count(c),
+                    median_guards,
+                    MIN_UNUSUAL_GUARD_COUNT,
+                    LARGE_GUARD_COUNT,
+                ) {
+                    unusual.push(format!(
+                        "{} guards (run median {})",
+                        guard_count(c),
+                        median_guards
+                    ));
+                }
+                if let Some(shape_env) = c.shape_env_guard_count.filter(|&n| {
+                    is_unusual(
+                        n,
+                        median_shape_env,
+                        MIN_UNUSUAL_SHAPE_ENV_GUARD_COUNT,
+                        LARGE_SHAPE_ENV_GUARD_COUNT,
+                    )
+                }) {
+                    unusual.push(format!(
+                        "{} shape env guards (run median {})",
+                        shape_env, median_shape_env
+                    ));
+                }
+                CompileGuardsContext {
+                    compile_id: c
+                        .compile_id_html
+                        .clone()
+                        .unwrap_or_else(|| c.compile_id.clone()),
+                    num_guards: c.num_guards,
+                    counts: c.counts.to_vec(),
+                    guard_count: c.guard_count.map_or("?".to_string(), |n| n.to_string()),
+                    shape_env_guard_count: c
+                        .shape_env_guard_count
+                        .map_or("?".to_string(), |n| n.to_string()),
+                    unusual: unusual.join(", "),
+                }
+            })
+            .collect();
+
+        let mut identical: Vec<(&String, &Vec<usize>)> = self
+            .identical
+            .iter()
+            .filter(|(_, compiles)| compiles.len() > 1)
+            .collect();
+        // Stable, so ties stay in the order the guards were first seen
+        identical.sort_by_key(|(_, compiles)| std::cmp::Reverse(compiles.len()));
+        let num_identical = identical.len();
+
+        let mut locations: Vec<(&String, &[usize; NUM_CATEGORIES])> =
+            self.locations.iter().collect();
+        locations.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.iter().sum::<usize>()));
+
+        GuardAnalyticsContext {
+            css,
+            qps,
+            num_guards: totals.iter().sum(),
+            num_compiles: compiles.len(),
+            num_unusual: compiles.iter().filter(|c| !c.unusual.is_empty()).count(),
+            category_names: GuardCategory::ALL.iter().map(|c| c.name()).collect(),
+            totals: totals.to_vec(),
+            compiles,
+            num_identical,
+            identical: identical
+                .into_iter()
+                .take(IDENTICAL_GUARDS_LIMIT)
+                .map(|(code, compiles)| IdenticalGuardContext {
+                    code: code.clone(),
+                    category: categorize(code).name(),
+                    num_compiles: compiles.len(),
+                    compile_ids: compiles
+                        .iter()
+                        .map(|&i| self.compiles[i].compile_id.clone())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                })
+                .collect(),
+            locations: locations
+                .into_iter()
+                .map(|(location, counts)| GuardLocationContext {
+                    location: location.clone(),
+                    count: counts.iter().sum(),
+                    categories: format_counts(counts),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        let cases = [
+            (
+                "check_tensor(L['x'], Tensor, DispatchKeySet(CPU), torch.float32, device=None, requires_grad=False, size=[2, 2], stride=[2, 1])",
+                GuardCategory::TensorMatch,
+            ),
+            ("___check_obj_id(L['fn'], 140439264606080)", GuardCategory::IdMatch),
+            ("___check_type_id(L['self'], 88494992)", GuardCategory::TypeMatch),
+            ("2 <= L['x'].size()[0]", GuardCategory::ShapeEnv),
+            ("utils_device.CURRENT_DEVICE == None", GuardCategory::GlobalState),
+            ("___check_global_state()", GuardCategory::GlobalState),
+            ("not ___dict_contains('forward', L['self'].__dict__)", GuardCategory::DictKeys),
+            ("L['args'][2] == 2", GuardCategory::Constant),
+            ("len(L['args']) == 1", GuardCategory::Length),
+            ("hasattr(L['x'], '_dynamo_dynamic_indices') == False", GuardCategory::Attribute),
+            // Leaves of the C++ guard tree
+            ("TENSOR_MATCH: check_tensor(L['x'], Tensor)", GuardCategory::TensorMatch),
+            ("NO_TENSOR_ALIASING", GuardCategory::Aliasing),
+            (
+                "LAMBDA_GUARD: torch._functorch.aot_autograd.utils.top_saved_tensors_hooks ids == None",
+                GuardCategory::GlobalState,
+            ),
+            ("LAMBDA_GUARD: 2 <= L['x'].size()[0]", GuardCategory::ShapeEnv),
+        ];
+        for (code, category) in cases {
+            assert_eq!(categorize(code), category, "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_cpp_guard_tree() {
+        let payload = "\nTREE_GUARD_MANAGER:\n+- RootGuardManager\n| +- GLOBAL_STATE: ___check_global_state()\n| +- GuardManager: source=L['x'], accessed_by=FrameLocalsGuardAccessor(key='x', framelocals_idx=0)\n| | +- TENSOR_MATCH: check_tensor(L['x'], size=[2, 2])  # y = x.sum()  # model.py:3 in f\n| | +- NO_HASATTR: hasattr(L['x'], '_dynamo_dynamic_indices') == False\n\nGuard latency = 15.88 us";
+        let tree = parse_cpp_guard_tree(payload);
+        assert_eq!(tree.header, vec!["TREE_GUARD_MANAGER:"]);
+        assert_eq!(tree.footer, vec!["Guard latency = 15.88 us"]);
+        assert_eq!(tree.roots.len(), 1);
+        let root = &tree.roots[0];
+        assert_eq!(root.num_guards(), 3);
+        let x = &root.children[1];
+        assert_eq!(x.source(), Some("L['x']"));
+        assert_eq!(x.num_guards(), 2);
+        assert_eq!(
+            x.children[0].text,
+            "TENSOR_MATCH: check_tensor(L['x'], size=[2, 2])"
+        );
+        assert_eq!(
+            x.children[0].comment.as_deref(),
+            Some("y = x.sum()  # model.py:3 in f")
+        );
+
+        let mut user_stacks = FxHashMap::default();
+        user_stacks.insert(
+            "hasattr(L['x'], '_dynamo_dynamic_indices') == False",
+            "stack-0".to_string(),
+        );
+        let html = cpp_guard_tree_html(&tree, &user_stacks);
+        assert!(html.contains("<span class='guard-count'>(2 guard(s))</span>"));
+        assert!(html.contains(
+            "NO_HASATTR: hasattr(L['x'], '_dynamo_dynamic_indices') == False</code> <a href='#stack-0'>user stack</a>"
+        ));
+        assert_eq!(html.matches("data-source=\"L['x']\"").count(), 3);
+    }
+
+    #[test]
+    fn test_cpp_guard_tree_dict_manager() {
+        let payload = "\nTREE_GUARD_MANAGER:\n+- RootGuardManager\n| +- DictGuardManager: source=G['d'], accessed_by=DictGetItemGuardAccessor('d')\n| | +- DICT_VERSION: ___dict_version(G['d']) == 366913\n| | +- KeyValueManager pair at index=1\n| | | +- KeyManager: GuardManager: source=list(G['d'].keys())[1]\n| | | | +- ID_MATCH: ___check_obj_id(list(G['d'].keys())[1], 94)\n| | | +- ValueManager: GuardManager: source=G['d'][list(G['d'].keys())[1]]\n| | | | +- ID_MATCH: ___check_obj_id(G['d'][list(G['d'].keys())[1]], 95)\n| | +- KeyValueManager pair at index=2\n";
+        let tree = parse_cpp_guard_tree(payload);
+        let dict = &tree.roots[0].children[0];
+        assert_eq!(dict.num_guards(), 3);
+        let pair = &dict.children[1];
+        assert_eq!(pair.manager_kind(), Some("KeyValueManager"));
+        assert_eq!(pair.num_guards(), 2);
+        assert_eq!(pair.children[0].manager_kind(), Some("GuardManager"));
+        assert_eq!(
+            pair.children[1].source(),
+            Some("G['d'][list(G['d'].keys())[1]]")
+        );
+        // A manager without children still counts no guards
+        assert_eq!(dict.children[2].num_guards(), 0);
+        assert_eq!(dict.children[0].manager_kind(), None);
+        assert_eq!(tree.leaves().len(), 3);
+
+        let html = cpp_guard_tree_html(&tree, &FxHashMap::default());
+        assert!(html.contains(
+            "KeyValueManager pair at index=1</code> <span class='guard-count'>(2 guard(s))</span>"
+        ));
+        assert!(html.contains("keys())[1]], 95)</code>"));
+    }
+
+    #[test]
+    fn test_analytics() {
+        let guard = |code: &str| DynamoGuard {
+            code: code.to_string(),
+            stack: None,
+            user_stack: None,
+        };
+        let compile_id = |frame_id| {
+            Some(CompileId {
+                compiled_autograd_id: None,
+                frame_id: Some(frame_id),
+                frame_compile_id: Some(0),
+                attempt: Some(0),
+            })
+        };
+        let mut analytics = GuardAnalytics::default();
+        analytics.add_guards(
+            &compile_id(0),
+            &[guard("___check_global_state()"), guard("L['a'] == 1")],
+        );
+        analytics.add_guards(&compile_id(1), &[guard("___check_global_state()")]);
+        for frame_id in 2..5 {
+            analytics.add_metrics(
+                &compile_id(frame_id),
+                "",
+                &CompilationMetricsMetadata {
+                    guard_count: Some(if frame_id == 4 { 500 } else { 10 }),
+                    shape_env_guard_count: Some(0),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let ctx = analytics.finish("", "");
+        assert_eq!(ctx.num_guards, 3);
+        assert_eq!(ctx.totals[GuardCategory::GlobalState.index()], 2);
+        assert_eq!(ctx.identical.len(), 1);
+        assert_eq!(ctx.identical[0].compile_ids, "[0/0] [1/0]");
+        assert_eq!(ctx.num_unusual, 1);
+        assert_eq!(ctx.compiles[4].unusual, "500 guards (run median 10)");
+    }
+}
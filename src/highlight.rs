@@ -0,0 +1,674 @@
+//! Syntax highlighted, cross-linked HTML views of graphs and generated code.
+//!
+//! Every line gets an `L<n>` anchor (the same scheme as the `dump_file` pages), so other pages
+//! and the search index can deep link into a graph. On top of highlighting:
+//!
+//! - In FX graphs, every use of a node name links to the line that defines it.
+//! - `# File: path:line` comments link to the stack frame they refer to: FX `<eval_with_key>`
+//!   frames to their module under `dump_file/`, and user code to its line in the user source
+//!   index.
+//!
+//! Highlighting is slow, so `parse_path` hands `CodeView`s to a `HighlightPool` instead of
+//! rendering them on the parse thread. Sources above a size limit are written as plain text;
+//! the page can highlight itself in the browser when asked to.
+
+use fxhash::{FxHashMap, FxHashSet};
+use html_escape::encode_text;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Style, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::source_index::source_anchor;
+use crate::types::extract_eval_with_key_id;
+
+static IDENTIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap());
+/// `name = ...` or `name: "f32[3]" = ...`, as printed by FX
+static NODE_DEFINITION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*(?::\s*"[^"]*")?\s*=[^=]"#).unwrap());
+static DEF_SIGNATURE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*def\s+[A-Za-z_][A-Za-z0-9_]*\((.*)\)").unwrap());
+/// The `# File: path:line in function, code: source` comment FX puts above the nodes of a graph
+/// traced from that line of user code
+pub static FILE_COMMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^\s*# File: (?P<frame>(?P<path>.+?):(?P<line>\d+))\b(?: in (?P<function>[^,]+))?(?:, code: (?P<code>.*))?",
+    )
+    .unwrap()
+});
+
+// Loading these takes tens of milliseconds, so it's done once and shared by all workers
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME: Lazy<Theme> = Lazy::new(|| {
+    ThemeSet::load_defaults()
+        .themes
+        .remove("InspiredGitHub")
+        .unwrap()
+});
+
+/// Source language of a graph or code artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    Cpp,
+    Cuda,
+    Triton,
+}
+
+impl Language {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Python => "Python",
+            Language::Cpp => "C++",
+            Language::Cuda => "CUDA",
+            Language::Triton => "Triton",
+        }
+    }
+
+    /// Extension of the syntect syntax used to highlight this language. Triton kernels are
+    /// Python, and syntect has no CUDA syntax so CUDA is highlighted as C++.
+    fn syntax_extension(&self) -> &'static str {
+        match self {
+            Language::Python | Language::Triton => "py",
+            Language::Cpp | Language::Cuda => "cpp",
+        }
+    }
+}
+
+/// Guess the language of `source`, using the extension of `filename` if there is one
+pub fn detect_language(filename: Option<&str>, source: &str) -> Language {
+    let extension = filename
+        .and_then(|f| std::path::Path::new(f).extension())
+        .and_then(|e| e.to_str());
+    match extension {
+        Some("cu") | Some("cuh") => return Language::Cuda,
+        Some("cpp") | Some("cc") | Some("h") | Some("hpp") => return Language::Cpp,
+        _ => {}
+    }
+    let looks_like_cpp = source
+        .lines()
+        .take(200)
+        .any(|l| l.starts_with("#include") || l.starts_with("extern \"C\""));
+    if looks_like_cpp {
+        if source.contains("__global__") || source.contains("<cuda") {
+            Language::Cuda
+        } else {
+            Language::Cpp
+        }
+    } else if source.contains("@triton.jit") || source.contains("import triton") {
+        Language::Triton
+    } else {
+        Language::Python
+    }
+}
+
+/// String artifacts that hold an FX graph or generated code, and get a highlighted view
+pub fn is_graph_or_code_artifact(name: &str) -> bool {
+    name.ends_with("_graph")
+        || name.ends_with("_pre_grad")
+        || name.ends_with("_post_grad")
+        || name.ends_with("_code")
+        || name == "fx_graph_runnable"
+}
+
+/// Where a page is written in the report, for its links to other pages
+#[derive(Debug, Clone, Default)]
+pub struct PageLinks {
+    /// Relative path from the page to the root of the report, e.g. `../`
+    pub root: String,
+    /// Link the `# File:` frames of user code to the user source index
+    pub user_source: bool,
+}
+
+impl PageLinks {
+    pub fn for_page(path: &Path, user_source: bool) -> Self {
+        Self {
+            root: "../".repeat(path.components().count().saturating_sub(1)),
+            user_source,
+        }
+    }
+}
+
+/// Byte offset of the first `#` on a Python line that isn't inside a string literal
+fn comment_start(line: &str) -> usize {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return i,
+            None => {}
+        }
+    }
+    line.len()
+}
+
+/// Links to add to one line, as byte ranges of the line and their targets
+fn line_links(
+    line: &str,
+    lineno: usize,
+    definitions: Option<&mut FxHashMap<String, usize>>,
+    page_links: &PageLinks,
+) -> Vec<(Range<usize>, String)> {
+    let mut links = Vec::new();
+    if let Some(caps) = FILE_COMMENT.captures(line) {
+        let frame = caps.name("frame").unwrap();
+        let root = &page_links.root;
+        if let Some(fx_id) = extract_eval_with_key_id(&caps["path"]) {
+            links.push((
+                frame.range(),
+                format!(
+                    "{root}dump_file/eval_with_key_{}.html#L{}",
+                    fx_id, &caps["line"]
+                ),
+            ));
+        } else if let (true, Ok(line)) = (page_links.user_source, caps["line"].parse()) {
+            links.push((
+                frame.range(),
+                format!(
+                    "{root}user_source.html#{}",
+                    source_anchor(&caps["path"], line)
+                ),
+            ));
+        }
+        return links;
+    }
+    let Some(definitions) = definitions else {
+        return links;
+    };
+
+    let code = &line[..comment_start(line)];
+    let mut defined: Vec<(String, usize)> = Vec::new();
+    if let Some(caps) = NODE_DEFINITION.captures(code) {
+        let name = caps.get(1).unwrap();
+        defined.push((name.as_str().to_string(), name.start()));
+    } else if let Some(caps) = DEF_SIGNATURE.captures(code) {
+        let params = caps.get(1).unwrap();
+        for param in IDENTIFIER.find_iter(params.as_str()) {
+            // Only the parameter names, not the identifiers inside their annotations
+            let before = params.as_str()[..param.start()].trim_end();
+            if (before.is_empty() || before.ends_with(',')) && param.as_str() != "self" {
+                defined.push((param.as_str().to_string(), params.start() + param.start()));
+            }
+        }
+    }
+
+    for ident in IDENTIFIER.find_iter(code) {
+        if defined.iter().any(|(_, start)| *start == ident.start()) {
+            continue;
+        }
+        if let Some(def_line) = definitions.get(ident.as_str()) {
+            links.push((ident.range(), format!("#L{}", def_line)));
+        }
+    }
+    for (name, _) in defined {
+        definitions.insert(name, lineno);
+    }
+    links
+}
+
+fn style_attr(style: &Style) -> String {
+    let c = style.foreground;
+    let mut css = format!("color:#{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+    if style.font_style.contains(FontStyle::BOLD) {
+        css.push_str(";font-weight:bold");
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        css.push_str(";font-style:italic");
+    }
+    css
+}
+
+/// Render one highlighted line, wrapping the given byte ranges in links. A link is only added
+/// if it lies within a single highlighted region, which is always the case for identifiers
+/// and for the path of a `# File:` comment.
+fn render_line(
+    out: &mut String,
+    line: &str,
+    regions: &[(Style, &str)],
+    links: &[(Range<usize>, String)],
+) {
+    // Merge adjacent regions with the same style, which keeps the output a lot smaller
+    let mut merged: Vec<(Style, Range<usize>)> = Vec::new();
+    let mut offset = 0;
+    for (style, text) in regions {
+        let range = offset..offset + text.len();
+        offset = range.end;
+        match merged.last_mut() {
+            Some((last, r)) if last == style => r.end = range.end,
+            _ => merged.push((*style, range)),
+        }
+    }
+
+    let mut offset = 0;
+    for (style, range) in &merged {
+        let text = line[range.clone()].trim_end_matches(['\n', '\r']);
+        let region = offset..offset + text.len();
+        write!(out, "<span style=\"{}\">", style_attr(style)).unwrap();
+        let mut pos = region.start;
+        for (range, href) in links {
+            if range.start < pos || range.end > region.end {
+                continue;
+            }
+            out.push_str(&encode_text(
+                &text[pos - region.start..range.start - region.start],
+            ));
+            write!(
+                out,
+                "<a href=\"{}\">{}</a>",
+                encode_text(href),
+                encode_text(&text[range.start - region.start..range.end - region.start])
+            )
+            .unwrap();
+            pos = range.end;
+        }
+        out.push_str(&encode_text(&text[pos - region.start..]));
+        out.push_str("</span>");
+        offset = region.end;
+    }
+}
+
+fn page_header(title: &str, language: Language, note: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="UTF-8">
+<title>{title}</title>
+<style>
+body {{ background-color: #ffffff; }}
+pre.code-view {{ margin: 0; }}
+.code-view .line {{ display: block; }}
+.code-view .line:target {{ background-color: #ffffcc; }}
+.code-view .lineno {{ color: #999; width: 5em; display: inline-block; text-align: right; margin-right: 1em; text-decoration: none; user-select: none; }}
+.code-view a {{ color: inherit; }}
+</style>
+</head>
+<body>
+<p>{title} <small>({language})</small></p>
+{note}<pre class="code-view" data-syntax="{syntax}">
+"#,
+        title = encode_text(title),
+        language = language.name(),
+        syntax = language.syntax_extension(),
+    )
+}
+
+/// Render `source` as a standalone HTML page with highlighting and line anchors. If
+/// `link_nodes` is set, `source` is treated as an FX graph and node uses link to their
+/// definitions.
+pub fn render_code_html(
+    title: &str,
+    source: &str,
+    language: Language,
+    link_nodes: bool,
+    page_links: &PageLinks,
+) -> anyhow::Result<String> {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_extension(language.syntax_extension())
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, &THEME);
+
+    let mut html = page_header(title, language, "");
+    let mut definitions = FxHashMap::default();
+    for (i, line) in LinesWithEndings::from(source).enumerate() {
+        let lineno = i + 1;
+        let regions = highlighter.highlight_line(line, &SYNTAX_SET)?;
+        let links = line_links(
+            line.trim_end_matches(['\n', '\r']),
+            lineno,
+            link_nodes.then_some(&mut definitions),
+            page_links,
+        );
+        write!(
+            html,
+            "<span class=\"line\" id=\"L{lineno}\"><a class=\"lineno\" href=\"#L{lineno}\">{lineno}</a>"
+        )?;
+        render_line(&mut html, line, &regions, &links);
+        html.push_str("</span>\n");
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    Ok(html)
+}
+
+// Highlights a plain text page in the browser when it is opened with ?highlight. This is a
+// rough approximation of the syntect highlighting (comments, strings, numbers and keywords),
+// done a chunk of lines at a time so that huge files don't freeze the page.
+const ON_DEMAND_HIGHLIGHT_JAVASCRIPT: &str = r##"<script>
+(function () {
+  if (!new URLSearchParams(window.location.search).has("highlight")) return;
+  var pre = document.querySelector("pre.code-view");
+  var cpp = pre.dataset.syntax === "cpp";
+  var keywords = cpp
+    ? "auto bool break case char const constexpr continue default delete do double else enum extern float for if inline int long namespace nullptr return short signed sizeof static struct switch template this typedef typename union unsigned using void volatile while"
+    : "and as assert async await break class continue def del elif else except False finally for from global if import in is lambda None nonlocal not or pass raise return True try while with yield";
+  var token = new RegExp(
+    "(" + (cpp ? "//.*$" : "#.*$") + ")" +
+    "|(\"(?:[^\"\\\\]|\\\\.)*\"|'(?:[^'\\\\]|\\\\.)*')" +
+    "|\\b(\\d+(?:\\.\\d+)?)\\b" +
+    "|\\b(" + keywords.split(" ").join("|") + ")\\b",
+    "g"
+  );
+  var colors = ["#969896", "#183691", "#0086b3", "#a71d5d"];
+  function escape(s) {
+    return s.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
+  }
+  var lines = pre.querySelectorAll(".line .code");
+  var i = 0;
+  function highlightChunk() {
+    var end = Math.min(i + 2000, lines.length);
+    for (; i < end; i++) {
+      var text = lines[i].textContent;
+      var html = "";
+      var last = 0;
+      var m;
+      token.lastIndex = 0;
+      while ((m = token.exec(text)) !== null) {
+        var kind = m[1] ? 0 : m[2] ? 1 : m[3] ? 2 : 3;
+        html += escape(text.slice(last, m.index)) +
+          '<span style="color:' + colors[kind] + '">' + escape(m[0]) + "</span>";
+        last = token.lastIndex;
+      }
+      lines[i].innerHTML = html + escape(text.slice(last));
+    }
+    if (i < lines.length) setTimeout(highlightChunk, 0);
+  }
+  highlightChunk();
+})();
+</script>
+"##;
+
+/// Render `source` as a plain text page with the same line anchors as `render_code_html`,
+/// for sources too large to highlight while parsing. `reason` is shown above the source,
+/// followed by a link that highlights the page in the browser.
+pub fn render_plain_code_html(
+    title: &str,
+    source: &str,
+    language: Language,
+    reason: &str,
+) -> String {
+    let note = format!(
+        "<p><small>{} <a href=\"?highlight\">Highlight it</a></small></p>\n",
+        encode_text(reason)
+    );
+    let mut html = page_header(title, language, &note);
+    for (i, line) in source.lines().enumerate() {
+        let lineno = i + 1;
+        writeln!(
+            html,
+            "<span class=\"line\" id=\"L{lineno}\"><a class=\"lineno\" href=\"#L{lineno}\">{lineno}</a><span class=\"code\">{}</span></span>",
+            encode_text(line)
+        )
+        .unwrap();
+    }
+    html.push_str("</pre>\n");
+    html.push_str(ON_DEMAND_HIGHLIGHT_JAVASCRIPT);
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// A graph or code payload to be rendered as an HTML page. Either way, the page's `L<n>`
+/// anchors match the lines of `source`.
+pub struct CodeView {
+    pub title: String,
+    pub source: String,
+    pub language: Language,
+    /// Treat `source` as an FX graph and link node uses to their definitions
+    pub link_nodes: bool,
+}
+
+impl CodeView {
+    /// Highlight the source, or write it as plain text if it is larger than `size_limit` bytes
+    /// or can't be highlighted
+    pub fn render(&self, size_limit: usize, page_links: &PageLinks) -> String {
+        if self.source.len() > size_limit {
+            let reason = format!(
+                "Shown as plain text because it is larger than {} bytes.",
+                size_limit
+            );
+            return render_plain_code_html(&self.title, &self.source, self.language, &reason);
+        }
+        render_code_html(
+            &self.title,
+            &self.source,
+            self.language,
+            self.link_nodes,
+            page_links,
+        )
+        .unwrap_or_else(|err| {
+            let reason = format!("Shown as plain text because highlighting failed: {err}.");
+            render_plain_code_html(&self.title, &self.source, self.language, &reason)
+        })
+    }
+}
+
+/// Renders `CodeView`s on worker threads. Each view is submitted with a key (the index of its
+/// output file) and `finish` returns the pages sorted by key, so the output doesn't depend on
+/// which worker finishes first. Workers are started on the first submission. The pages are
+/// part of a full report, so their `# File:` frames link to the user source index.
+pub struct HighlightPool {
+    size_limit: usize,
+    submitted: FxHashSet<usize>,
+    jobs: Option<mpsc::Sender<(usize, PageLinks, CodeView)>>,
+    results: Option<mpsc::Receiver<(usize, String)>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl HighlightPool {
+    pub fn new(size_limit: usize) -> Self {
+        Self {
+            size_limit,
+            submitted: FxHashSet::default(),
+            jobs: None,
+            results: None,
+            workers: Vec::new(),
+        }
+    }
+
+    fn start(&mut self) -> mpsc::Sender<(usize, PageLinks, CodeView)> {
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, PageLinks, CodeView)>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        for _ in 0..threads {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let size_limit = self.size_limit;
+            self.workers.push(std::thread::spawn(move || loop {
+                // Hold the lock only while waiting for a job, not while rendering it
+                let job = job_receiver.lock().unwrap().recv();
+                let Ok((key, page_links, view)) = job else {
+                    break;
+                };
+                let page = view.render(size_limit, &page_links);
+                if result_sender.send((key, page)).is_err() {
+                    break;
+                }
+            }));
+        }
+        self.results = Some(result_receiver);
+        job_sender
+    }
+
+    /// Submit the view of the page written to `path`
+    pub fn submit(&mut self, key: usize, path: &Path, view: CodeView) {
+        self.submitted.insert(key);
+        let jobs = match self.jobs.take() {
+            Some(jobs) => jobs,
+            None => self.start(),
+        };
+        jobs.send((key, PageLinks::for_page(path, true), view))
+            .unwrap();
+        self.jobs = Some(jobs);
+    }
+
+    /// Whether a view was submitted under `key`
+    pub fn is_submitted(&self, key: usize) -> bool {
+        self.submitted.contains(&key)
+    }
+
+    /// Wait for all submitted views and return the rendered pages, sorted by key
+    pub fn finish(mut self) -> Vec<(usize, String)> {
+        // Closing the job channel stops the workers once the queue is drained
+        self.jobs = None;
+        let mut pages: Vec<(usize, String)> = match self.results.take() {
+            Some(results) => results.iter().collect(),
+            None => Vec::new(),
+        };
+        for worker in self.workers.drain(..) {
+            worker.join().expect("highlighting worker panicked");
+        }
+        pages.sort_by_key(|(key, _)| *key);
+        pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language(None, "def f(x):\n    return x\n"),
+            Language::Python
+        );
+        assert_eq!(
+            detect_language(None, "import triton\n@triton.jit\ndef k(): pass\n"),
+            Language::Triton
+        );
+        assert_eq!(
+            detect_language(
+                None,
+                "#include <torch/csrc/inductor/aoti_runtime/interface.h>\n"
+            ),
+            Language::Cpp
+        );
+        assert_eq!(
+            detect_language(None, "#include <cuda.h>\n__global__ void k() {}\n"),
+            Language::Cuda
+        );
+        assert_eq!(
+            detect_language(Some("/tmp/cabc.cpp"), "def f(): pass\n"),
+            Language::Cpp
+        );
+    }
+
+    #[test]
+    fn test_fx_graph_links() {
+        let graph = r#"class GraphModule(torch.nn.Module):
+    def forward(self, arg0_1: "f32[3]", arg1_1: "f32[3]"):
+        # File: <eval_with_key>.4:7 in forward, code: add = arg0_1 + arg1_1
+        add: "f32[3]" = torch.ops.aten.add.Tensor(arg0_1, arg1_1);  arg0_1 = None
+        sin: "f32[3]" = torch.ops.aten.sin.default(add);  add = None
+        # File: /repo#link-tree/pkg/model.py:3 in f, code: return x.cos()
+        cos: "f32[3]" = torch.ops.aten.cos.default(sin);  sin = None
+        return (cos,)
+"#;
+        let page_links = PageLinks::for_page(Path::new("-_0_0_0/aot_forward_graph_1.html"), true);
+        let html = render_code_html(
+            "aot_forward_graph",
+            graph,
+            Language::Python,
+            true,
+            &page_links,
+        )
+        .unwrap();
+        assert!(html.contains(r#"id="L6""#));
+        // Uses link to the definition, definitions themselves and comments don't link
+        assert!(html.contains(r##"<a href="#L2">arg0_1</a>"##));
+        assert!(html.contains(r##"<a href="#L4">add</a>"##));
+        assert!(html.contains(r##"<a href="#L5">sin</a>"##));
+        let line4 = html
+            .split(r#"id="L4""#)
+            .nth(1)
+            .unwrap()
+            .split('\n')
+            .next()
+            .unwrap();
+        assert!(!line4.contains(">add</a>"));
+        assert!(html.contains(
+            r#"<a href="../dump_file/eval_with_key_4.html#L7">&lt;eval_with_key&gt;.4:7</a>"#
+        ));
+        assert!(html.contains(&format!(
+            r#"<a href="../user_source.html#{}">/repo#link-tree/pkg/model.py:3</a>"#,
+            source_anchor("pkg/model.py", 3)
+        )));
+        assert!(html.contains(r#"<pre class="code-view""#));
+
+        // Links are relative to where the page is written
+        let page_links = PageLinks::for_page(Path::new("aot_forward_graph_1.html"), false);
+        let html = render_code_html(
+            "aot_forward_graph",
+            graph,
+            Language::Python,
+            true,
+            &page_links,
+        )
+        .unwrap();
+        assert!(html.contains(r#"<a href="dump_file/eval_with_key_4.html#L7">"#));
+        assert!(!html.contains("user_source.html"));
+    }
+
+    #[test]
+    fn test_highlight_pool() {
+        let mut pool = HighlightPool::new(20);
+        for key in [5, 1, 3] {
+            pool.submit(
+                key,
+                Path::new("-_0_0_0/graph.html"),
+                CodeView {
+                    title: format!("graph_{key}"),
+                    source: "x = 1\n".repeat(key),
+                    language: Language::Python,
+                    link_nodes: false,
+                },
+            );
+        }
+        assert!(pool.is_submitted(3) && !pool.is_submitted(2));
+        let pages = pool.finish();
+        let keys: Vec<usize> = pages.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![1, 3, 5]);
+        assert!(pages[1].1.contains("<title>graph_3</title>"));
+        // 18 bytes are highlighted, 30 are over the limit
+        assert!(!pages[1].1.contains("Highlight it"));
+        assert!(pages[2]
+            .1
+            .contains(r#"<a href="?highlight">Highlight it</a>"#));
+        assert!(pages[2].1.contains(r#"id="L5""#));
+    }
+
+    #[test]
+    fn test_comment_start() {
+        assert_eq!(comment_start("x = '#' # c"), 8);
+        assert_eq!(comment_start(r##"x = "a\"#" "##), 11);
+    }
+
+    #[test]
+    fn test_file_comment() {
+        let caps = FILE_COMMENT
+            .captures("    # File: /repo/m.py:10 in forward, code: y = x[1:2, 0]")
+            .unwrap();
+        assert_eq!(&caps["frame"], "/repo/m.py:10");
+        assert_eq!(&caps["path"], "/repo/m.py");
+        assert_eq!(&caps["function"], "forward");
+        assert_eq!(&caps["code"], "y = x[1:2, 0]");
+
+        let caps = FILE_COMMENT
+            .captures("# File: <eval_with_key>.3:5")
+            .unwrap();
+        assert_eq!(&caps["line"], "5");
+        assert!(caps.name("function").is_none() && caps.name("code").is_none());
+    }
+}
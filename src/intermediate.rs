@@ -8,10 +8,27 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::io::{BufRead, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
-use crate::types::{CompileId, Envelope};
+use crate::timestamps::GlogClock;
+use crate::types::{CompileId, Envelope, Stats};
+
+pub mod schema;
+pub mod validate;
+
+/// Version of the intermediate file format written by this build of tlparse.
+///
+/// The major component is bumped whenever a change would break existing readers; readers
+/// accept any minor version within the same major version.
+/// The changes of each version are listed under "Versioning and Validation" in
+/// docs/REFACTOR_PLAN.md.
+pub const INTERMEDIATE_FORMAT_VERSION: &str = "2.1";
+
+/// Major component of a "major.minor" version string
+fn major_version(version: &str) -> Option<u32> {
+    version.split('.').next()?.trim().parse().ok()
+}
 
 /// Categories of intermediate files
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -91,6 +108,7 @@ pub fn envelope_type_to_file(envelope_type: &str) -> Option<IntermediateFileType
         | "guard_added_fast"
         | "propagate_real_tensors_provenance"
         | "guard_added"
+        | "create_symbol"
         | "create_unbacked_symbol"
         | "expression_created" => Some(IntermediateFileType::Guards),
 
@@ -137,6 +155,14 @@ pub fn route_artifact(artifact_name: &str) -> IntermediateFileType {
     }
 }
 
+/// Determines which intermediate file an envelope belongs to, routing artifacts by name
+pub fn route_envelope(e: &Envelope, envelope_type: &str) -> Option<IntermediateFileType> {
+    match (envelope_type, &e.artifact) {
+        ("artifact", Some(artifact)) => Some(route_artifact(&artifact.name)),
+        _ => envelope_type_to_file(envelope_type),
+    }
+}
+
 /// An entry in an intermediate JSONL file
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IntermediateEntry {
@@ -186,6 +212,33 @@ pub struct IntermediateManifest {
     pub files: Vec<String>,
 }
 
+impl IntermediateManifest {
+    /// Ensure this manifest was written in a format version we know how to read.
+    pub fn check_compatible(&self) -> Result<()> {
+        let expected = major_version(INTERMEDIATE_FORMAT_VERSION);
+        let actual = major_version(&self.version);
+        if actual.is_none() || actual != expected {
+            anyhow::bail!(
+                "Intermediate files have format version {}, but this tlparse reads version {}.x; \
+                 regenerate them with `tlparse --intermediate-only`",
+                self.version,
+                expected.unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Compute the hash recorded as `source_file_hash` in the manifest ("md5:<hex>").
+pub fn hash_source_file(path: &Path) -> Result<String> {
+    use md5::{Digest, Md5};
+
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("md5:{:x}", hasher.finalize()))
+}
+
 /// Writer that manages multiple intermediate JSONL files
 pub struct IntermediateWriter {
     output_dir: PathBuf,
@@ -195,6 +248,7 @@ pub struct IntermediateWriter {
     compile_ids: std::collections::HashSet<String>,
     ranks: std::collections::HashSet<u32>,
     total_envelopes: u64,
+    source_file_hash: Option<String>,
 }
 
 impl IntermediateWriter {
@@ -221,6 +275,7 @@ impl IntermediateWriter {
             compile_ids: std::collections::HashSet::new(),
             ranks: std::collections::HashSet::new(),
             total_envelopes: 0,
+            source_file_hash: None,
         })
     }
 
@@ -275,6 +330,11 @@ impl IntermediateWriter {
         Ok(())
     }
 
+    /// Record the hash of the log file the entries were parsed from
+    pub fn set_source_file_hash(&mut self, hash: String) {
+        self.source_file_hash = Some(hash);
+    }
+
     /// Write the string table to string_table.json
     pub fn write_string_table(&self, string_table: &HashMap<u32, String>) -> Result<()> {
         let path = self.output_dir.join("string_table.json");
@@ -316,10 +376,10 @@ impl IntermediateWriter {
 
         // Create manifest
         let manifest = IntermediateManifest {
-            version: "2.0".to_string(),
+            version: INTERMEDIATE_FORMAT_VERSION.to_string(),
             generated_at: chrono::Utc::now().to_rfc3339(),
             source_file: source_file.to_string(),
-            source_file_hash: None, // TODO: compute hash
+            source_file_hash: self.source_file_hash,
             total_envelopes: self.total_envelopes,
             envelope_counts: self.envelope_counts,
             compile_ids: {
@@ -342,6 +402,9 @@ impl IntermediateWriter {
         let manifest_file = File::create(manifest_path)?;
         serde_json::to_writer_pretty(manifest_file, &manifest)?;
 
+        // Publish the schemas the files above conform to, so external consumers can check them
+        schema::write_schemas(&self.output_dir)?;
+
         Ok(manifest)
     }
 }
@@ -366,6 +429,40 @@ pub fn format_compile_id(compile_id: &Option<CompileId>) -> Option<String> {
     })
 }
 
+/// Inverse of [`format_compile_id`]
+pub fn parse_compile_id(s: &str) -> Option<CompileId> {
+    let (compiled_autograd_id, rest) = match s.strip_prefix('!') {
+        Some(rest) => {
+            let (ca, rest) = rest.split_once('_')?;
+            (Some(ca.parse().ok()?), rest)
+        }
+        None => (None, s),
+    };
+    let id = |v: &str| -> Option<Option<u32>> {
+        if v.is_empty() {
+            Some(None)
+        } else {
+            v.parse().ok().map(Some)
+        }
+    };
+    let mut parts = rest.split('_');
+    let frame_id = id(parts.next()?)?;
+    let frame_compile_id = id(parts.next()?)?;
+    let attempt = match parts.next() {
+        Some(a) => Some(a.parse().ok()?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(CompileId {
+        compiled_autograd_id,
+        frame_id,
+        frame_compile_id,
+        attempt,
+    })
+}
+
 /// Detect which envelope type is present in an Envelope struct
 pub fn detect_envelope_type(e: &Envelope) -> Option<&'static str> {
     // Check each field in order of typical frequency
@@ -438,6 +535,9 @@ pub fn detect_envelope_type(e: &Envelope) -> Option<&'static str> {
     if e.guard_added.is_some() {
         return Some("guard_added");
     }
+    if e.create_symbol.is_some() {
+        return Some("create_symbol");
+    }
     if e.create_unbacked_symbol.is_some() {
         return Some("create_unbacked_symbol");
     }
@@ -537,6 +637,7 @@ pub fn extract_metadata(e: &Envelope, envelope_type: &str) -> Value {
             serde_json::to_value(&e.propagate_real_tensors_provenance).unwrap_or(Value::Null)
         }
         "guard_added" => serde_json::to_value(&e.guard_added).unwrap_or(Value::Null),
+        "create_symbol" => serde_json::to_value(&e.create_symbol).unwrap_or(Value::Null),
         "create_unbacked_symbol" => {
             serde_json::to_value(&e.create_unbacked_symbol).unwrap_or(Value::Null)
         }
@@ -557,6 +658,164 @@ pub fn extract_metadata(e: &Envelope, envelope_type: &str) -> Value {
     }
 }
 
+/// A record read from a raw log by [`LogEntries`]
+pub enum LogRecord {
+    /// An envelope converted to its intermediate form, with the file it is written to, if any
+    Entry(IntermediateEntry, Option<IntermediateFileType>),
+    /// The payload of a `chromium_event` envelope
+    ChromiumEvent(Value),
+}
+
+/// Iterator over the envelopes of a raw TORCH_TRACE log, converted to intermediate entries.
+///
+/// String table entries are collected into `string_table` rather than yielded, and envelopes
+/// from a rank other than the first one seen (or the one chosen with `with_rank`) are skipped.
+pub struct LogEntries<'a> {
+    lines: std::iter::Peekable<Box<dyn Iterator<Item = (usize, String)> + 'a>>,
+    re_glog: regex::Regex,
+    expected_rank: Option<Option<u32>>,
+    pub stats: Stats,
+    pub string_table: HashMap<u32, String>,
+    /// Bytes consumed so far, for progress reporting
+    pub bytes_read: u64,
+    /// Line number of the most recently read envelope
+    pub lineno: usize,
+    clock: GlogClock,
+}
+
+impl<'a> LogEntries<'a> {
+    pub fn new(reader: impl BufRead + 'a) -> Result<Self> {
+        let lines: Box<dyn Iterator<Item = (usize, String)> + 'a> =
+            Box::new(reader.lines().enumerate().filter_map(|(i, l)| match l {
+                Ok(l) if !l.is_empty() => Some((i + 1, l)),
+                _ => None,
+            }));
+        let re_glog = regex::Regex::new(concat!(
+            r"(?<level>[VIWEC])(?<month>\d{2})(?<day>\d{2}) ",
+            r"(?<hour>\d{2}):(?<minute>\d{2}):(?<second>\d{2}).(?<millisecond>\d{6}) ",
+            r"(?<thread>\d+)",
+            r"(?<pathname>[^:]+):(?<line>\d+)\] ",
+            r"(?<payload>.)"
+        ))?;
+        Ok(Self {
+            lines: lines.peekable(),
+            re_glog,
+            expected_rank: None,
+            stats: Stats::default(),
+            string_table: HashMap::new(),
+            bytes_read: 0,
+            lineno: 0,
+            clock: GlogClock::default(),
+        })
+    }
+
+    /// Infer the year and timezone of timestamps from the log file at `path`, rather than
+    /// assuming the current year
+    pub fn with_timestamps_from(mut self, path: &Path) -> Self {
+        self.clock = GlogClock::for_file(path);
+        self
+    }
+
+    /// Read the envelopes of `rank`, rather than those of the first rank seen
+    pub fn with_rank(mut self, rank: u32) -> Self {
+        self.expected_rank = Some(Some(rank));
+        self
+    }
+
+    /// The rank being read: the one chosen with `with_rank`, or else the first one seen so far
+    pub fn rank(&self) -> Option<u32> {
+        self.expected_rank.flatten()
+    }
+
+    /// Collect the tab-indented payload lines following an envelope
+    fn read_payload(&mut self) -> Option<String> {
+        let mut payload_lines = Vec::new();
+        while let Some((_, next_line)) = self.lines.peek() {
+            if !next_line.starts_with('\t') {
+                break;
+            }
+            if let Some((_, pl)) = self.lines.next() {
+                self.bytes_read += pl.len() as u64 + 1;
+                payload_lines.push(pl[1..].to_string());
+            }
+        }
+        (!payload_lines.is_empty()).then(|| payload_lines.join("\n"))
+    }
+}
+
+impl Iterator for LogEntries<'_> {
+    type Item = LogRecord;
+
+    fn next(&mut self) -> Option<LogRecord> {
+        while let Some((lineno, line)) = self.lines.next() {
+            self.bytes_read += line.len() as u64;
+            self.lineno = lineno;
+
+            let Some(caps) = self.re_glog.captures(&line) else {
+                self.stats.fail_glog += 1;
+                continue;
+            };
+            let timestamp = self.clock.timestamp(&caps);
+            let payload_str = &line[caps.name("payload").unwrap().start()..];
+            let e: Envelope = match serde_json::from_str(payload_str) {
+                Ok(e) => e,
+                Err(_) => {
+                    self.stats.fail_json += 1;
+                    continue;
+                }
+            };
+
+            if let Some((s, id)) = &e.str {
+                self.string_table.insert(*id, s.clone());
+                continue;
+            }
+
+            match self.expected_rank {
+                Some(rank) if rank != e.rank => {
+                    self.stats.other_rank += 1;
+                    continue;
+                }
+                None if e.rank.is_some() => self.expected_rank = Some(e.rank),
+                _ => {}
+            }
+
+            let payload = if e.has_payload.is_some() {
+                self.read_payload()
+            } else {
+                None
+            };
+            self.stats.ok += 1;
+
+            let Some(envelope_type) = detect_envelope_type(&e) else {
+                continue;
+            };
+            let file_type = route_envelope(&e, envelope_type);
+
+            // Chromium events are stored as the raw trace event from the payload
+            if file_type == Some(IntermediateFileType::ChromiumEvents) {
+                match payload.and_then(|p| serde_json::from_str::<Value>(&p).ok()) {
+                    Some(event) => return Some(LogRecord::ChromiumEvent(event)),
+                    None => continue,
+                }
+            }
+
+            let entry = IntermediateEntry {
+                entry_type: envelope_type.to_string(),
+                compile_id: format_compile_id(&e.compile_id),
+                rank: e.rank,
+                timestamp,
+                thread: caps.name("thread").unwrap().as_str().parse().unwrap_or(0),
+                pathname: caps.name("pathname").unwrap().as_str().to_string(),
+                lineno: caps.name("line").unwrap().as_str().parse().unwrap_or(0),
+                metadata: extract_metadata(&e, envelope_type),
+                payload,
+            };
+            return Some(LogRecord::Entry(entry, file_type));
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -738,5 +997,103 @@ mod tests {
 
         // None
         assert_eq!(format_compile_id(&None), None);
+
+        // Round trip
+        for cid in [cid, cid_attempt, cid_autograd] {
+            assert_eq!(parse_compile_id(&format_compile_id(&cid).unwrap()), cid);
+        }
+    }
+
+    #[test]
+    fn test_manifest_version_compatibility() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let writer = IntermediateWriter::new(temp_dir.path())?;
+        let mut manifest = writer.finalize("test.log", "normal", 0)?;
+        assert_eq!(manifest.version, INTERMEDIATE_FORMAT_VERSION);
+        assert!(manifest.check_compatible().is_ok());
+
+        // Minor bumps are readable, major bumps and garbage are not
+        manifest.version = "2.7".to_string();
+        assert!(manifest.check_compatible().is_ok());
+        manifest.version = "3.0".to_string();
+        assert!(manifest.check_compatible().is_err());
+        manifest.version = "banana".to_string();
+        assert!(manifest.check_compatible().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_entries_rank() -> Result<()> {
+        let log = [
+            r#"V0804 12:34:15.775000 1 torch/_dynamo/utils.py:1931] {"dynamo_start": {}, "rank": 0, "frame_id": 0, "frame_compile_id": 0, "attempt": 0}"#,
+            r#"V0804 12:34:15.775000 2 torch/_dynamo/utils.py:1931] {"dynamo_start": {}, "rank": 1, "frame_id": 0, "frame_compile_id": 0, "attempt": 0}"#,
+            r#"V0804 12:34:15.776000 2 torch/_inductor/graph.py:10] {"artifact": {"name": "fx_graph_cache_hit", "encoding": "json"}, "rank": 1, "frame_id": 0, "frame_compile_id": 0, "attempt": 0}"#,
+        ]
+        .join("\n");
+        let ranks = |entries: LogEntries| -> Vec<(Option<u32>, String, bool)> {
+            entries
+                .filter_map(|record| match record {
+                    LogRecord::Entry(entry, file_type) => {
+                        Some((entry.rank, entry.entry_type, file_type.is_some()))
+                    }
+                    LogRecord::ChromiumEvent(_) => None,
+                })
+                .collect()
+        };
+        // The first rank seen, unless another is chosen
+        assert_eq!(
+            ranks(LogEntries::new(log.as_bytes())?),
+            vec![(Some(0), "dynamo_start".to_string(), true)]
+        );
+        // Artifacts are routed by name
+        assert_eq!(
+            ranks(LogEntries::new(log.as_bytes())?.with_rank(1)),
+            vec![
+                (Some(1), "dynamo_start".to_string(), true),
+                (Some(1), "artifact".to_string(), true)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_file_hash_and_validation() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source = temp_dir.path().join("test.log");
+        fs::write(&source, "hello")?;
+        let hash = hash_source_file(&source)?;
+        assert_eq!(hash, "md5:5d41402abc4b2a76b9719d911017c592");
+
+        let out = temp_dir.path().join("out");
+        let mut writer = IntermediateWriter::new(&out)?;
+        writer.set_source_file_hash(hash.clone());
+        writer.write_entry(
+            IntermediateEntry {
+                entry_type: "link".to_string(),
+                compile_id: Some("0_0_0".to_string()),
+                rank: None,
+                timestamp: "2024-11-28T12:00:00.000000Z".to_string(),
+                thread: 1,
+                pathname: "test.py".to_string(),
+                lineno: 1,
+                metadata: serde_json::json!({"name": "docs", "url": "https://example.com"}),
+                payload: None,
+            },
+            IntermediateFileType::CompileArtifacts,
+        )?;
+        let manifest = writer.finalize(&source.to_string_lossy(), "normal", 0)?;
+        assert_eq!(manifest.source_file_hash, Some(hash));
+        assert!(out.join("schemas/link.schema.json").exists());
+
+        let report = validate::validate_intermediate_dir(&out)?;
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty());
+
+        // Editing the source log afterwards is reported as a warning, not an error
+        fs::write(&source, "hello, world")?;
+        let report = validate::validate_intermediate_dir(&out)?;
+        assert!(report.is_ok());
+        assert_eq!(report.warnings.len(), 1);
+        Ok(())
     }
 }
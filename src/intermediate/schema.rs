@@ -0,0 +1,544 @@
+//! JSON Schemas for the intermediate file format.
+//!
+//! The schemas describe `manifest.json` and one `IntermediateEntry` shape per entry type, and
+//! are published next to the intermediate files under `schemas/`. The small validator below
+//! understands exactly the subset of JSON Schema used here (`type`, `const`, `enum`,
+//! `required`, `properties`, `items`, `additionalProperties` and `minimum`), which keeps the
+//! `validate` subcommand free of extra dependencies.
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::intermediate::INTERMEDIATE_FORMAT_VERSION;
+
+/// Directory (relative to the intermediate dir) the schemas are written to
+pub const SCHEMA_DIR: &str = "schemas";
+
+/// Every entry type that can appear in an intermediate JSONL file
+pub const ENTRY_TYPES: &[&str] = &[
+    "dynamo_output_graph",
+    "optimize_ddp_split_graph",
+    "optimize_ddp_split_child",
+    "compiled_autograd_graph",
+    "aot_forward_graph",
+    "aot_backward_graph",
+    "aot_inference_graph",
+    "aot_joint_graph",
+    "inductor_pre_grad_graph",
+    "inductor_post_grad_graph",
+    "graph_dump",
+    "inductor_output_code",
+    "dump_file",
+    "link",
+    "artifact",
+    "dynamo_guards",
+    "dynamo_cpp_guards_str",
+    "symbolic_shape_specialization",
+    "guard_added_fast",
+    "propagate_real_tensors_provenance",
+    "guard_added",
+    "create_symbol",
+    "create_unbacked_symbol",
+    "expression_created",
+    "compilation_metrics",
+    "bwd_compilation_metrics",
+    "aot_autograd_backward_compilation_metrics",
+    "dynamo_start",
+    "stack",
+    "describe_tensor",
+    "describe_storage",
+    "describe_source",
+    "missing_fake_kernel",
+    "mismatched_fake_kernel",
+    "exported_program",
+];
+
+fn nullable(ty: &str) -> Value {
+    json!({ "type": [ty, "null"] })
+}
+
+fn object(properties: &[(&str, Value)], required: &[&str]) -> Value {
+    let props: Map<String, Value> = properties
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect();
+    json!({ "type": "object", "properties": props, "required": required })
+}
+
+fn frame_schema() -> Value {
+    object(
+        &[
+            ("filename", json!({ "type": "integer", "minimum": 0 })),
+            ("line", json!({ "type": "integer" })),
+            ("name", json!({ "type": "string" })),
+            ("loc", nullable("string")),
+            ("uninterned_filename", nullable("string")),
+        ],
+        &["filename", "line", "name"],
+    )
+}
+
+fn stack_schema() -> Value {
+    json!({ "type": ["array", "null"], "items": frame_schema() })
+}
+
+fn nullable_string_array() -> Value {
+    json!({ "type": ["array", "null"], "items": { "type": "string" } })
+}
+
+/// Schema for the `metadata` field of an entry of the given type
+pub fn metadata_schema(entry_type: &str) -> Option<Value> {
+    let schema = match entry_type {
+        "optimize_ddp_split_graph"
+        | "compiled_autograd_graph"
+        | "aot_forward_graph"
+        | "aot_backward_graph"
+        | "aot_inference_graph"
+        | "aot_joint_graph"
+        | "inductor_pre_grad_graph"
+        | "inductor_post_grad_graph"
+        | "dynamo_guards"
+        | "dynamo_cpp_guards_str"
+        | "exported_program" => object(&[], &[]),
+        "dynamo_output_graph" => object(&[("sizes", nullable("object"))], &[]),
+        "optimize_ddp_split_child" | "graph_dump" | "dump_file" => {
+            object(&[("name", json!({ "type": "string" }))], &["name"])
+        }
+        "inductor_output_code" => object(&[("filename", nullable("string"))], &[]),
+        "link" => object(
+            &[
+                ("name", json!({ "type": "string" })),
+                ("url", json!({ "type": "string" })),
+            ],
+            &["name", "url"],
+        ),
+        "artifact" => object(
+            &[
+                ("name", json!({ "type": "string" })),
+                ("encoding", json!({ "type": "string" })),
+            ],
+            &["name", "encoding"],
+        ),
+        "symbolic_shape_specialization" => object(
+            &[
+                ("symbol", nullable("string")),
+                ("sources", nullable_string_array()),
+                ("value", nullable("string")),
+                ("reason", nullable("string")),
+                ("stack", stack_schema()),
+                ("user_stack", stack_schema()),
+            ],
+            &[],
+        ),
+        "guard_added_fast" => object(
+            &[
+                ("expr", nullable("string")),
+                ("stack", stack_schema()),
+                ("user_stack", stack_schema()),
+            ],
+            &[],
+        ),
+        "propagate_real_tensors_provenance" | "guard_added" => object(
+            &[
+                ("expr", nullable("string")),
+                ("result", nullable("string")),
+                ("user_stack", stack_schema()),
+                ("stack", stack_schema()),
+                ("expr_node_id", nullable("integer")),
+                ("symbol_to_sources", nullable("object")),
+                ("frame_locals", nullable("object")),
+                ("prefix", nullable("string")),
+            ],
+            &[],
+        ),
+        "create_symbol" => object(
+            &[
+                ("symbol", nullable("string")),
+                ("val", nullable("string")),
+                ("vr", nullable("string")),
+                ("source", nullable("string")),
+                ("user_stack", stack_schema()),
+                ("stack", stack_schema()),
+            ],
+            &[],
+        ),
+        "create_unbacked_symbol" => object(
+            &[
+                ("symbol", nullable("string")),
+                ("node_id", nullable("integer")),
+                ("user_stack", stack_schema()),
+                ("stack", stack_schema()),
+                ("vr", nullable("string")),
+            ],
+            &[],
+        ),
+        "expression_created" => object(
+            &[
+                ("method", nullable("string")),
+                ("result", nullable("string")),
+                ("result_id", nullable("integer")),
+                ("arguments", nullable_string_array()),
+                (
+                    "argument_ids",
+                    json!({ "type": ["array", "null"], "items": { "type": "integer" } }),
+                ),
+                ("user_stack", stack_schema()),
+                ("stack", stack_schema()),
+            ],
+            &[],
+        ),
+        "compilation_metrics" => object(
+            &[
+                ("co_name", nullable("string")),
+                ("co_filename", nullable("string")),
+                ("co_firstlineno", nullable("integer")),
+                ("cache_size", nullable("integer")),
+                ("accumulated_cache_size", nullable("integer")),
+                ("guard_count", nullable("integer")),
+                ("shape_env_guard_count", nullable("integer")),
+                ("graph_op_count", nullable("integer")),
+                ("graph_node_count", nullable("integer")),
+                ("graph_input_count", nullable("integer")),
+                ("start_time", nullable("number")),
+                ("entire_frame_compile_time_s", nullable("number")),
+                ("backend_compile_time_s", nullable("number")),
+                ("inductor_compile_time_s", nullable("number")),
+                ("code_gen_time_s", nullable("number")),
+                ("fail_type", nullable("string")),
+                ("fail_reason", nullable("string")),
+                ("fail_user_frame_filename", nullable("string")),
+                ("fail_user_frame_lineno", nullable("integer")),
+                ("non_compliant_ops", nullable_string_array()),
+                ("compliant_custom_ops", nullable_string_array()),
+                ("restart_reasons", nullable_string_array()),
+                ("dynamo_time_before_restart_s", nullable("number")),
+            ],
+            &[],
+        ),
+        "bwd_compilation_metrics" => object(
+            &[
+                ("inductor_compile_time_s", nullable("number")),
+                ("code_gen_time_s", nullable("number")),
+                ("fail_type", nullable("string")),
+                ("fail_reason", nullable("string")),
+            ],
+            &[],
+        ),
+        "aot_autograd_backward_compilation_metrics" => object(
+            &[
+                ("start_time", nullable("number")),
+                ("elapsed_time", nullable("number")),
+                ("fail_type", nullable("string")),
+                ("fail_reason", nullable("string")),
+            ],
+            &[],
+        ),
+        "dynamo_start" => object(&[("stack", stack_schema())], &[]),
+        "stack" => stack_schema(),
+        "describe_tensor" => object(
+            &[
+                ("id", json!({ "type": "integer", "minimum": 0 })),
+                ("describer_id", json!({ "type": "integer", "minimum": 0 })),
+                ("ndim", json!({ "type": "integer", "minimum": 0 })),
+                ("dtype", json!({ "type": "string" })),
+                ("device", json!({ "type": "string" })),
+                ("size", json!({ "type": "array" })),
+                ("stride", json!({ "type": ["array", "null"] })),
+                ("layout", json!({ "type": "string" })),
+            ],
+            &["id", "describer_id", "ndim", "dtype", "device", "size"],
+        ),
+        "describe_storage" => object(
+            &[
+                ("id", json!({ "type": "integer", "minimum": 0 })),
+                ("describer_id", json!({ "type": "integer", "minimum": 0 })),
+                ("size", json!({ "type": "integer", "minimum": 0 })),
+            ],
+            &["id", "describer_id", "size"],
+        ),
+        "describe_source" => object(
+            &[
+                ("describer_id", json!({ "type": "integer", "minimum": 0 })),
+                ("id", json!({ "type": "integer", "minimum": 0 })),
+                ("source", json!({ "type": "string" })),
+            ],
+            &["describer_id", "id", "source"],
+        ),
+        "missing_fake_kernel" | "mismatched_fake_kernel" => object(
+            &[("op", nullable("string")), ("reason", nullable("string"))],
+            &[],
+        ),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+/// Schema for a full `IntermediateEntry` line of the given type
+pub fn entry_schema(entry_type: &str) -> Option<Value> {
+    let metadata = metadata_schema(entry_type)?;
+    let mut schema = object(
+        &[
+            ("type", json!({ "const": entry_type })),
+            ("compile_id", nullable("string")),
+            ("rank", json!({ "type": ["integer", "null"], "minimum": 0 })),
+            ("timestamp", json!({ "type": "string" })),
+            ("thread", json!({ "type": "integer", "minimum": 0 })),
+            ("pathname", json!({ "type": "string" })),
+            ("lineno", json!({ "type": "integer", "minimum": 0 })),
+            ("metadata", metadata),
+            ("payload", json!({ "type": "string" })),
+        ],
+        &[
+            "type",
+            "compile_id",
+            "rank",
+            "timestamp",
+            "thread",
+            "pathname",
+            "lineno",
+            "metadata",
+        ],
+    );
+    let obj = schema.as_object_mut().unwrap();
+    obj.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    obj.insert("title".to_string(), json!(format!("{entry_type} entry")));
+    obj.insert("additionalProperties".to_string(), json!(false));
+    Some(schema)
+}
+
+/// Schema for `manifest.json`
+pub fn manifest_schema() -> Value {
+    let mut schema = object(
+        &[
+            ("version", json!({ "type": "string" })),
+            ("generated_at", json!({ "type": "string" })),
+            ("source_file", json!({ "type": "string" })),
+            ("source_file_hash", nullable("string")),
+            (
+                "total_envelopes",
+                json!({ "type": "integer", "minimum": 0 }),
+            ),
+            ("envelope_counts", json!({ "type": "object" })),
+            (
+                "compile_ids",
+                json!({ "type": "array", "items": { "type": "string" } }),
+            ),
+            (
+                "string_table_entries",
+                json!({ "type": "integer", "minimum": 0 }),
+            ),
+            ("parse_mode", json!({ "enum": ["normal", "export"] })),
+            (
+                "ranks",
+                json!({ "type": "array", "items": { "type": "integer", "minimum": 0 } }),
+            ),
+            (
+                "files",
+                json!({ "type": "array", "items": { "type": "string" } }),
+            ),
+        ],
+        &[
+            "version",
+            "generated_at",
+            "source_file",
+            "total_envelopes",
+            "envelope_counts",
+            "compile_ids",
+            "string_table_entries",
+            "parse_mode",
+            "ranks",
+            "files",
+        ],
+    );
+    let obj = schema.as_object_mut().unwrap();
+    obj.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    obj.insert(
+        "title".to_string(),
+        json!(format!(
+            "tlparse intermediate manifest (format {INTERMEDIATE_FORMAT_VERSION})"
+        )),
+    );
+    schema
+}
+
+/// Write `manifest.schema.json` and one `<entry_type>.schema.json` per entry type into
+/// `<output_dir>/schemas/`.
+pub fn write_schemas(output_dir: &Path) -> Result<()> {
+    let dir = output_dir.join(SCHEMA_DIR);
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join("manifest.schema.json"),
+        serde_json::to_string_pretty(&manifest_schema())?,
+    )?;
+    for entry_type in ENTRY_TYPES {
+        if let Some(schema) = entry_schema(entry_type) {
+            fs::write(
+                dir.join(format!("{entry_type}.schema.json")),
+                serde_json::to_string_pretty(&schema)?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => false,
+    }
+}
+
+/// Validate `value` against `schema`, returning one message per violation.
+///
+/// Messages are prefixed with a JSON-pointer style path to the offending value.
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, "", &mut errors);
+    errors
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    let at = if path.is_empty() { "/" } else { path };
+
+    if let Some(ty) = schema.get("type") {
+        let allowed: Vec<&str> = match ty {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(a) => a.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|t| type_matches(value, t)) {
+            errors.push(format!(
+                "{at}: expected {}, got {value}",
+                allowed.join(" or ")
+            ));
+            return;
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            errors.push(format!("{at}: expected {expected}, got {value}"));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{at}: {value} is not one of {}",
+                Value::from(options.clone())
+            ));
+        }
+    }
+    if let (Some(min), Some(n)) = (
+        schema.get("minimum").and_then(|m| m.as_f64()),
+        value.as_f64(),
+    ) {
+        if n < min {
+            errors.push(format!("{at}: {n} is less than the minimum {min}"));
+        }
+    }
+    if let Value::Object(obj) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    errors.push(format!("{at}: missing required property '{key}'"));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for (key, child) in obj {
+            match properties.and_then(|p| p.get(key)) {
+                Some(child_schema) => {
+                    validate_at(child, child_schema, &format!("{path}/{key}"), errors)
+                }
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{at}: unexpected property '{key}'"))
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item, item_schema, &format!("{path}/{i}"), errors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_entry_type_has_a_schema() {
+        for entry_type in ENTRY_TYPES {
+            assert!(
+                entry_schema(entry_type).is_some(),
+                "missing schema for {entry_type}"
+            );
+        }
+        assert!(entry_schema("chromium_event").is_none());
+    }
+
+    #[test]
+    fn test_validate_entry() {
+        let schema = entry_schema("artifact").unwrap();
+        let good = json!({
+            "type": "artifact",
+            "compile_id": "0_0_0",
+            "rank": null,
+            "timestamp": "2024-01-01T00:00:00.000000Z",
+            "thread": 1,
+            "pathname": "test.py",
+            "lineno": 3,
+            "metadata": {"name": "fx_graph_cache_hit", "encoding": "json"},
+            "payload": "{}"
+        });
+        assert!(validate(&good, &schema).is_empty());
+
+        let mut bad = good.clone();
+        bad["type"] = json!("link");
+        bad["metadata"] = json!({"name": 3});
+        bad["extra"] = json!(true);
+        let errors = validate(&bad, &schema);
+        assert!(errors.iter().any(|e| e.starts_with("/type:")));
+        assert!(errors.iter().any(|e| e.starts_with("/metadata/name:")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("missing required property 'encoding'")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("unexpected property 'extra'")));
+    }
+
+    #[test]
+    fn test_validate_manifest() {
+        let manifest = json!({
+            "version": "2.0",
+            "generated_at": "2024-01-01T00:00:00Z",
+            "source_file": "test.log",
+            "source_file_hash": null,
+            "total_envelopes": 1,
+            "envelope_counts": {},
+            "compile_ids": [],
+            "string_table_entries": 0,
+            "parse_mode": "sideways",
+            "ranks": [-1],
+            "files": []
+        });
+        let errors = validate(&manifest, &manifest_schema());
+        assert_eq!(errors.len(), 2, "{errors:?}");
+    }
+}
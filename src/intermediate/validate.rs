@@ -0,0 +1,232 @@
+//! Consistency checks for a directory of intermediate files.
+//!
+//! `validate_intermediate_dir` is what backs `tlparse validate`: it checks the manifest and
+//! every JSONL line against the published schemas, and cross-checks the manifest's bookkeeping
+//! (counts, compile ids, file list, source hash) against what is actually on disk.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::schema;
+use super::{
+    envelope_type_to_file, hash_source_file, route_artifact, IntermediateFileType,
+    IntermediateManifest,
+};
+
+/// Outcome of validating an intermediate directory
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Problems that make the intermediate files unusable or inconsistent
+    pub errors: Vec<String>,
+    /// Suspicious but non-fatal findings (e.g. the source log changed since generation)
+    pub warnings: Vec<String>,
+    /// Number of JSONL entries and chromium events that were checked
+    pub entries_checked: u64,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// The file an entry of the given type must have been written to
+fn expected_file(entry_type: &str, metadata: &Value) -> Option<IntermediateFileType> {
+    if entry_type == "artifact" {
+        let name = metadata.get("name").and_then(|n| n.as_str()).unwrap_or("");
+        return Some(route_artifact(name));
+    }
+    envelope_type_to_file(entry_type)
+}
+
+/// Validate the intermediate files in `dir`.
+///
+/// Only I/O failures on the manifest itself are returned as `Err`; everything else is
+/// collected into the report so that a single run lists all problems.
+pub fn validate_intermediate_dir(dir: &Path) -> Result<ValidationReport> {
+    let mut report = ValidationReport::default();
+
+    let manifest_path = dir.join("manifest.json");
+    let manifest_text = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest_value: Value = serde_json::from_str(&manifest_text)
+        .with_context(|| format!("{} is not valid JSON", manifest_path.display()))?;
+
+    let manifest_errors = schema::validate(&manifest_value, &schema::manifest_schema());
+    if !manifest_errors.is_empty() {
+        report.errors.extend(
+            manifest_errors
+                .into_iter()
+                .map(|e| format!("manifest.json: {}", e)),
+        );
+        return Ok(report);
+    }
+    let manifest: IntermediateManifest = serde_json::from_value(manifest_value)?;
+    if let Err(e) = manifest.check_compatible() {
+        report.errors.push(format!("manifest.json: {}", e));
+        return Ok(report);
+    }
+
+    let manifest_compile_ids: HashSet<&str> =
+        manifest.compile_ids.iter().map(|s| s.as_str()).collect();
+    let mut seen_counts: HashMap<String, u64> = HashMap::new();
+    let mut schemas: HashMap<String, Value> = HashMap::new();
+
+    for file_type in IntermediateFileType::all() {
+        let filename = file_type.filename();
+        let path = dir.join(filename);
+        let listed = manifest.files.iter().any(|f| f == filename);
+        if !path.exists() {
+            if listed {
+                report
+                    .errors
+                    .push(format!("{}: listed in manifest but missing", filename));
+            }
+            continue;
+        }
+
+        if *file_type == IntermediateFileType::ChromiumEvents {
+            let events: Value = match serde_json::from_reader(BufReader::new(File::open(&path)?)) {
+                Ok(v) => v,
+                Err(e) => {
+                    report
+                        .errors
+                        .push(format!("{}: invalid JSON: {}", filename, e));
+                    continue;
+                }
+            };
+            match events.as_array() {
+                Some(events) => {
+                    report.entries_checked += events.len() as u64;
+                    *seen_counts.entry("chromium_event".to_string()).or_insert(0) +=
+                        events.len() as u64;
+                }
+                None => report
+                    .errors
+                    .push(format!("{}: expected a JSON array of events", filename)),
+            }
+            continue;
+        }
+
+        let mut line_count = 0;
+        for (i, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            line_count += 1;
+            report.entries_checked += 1;
+            let loc = format!("{}:{}", filename, i + 1);
+
+            let entry: Value = match serde_json::from_str(&line) {
+                Ok(v) => v,
+                Err(e) => {
+                    report.errors.push(format!("{}: invalid JSON: {}", loc, e));
+                    continue;
+                }
+            };
+            let Some(entry_type) = entry.get("type").and_then(|t| t.as_str()) else {
+                report
+                    .errors
+                    .push(format!("{}: missing string field 'type'", loc));
+                continue;
+            };
+            *seen_counts.entry(entry_type.to_string()).or_insert(0) += 1;
+
+            if !schemas.contains_key(entry_type) {
+                match schema::entry_schema(entry_type) {
+                    Some(s) => {
+                        schemas.insert(entry_type.to_string(), s);
+                    }
+                    None => {
+                        report
+                            .errors
+                            .push(format!("{}: unknown entry type '{}'", loc, entry_type));
+                        continue;
+                    }
+                }
+            }
+            for e in schema::validate(&entry, &schemas[entry_type]) {
+                report.errors.push(format!("{}: {}", loc, e));
+            }
+
+            let metadata = entry.get("metadata").unwrap_or(&Value::Null);
+            if let Some(expected) = expected_file(entry_type, metadata) {
+                if expected != *file_type {
+                    report.errors.push(format!(
+                        "{}: '{}' entry belongs in {}",
+                        loc,
+                        entry_type,
+                        expected.filename()
+                    ));
+                }
+            }
+
+            if let Some(cid) = entry.get("compile_id").and_then(|c| c.as_str()) {
+                if !manifest_compile_ids.contains(cid) {
+                    report.errors.push(format!(
+                        "{}: compile_id '{}' is not listed in the manifest",
+                        loc, cid
+                    ));
+                }
+            }
+        }
+
+        if line_count > 0 && !listed {
+            report.errors.push(format!(
+                "{}: has entries but is not listed in manifest",
+                filename
+            ));
+        }
+    }
+
+    let mut types: Vec<&String> = manifest
+        .envelope_counts
+        .keys()
+        .chain(seen_counts.keys())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    types.sort();
+    for ty in types {
+        let expected = manifest.envelope_counts.get(ty).copied().unwrap_or(0);
+        let actual = seen_counts.get(ty).copied().unwrap_or(0);
+        if expected != actual {
+            report.errors.push(format!(
+                "manifest.json: envelope_counts['{}'] is {} but {} entries were found",
+                ty, expected, actual
+            ));
+        }
+    }
+    let counted: u64 = manifest.envelope_counts.values().sum();
+    if counted != manifest.total_envelopes {
+        report.errors.push(format!(
+            "manifest.json: total_envelopes is {} but envelope_counts sum to {}",
+            manifest.total_envelopes, counted
+        ));
+    }
+
+    if let Some(expected_hash) = &manifest.source_file_hash {
+        let source = Path::new(&manifest.source_file);
+        if source.is_file() {
+            let actual_hash = hash_source_file(source)?;
+            if &actual_hash != expected_hash {
+                report.warnings.push(format!(
+                    "{} has changed since these files were generated ({} != {})",
+                    manifest.source_file, actual_hash, expected_hash
+                ));
+            }
+        } else {
+            report.warnings.push(format!(
+                "{} not found; skipping source hash check",
+                manifest.source_file
+            ));
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,412 @@
+//! Inductor kernel browser.
+//!
+//! Splits the output code of each compile id into its kernels: the Triton and C++ kernels it
+//! defines with `async_compile`, and the extern kernels and fallback ops its wrapper calls. They
+//! are joined with the `triton_kernel_info` and `inductor_triton_kernel_to_post_grad_nodes`
+//! artifacts, which are keyed by kernel name, into a page per kernel and a table per compile id.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::types::*;
+
+/// How many of the slowest kernels to compile are listed
+const SLOWEST_KERNELS_LIMIT: usize = 10;
+
+static DEFINITION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?<name>\w+) = async_compile\.(?<how>\w+)\(").unwrap());
+static EXTERN_CALL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(?<name>extern_kernels\.\w+|torch\.ops\.[\w.]+)\(").unwrap());
+static SOURCE_NODES: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^# Topologically Sorted Source Nodes: \[(?<nodes>.*?)\]").unwrap());
+
+fn kernel_kind(name: &str, how: &str) -> &'static str {
+    match how {
+        "triton" => match name.split('_').nth(1) {
+            Some("poi") => "pointwise",
+            Some("red") => "reduction",
+            Some("per") => "persistent reduction",
+            Some("tem") => "template",
+            Some("for") => "foreach",
+            _ => "triton",
+        },
+        h if h.starts_with("cpp") => "cpp",
+        "cuda" => "cuda template",
+        "rocm" => "rocm template",
+        "halide" => "halide",
+        _ => "other",
+    }
+}
+
+struct Kernel {
+    name: String,
+    kind: &'static str,
+    // Path of the output code page, and the line the kernel is defined on
+    output_code: Option<String>,
+    line: Option<usize>,
+    source: String,
+    source_nodes: Option<String>,
+    // Lines of the wrapper that launch or call the kernel
+    launches: Vec<(usize, String)>,
+    info: Option<serde_json::Map<String, Value>>,
+    post_grad_nodes: Option<Vec<String>>,
+}
+
+impl Kernel {
+    fn compile_time_us(&self) -> Option<u64> {
+        self.info.as_ref()?.get("compile_time_us")?.as_u64()
+    }
+
+    fn info_field(&self, field: &str) -> String {
+        self.info
+            .as_ref()
+            .and_then(|info| info.get(field))
+            .map_or(String::new(), |v| match v {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            })
+    }
+}
+
+/// The kernels defined and called by an output code payload, in order
+fn split_output_code(payload: &str, output_code: Option<String>) -> Vec<Kernel> {
+    let mut kernels: Vec<Kernel> = Vec::new();
+    let mut comments: Vec<&str> = Vec::new();
+    let mut lines = payload.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let lineno = i + 1;
+        if line.starts_with('#') {
+            comments.push(line);
+            continue;
+        }
+        if let Some(caps) = DEFINITION.captures(line) {
+            // The kernel's source is a string literal that ends on a line starting with '''
+            let mut source = Vec::new();
+            for (_, source_line) in lines.by_ref() {
+                if source_line.starts_with("'''") {
+                    break;
+                }
+                source.push(source_line);
+            }
+            kernels.push(Kernel {
+                name: caps["name"].to_string(),
+                kind: kernel_kind(&caps["name"], &caps["how"]),
+                output_code: output_code.clone(),
+                line: Some(lineno),
+                source: source.join("\n"),
+                source_nodes: comments
+                    .iter()
+                    .find_map(|c| Some(SOURCE_NODES.captures(c)?["nodes"].to_string())),
+                launches: Vec::new(),
+                info: None,
+                post_grad_nodes: None,
+            });
+            comments.clear();
+            continue;
+        }
+        if !line.trim().is_empty() {
+            comments.clear();
+        }
+        let code = line.trim();
+        if code.starts_with('#') {
+            continue;
+        }
+        // Triton kernels are launched with .run(), C++ kernels are called directly
+        let launched = kernels.iter_mut().find(|k| {
+            code.strip_prefix(k.name.as_str())
+                .is_some_and(|rest| rest.starts_with(".run(") || rest.starts_with('('))
+        });
+        if let Some(kernel) = launched {
+            kernel.launches.push((lineno, code.to_string()));
+            continue;
+        }
+        for caps in EXTERN_CALL.captures_iter(code) {
+            let name = &caps["name"];
+            // Ops aliased at the top of the wrapper, like `aten = torch.ops.aten`
+            if !name.contains('.') || code.contains(" = torch.ops.") {
+                continue;
+            }
+            let kernel = match kernels.iter().position(|k| k.name == name) {
+                Some(i) => &mut kernels[i],
+                None => {
+                    kernels.push(Kernel {
+                        name: name.to_string(),
+                        kind: if name.starts_with("extern_kernels.") {
+                            "extern"
+                        } else {
+                            "fallback"
+                        },
+                        output_code: output_code.clone(),
+                        line: None,
+                        source: String::new(),
+                        source_nodes: None,
+                        launches: Vec::new(),
+                        info: None,
+                        post_grad_nodes: None,
+                    });
+                    kernels.last_mut().unwrap()
+                }
+            };
+            kernel.launches.push((lineno, code.to_string()));
+        }
+    }
+    kernels
+}
+
+/// Kernel tables and kernel pages, keyed by filename
+type KernelPages = (
+    Vec<(String, CompileKernelsContext)>,
+    Vec<(String, KernelContext)>,
+);
+
+#[derive(Default)]
+struct CompileKernels {
+    compile_id: String,
+    kernels: Vec<Kernel>,
+}
+
+#[derive(Default)]
+pub struct KernelIndex {
+    compiles: FxIndexMap<Option<CompileId>, CompileKernels>,
+}
+
+impl KernelIndex {
+    /// Record the kernels of an `inductor_output_code` envelope, whose page was written to
+    /// `output_code`, and the kernel artifacts
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str, output_code: Option<&Path>) {
+        let artifact = e.artifact.as_ref().map(|a| a.name.as_str());
+        if e.inductor_output_code.is_none()
+            && artifact != Some("triton_kernel_info")
+            && artifact != Some("inductor_triton_kernel_to_post_grad_nodes")
+        {
+            return;
+        }
+        let compile = self
+            .compiles
+            .entry(e.compile_id.clone())
+            .or_insert_with(|| CompileKernels {
+                compile_id: e
+                    .compile_id
+                    .as_ref()
+                    .map_or("(unknown)".to_string(), |c| c.to_string()),
+                ..Default::default()
+            });
+        if e.inductor_output_code.is_some() {
+            let output_code = output_code.map(|p| p.to_string_lossy().to_string());
+            compile
+                .kernels
+                .extend(split_output_code(payload, output_code));
+            return;
+        }
+        let Ok(Value::Object(by_kernel)) = serde_json::from_str::<Value>(payload) else {
+            return;
+        };
+        // Forward and backward graphs can have kernels of the same name, so an artifact applies
+        // to the latest kernel of each name that it hasn't been joined with yet
+        for (name, value) in by_kernel {
+            let latest = |kernels: &[Kernel], joined: fn(&Kernel) -> bool| {
+                kernels
+                    .iter()
+                    .rposition(|k| k.name == name && !joined(k))
+                    .or_else(|| kernels.iter().rposition(|k| k.name == name))
+            };
+            if artifact == Some("triton_kernel_info") {
+                if let Value::Object(info) = value {
+                    if let Some(i) = latest(&compile.kernels, |k| k.info.is_some()) {
+                        compile.kernels[i].info = Some(info);
+                    }
+                }
+            } else if let Value::Array(nodes) = value {
+                let nodes = nodes
+                    .iter()
+                    .filter_map(|n| n.as_str().map(str::to_string))
+                    .collect();
+                if let Some(i) = latest(&compile.kernels, |k| k.post_grad_nodes.is_some()) {
+                    compile.kernels[i].post_grad_nodes = Some(nodes);
+                }
+            }
+        }
+    }
+
+    /// The kernel table of a compile id, if its output code defined or called any kernels
+    pub fn page_name(&self, compile_id: &Option<CompileId>) -> Option<String> {
+        let cid = compile_id.as_ref()?;
+        self.compiles
+            .get(compile_id)
+            .filter(|c| !c.kernels.is_empty())
+            .map(|_| format!("{}/kernels.html", cid.as_directory_name()))
+    }
+
+    /// Build the kernel table of each compile id and the pages of its kernels, keyed by filename
+    pub fn finish(&self, css: &'static str, qps: &'static str) -> KernelPages {
+        let mut tables = Vec::new();
+        let mut pages = Vec::new();
+        for (compile_id, compile) in &self.compiles {
+            let Some(table) = self.page_name(compile_id) else {
+                continue;
+            };
+            let directory = compile_id.as_ref().unwrap().as_directory_name();
+            let mut rows = Vec::new();
+            let mut page_names: Vec<String> = Vec::new();
+            for kernel in &compile.kernels {
+                let mut page = format!("{}/kernel_{}.html", directory, kernel.name);
+                let mut n = 1;
+                while page_names.contains(&page) {
+                    page = format!("{}/kernel_{}_{}.html", directory, kernel.name, n);
+                    n += 1;
+                }
+                page_names.push(page.clone());
+                rows.push(KernelRowContext {
+                    name: kernel.name.clone(),
+                    page: page.clone(),
+                    kind: kernel.kind,
+                    cache_state: kernel.info_field("autotune_cache_state"),
+                    num_configs: kernel.info_field("num_configs"),
+                    compile_time: kernel
+                        .compile_time_us()
+                        .map_or(String::new(), |us| format!("{:.1}", us as f64 / 1e3)),
+                    only_config: kernel.info_field("only_config"),
+                    num_post_grad_nodes: kernel
+                        .post_grad_nodes
+                        .as_ref()
+                        .map_or(String::new(), |n| n.len().to_string()),
+                    num_launches: kernel.launches.len(),
+                });
+                pages.push((
+                    page,
+                    KernelContext {
+                        css,
+                        qps,
+                        compile_id: compile.compile_id.clone(),
+                        kernels_page: table.clone(),
+                        name: kernel.name.clone(),
+                        kind: kernel.kind,
+                        output_code: kernel.output_code.clone().unwrap_or_default(),
+                        line: kernel.line.map_or(String::new(), |l| l.to_string()),
+                        info: kernel
+                            .info
+                            .iter()
+                            .flatten()
+                            .map(|(key, value)| KernelInfoContext {
+                                key: key.clone(),
+                                value: match value {
+                                    Value::String(s) => s.clone(),
+                                    v => v.to_string(),
+                                },
+                            })
+                            .collect(),
+                        post_grad_nodes: kernel
+                            .post_grad_nodes
+                            .as_ref()
+                            .map_or(String::new(), |n| n.join(", ")),
+                        source_nodes: kernel.source_nodes.clone().unwrap_or_default(),
+                        launches: kernel
+                            .launches
+                            .iter()
+                            .map(|(line, code)| KernelLaunchContext {
+                                line: *line,
+                                code: code.clone(),
+                            })
+                            .collect(),
+                        source: kernel.source.clone(),
+                    },
+                ));
+            }
+
+            let mut counts: FxIndexMap<&'static str, usize> = FxIndexMap::default();
+            for kernel in &compile.kernels {
+                *counts.entry(kernel.kind).or_default() += 1;
+            }
+            let mut slowest: Vec<usize> = (0..compile.kernels.len())
+                .filter(|&i| compile.kernels[i].compile_time_us().is_some())
+                .collect();
+            slowest.sort_by_key(|&i| std::cmp::Reverse(compile.kernels[i].compile_time_us()));
+            slowest.truncate(SLOWEST_KERNELS_LIMIT);
+            let slowest = slowest.into_iter().map(|i| rows[i].clone()).collect();
+            tables.push((
+                table,
+                CompileKernelsContext {
+                    css,
+                    qps,
+                    compile_id: compile.compile_id.clone(),
+                    counts: counts
+                        .into_iter()
+                        .map(|(kind, count)| KernelKindContext { kind, count })
+                        .collect(),
+                    kernels: rows,
+                    slowest_limit: SLOWEST_KERNELS_LIMIT,
+                    slowest,
+                },
+            ));
+        }
+        (tables, pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT_CODE: &str = r#"from torch._inductor.select_algorithm import extern_kernels
+aten = torch.ops.aten
+
+# kernel path: /tmp/a.py
+# Topologically Sorted Source Nodes: [relu], Original ATen: [aten.relu]
+# Source node to ATen node mapping:
+#   relu => relu
+triton_poi_fused_relu_0 = async_compile.triton('triton_poi_fused_relu_0', '''
+import triton
+
+@triton.jit
+def triton_poi_fused_relu_0(in_out_ptr0, xnumel, XBLOCK : tl.constexpr):
+    xoffset = tl.program_id(0) * XBLOCK
+''', device_str='cuda')
+
+
+cpp_fused_add_1 = async_compile.cpp_pybinding(['float*'], '''
+extern "C" void kernel(float* out_ptr0) {}
+''')
+
+
+def call(args):
+    arg0_1, = args
+    buf0 = empty_strided_cuda((4, 4), (4, 1), torch.float32)
+    extern_kernels.mm(arg0_1, arg0_1, out=buf0)
+    triton_poi_fused_relu_0.run(buf0, 16, stream=stream0)
+    cpp_fused_add_1(buf0)
+    torch.ops._c10d_functional.all_reduce_.default(buf0, 'avg', '0')
+    extern_kernels.mm(buf0, buf0, out=buf1)
+    return (buf1, )
+"#;
+
+    #[test]
+    fn test_split_output_code() {
+        let kernels = split_output_code(OUTPUT_CODE, Some("a/code.html".to_string()));
+        let summary: Vec<(&str, &str, Option<usize>, usize)> = kernels
+            .iter()
+            .map(|k| (k.name.as_str(), k.kind, k.line, k.launches.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("triton_poi_fused_relu_0", "pointwise", Some(8), 1),
+                ("cpp_fused_add_1", "cpp", Some(17), 1),
+                ("extern_kernels.mm", "extern", None, 2),
+                (
+                    "torch.ops._c10d_functional.all_reduce_.default",
+                    "fallback",
+                    None,
+                    1
+                ),
+            ]
+        );
+        assert_eq!(kernels[0].source_nodes.as_deref(), Some("relu"));
+        assert!(kernels[0]
+            .source
+            .contains("def triton_poi_fused_relu_0(in_out_ptr0"));
+        assert_eq!(kernels[2].launches[0].0, 25);
+    }
+}
@@ -1,5 +1,4 @@
 use anyhow::{anyhow, bail};
-use chrono::Datelike;
 use fxhash::{FxHashMap, FxHashSet};
 use md5::{Digest, Md5};
 use std::ffi::{OsStr, OsString};
@@ -7,6 +6,7 @@ use std::ffi::{OsStr, OsString};
 use html_escape::encode_text;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use regex::Regex;
+use serde::Deserialize;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::fs::{self, File};
@@ -15,15 +15,49 @@ use std::path::{Path, PathBuf};
 use std::time::Instant;
 use tinytemplate::TinyTemplate;
 
-use crate::parsers::default_parsers;
+use crate::autotuning::AutotuneReport;
+use crate::compile_time::CompileTimeProfile;
+use crate::dynamic_shapes::DynamicShapes;
+use crate::graph_breaks::GraphBreakLeaderboard;
+use crate::graph_stats::GraphStats;
+use crate::guards::GuardAnalytics;
+use crate::highlight::HighlightPool;
+use crate::kernels::KernelIndex;
+use crate::parsers::default_parsers_with_sym_exprs;
 use crate::parsers::ParserOutput;
 use crate::parsers::StructuredLogParser;
+use crate::provenance::ProvenanceIndex;
+use crate::recompiles::RecompileIndex;
+use crate::search::SearchIndex;
+use crate::source_index::SourceIndex;
 use crate::templates::*;
+use crate::tensor_metadata::TensorMetadataIndex;
+use crate::timeline::CompileTimeline;
+use crate::timestamps::GlogClock;
 use crate::types::*;
+mod autotuning;
+mod compile_stragglers;
+mod compile_time;
+mod dynamic_shapes;
+mod first_divergence;
+mod graph_breaks;
+mod graph_stats;
+mod guards;
+mod highlight;
 pub mod intermediate;
+mod kernels;
 pub mod modules;
 pub mod parsers;
+mod provenance;
+pub mod query;
+mod rank_comparison;
+mod recompiles;
+mod search;
+mod source_index;
 mod templates;
+mod tensor_metadata;
+mod timeline;
+mod timestamps;
 mod types;
 
 pub use types::{
@@ -60,11 +94,36 @@ pub struct ParseConfig {
     pub verbose: bool,
     pub plain_text: bool,
     pub export: bool,
+    /// Deprecated, has no effect: provenance pages are generated whenever the log has node
+    /// mappings
     pub inductor_provenance: bool,
     /// If set, generate intermediate JSON files to this directory
     pub intermediate_output: Option<PathBuf>,
+    /// Load the build products of each compile id on demand instead of inlining them all into
+    /// index.html. Always on for logs with more than `LAZY_INDEX_THRESHOLD` compile ids.
+    pub lazy_index: bool,
+    /// Report the time spent on each envelope type to stderr after parsing
+    pub profile: bool,
+    /// Graphs and code larger than this many bytes are written as plain text, which the page
+    /// can highlight in the browser on demand
+    pub highlight_size_limit: usize,
+    /// Multi-rank reports flag ranks whose total compile time is above this percentile of all
+    /// ranks and more than 10% above the median
+    pub straggler_percentile: f64,
 }
 
+/// Number of compile ids above which index.html switches to lazy loading
+pub const LAZY_INDEX_THRESHOLD: usize = 1000;
+
+/// Default for `ParseConfig::highlight_size_limit`
+pub const DEFAULT_HIGHLIGHT_SIZE_LIMIT: usize = 1_000_000;
+
+/// Default for `ParseConfig::straggler_percentile`
+pub const DEFAULT_STRAGGLER_PERCENTILE: f64 = 90.0;
+
+/// How often the progress bar and stats spinner are refreshed while parsing
+const PROGRESS_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
 impl Default for ParseConfig {
     fn default() -> Self {
         Self {
@@ -77,6 +136,10 @@ impl Default for ParseConfig {
             export: false,
             inductor_provenance: false,
             intermediate_output: None,
+            lazy_index: false,
+            profile: false,
+            highlight_size_limit: DEFAULT_HIGHLIGHT_SIZE_LIMIT,
+            straggler_percentile: DEFAULT_STRAGGLER_PERCENTILE,
         }
     }
 }
@@ -218,6 +281,41 @@ fn add_stack_traces_html(
     html_path_str
 }
 
+// Which parsers to offer an envelope to, by envelope type. Parsers that declare their
+// envelope types are only run on those; the rest are run on everything. Parser order is
+// preserved, since later PayloadFilename results win.
+struct ParserDispatch {
+    by_type: FxHashMap<&'static str, Vec<usize>>,
+    untyped: Vec<usize>,
+}
+
+impl ParserDispatch {
+    // parser_types[i] is the envelope_types() of parser i
+    fn new(parser_types: &[Option<&[&'static str]>]) -> Self {
+        let untyped: Vec<usize> = (0..parser_types.len())
+            .filter(|&i| parser_types[i].is_none())
+            .collect();
+        let mut by_type: FxHashMap<&'static str, Vec<usize>> = FxHashMap::default();
+        for &ty in parser_types.iter().flatten().copied().flatten() {
+            by_type.entry(ty).or_insert_with(|| {
+                (0..parser_types.len())
+                    .filter(|&i| match parser_types[i] {
+                        Some(types) => types.contains(&ty),
+                        None => true,
+                    })
+                    .collect()
+            });
+        }
+        ParserDispatch { by_type, untyped }
+    }
+
+    fn parsers_for(&self, envelope_type: Option<&str>) -> &[usize] {
+        envelope_type
+            .and_then(|ty| self.by_type.get(ty))
+            .unwrap_or(&self.untyped)
+    }
+}
+
 fn run_parser<'t>(
     lineno: usize,
     parser: &Box<dyn StructuredLogParser + 't>,
@@ -226,6 +324,7 @@ fn run_parser<'t>(
     output_count: &mut i32,
     output: &mut ParseOutput,
     compile_directory: &mut Vec<OutputFile>,
+    highlighter: &mut HighlightPool,
     multi: &MultiProgress,
     stats: &mut Stats,
 ) -> ParserResult {
@@ -243,6 +342,18 @@ fn run_parser<'t>(
                         ParserOutput::GlobalFile(filename, out) => {
                             add_file_output(filename, out, output, compile_directory, output_count);
                         }
+                        ParserOutput::HighlightedFile(raw_filename, view) => {
+                            // The content is filled in once the pool has rendered the view
+                            let filename = add_unique_suffix(raw_filename, *output_count);
+                            highlighter.submit(output.len(), &filename, view);
+                            add_file_output(
+                                filename,
+                                String::new(),
+                                output,
+                                compile_directory,
+                                output_count,
+                            );
+                        }
                         ParserOutput::PayloadFile(raw_filename) => {
                             let filename = add_unique_suffix(raw_filename, *output_count);
                             payload_filename = ParserResult::PayloadFilename(
@@ -311,6 +422,22 @@ fn run_parser<'t>(
     payload_filename
 }
 
+fn output_files_to_json(output_files: &[OutputFile]) -> Vec<serde_json::Value> {
+    output_files
+        .iter()
+        .map(|file| {
+            serde_json::json!({
+                "url": file.url,
+                // Strip away any leading directory names, that will just be in the url path anyway
+                "name": file.name.split('/').last().unwrap_or(&file.name),
+                "number": file.number,
+                "suffix": file.suffix,
+                "readable_url": file.readable_url,
+            })
+        })
+        .collect()
+}
+
 fn directory_to_json(
     directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
 ) -> serde_json::Value {
@@ -321,25 +448,69 @@ fn directory_to_json(
             .as_ref()
             .map_or_else(|| "unknown".to_string(), |cid| cid.to_string());
 
-        let artifacts: Vec<serde_json::Value> = output_files
-            .iter()
-            .map(|file| {
-                serde_json::json!({
-                    "url": file.url,
-                    // Strip away any leading directory names, that will just be in the url path anyway
-                    "name": file.name.split('/').last().unwrap_or(&file.name),
-                    "number": file.number,
-                    "suffix": file.suffix,
-                    "readable_url": file.readable_url,
-                })
-            })
-            .collect();
-
-        json_map.insert(key, serde_json::json!({"artifacts": artifacts}));
+        json_map.insert(
+            key,
+            serde_json::json!({"artifacts": output_files_to_json(output_files)}),
+        );
     }
     serde_json::Value::Object(json_map)
 }
 
+/// Write one `index_shard.js` per compile id directory for the lazy index, returning the
+/// compile id list that index.html pages through.
+fn write_lazy_directory_shards(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    output: &mut ParseOutput,
+) -> anyhow::Result<String> {
+    let mut entries = Vec::with_capacity(directory.len());
+    for (compile_id, output_files) in directory {
+        let dir = compile_id
+            .as_ref()
+            .map_or_else(|| "unknown".to_string(), |cid| cid.as_directory_name());
+        output.push((
+            PathBuf::from(&dir).join("index_shard.js"),
+            format!(
+                "tlparseShard({}, {});\n",
+                serde_json::to_string(&dir)?,
+                serde_json::to_string(&output_files_to_json(output_files))?
+            ),
+        ));
+        entries.push(serde_json::json!({
+            "id": compile_id.as_ref().map_or("(unknown)".to_string(), |cid| cid.to_string()),
+            "dir": dir,
+            "count": output_files.len(),
+            "status": compile_status_class(metrics_index, compile_id),
+        }));
+    }
+    // Inlined into a <script>, so make sure no string can close it
+    Ok(serde_json::to_string(&entries)?.replace("</", "<\\/"))
+}
+
+/// Add the first output file of an envelope to the search index. Text outputs are indexed as
+/// written, so hits line up with the file's lines; rendered pages are indexed from the payload
+/// they were rendered from. `code_view` says whether the file is a highlighted code page, the
+/// only kind with `#L<line>` anchors to link hits to.
+fn add_to_search_index(
+    search_index: &mut SearchIndex,
+    compile_id: &str,
+    path: &Path,
+    content: &str,
+    payload: &str,
+    code_view: bool,
+) {
+    let url = path.to_string_lossy();
+    let name = path
+        .file_name()
+        .map_or_else(|| url.to_string(), |n| n.to_string_lossy().to_string());
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("txt") | Some("json") => search_index.add_document(compile_id, &url, &name, content),
+        // Highlighted source keeps the payload's lines one for one
+        _ if code_view => search_index.add_document(compile_id, &url, &name, payload),
+        _ => search_index.add_unanchored_document(compile_id, &url, &name, payload),
+    }
+}
+
 fn handle_guard(
     failure_type: &str,
     reason: &str,
@@ -349,17 +520,17 @@ fn handle_guard(
     output_count: &mut i32,
     output: &mut Vec<(PathBuf, String)>,
     compile_directory: &mut Vec<OutputFile>,
+    highlighter: &mut HighlightPool,
     multi: &MultiProgress,
     stats: &mut Stats,
     tt: &TinyTemplate,
     sym_expr_info_index: &RefCell<SymExprInfoIndex>,
     export_failures: &mut Vec<ExportFailure>,
 ) {
-    let sym_expr_info_index_borrowed = sym_expr_info_index.borrow();
     let parser: Box<dyn StructuredLogParser> =
         Box::new(crate::parsers::PropagateRealTensorsParser {
             tt,
-            sym_expr_info_index: &sym_expr_info_index_borrowed,
+            sym_expr_info_index,
         });
     let _ = run_parser(
         lineno,
@@ -369,6 +540,7 @@ fn handle_guard(
         output_count,
         output,
         compile_directory,
+        highlighter,
         multi,
         stats,
     );
@@ -433,24 +605,8 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         serde_json::Value::Number(serde_json::Number::from(parsed))
     };
 
-    // Helper function to format timestamp as ISO-8601
-    let format_timestamp = |caps: &regex::Captures| -> String {
-        let month: u32 = caps.name("month").unwrap().as_str().parse().unwrap();
-        let day: u32 = caps.name("day").unwrap().as_str().parse().unwrap();
-        let hour: u32 = caps.name("hour").unwrap().as_str().parse().unwrap();
-        let minute: u32 = caps.name("minute").unwrap().as_str().parse().unwrap();
-        let second: u32 = caps.name("second").unwrap().as_str().parse().unwrap();
-        let microsecond: u32 = caps.name("millisecond").unwrap().as_str().parse().unwrap();
-
-        // Assume current year since glog doesn't include year
-        let year = chrono::Utc::now().year();
-
-        // Format as ISO-8601 with microsecond precision
-        format!(
-            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
-            year, month, day, hour, minute, second, microsecond
-        )
-    };
+    // glog doesn't log the year or timezone, so infer them from the log
+    let mut clock = GlogClock::for_file(path);
 
     let mut stack_trie = StackTrieNode::default();
     let mut unknown_stack_trie = StackTrieNode::default();
@@ -459,10 +615,11 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
     let _mod_count: FxHashMap<String, i32> = FxHashMap::default();
 
     let mut bytes_read: u64 = 0;
+    let mut last_progress_update = Instant::now();
 
-    // Some stuff for profiling
-    let mut fastest_time = std::time::Duration::MAX;
-    let mut slowest_time = std::time::Duration::ZERO;
+    // With --profile, time spent on each envelope type, including its payload lines
+    let mut profile = EnvelopeProfile::default();
+    let mut profiled_envelope: Option<(&'static str, Instant)> = None;
 
     let mut expected_rank: Option<Option<u32>> = None;
 
@@ -487,15 +644,28 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
 
     let mut tt: TinyTemplate = TinyTemplate::new();
     tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+    tt.add_template(
+        "symbolic_guard_information.html",
+        TEMPLATE_SYMBOLIC_GUARD_INFO,
+    )?;
     if config.export {
         tt.add_template("index.html", TEMPLATE_EXPORT_INDEX)?;
-        tt.add_template(
-            "symbolic_guard_information.html",
-            TEMPLATE_SYMBOLIC_GUARD_INFO,
-        )?;
     } else {
         tt.add_template("index.html", TEMPLATE_INDEX)?;
         tt.add_template("failures_and_restarts.html", TEMPLATE_FAILURES_AND_RESTARTS)?;
+        tt.add_template("graph_breaks.html", TEMPLATE_GRAPH_BREAKS)?;
+        tt.add_template("recompiles.html", TEMPLATE_RECOMPILES)?;
+        tt.add_template("guards.html", TEMPLATE_GUARD_ANALYTICS)?;
+        tt.add_template("compile_time.html", TEMPLATE_COMPILE_TIME)?;
+        tt.add_template("timeline.html", TEMPLATE_TIMELINE)?;
+        tt.add_template("dynamic_shapes.html", TEMPLATE_DYNAMIC_SHAPES)?;
+        tt.add_template("tensor_metadata.html", TEMPLATE_TENSOR_METADATA)?;
+        tt.add_template("compile_graph_stats.html", TEMPLATE_COMPILE_GRAPH_STATS)?;
+        tt.add_template("graph_stats.html", TEMPLATE_GRAPH_STATS)?;
+        tt.add_template("compile_kernels.html", TEMPLATE_COMPILE_KERNELS)?;
+        tt.add_template("kernel.html", TEMPLATE_KERNEL)?;
+        tt.add_template("autotuning.html", TEMPLATE_AUTOTUNING)?;
+        tt.add_template("user_source.html", TEMPLATE_USER_SOURCE)?;
         tt.add_template("dynamo_guards.html", TEMPLATE_DYNAMO_GUARDS)?;
         tt.add_template("compilation_metrics.html", TEMPLATE_COMPILATION_METRICS)?;
         tt.add_template(
@@ -508,17 +678,34 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         )?;
     }
     tt.add_template("provenance_tracking.html", TEMPLATE_PROVENANCE_TRACKING)?;
+    tt.add_template("dynamo_cpp_guards_str.html", TEMPLATE_CPP_GUARDS)?;
 
     let mut unknown_fields: FxHashSet<String> = FxHashSet::default();
 
     let mut output_count = 0;
 
+    let mut search_index = SearchIndex::default();
+
     let mut breaks = RestartsAndFailuresContext {
         css: TEMPLATE_FAILURES_CSS,
         failures: Vec::new(),
         qps: TEMPLATE_QUERY_PARAM_SCRIPT,
     };
 
+    let mut graph_breaks = GraphBreakLeaderboard::default();
+    let mut recompiles = RecompileIndex::default();
+    let mut guard_analytics = GuardAnalytics::default();
+    let mut compile_time = CompileTimeProfile::default();
+    let mut timeline = CompileTimeline::default();
+    let mut dynamic_shapes = DynamicShapes::default();
+    let mut tensor_metadata = TensorMetadataIndex::default();
+    let mut graph_stats = GraphStats::default();
+    let mut kernels = KernelIndex::default();
+    let mut autotuning = AutotuneReport::default();
+    let mut provenance = ProvenanceIndex::default();
+    let mut source_index = SourceIndex::default();
+    let mut dynamo_guards_index: DynamoGuardsIndex = FxHashMap::default();
+
     let mut export_failures: Vec<ExportFailure> = Vec::new();
 
     // NB: Sometimes, the log output we get from Logarithm stutters with a blank line.
@@ -533,17 +720,30 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         })
         .peekable();
 
-    let default_parsers = default_parsers(&tt, config);
+    let default_parsers = default_parsers_with_sym_exprs(&tt, config, &sym_expr_info_index);
     let mut all_parsers: Vec<&Box<dyn StructuredLogParser>> = default_parsers.iter().collect();
     let mut chromium_events: Vec<serde_json::Value> = Vec::new();
     all_parsers.extend(config.custom_parsers.iter());
+    let parser_types: Vec<_> = all_parsers.iter().map(|p| p.envelope_types()).collect();
+    let parser_dispatch = ParserDispatch::new(&parser_types);
+    let mut highlighter = HighlightPool::new(config.highlight_size_limit);
 
     while let Some((lineno, line)) = iter.next() {
         bytes_read += line.len() as u64;
-        pb.set_position(bytes_read);
-        spinner.set_message(format!("{}", stats));
-        //spinner.set_message(format!("{:?} {:?}", slowest_time, fastest_time));
-        let start = Instant::now();
+        // Formatting stats for every line is surprisingly expensive, so only refresh
+        // the progress bars every so often
+        if last_progress_update.elapsed() >= PROGRESS_UPDATE_INTERVAL {
+            pb.set_position(bytes_read);
+            spinner.set_message(format!("{}", stats));
+            last_progress_update = Instant::now();
+        }
+        if config.profile {
+            let now = Instant::now();
+            if let Some((envelope_type, start)) = profiled_envelope.take() {
+                profile.record(envelope_type, now - start);
+            }
+            profiled_envelope = Some(("(unparsed)", now));
+        }
 
         let Some(caps) = re_glog.captures(&line) else {
             multi.suspend(|| eprintln!("Failed to parse glog prefix on line {}", lineno));
@@ -551,15 +751,8 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             continue;
         };
 
-        let end = start.elapsed();
-        if end < fastest_time {
-            fastest_time = end;
-        }
-        if end > slowest_time {
-            slowest_time = end;
-        }
         let payload = &line[caps.name("payload").unwrap().start()..];
-        let original_json_envelope = payload; // Store the original JSON envelope
+        let datetime = clock.datetime(&caps);
 
         // Helper function to safely insert keys and detect conflicts
         let try_insert = |obj: &mut serde_json::Map<String, serde_json::Value>,
@@ -580,19 +773,23 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             }
         };
 
-        // Create cleanup lambda to handle raw.jsonl writing as JSONL
+        // Create cleanup lambda to handle raw.jsonl writing as JSONL. It takes the envelope
+        // that was already parsed for the Envelope, rather than parsing the line again.
         let write_to_shortraw = |shortraw_content: &mut String,
+                                 envelope_value: Result<serde_json::Value, serde_json::Error>,
                                  payload_filename: Option<String>,
                                  multi: &MultiProgress,
                                  stats: &mut Stats| {
-            match serde_json::from_str::<serde_json::Value>(original_json_envelope) {
+            match envelope_value {
                 Ok(mut json_value) => {
                     if let Some(obj) = json_value.as_object_mut() {
                         // Try to add all log fields, abort on any conflict
                         let success = try_insert(
                             obj,
                             "timestamp",
-                            serde_json::Value::String(format_timestamp(&caps)),
+                            serde_json::Value::String(
+                                datetime.map_or(String::new(), |dt| clock.format(&dt)),
+                            ),
                             multi,
                             stats,
                         ) && try_insert(
@@ -668,7 +865,21 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             }
         };
 
-        let e = match serde_json::from_str::<Envelope>(payload) {
+        let envelope_value = match serde_json::from_str::<serde_json::Value>(payload) {
+            Ok(v) => v,
+            Err(err) => {
+                multi.suspend(|| {
+                    eprintln!(
+                        "Failed to parse metadata JSON: \n{:?} on line {}",
+                        err, lineno
+                    );
+                });
+                stats.fail_json += 1;
+                write_to_shortraw(&mut shortraw_content, Err(err), None, &multi, &mut stats);
+                continue;
+            }
+        };
+        let e = match Envelope::deserialize(&envelope_value) {
             Ok(r) => r,
             Err(err) => {
                 multi.suspend(|| {
@@ -678,10 +889,26 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     );
                 });
                 stats.fail_json += 1;
-                write_to_shortraw(&mut shortraw_content, None, &multi, &mut stats);
+                write_to_shortraw(
+                    &mut shortraw_content,
+                    Ok(envelope_value),
+                    None,
+                    &multi,
+                    &mut stats,
+                );
                 continue;
             }
         };
+        // Runtime metrics are logged long after the compile they belong to finished
+        if let Some(datetime) =
+            datetime.filter(|_| !e._other.contains_key("compilation_metrics_runtime"))
+        {
+            timeline.add(&e.compile_id, datetime);
+        }
+        let envelope_type = intermediate::detect_envelope_type(&e);
+        if let Some((profiled_type, _)) = profiled_envelope.as_mut() {
+            *profiled_type = envelope_type.unwrap_or("(unknown)");
+        }
 
         stats.unknown += e._other.len() as u64;
 
@@ -729,7 +956,13 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             Some(rank) => {
                 if rank != e.rank {
                     stats.other_rank += 1;
-                    write_to_shortraw(&mut shortraw_content, None, &multi, &mut stats);
+                    write_to_shortraw(
+                        &mut shortraw_content,
+                        Ok(envelope_value),
+                        None,
+                        &multi,
+                        &mut stats,
+                    );
                     continue;
                 }
             }
@@ -756,11 +989,17 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             }
         }
 
+        let search_compile_id = compile_id_entry
+            .as_ref()
+            .map_or("(unknown)".to_string(), |cid| cid.to_string());
+
         // TODO: output should be able to generate this without explicitly creating
         let compile_directory = directory.entry(compile_id_entry).or_default();
 
+        let output_start = output.len();
         let mut parser_payload_filename = ParserResult::NoPayload;
-        for parser in &all_parsers {
+        for &i in parser_dispatch.parsers_for(envelope_type) {
+            let parser = all_parsers[i];
             let result = run_parser(
                 lineno,
                 parser,
@@ -769,6 +1008,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                 &mut output_count,
                 &mut output,
                 compile_directory,
+                &mut highlighter,
                 &multi,
                 &mut stats,
             );
@@ -778,6 +1018,82 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             }
         }
 
+        if e.dynamo_guards.is_some() {
+            if let Ok(guards) = serde_json::from_str::<Vec<DynamoGuard>>(&payload) {
+                guard_analytics.add_guards(&e.compile_id, &guards);
+                source_index.add_guards(&e.compile_id, &guards);
+                // Only user stacks are needed, for the C++ guard tree
+                let guards: Vec<DynamoGuard> = guards
+                    .into_iter()
+                    .filter(|g| g.user_stack.is_some())
+                    .collect();
+                if !guards.is_empty() {
+                    dynamo_guards_index.insert(e.compile_id.clone(), guards);
+                }
+            }
+        }
+        if e.dynamo_cpp_guards_str.is_some() {
+            let parser: Box<dyn StructuredLogParser> = Box::new(
+                crate::parsers::DynamoCppGuardsParser::new(&tt, config, &dynamo_guards_index),
+            );
+            let result = run_parser(
+                lineno,
+                &parser,
+                &e,
+                &payload,
+                &mut output_count,
+                &mut output,
+                compile_directory,
+                &mut highlighter,
+                &multi,
+                &mut stats,
+            );
+            if matches!(result, ParserResult::PayloadFilename(_)) {
+                parser_payload_filename = result;
+            }
+        }
+
+        if !config.export && !payload.is_empty() && e.chromium_event.is_none() {
+            if let Some((path, content)) = output[output_start..].first() {
+                add_to_search_index(
+                    &mut search_index,
+                    &search_compile_id,
+                    path,
+                    content,
+                    &payload,
+                    highlighter.is_submitted(output_start),
+                );
+            }
+        }
+
+        recompiles.add_envelope(&e, &payload);
+        dynamic_shapes.add_envelope(&e, &payload);
+        tensor_metadata.add_envelope(&e);
+        graph_stats.add_envelope(&e, &payload);
+        kernels.add_envelope(
+            &e,
+            &payload,
+            output[output_start..]
+                .first()
+                .map(|(path, _)| path.as_path()),
+        );
+        autotuning.add_envelope(&e, &payload);
+        provenance.add_envelope(&e, &payload);
+        source_index.add_envelope(
+            &e,
+            &payload,
+            output[output_start..]
+                .first()
+                .map(|(path, _)| path.as_path()),
+        );
+
+        if let Some(ref m) = e.bwd_compilation_metrics {
+            compile_time.add_bwd_metrics(&e.compile_id, m);
+        }
+        if let Some(ref m) = e.aot_autograd_backward_compilation_metrics {
+            compile_time.add_aot_backward_metrics(&e.compile_id, m);
+        }
+
         if let Some(ref m) = e.compilation_metrics {
             let copied_directory = compile_directory.clone();
             let compile_id_dir: PathBuf = e
@@ -802,6 +1118,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                 &mut output_count,
                 &mut output,
                 compile_directory,
+                &mut highlighter,
                 &multi,
                 &mut stats,
             );
@@ -823,6 +1140,22 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     cid = c,
                 )
             });
+            let reasons: Vec<&str> = m
+                .restart_reasons
+                .iter()
+                .flatten()
+                .chain(m.fail_type.iter())
+                .chain(m.fail_reason.iter())
+                .map(String::as_str)
+                .collect();
+            if !reasons.is_empty() {
+                search_index.add_unanchored_document(
+                    &search_compile_id,
+                    &format!("{}/{}", compile_id_dir.display(), metrics_filename),
+                    &metrics_filename,
+                    &reasons.join("\n"),
+                );
+            }
             if let Some(rr) = m.restart_reasons.as_ref() {
                 for restart in rr {
                     breaks.failures.push((
@@ -851,6 +1184,10 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     .failures
                     .push((id.clone(), format!("{failure_reason}")));
             }
+            graph_breaks.add(&e.compile_id, id.trim_end(), m, &stack_index.borrow());
+            recompiles.add_metrics(&e.compile_id, id.trim_end(), m);
+            guard_analytics.add_metrics(&e.compile_id, id.trim_end(), m);
+            compile_time.add_metrics(&e.compile_id, id.trim_end(), m);
             let mut cid = e.compile_id.clone();
             if let Some(c) = cid.as_mut() {
                 if let Some(_frame_id) = c.frame_compile_id {
@@ -864,7 +1201,13 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         if config.export {
             if let Some(ref guard) = e.guard_added {
                 if guard.prefix.as_deref() != Some("eval") {
-                    write_to_shortraw(&mut shortraw_content, None, &multi, &mut stats);
+                    write_to_shortraw(
+                        &mut shortraw_content,
+                        Ok(envelope_value),
+                        None,
+                        &multi,
+                        &mut stats,
+                    );
                     continue;
                 }
                 let failure_type = "Guard Evaluated";
@@ -884,6 +1227,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     &mut output_count,
                     &mut output,
                     compile_directory,
+                    &mut highlighter,
                     &multi,
                     &mut stats,
                     &tt,
@@ -913,6 +1257,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     &mut output_count,
                     &mut output,
                     compile_directory,
+                    &mut highlighter,
                     &multi,
                     &mut stats,
                     &tt,
@@ -957,16 +1302,21 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
                     additional_info: additional_info.to_string(),
                 });
             }
+        }
 
-            if let Some(sym_expr_info) = e.expression_created {
+        // The expression trees of symbolic guards, in export mode and out of it
+        if let Some(sym_expr_info) = e.expression_created {
+            if let Some(result_id) = sym_expr_info.result_id {
                 sym_expr_info_index
                     .borrow_mut()
-                    .insert(sym_expr_info.result_id.unwrap(), sym_expr_info);
+                    .insert(result_id, sym_expr_info);
             }
+        }
 
-            if let Some(unbacked_symbol) = e.create_unbacked_symbol {
+        if let Some(unbacked_symbol) = e.create_unbacked_symbol {
+            if let Some(node_id) = unbacked_symbol.node_id {
                 sym_expr_info_index.borrow_mut().insert(
-                    unbacked_symbol.node_id.unwrap(),
+                    node_id,
                     SymExprInfoMetadata {
                         result: unbacked_symbol.symbol.clone(),
                         result_id: unbacked_symbol.node_id.clone(),
@@ -1012,6 +1362,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         if let Some(m) = e.dynamo_start {
             if let Some(mut stack) = m.stack {
                 maybe_remove_convert_frame_suffixes(&mut stack);
+                recompiles.add_stack(&e.compile_id, &stack);
                 stack_index
                     .borrow_mut()
                     .insert(e.compile_id.clone(), stack.clone());
@@ -1043,12 +1394,26 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         if e.chromium_event.is_none() {
             write_to_shortraw(
                 &mut shortraw_content,
+                Ok(envelope_value),
                 final_payload_filename,
                 &multi,
                 &mut stats,
             );
         }
     }
+    pb.set_position(bytes_read);
+    spinner.set_message(format!("{}", stats));
+
+    for (index, html) in highlighter.finish() {
+        output[index].1 = html;
+    }
+
+    if config.profile {
+        if let Some((envelope_type, start)) = profiled_envelope.take() {
+            profile.record(envelope_type, start.elapsed());
+        }
+        multi.suspend(|| eprint!("{}", profile));
+    }
 
     if config.export {
         let num_failures = export_failures.len();
@@ -1086,6 +1451,178 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         PathBuf::from("failures_and_restarts.html"),
         tt.render("failures_and_restarts.html", &breaks)?,
     ));
+    if !graph_breaks.is_empty() {
+        let graph_breaks_context = graph_breaks.finish(
+            &mut stack_trie,
+            TEMPLATE_FAILURES_CSS,
+            TEMPLATE_QUERY_PARAM_SCRIPT,
+        );
+        output.push((
+            PathBuf::from("graph_breaks.html"),
+            tt.render("graph_breaks.html", &graph_breaks_context)?,
+        ));
+    }
+    for (filename, recompiles_context) in recompiles.finish(
+        &mut stack_trie,
+        TEMPLATE_FAILURES_CSS,
+        TEMPLATE_QUERY_PARAM_SCRIPT,
+    ) {
+        output.push((
+            PathBuf::from(&filename),
+            tt.render("recompiles.html", &recompiles_context)?,
+        ));
+    }
+    let has_guard_analytics = !guard_analytics.is_empty();
+    if has_guard_analytics {
+        output.push((
+            PathBuf::from("guards.html"),
+            tt.render(
+                "guards.html",
+                &guard_analytics.finish(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT),
+            )?,
+        ));
+    }
+    let has_compile_time = !compile_time.is_empty() || !chromium_events.is_empty();
+    if has_compile_time {
+        output.push((
+            PathBuf::from("compile_time.html"),
+            tt.render(
+                "compile_time.html",
+                &compile_time.finish(
+                    &chromium_events,
+                    TEMPLATE_FAILURES_CSS,
+                    TEMPLATE_QUERY_PARAM_SCRIPT,
+                ),
+            )?,
+        ));
+    }
+    let has_timeline = !timeline.is_empty();
+    if has_timeline {
+        output.push((
+            PathBuf::from("timeline.html"),
+            tt.render(
+                "timeline.html",
+                &timeline.finish(
+                    &metrics_index,
+                    clock.timezone(),
+                    clock.year_source(),
+                    CSS,
+                    TEMPLATE_QUERY_PARAM_SCRIPT,
+                ),
+            )?,
+        ));
+    }
+    let has_dynamic_shapes = !dynamic_shapes.is_empty();
+    if has_dynamic_shapes {
+        for (filename, shapes_context) in
+            dynamic_shapes.compile_pages(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT)
+        {
+            output.push((
+                PathBuf::from(&filename),
+                tt.render("dynamic_shapes.html", &shapes_context)?,
+            ));
+        }
+        output.push((
+            PathBuf::from("dynamic_shapes.html"),
+            tt.render(
+                "dynamic_shapes.html",
+                &dynamic_shapes.finish(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT),
+            )?,
+        ));
+    }
+    for (filename, tensors_context) in
+        tensor_metadata.finish(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT)
+    {
+        output.push((
+            PathBuf::from(&filename),
+            tt.render("tensor_metadata.html", &tensors_context)?,
+        ));
+    }
+    let has_graph_stats = !graph_stats.is_empty();
+    if has_graph_stats {
+        for (filename, graphs_context) in
+            graph_stats.compile_pages(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT)
+        {
+            output.push((
+                PathBuf::from(&filename),
+                tt.render("compile_graph_stats.html", &graphs_context)?,
+            ));
+        }
+        output.push((
+            PathBuf::from("graph_stats.html"),
+            tt.render(
+                "graph_stats.html",
+                &graph_stats.finish(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT),
+            )?,
+        ));
+    }
+    let (kernel_tables, kernel_pages) =
+        kernels.finish(TEMPLATE_FAILURES_CSS, TEMPLATE_QUERY_PARAM_SCRIPT);
+    for (filename, kernels_context) in kernel_tables {
+        output.push((
+            PathBuf::from(&filename),
+            tt.render("compile_kernels.html", &kernels_context)?,
+        ));
+    }
+    for (filename, kernel_context) in kernel_pages {
+        output.push((
+            PathBuf::from(&filename),
+            tt.render("kernel.html", &kernel_context)?,
+        ));
+    }
+    autotuning.add_benchmarks(&chromium_events);
+    let has_autotuning = !autotuning.is_empty();
+    if has_autotuning {
+        output.push((
+            PathBuf::from("autotuning.html"),
+            tt.render(
+                "autotuning.html",
+                &autotuning.finish(
+                    |compile_id| kernels.page_name(compile_id),
+                    TEMPLATE_FAILURES_CSS,
+                    TEMPLATE_QUERY_PARAM_SCRIPT,
+                ),
+            )?,
+        ));
+    }
+    source_index.add_graph_breaks(&graph_breaks);
+    let has_user_source = !source_index.is_empty();
+    if has_user_source {
+        output.push((
+            PathBuf::from("user_source.html"),
+            tt.render(
+                "user_source.html",
+                &source_index.finish(
+                    |compile_id| kernels.page_name(compile_id),
+                    TEMPLATE_FAILURES_CSS,
+                    TEMPLATE_QUERY_PARAM_SCRIPT,
+                ),
+            )?,
+        ));
+    }
+    // Provenance pages are built for every compile id that Inductor logged node mappings for
+    let mut provenance_pages = Vec::new();
+    for (compile_id, compile_directory) in directory.iter_mut() {
+        let pages = [
+            recompiles.page_name(compile_id),
+            dynamic_shapes.page_name(compile_id),
+            tensor_metadata.page_name(compile_id),
+            graph_stats.page_name(compile_id),
+            kernels.page_name(compile_id),
+        ];
+        let compile_provenance_pages = provenance.page_names(compile_id);
+        provenance_pages.extend(compile_provenance_pages.iter().cloned());
+        for filename in pages.into_iter().flatten().chain(compile_provenance_pages) {
+            compile_directory.push(OutputFile {
+                url: filename.clone(),
+                name: filename,
+                number: output_count,
+                suffix: "".to_string(),
+                readable_url: None,
+            });
+            output_count += 1;
+        }
+    }
     pb.finish_with_message("done");
     spinner.finish();
 
@@ -1104,17 +1641,31 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
 
     let has_unknown_compile_id = directory.contains_key(&None);
 
-    let directory_names: Vec<String> = directory
-        .iter()
-        .map(|(x, _)| {
-            x.as_ref()
-                .map_or("(unknown)".to_string(), |e| e.as_directory_name())
-        })
-        .collect();
     output.push((
         PathBuf::from("compile_directory.json"),
         serde_json::to_string_pretty(&directory_to_json(&directory))?,
     ));
+    let num_compile_ids = directory.len();
+    let lazy_directory = config.lazy_index || num_compile_ids > LAZY_INDEX_THRESHOLD;
+    let lazy_directory_json = if lazy_directory {
+        let json = write_lazy_directory_shards(&directory, &metrics_index, &mut output)?;
+        directory.clear();
+        json
+    } else {
+        String::new()
+    };
+    let stack_trie_html = if lazy_directory {
+        stack_trie.fmt_collapsed(Some(&metrics_index), "Stack", false)
+    } else {
+        stack_trie.fmt(Some(&metrics_index), "Stack", false)
+    };
+    let has_search_index = !search_index.is_empty();
+    if has_search_index {
+        output.push((
+            PathBuf::from("search_index.js"),
+            search_index.to_javascript()?,
+        ));
+    }
     let index_context = IndexContext {
         css: CSS,
         javascript: JAVASCRIPT,
@@ -1123,9 +1674,7 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
             .drain(..)
             .map(|(x, y)| (x.map_or("(unknown)".to_string(), |e| e.to_string()), y))
             .collect(),
-        stack_trie_html: stack_trie
-            .fmt(Some(&metrics_index), "Stack", false)
-            .unwrap(),
+        stack_trie_html: stack_trie_html.unwrap(),
         unknown_stack_trie_html: unknown_stack_trie
             .fmt(Some(&metrics_index), "Stack", false)
             .unwrap(),
@@ -1133,8 +1682,21 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         num_breaks: breaks.failures.len(),
         has_chromium_events: !chromium_events.is_empty(),
         qps: TEMPLATE_QUERY_PARAM_SCRIPT,
-        has_inductor_provenance: config.inductor_provenance,
-        directory_names: directory_names.clone(),
+        has_inductor_provenance: !provenance_pages.is_empty(),
+        provenance_pages,
+        num_compile_ids,
+        lazy_directory,
+        lazy_directory_json,
+        lazy_directory_javascript: LAZY_DIRECTORY_JAVASCRIPT,
+        has_search_index,
+        search_javascript: SEARCH_JAVASCRIPT,
+        has_guard_analytics,
+        has_compile_time,
+        has_timeline,
+        has_dynamic_shapes,
+        has_graph_stats,
+        has_autotuning,
+        has_user_source,
     };
     output.push((
         PathBuf::from("index.html"),
@@ -1186,70 +1748,30 @@ pub fn parse_path(path: &PathBuf, config: &ParseConfig) -> anyhow::Result<ParseO
         return Err(anyhow!("Some log entries did not have compile id"));
     }
 
-    if config.inductor_provenance {
-        // Helper function to get file content for a specific directory name
-        fn get_file_content(
-            output: &[(PathBuf, String)],
-            filename_patterns: &[&str],
-            directory_name: &str,
-        ) -> String {
-            // Try each pattern in order and return the first match found
-            for pattern in filename_patterns {
-                if let Some((_, content)) = output.iter().rev().find(|(path, _)| {
-                    path.to_string_lossy()
-                        .contains(&format!("{}/{}", directory_name, pattern))
-                }) {
-                    return content.clone();
-                }
-            }
-            String::default()
-        }
-
-        // Generate HTML for each directory name
-        for directory_name in &directory_names {
-            let pre_grad_graph_content = get_file_content(
-                &output,
-                &["before_pre_grad_graph", "inductor_pre_grad_graph"],
-                directory_name,
-            );
-            let post_grad_graph_content = get_file_content(
-                &output,
-                &["after_post_grad_graph", "inductor_post_grad_graph"],
-                directory_name,
-            );
-            let output_code_content =
-                get_file_content(&output, &["inductor_output_code"], directory_name);
-            let aot_code_content =
-                get_file_content(&output, &["inductor_aot_wrapper_code"], directory_name);
-            let node_mappings_content = get_file_content(
-                &output,
-                &["inductor_provenance_tracking_node_mappings"],
-                directory_name,
-            );
-
-            // Convert node mappings to line number mappings
-            let line_mappings_content = convert_node_mappings_to_line_numbers(
-                &node_mappings_content,
-                &pre_grad_graph_content,
-                &post_grad_graph_content,
-                &output_code_content,
-                &aot_code_content,
-            );
-            let line_mappings_content_str = serde_json::to_string_pretty(&line_mappings_content)
+    for compile_id in provenance.compile_ids() {
+        let pages = provenance
+            .page_names(compile_id)
+            .into_iter()
+            .zip(provenance.pages(compile_id));
+        for (filename, inputs) in pages {
+            let line_mappings = inputs.line_mappings();
+            let line_mappings_content_str = serde_json::to_string_pretty(&line_mappings.mappings)
                 .unwrap_or_else(|_| "{}".to_string());
 
             output.push((
-                PathBuf::from(format!("provenance_tracking_{}.html", directory_name)),
+                PathBuf::from(&filename),
                 tt.render(
                     "provenance_tracking.html",
                     &ProvenanceContext {
                         css: PROVENANCE_CSS,
                         js: PROVENANCE_JS,
-                        pre_grad_graph_content,
-                        post_grad_graph_content,
-                        output_code_content,
-                        aot_code_content,
+                        pre_grad_graph_content: inputs.pre_grad_graph.to_string(),
+                        post_grad_graph_content: inputs.post_grad_graph.to_string(),
+                        output_code_content: inputs.output_code.to_string(),
+                        aot_code_content: inputs.aot_code.to_string(),
                         line_mappings_content: line_mappings_content_str,
+                        num_unresolved_mappings: line_mappings.unresolved.len(),
+                        unresolved_mappings: line_mappings.unresolved,
                     },
                 )?,
             ));
@@ -1268,10 +1790,7 @@ pub fn generate_intermediate_files(
     output_dir: &Path,
     config: &ParseConfig,
 ) -> anyhow::Result<intermediate::IntermediateManifest> {
-    use crate::intermediate::{
-        detect_envelope_type, envelope_type_to_file, extract_metadata, format_compile_id,
-        IntermediateEntry, IntermediateWriter,
-    };
+    use crate::intermediate::{IntermediateWriter, LogEntries, LogRecord};
 
     if !path.is_file() {
         bail!("{} is not a file", path.display())
@@ -1288,159 +1807,33 @@ pub fn generate_intermediate_files(
     let spinner = multi.add(ProgressBar::new_spinner());
     spinner.set_message("Generating intermediate files...");
 
-    let reader = io::BufReader::new(file);
-
-    let re_glog = Regex::new(concat!(
-        r"(?<level>[VIWEC])(?<month>\d{2})(?<day>\d{2}) ",
-        r"(?<hour>\d{2}):(?<minute>\d{2}):(?<second>\d{2}).(?<millisecond>\d{6}) ",
-        r"(?<thread>\d+)",
-        r"(?<pathname>[^:]+):(?<line>\d+)\] ",
-        r"(?<payload>.)"
-    ))?;
-
-    let format_timestamp = |caps: &regex::Captures| -> String {
-        let month: u32 = caps.name("month").unwrap().as_str().parse().unwrap();
-        let day: u32 = caps.name("day").unwrap().as_str().parse().unwrap();
-        let hour: u32 = caps.name("hour").unwrap().as_str().parse().unwrap();
-        let minute: u32 = caps.name("minute").unwrap().as_str().parse().unwrap();
-        let second: u32 = caps.name("second").unwrap().as_str().parse().unwrap();
-        let microsecond: u32 = caps.name("millisecond").unwrap().as_str().parse().unwrap();
-        let year = chrono::Utc::now().year();
-        format!(
-            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
-            year, month, day, hour, minute, second, microsecond
-        )
-    };
-
     let mut writer = IntermediateWriter::new(output_dir)?;
-    let mut string_table: std::collections::HashMap<u32, String> = std::collections::HashMap::new();
-    let mut bytes_read: u64 = 0;
-    let mut expected_rank: Option<Option<u32>> = None;
-    let mut stats = Stats::default();
-
-    let mut iter = reader
-        .lines()
-        .enumerate()
-        .filter_map(|(i, l)| match l {
-            Ok(l) if !l.is_empty() => Some((i + 1, l)),
-            _ => None,
-        })
-        .peekable();
+    let mut entries = LogEntries::new(io::BufReader::new(file))?.with_timestamps_from(path);
 
-    while let Some((lineno, line)) = iter.next() {
-        bytes_read += line.len() as u64;
-        pb.set_position(bytes_read);
-        spinner.set_message(format!("Line {} - {}", lineno, stats));
-
-        let Some(caps) = re_glog.captures(&line) else {
-            stats.fail_glog += 1;
-            continue;
-        };
+    while let Some(record) = entries.next() {
+        pb.set_position(entries.bytes_read);
+        spinner.set_message(format!("Line {} - {}", entries.lineno, entries.stats));
 
-        let payload_str = &line[caps.name("payload").unwrap().start()..];
-        let timestamp = format_timestamp(&caps);
-        let thread: u64 = caps.name("thread").unwrap().as_str().parse().unwrap_or(0);
-        let pathname = caps.name("pathname").unwrap().as_str().to_string();
-        let lineno_parsed: u64 = caps.name("line").unwrap().as_str().parse().unwrap_or(0);
-
-        // Try to parse as JSON envelope
-        let e: Envelope = match serde_json::from_str(payload_str) {
-            Ok(e) => e,
-            Err(_) => {
-                stats.fail_json += 1;
-                continue;
-            }
-        };
-
-        // Handle string table entries
-        if let Some((s, id)) = &e.str {
-            string_table.insert(*id, s.clone());
-            continue;
+        match record {
+            LogRecord::ChromiumEvent(event) => writer.write_chromium_event(event)?,
+            LogRecord::Entry(entry, Some(file_type)) => writer.write_entry(entry, file_type)?,
+            // Envelope types the intermediate format doesn't include
+            LogRecord::Entry(_, None) => {}
         }
-
-        // Handle rank filtering
-        match expected_rank {
-            Some(rank) => {
-                if rank != e.rank {
-                    stats.other_rank += 1;
-                    continue;
-                }
-            }
-            None => {
-                if e.rank.is_some() {
-                    expected_rank = Some(e.rank);
-                }
-            }
-        };
-
-        // Collect payload if present
-        let mut payload: Option<String> = None;
-        if e.has_payload.is_some() {
-            let mut payload_lines = Vec::new();
-            while let Some((_, next_line)) = iter.peek() {
-                if next_line.starts_with('\t') {
-                    if let Some((_, pl)) = iter.next() {
-                        payload_lines.push(pl[1..].to_string());
-                        bytes_read += pl.len() as u64 + 1;
-                    }
-                } else {
-                    break;
-                }
-            }
-            if !payload_lines.is_empty() {
-                payload = Some(payload_lines.join("\n"));
-            }
-        }
-
-        stats.ok += 1;
-
-        // Detect the envelope type
-        let Some(envelope_type) = detect_envelope_type(&e) else {
-            continue;
-        };
-
-        // Route to the appropriate file
-        let Some(file_type) = envelope_type_to_file(envelope_type) else {
-            continue;
-        };
-
-        // Special handling for chromium events - they have a different format
-        if envelope_type == "chromium_event" {
-            if let Some(payload_content) = &payload {
-                if let Ok(event) = serde_json::from_str::<serde_json::Value>(payload_content) {
-                    writer.write_chromium_event(event)?;
-                }
-            }
-            continue;
-        }
-
-        // Extract metadata for this envelope type
-        let metadata = extract_metadata(&e, envelope_type);
-
-        let entry = IntermediateEntry {
-            entry_type: envelope_type.to_string(),
-            compile_id: format_compile_id(&e.compile_id),
-            rank: e.rank,
-            timestamp,
-            thread,
-            pathname,
-            lineno: lineno_parsed,
-            metadata,
-            payload,
-        };
-
-        writer.write_entry(entry, file_type)?;
     }
 
     pb.finish_with_message("Done parsing");
-    spinner.finish_with_message(format!("Final stats: {}", stats));
+    spinner.finish_with_message(format!("Final stats: {}", entries.stats));
 
     // Write string table
+    let string_table = entries.string_table;
     writer.write_string_table(&string_table)?;
 
     // Determine parse mode
     let parse_mode = if config.export { "export" } else { "normal" };
 
+    writer.set_source_file_hash(intermediate::hash_source_file(path)?);
+
     // Finalize and generate manifest
     let manifest = writer.finalize(
         &path.to_string_lossy(),
@@ -1598,6 +1991,18 @@ pub fn generate_multi_rank_landing(
             false
         };
 
+    // Group ranks by their compile ids, for the rank comparison
+    let compile_id_groups: FxHashMap<String, Vec<u32>> =
+        rank_metadata
+            .values()
+            .fold(FxHashMap::default(), |mut acc, md| {
+                let mut compile_ids: Vec<&str> =
+                    md.compile_ids.iter().map(|s| s.as_str()).collect();
+                compile_ids.sort_unstable();
+                acc.entry(compile_ids.join(",")).or_default().push(md.rank);
+                acc
+            });
+
     // Group ranks by their cache hit/miss sequence
     let cache_seq_groups: FxHashMap<String, Vec<u32>> =
         rank_metadata
@@ -1856,12 +2261,57 @@ pub fn generate_multi_rank_landing(
 
     let exec_order_summary = build_exec_order_summary(&out_path, &rank_nums, &collective_schedules);
 
+    let divergence = DivergenceFlags {
+        cache: cache_seq_groups.len() > 1,
+        collective: collective_seq_groups.len() > 1,
+        tensor_meta: tensor_meta_groups.len() > 1,
+    };
+    let show_desync_warning = compile_id_divergence
+        || divergence.cache
+        || divergence.collective
+        || divergence.tensor_meta;
+
+    // Diff the artifacts of a representative rank of each divergence group
+    let rank_comparison = show_desync_warning
+        && crate::rank_comparison::write_rank_comparison(
+            &out_path,
+            &crate::rank_comparison::representatives(
+                &[
+                    &compile_id_groups,
+                    &cache_seq_groups,
+                    &tensor_meta_groups,
+                    &collective_seq_groups,
+                ],
+                &rank_nums,
+            ),
+        )?;
+
+    // Find where the compile events of the ranks first disagree
+    let first_divergence =
+        crate::first_divergence::find_first_divergence(&out_path, &rank_nums, rank_comparison);
+
+    // Compare compile times across ranks
+    let compile_stragglers = crate::compile_stragglers::CompileStragglers::new(
+        &out_path,
+        &rank_nums,
+        cfg.straggler_percentile,
+    );
+    let compile_time = if compile_stragglers.is_empty() {
+        None
+    } else {
+        let (context, summary) = compile_stragglers.finish();
+        let mut tt = TinyTemplate::new();
+        tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+        tt.add_template("compile_stragglers.html", TEMPLATE_COMPILE_STRAGGLERS)?;
+        fs::write(
+            out_path.join("compile_stragglers.html"),
+            tt.render("compile_stragglers.html", &context)?,
+        )?;
+        Some(summary)
+    };
+
     let diagnostics = Diagnostics {
-        divergence: DivergenceFlags {
-            cache: cache_seq_groups.len() > 1,
-            collective: collective_seq_groups.len() > 1,
-            tensor_meta: tensor_meta_groups.len() > 1,
-        },
+        divergence,
         artifacts: ArtifactFlags {
             runtime_trace: !runtime_estimations.is_empty(),
         },
@@ -1870,14 +2320,13 @@ pub fn generate_multi_rank_landing(
         collective_groups: collective_divergence_groups.clone(),
         tensor_meta_groups: tensor_meta_divergence_groups.clone(),
         exec_order: exec_order_summary,
+        rank_comparison,
+        compile_time,
+        first_divergence,
     };
 
     // Emit landing page identical to CLI
     let has_chromium_events = !all_chromium_events.is_empty();
-    let show_desync_warning = compile_id_divergence
-        || diagnostics.divergence.cache
-        || diagnostics.divergence.collective
-        || diagnostics.divergence.tensor_meta;
 
     let (landing_page_path, landing_html) = generate_multi_rank_html(
         &out_path,
@@ -2218,414 +2667,6 @@ pub fn analyze_graph_runtime_deltas(
     })
 }
 
-/// Converts node-based mappings to line number-based mappings for visualization.
-///
-/// This function processes node mappings and converts them to line number mappings
-/// that can be used to highlight corresponding lines across different views.
-/// It handles pre-grad graph, post-grad graph, and generated code files.
-fn convert_node_mappings_to_line_numbers(
-    node_mappings_content: &str,
-    pre_grad_graph_content: &str,
-    post_grad_graph_content: &str,
-    output_code_content: &str,
-    aot_code_content: &str,
-) -> serde_json::Value {
-    // Parse the node mappings JSON
-    let node_mappings: serde_json::Value = match serde_json::from_str(node_mappings_content) {
-        Ok(mappings) => mappings,
-        Err(_) => return serde_json::json!({}),
-    };
-
-    let version = node_mappings
-        .get("version")
-        .and_then(|v| v.as_f64())
-        .unwrap_or(1.0) as i64;
-
-    // Helper function to check if a line is valid (not empty and doesn't start with comment)
-    fn valid_line(line: &str, symbol: &str) -> bool {
-        let stripped = line.trim();
-        !stripped.is_empty() && !stripped.starts_with(symbol)
-    }
-
-    // Helper function to extract node name from a line
-    fn extract_node_name(line: &str) -> Option<String> {
-        let trimmed = line.trim();
-        if valid_line(trimmed, "#") {
-            // Split on '=' and take everything before it
-            let before_equals = trimmed.split('=').next()?;
-            // Split on ':' and take everything before it
-            let node_name = before_equals.split(':').next()?.trim();
-            if !node_name.is_empty() {
-                return Some(node_name.to_string());
-            }
-        }
-        None
-    }
-
-    // Helper function to build node-to-line lookup map from graph content
-    fn build_node_to_lines_map(content: &str) -> std::collections::HashMap<String, usize> {
-        let mut node_to_lines = std::collections::HashMap::new();
-        for (i, line) in content.lines().enumerate() {
-            if let Some(node_name) = extract_node_name(line) {
-                node_to_lines.insert(node_name, i + 1); // 1-based line numbers
-            }
-        }
-        node_to_lines
-    }
-
-    // Helper function to build Python kernel-to-lines lookup map
-    fn build_python_kernel_to_lines_map(
-        content: &str,
-        kernel_names: &[&str],
-        _version: i64,
-    ) -> std::collections::HashMap<String, Vec<usize>> {
-        let content = content
-            .lines()
-            .skip_while(|line| line.is_empty())
-            .collect::<Vec<&str>>()
-            .join("\n");
-        let mut kernel_to_lines = std::collections::HashMap::new();
-
-        // Find the line number of "def call(args)" - allowing for whitespace between tokens
-        let run_impl_line = content
-            .lines()
-            .position(|line| {
-                line.contains("def") && line.contains("call") && line.contains("(args)")
-            })
-            .unwrap_or(0);
-        let first_line_number = content
-            .lines()
-            .position(|line| line.contains("# AOT ID:"))
-            .unwrap_or(0);
-
-        // For each kernel name (e.g. triton_poi_fused_mul_1:2):
-        // - Extract pure_kernel_name (triton_poi_fused_mul_1) before the ':'
-        // - If kernel name found: map to next line containing pure_kernel_name
-        // - If kernel_name not found: map to all lines with pure_kernel_name
-        for kernel_name in kernel_names {
-            // Get pure kernel name before ':' if it exists
-            let pure_kernel_name = if let Some(idx) = kernel_name.find(':') {
-                &kernel_name[..idx]
-            } else {
-                kernel_name
-            };
-
-            let mut found = false;
-            // If kernel_name contains a debug handle and we found it, we can stop after first match
-            if kernel_name.contains(':') {
-                for (i, line) in content.lines().enumerate().skip(run_impl_line) {
-                    if line.contains(kernel_name) {
-                        // Found kernel name, look for next line with pure_kernel_name
-                        for (j, next_line) in content.lines().enumerate().skip(i + 1) {
-                            if next_line.contains(pure_kernel_name) {
-                                kernel_to_lines
-                                    .entry(kernel_name.to_string())
-                                    .or_insert_with(Vec::new)
-                                    .push(j + 1 - first_line_number);
-                                found = true;
-                                break;
-                            }
-                        }
-                        break;
-                    }
-                }
-            }
-
-            // If exact kernel name not found, map all lines with pure kernel name
-            if !found {
-                for (i, line) in content.lines().enumerate().skip(run_impl_line) {
-                    if line.contains(pure_kernel_name) {
-                        kernel_to_lines
-                            .entry(kernel_name.to_string())
-                            .or_insert_with(Vec::new)
-                            .push(i + 1 - first_line_number);
-                    }
-                }
-            }
-        }
-        kernel_to_lines
-    }
-
-    // Helper function to build C++ kernel-to-lines lookup map
-    // We only consider lines after "::run_impl(" and skip the empty lines at the beginning when computing line numbers
-    fn build_cpp_kernel_to_lines_map(
-        content: &str,
-        kernel_names: &[&str],
-        _version: i64,
-    ) -> std::collections::HashMap<String, Vec<usize>> {
-        // remove empty lines at the beginning and end of the content
-        // We need to do this because empty lines are ignored in html <pre> tags
-        let content = content
-            .lines()
-            .skip_while(|line| line.is_empty())
-            .collect::<Vec<&str>>()
-            .join("\n");
-        let mut kernel_to_lines = std::collections::HashMap::new();
-
-        // Find the line number of "::run_impl("
-        let run_impl_line = content
-            .lines()
-            .position(|line| line.contains("::run_impl("))
-            .unwrap_or(0);
-
-        // For each kernel name (e.g. triton_poi_fused_mul_1:2):
-        // - Extract pure_kernel_name (triton_poi_fused_mul_1) before the ':'
-        // - If kernel name found: map to next line containing pure_kernel_name
-        // - If kernel_name not found: map to all lines with pure_kernel_name
-        for kernel_name in kernel_names {
-            // Get pure kernel name before ':' if it exists
-            let pure_kernel_name = if let Some(idx) = kernel_name.rfind(':') {
-                &kernel_name[..idx]
-            } else {
-                kernel_name
-            };
-
-            let mut found = false;
-            if kernel_name.contains(':') {
-                for (i, line) in content.lines().enumerate().skip(run_impl_line) {
-                    if valid_line(line, "def")
-                        && valid_line(line, "static inline void")
-                        && line.contains(kernel_name)
-                    {
-                        // Found exact kernel name - map to next matching line
-                        let next_line = content
-                            .lines()
-                            .skip(i + 1)
-                            // Filter out variable declarations like "int64_t kernel_xnumel_1 = value;" but keep function calls
-                            .position(|l| {
-                                l.contains(pure_kernel_name)
-                                    && !l.contains("_xnumel = ")
-                                    && !Regex::new(r"^\s*\w+\s+.*_xnumel(?:_\d+)?\s*=")
-                                        .unwrap()
-                                        .is_match(l)
-                            })
-                            .map(|pos| i + pos + 2);
-
-                        if let Some(line_num) = next_line {
-                            kernel_to_lines
-                                .entry(kernel_name.to_string())
-                                .or_insert_with(Vec::new)
-                                .push(line_num);
-                            found = true;
-                            break;
-                        }
-                    }
-                }
-            }
-            if !found {
-                for (i, line) in content.lines().enumerate().skip(run_impl_line) {
-                    if line.contains(pure_kernel_name) {
-                        kernel_to_lines
-                            .entry(kernel_name.to_string())
-                            .or_insert_with(Vec::new)
-                            .push(i + 1);
-                    }
-                }
-            }
-        }
-        kernel_to_lines
-    }
-
-    // Helper function to process mappings from source to target
-    fn process_mappings<F>(
-        source_mappings: &serde_json::Map<String, serde_json::Value>,
-        source_lookup: &std::collections::HashMap<String, usize>,
-        _target_lookup: &std::collections::HashMap<String, usize>,
-        target_line_processor: F,
-    ) -> std::collections::HashMap<usize, Vec<usize>>
-    where
-        F: Fn(&str) -> Option<usize>,
-    {
-        let mut result = std::collections::HashMap::new();
-
-        for (source_node, target_nodes) in source_mappings {
-            if let Some(source_line) = source_lookup.get(source_node) {
-                let mut target_lines = Vec::new();
-                if let Some(target_nodes_array) = target_nodes.as_array() {
-                    for target_node in target_nodes_array {
-                        if let Some(target_node_str) = target_node.as_str() {
-                            if let Some(target_line) = target_line_processor(target_node_str) {
-                                target_lines.push(target_line);
-                            }
-                        }
-                    }
-                }
-                if !target_lines.is_empty() {
-                    result.insert(*source_line, target_lines);
-                }
-            }
-        }
-        result
-    }
-
-    // Helper function to process kernel-to-post mappings
-    fn process_kernel_to_post_mappings(
-        kernel_mappings: &serde_json::Map<String, serde_json::Value>,
-        kernel_lookup: &std::collections::HashMap<String, Vec<usize>>,
-        post_lookup: &std::collections::HashMap<String, usize>,
-    ) -> std::collections::HashMap<usize, Vec<usize>> {
-        let mut result = std::collections::HashMap::new();
-
-        for (kernel_name, post_nodes) in kernel_mappings {
-            if let Some(kernel_lines) = kernel_lookup.get(kernel_name) {
-                for kernel_line in kernel_lines {
-                    let mut target_lines = Vec::new();
-                    if let Some(post_nodes_array) = post_nodes.as_array() {
-                        for post_node in post_nodes_array {
-                            if let Some(post_node_str) = post_node.as_str() {
-                                if let Some(post_line) = post_lookup.get(post_node_str) {
-                                    target_lines.push(*post_line);
-                                }
-                            }
-                        }
-                    }
-                    if !target_lines.is_empty() {
-                        result.insert(*kernel_line, target_lines);
-                    }
-                }
-            }
-        }
-        result
-    }
-
-    // Helper function to process post-to-kernel mappings
-    fn process_post_to_kernel_mappings(
-        post_mappings: &serde_json::Map<String, serde_json::Value>,
-        post_lookup: &std::collections::HashMap<String, usize>,
-        kernel_lookup: &std::collections::HashMap<String, Vec<usize>>,
-    ) -> std::collections::HashMap<usize, Vec<usize>> {
-        let mut result = std::collections::HashMap::new();
-
-        for (post_node, kernel_names) in post_mappings {
-            if let Some(post_line) = post_lookup.get(post_node) {
-                let mut target_lines = Vec::new();
-                if let Some(kernel_names_array) = kernel_names.as_array() {
-                    for kernel_name in kernel_names_array {
-                        if let Some(kernel_name_str) = kernel_name.as_str() {
-                            if let Some(kernel_lines) = kernel_lookup.get(kernel_name_str) {
-                                target_lines.extend(kernel_lines);
-                            }
-                        }
-                    }
-                }
-                if !target_lines.is_empty() {
-                    result.insert(*post_line, target_lines);
-                }
-            }
-        }
-        result
-    }
-
-    // Helper function to convert HashMap to JSON Map
-    fn hashmap_to_json_map(
-        map: std::collections::HashMap<usize, Vec<usize>>,
-    ) -> serde_json::Map<String, serde_json::Value> {
-        map.into_iter()
-            .map(|(k, v)| (k.to_string(), serde_json::json!(v)))
-            .collect()
-    }
-
-    let kernel_names: Vec<&str> = node_mappings
-        .get("cppCodeToPost")
-        .and_then(|v| v.as_object())
-        .map(|obj| obj.keys().map(|k| k.as_str()).collect())
-        .unwrap_or_default();
-
-    // Build lookup maps
-    let pre_grad_node_to_lines = build_node_to_lines_map(pre_grad_graph_content);
-    let post_grad_node_to_lines = build_node_to_lines_map(post_grad_graph_content);
-    let py_kernel_to_lines =
-        build_python_kernel_to_lines_map(output_code_content, &kernel_names, version);
-    let cpp_code_to_lines = build_cpp_kernel_to_lines_map(aot_code_content, &kernel_names, version);
-
-    // Process all mappings using helper functions
-    let line_pre_to_post =
-        if let Some(pre_to_post) = node_mappings.get("preToPost").and_then(|v| v.as_object()) {
-            process_mappings(
-                pre_to_post,
-                &pre_grad_node_to_lines,
-                &post_grad_node_to_lines,
-                |node_name| post_grad_node_to_lines.get(node_name).copied(),
-            )
-        } else {
-            std::collections::HashMap::new()
-        };
-
-    let line_post_to_pre =
-        if let Some(post_to_pre) = node_mappings.get("postToPre").and_then(|v| v.as_object()) {
-            process_mappings(
-                post_to_pre,
-                &post_grad_node_to_lines,
-                &pre_grad_node_to_lines,
-                |node_name| pre_grad_node_to_lines.get(node_name).copied(),
-            )
-        } else {
-            std::collections::HashMap::new()
-        };
-
-    let line_cpp_code_to_post = if let Some(cpp_code_to_post) = node_mappings
-        .get("cppCodeToPost")
-        .and_then(|v| v.as_object())
-    {
-        process_kernel_to_post_mappings(
-            cpp_code_to_post,
-            &cpp_code_to_lines,
-            &post_grad_node_to_lines,
-        )
-    } else {
-        std::collections::HashMap::new()
-    };
-
-    let line_post_to_cpp_code = if let Some(post_to_cpp_code) = node_mappings
-        .get("postToCppCode")
-        .and_then(|v| v.as_object())
-    {
-        process_post_to_kernel_mappings(
-            post_to_cpp_code,
-            &post_grad_node_to_lines,
-            &cpp_code_to_lines,
-        )
-    } else {
-        std::collections::HashMap::new()
-    };
-
-    let line_py_code_to_post = if let Some(cpp_code_to_post) = node_mappings
-        .get("cppCodeToPost")
-        .and_then(|v| v.as_object())
-    {
-        process_kernel_to_post_mappings(
-            cpp_code_to_post,
-            &py_kernel_to_lines,
-            &post_grad_node_to_lines,
-        )
-    } else {
-        std::collections::HashMap::new()
-    };
-
-    let line_post_to_py_code = if let Some(post_to_cpp_code) = node_mappings
-        .get("postToCppCode")
-        .and_then(|v| v.as_object())
-    {
-        process_post_to_kernel_mappings(
-            post_to_cpp_code,
-            &post_grad_node_to_lines,
-            &py_kernel_to_lines,
-        )
-    } else {
-        std::collections::HashMap::new()
-    };
-
-    // Convert all HashMaps to JSON objects
-    serde_json::json!({
-        "preToPost": hashmap_to_json_map(line_pre_to_post),
-        "postToPre": hashmap_to_json_map(line_post_to_pre),
-        "pyCodeToPost": hashmap_to_json_map(line_py_code_to_post),
-        "postToPyCode": hashmap_to_json_map(line_post_to_py_code),
-        "cppCodeToPost": hashmap_to_json_map(line_cpp_code_to_post),
-        "postToCppCode": hashmap_to_json_map(line_post_to_cpp_code)
-    })
-}
-
 pub mod execution_order {
     use fxhash::FxHashMap;
 
@@ -2768,10 +2809,16 @@ pub fn render_from_intermediate(
 ) -> anyhow::Result<ParseOutput> {
     // Load manifest
     let manifest_path = intermediate_dir.join("manifest.json");
-    let manifest_file = File::open(&manifest_path)
-        .map_err(|e| anyhow!("Failed to open manifest at {}: {}", manifest_path.display(), e))?;
+    let manifest_file = File::open(&manifest_path).map_err(|e| {
+        anyhow!(
+            "Failed to open manifest at {}: {}",
+            manifest_path.display(),
+            e
+        )
+    })?;
     let manifest: IntermediateManifest = serde_json::from_reader(manifest_file)
         .map_err(|e| anyhow!("Failed to parse manifest: {}", e))?;
+    manifest.check_compatible()?;
 
     // Create context for modules
     let ctx = modules::context::ModuleContext::new(intermediate_dir, output_dir, &manifest, config);
@@ -11,6 +11,7 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::highlight::{detect_language, is_graph_or_code_artifact, CodeView, PageLinks};
 use crate::intermediate::IntermediateFileType;
 use crate::modules::context::ModuleContext;
 use crate::modules::{DirectoryEntry, Module, ModuleOutput};
@@ -18,11 +19,51 @@ use crate::modules::{DirectoryEntry, Module, ModuleOutput};
 /// Module that generates per-compile artifact files.
 pub struct CompileArtifactsModule {
     plain_text: bool,
+    highlight_size_limit: usize,
 }
 
 impl CompileArtifactsModule {
     pub fn new(plain_text: bool) -> Self {
-        Self { plain_text }
+        Self {
+            plain_text,
+            highlight_size_limit: crate::DEFAULT_HIGHLIGHT_SIZE_LIMIT,
+        }
+    }
+
+    /// Leave graphs and code larger than `limit` bytes for the browser to highlight on demand
+    pub fn with_highlight_size_limit(mut self, limit: usize) -> Self {
+        self.highlight_size_limit = limit;
+        self
+    }
+
+    /// Output file for a graph or code payload: highlighted HTML, or the raw text with
+    /// `--plain-text`. Returns the file name and its content.
+    fn code_file(
+        &self,
+        name: &str,
+        payload: String,
+        source_filename: Option<&str>,
+        link_nodes: bool,
+    ) -> Result<(String, String)> {
+        if self.plain_text {
+            return Ok((format!("{}.txt", name), payload));
+        }
+        let view = CodeView {
+            title: name.to_string(),
+            language: detect_language(source_filename, &payload),
+            source: payload,
+            link_nodes,
+        };
+        // Pages are written to a compile id directory, and there is no user source index
+        // to link to
+        let page_links = PageLinks {
+            root: "../".to_string(),
+            user_source: false,
+        };
+        Ok((
+            format!("{}.html", name),
+            view.render(self.highlight_size_limit, &page_links),
+        ))
     }
 }
 
@@ -66,9 +107,13 @@ impl Module for CompileArtifactsModule {
                 | "inductor_post_grad_graph"
                 | "optimize_ddp_split_graph"
                 | "compiled_autograd_graph" => {
-                    let filename = format!("{}.txt", entry.entry_type);
+                    let (filename, content) = self.code_file(
+                        &entry.entry_type,
+                        entry.payload.unwrap_or_default(),
+                        None,
+                        true,
+                    )?;
                     let path = PathBuf::from(&compile_id).join(&filename);
-                    let content = entry.payload.unwrap_or_default();
                     files.push((path.clone(), content));
 
                     directory_entries
@@ -86,9 +131,13 @@ impl Module for CompileArtifactsModule {
                         .get("name")
                         .and_then(|v| v.as_str())
                         .unwrap_or("unknown");
-                    let filename = format!("optimize_ddp_split_child_{}.txt", name);
+                    let (filename, content) = self.code_file(
+                        &format!("optimize_ddp_split_child_{}", name),
+                        entry.payload.unwrap_or_default(),
+                        None,
+                        true,
+                    )?;
                     let path = PathBuf::from(&compile_id).join(&filename);
-                    let content = entry.payload.unwrap_or_default();
                     files.push((path.clone(), content));
 
                     directory_entries
@@ -106,9 +155,9 @@ impl Module for CompileArtifactsModule {
                         .get("name")
                         .and_then(|v| v.as_str())
                         .unwrap_or("graph_dump");
-                    let filename = format!("{}.txt", name);
+                    let (filename, content) =
+                        self.code_file(name, entry.payload.unwrap_or_default(), None, true)?;
                     let path = PathBuf::from(&compile_id).join(&filename);
-                    let content = entry.payload.unwrap_or_default();
                     files.push((path.clone(), content));
 
                     directory_entries
@@ -122,19 +171,21 @@ impl Module for CompileArtifactsModule {
 
                 // Codegen
                 "inductor_output_code" => {
-                    let base_filename = entry
-                        .metadata
-                        .get("filename")
-                        .and_then(|v| v.as_str())
+                    let source_filename = entry.metadata.get("filename").and_then(|v| v.as_str());
+                    let base_filename = source_filename
                         .and_then(|p| std::path::Path::new(p).file_stem())
                         .and_then(|s| s.to_str())
                         .map(|s| format!("inductor_output_code_{}", s))
                         .unwrap_or_else(|| "inductor_output_code".to_string());
 
-                    let payload = entry.payload.unwrap_or_default();
-                    let filename = format!("{}.txt", base_filename);
+                    let (filename, content) = self.code_file(
+                        &base_filename,
+                        entry.payload.unwrap_or_default(),
+                        source_filename,
+                        false,
+                    )?;
                     let path = PathBuf::from(&compile_id).join(&filename);
-                    files.push((path.clone(), payload));
+                    files.push((path.clone(), content));
 
                     directory_entries
                         .entry(compile_id)
@@ -167,6 +218,9 @@ impl Module for CompileArtifactsModule {
                                 .unwrap_or(payload);
                             (format!("{}.json", name), formatted)
                         }
+                        _ if is_graph_or_code_artifact(name) => {
+                            self.code_file(name, payload, None, !name.ends_with("_code"))?
+                        }
                         _ => (format!("{}.txt", name), payload),
                     };
 
@@ -324,11 +378,28 @@ mod tests {
         let output = module.render(&ctx)?;
 
         assert_eq!(output.files.len(), 1);
+        assert_eq!(
+            output.files[0].0,
+            PathBuf::from("0_0/dynamo_output_graph.html")
+        );
+        assert!(output.files[0].1.contains("id=\"L1\""));
+
+        let module = CompileArtifactsModule::new(true);
+        let output = module.render(&ctx)?;
         assert_eq!(
             output.files[0].0,
             PathBuf::from("0_0/dynamo_output_graph.txt")
         );
 
+        // Past the configured size limit, the page is left for the browser to highlight
+        let config = ModuleConfig {
+            highlight_size_limit: 10,
+            ..ModuleConfig::default()
+        };
+        let ctx = ModuleContext::new(temp_dir.path(), temp_dir.path(), &manifest, &config);
+        let output = crate::modules::ModuleRegistry::with_defaults(&config).render_all(&ctx)?;
+        assert!(output.files[0].1.contains("<a href=\"?highlight\">"));
+
         Ok(())
     }
 
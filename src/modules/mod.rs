@@ -97,6 +97,8 @@ pub struct ModuleConfig {
     pub custom_header_html: String,
     /// Whether running in export mode
     pub export_mode: bool,
+    /// Graphs and code larger than this many bytes are not highlighted ahead of time
+    pub highlight_size_limit: usize,
 }
 
 impl Default for ModuleConfig {
@@ -105,6 +107,7 @@ impl Default for ModuleConfig {
             plain_text: false,
             custom_header_html: String::new(),
             export_mode: false,
+            highlight_size_limit: crate::DEFAULT_HIGHLIGHT_SIZE_LIMIT,
         }
     }
 }
@@ -162,7 +165,10 @@ impl ModuleRegistry {
         let mut registry = Self::new();
 
         // Add compile artifacts module (graphs, codegen, artifacts)
-        registry.register(Box::new(CompileArtifactsModule::new(config.plain_text)));
+        registry.register(Box::new(
+            CompileArtifactsModule::new(config.plain_text)
+                .with_highlight_size_limit(config.highlight_size_limit),
+        ));
 
         // Add chromium trace module
         registry.register(Box::new(ChromiumTraceModule::new()));
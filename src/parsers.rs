@@ -1,5 +1,7 @@
-use crate::templates::TEMPLATE_QUERY_PARAM_SCRIPT;
+use crate::highlight::{detect_language, is_graph_or_code_artifact, CodeView};
+use crate::templates::{CPP_GUARDS_JAVASCRIPT, TEMPLATE_QUERY_PARAM_SCRIPT};
 use crate::{types::*, ParseConfig};
+use fxhash::FxHashMap;
 use html_escape::encode_text;
 use std::cell::RefCell;
 use std::collections::HashSet;
@@ -20,9 +22,6 @@ fn format_json_pretty(payload: &str) -> Result<String, anyhow::Error> {
     }
 }
 
-use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
-
 // Re-export types from types.rs for external use
 pub use crate::types::{CompileId, EmptyMetadata, Envelope, GraphRuntime, Metadata, OpRuntime};
 
@@ -31,6 +30,7 @@ pub enum ParserOutput {
     GlobalFile(PathBuf, String), // Like file, but don't give a unique suffix
     PayloadFile(PathBuf),        // File using payload directly from log entry
     PayloadReformatFile(PathBuf, fn(&str) -> Result<String, anyhow::Error>), // File using reformatted payload from log entry
+    HighlightedFile(PathBuf, CodeView), // Like File, but rendered from the view off the parse thread
     Link(String, String), // External href to (name, url) (linked in compile_directory, not returned)
 }
 
@@ -61,6 +61,13 @@ pub trait StructuredLogParser {
 
     // Name of the parser, for error logging
     fn name(&self) -> &'static str;
+
+    // Envelope types (as named by intermediate::detect_envelope_type) this parser handles.
+    // The parse loop only offers the parser envelopes of these types; None offers it every
+    // envelope, which is what custom parsers get unless they override this.
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        None
+    }
 }
 
 // Helper function to build file path with compile ID directory
@@ -105,12 +112,51 @@ fn payload_reformat_file_output(
     Ok(Vec::from([ParserOutput::PayloadReformatFile(f, formatter)]))
 }
 
+// Graphs and code are written as plain text only with --plain-text
+fn graphs_as_plain_text(config: &ParseConfig) -> bool {
+    config.plain_text
+}
+
+// Takes a filename and a graph or code payload, returns a HighlightedFile output for it
+fn highlighted_file_output(
+    filename: &str,
+    lineno: usize,
+    compile_id: &Option<CompileId>,
+    view: CodeView,
+) -> anyhow::Result<ParserResults> {
+    let f = build_file_path(filename, lineno, compile_id);
+    Ok(Vec::from([ParserOutput::HighlightedFile(f, view)]))
+}
+
+// Writes an FX graph payload either as a plain text payload file or as highlighted HTML
+fn graph_file_output(
+    name: &str,
+    lineno: usize,
+    compile_id: &Option<CompileId>,
+    payload: &str,
+    plain_text: bool,
+) -> anyhow::Result<ParserResults> {
+    if plain_text {
+        payload_file_output(&format!("{}.txt", name), lineno, compile_id)
+    } else {
+        let view = CodeView {
+            title: name.to_string(),
+            source: payload.to_string(),
+            language: detect_language(None, payload),
+            link_nodes: true,
+        };
+        highlighted_file_output(&format!("{}.html", name), lineno, compile_id, view)
+    }
+}
+
 /**
  * Parser for simple output dumps where the metadata is a sentinel {}
  */
 pub struct SentinelFileParser {
     filename: &'static str,
     get_sentinel: fn(&Envelope) -> Option<&EmptyMetadata>,
+    // If false the payload is an FX graph, rendered as highlighted html
+    plain_text: bool,
 }
 impl SentinelFileParser {
     pub fn new(
@@ -120,6 +166,20 @@ impl SentinelFileParser {
         Self {
             filename,
             get_sentinel,
+            plain_text: true,
+        }
+    }
+
+    // Like new, but for payloads that are FX graphs
+    pub fn graph(
+        filename: &'static str,
+        get_sentinel: fn(&Envelope) -> Option<&EmptyMetadata>,
+        config: &ParseConfig,
+    ) -> Self {
+        Self {
+            filename,
+            get_sentinel,
+            plain_text: graphs_as_plain_text(config),
         }
     }
 }
@@ -127,6 +187,9 @@ impl StructuredLogParser for SentinelFileParser {
     fn name(&self) -> &'static str {
         self.filename
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(std::slice::from_ref(&self.filename))
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         (self.get_sentinel)(e).map(|m| Metadata::Empty(m))
     }
@@ -136,20 +199,32 @@ impl StructuredLogParser for SentinelFileParser {
         _metadata: Metadata<'e>,
         _rank: Option<u32>,
         compile_id: &Option<CompileId>,
-        _payload: &str,
+        payload: &str,
     ) -> anyhow::Result<ParserResults> {
-        payload_file_output(&format!("{}.txt", self.filename), lineno, compile_id)
+        graph_file_output(self.filename, lineno, compile_id, payload, self.plain_text)
     }
 }
 
 /**
  * Generic parser for graph_dump entries
  */
-pub struct GraphDumpParser;
+pub struct GraphDumpParser {
+    plain_text: bool,
+}
+impl GraphDumpParser {
+    pub fn new(config: &ParseConfig) -> Self {
+        GraphDumpParser {
+            plain_text: graphs_as_plain_text(config),
+        }
+    }
+}
 impl StructuredLogParser for GraphDumpParser {
     fn name(&self) -> &'static str {
         "graph_dump" // ToDO: more specific?
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["graph_dump"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.graph_dump.as_ref().map(|m| Metadata::GraphDump(m))
     }
@@ -159,15 +234,10 @@ impl StructuredLogParser for GraphDumpParser {
         metadata: Metadata<'e>,
         _rank: Option<u32>,
         compile_id: &Option<CompileId>,
-        _payload: &str,
+        payload: &str,
     ) -> anyhow::Result<ParserResults> {
         if let Metadata::GraphDump(metadata) = metadata {
-            let filename: PathBuf = {
-                let mut r = OsString::from(&metadata.name);
-                r.push(OsStr::new(".txt"));
-                r.into()
-            };
-            payload_file_output(&filename.to_string_lossy(), lineno, compile_id)
+            graph_file_output(&metadata.name, lineno, compile_id, payload, self.plain_text)
         } else {
             Err(anyhow::anyhow!("Expected GraphDump metadata"))
         }
@@ -175,11 +245,23 @@ impl StructuredLogParser for GraphDumpParser {
 }
 
 // Same as SentinelFileParser, but can log the size of the graph
-pub struct DynamoOutputGraphParser;
+pub struct DynamoOutputGraphParser {
+    plain_text: bool,
+}
+impl DynamoOutputGraphParser {
+    pub fn new(config: &ParseConfig) -> Self {
+        DynamoOutputGraphParser {
+            plain_text: graphs_as_plain_text(config),
+        }
+    }
+}
 impl StructuredLogParser for DynamoOutputGraphParser {
     fn name(&self) -> &'static str {
         "dynamo_output_graph"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["dynamo_output_graph"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.dynamo_output_graph
             .as_ref()
@@ -188,12 +270,18 @@ impl StructuredLogParser for DynamoOutputGraphParser {
     fn parse<'e>(
         &self,
         lineno: usize,
-        _metadata: Metadata<'e>, // TODO: log size of graph
+        _metadata: Metadata<'e>, // sizes are shown on graph_stats.html
         _rank: Option<u32>,
         compile_id: &Option<CompileId>,
-        _payload: &str,
+        payload: &str,
     ) -> anyhow::Result<ParserResults> {
-        payload_file_output("dynamo_output_graph.txt", lineno, compile_id)
+        graph_file_output(
+            "dynamo_output_graph",
+            lineno,
+            compile_id,
+            payload,
+            self.plain_text,
+        )
     }
 }
 
@@ -204,6 +292,9 @@ impl StructuredLogParser for DynamoGuardParser<'_> {
     fn name(&self) -> &'static str {
         "dynamo_guards"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["dynamo_guards"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.dynamo_guards.as_ref().map(|m| Metadata::Empty(m))
     }
@@ -218,7 +309,8 @@ impl StructuredLogParser for DynamoGuardParser<'_> {
         let filename = format!("{}.html", self.name());
         let guards = serde_json::from_str::<Vec<DynamoGuard>>(payload)?;
         let guards_context = DynamoGuardsContext {
-            guards,
+            num_guards: guards.len(),
+            categories: crate::guards::group_by_category(&guards),
             qps: TEMPLATE_QUERY_PARAM_SCRIPT,
         };
         let output = self.tt.render(&filename, &guards_context)?;
@@ -226,6 +318,91 @@ impl StructuredLogParser for DynamoGuardParser<'_> {
     }
 }
 
+/// Renders the `dynamo_cpp_guards_str` guard tree as a collapsible, searchable page, linking
+/// leaf guards to the user stacks `dynamo_guards` recorded for them. `dynamo_guards` is logged
+/// first, so its guards are already in `dynamo_guards_index`.
+pub struct DynamoCppGuardsParser<'t> {
+    tt: &'t TinyTemplate<'t>,
+    // If true the tree is written verbatim as plain text
+    plain_text: bool,
+    dynamo_guards_index: &'t DynamoGuardsIndex,
+}
+impl<'t> DynamoCppGuardsParser<'t> {
+    pub fn new(
+        tt: &'t TinyTemplate<'t>,
+        config: &ParseConfig,
+        dynamo_guards_index: &'t DynamoGuardsIndex,
+    ) -> Self {
+        DynamoCppGuardsParser {
+            tt,
+            plain_text: graphs_as_plain_text(config),
+            dynamo_guards_index,
+        }
+    }
+}
+impl StructuredLogParser for DynamoCppGuardsParser<'_> {
+    fn name(&self) -> &'static str {
+        "dynamo_cpp_guards_str"
+    }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["dynamo_cpp_guards_str"])
+    }
+    fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
+        e.dynamo_cpp_guards_str.as_ref().map(Metadata::Empty)
+    }
+    fn parse<'e>(
+        &self,
+        lineno: usize,
+        _metadata: Metadata<'e>,
+        _rank: Option<u32>,
+        compile_id: &Option<CompileId>,
+        payload: &str,
+    ) -> anyhow::Result<ParserResults> {
+        if self.plain_text {
+            return payload_file_output(&format!("{}.txt", self.name()), lineno, compile_id);
+        }
+        let tree = crate::guards::parse_cpp_guard_tree(payload);
+
+        let mut user_stacks: FxHashMap<&str, String> = FxHashMap::default();
+        let mut stacks: Vec<UserStackContext> = Vec::new();
+        for guard in self
+            .dynamo_guards_index
+            .get(compile_id)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(user_stack) = &guard.user_stack {
+                let html = format_stack(user_stack, &guard.code, false);
+                let anchor = match stacks.iter().find(|s| s.html == html) {
+                    Some(s) => s.anchor.clone(),
+                    None => {
+                        let anchor = format!("stack-{}", stacks.len());
+                        stacks.push(UserStackContext {
+                            anchor: anchor.clone(),
+                            html,
+                        });
+                        anchor
+                    }
+                };
+                user_stacks.insert(guard.code.as_str(), anchor);
+            }
+        }
+
+        let filename = format!("{}.html", self.name());
+        let context = CppGuardsContext {
+            javascript: CPP_GUARDS_JAVASCRIPT,
+            qps: TEMPLATE_QUERY_PARAM_SCRIPT,
+            num_guards: tree.leaves().len(),
+            header: tree.header.join("\n"),
+            footer: tree.footer.join("\n"),
+            tree_html: crate::guards::cpp_guard_tree_html(&tree, &user_stacks),
+            stacks,
+        };
+        let output = self.tt.render(&filename, &context)?;
+        simple_file_output(&filename, lineno, compile_id, &output)
+    }
+}
+
 pub struct InductorOutputCodeParser {
     // If true we output the code as plain text, otherwise we output it as rendered html
     plain_text: bool,
@@ -234,7 +411,7 @@ pub struct InductorOutputCodeParser {
 impl InductorOutputCodeParser {
     pub fn new(config: &ParseConfig) -> Self {
         InductorOutputCodeParser {
-            plain_text: config.plain_text,
+            plain_text: graphs_as_plain_text(config),
         }
     }
 }
@@ -243,6 +420,9 @@ impl StructuredLogParser for InductorOutputCodeParser {
     fn name(&self) -> &'static str {
         "inductor_output_code"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["inductor_output_code"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.inductor_output_code
             .as_ref()
@@ -285,18 +465,15 @@ impl StructuredLogParser for InductorOutputCodeParser {
             if self.plain_text {
                 payload_file_output(&filename.to_string_lossy(), lineno, compile_id)
             } else {
-                let output_content = match generate_html_output(payload) {
-                    Ok(html) => html,
-                    Err(_e) => {
-                        return Err(anyhow::anyhow!("Failed to parse inductor code to html"))
-                    }
+                let title = filename.with_extension("").to_string_lossy().to_string();
+                let source_filename = metadata.filename.as_ref().map(|p| p.to_string_lossy());
+                let view = CodeView {
+                    title,
+                    source: payload.to_string(),
+                    language: detect_language(source_filename.as_deref(), payload),
+                    link_nodes: false,
                 };
-                simple_file_output(
-                    &filename.to_string_lossy(),
-                    lineno,
-                    compile_id,
-                    &output_content,
-                )
+                highlighted_file_output(&filename.to_string_lossy(), lineno, compile_id, view)
             }
         } else {
             Err(anyhow::anyhow!("Expected InductorOutputCode metadata"))
@@ -304,24 +481,23 @@ impl StructuredLogParser for InductorOutputCodeParser {
     }
 }
 
-fn generate_html_output(payload: &str) -> Result<String, anyhow::Error> {
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let theme_set = ThemeSet::load_defaults();
-    let syntax = syntax_set.find_syntax_by_extension("py").unwrap();
-    let html = syntect::html::highlighted_html_for_string(
-        &payload,
-        &syntax_set,
-        &syntax,
-        &theme_set.themes["InspiredGitHub"],
-    );
-    Ok(html?)
+pub struct OptimizeDdpSplitChildParser {
+    plain_text: bool,
+}
+impl OptimizeDdpSplitChildParser {
+    pub fn new(config: &ParseConfig) -> Self {
+        OptimizeDdpSplitChildParser {
+            plain_text: graphs_as_plain_text(config),
+        }
+    }
 }
-
-pub struct OptimizeDdpSplitChildParser;
 impl StructuredLogParser for OptimizeDdpSplitChildParser {
     fn name(&self) -> &'static str {
         "optimize_ddp_split_child"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["optimize_ddp_split_child"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.optimize_ddp_split_child
             .as_ref()
@@ -334,11 +510,11 @@ impl StructuredLogParser for OptimizeDdpSplitChildParser {
         metadata: Metadata<'e>,
         _rank: Option<u32>,
         compile_id: &Option<CompileId>,
-        _payload: &str,
+        payload: &str,
     ) -> anyhow::Result<ParserResults> {
         if let Metadata::OptimizeDdpSplitChild(m) = metadata {
-            let filename = format!("optimize_ddp_split_child_{}.txt", m.name);
-            payload_file_output(&filename, lineno, compile_id)
+            let name = format!("optimize_ddp_split_child_{}", m.name);
+            graph_file_output(&name, lineno, compile_id, payload, self.plain_text)
         } else {
             Err(anyhow::anyhow!("Expected OptimizeDdpSplitChild metadata"))
         }
@@ -350,6 +526,9 @@ impl StructuredLogParser for LinkParser {
     fn name(&self) -> &'static str {
         "link_parser"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["link"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.link.as_ref().map(|m| Metadata::Link(m))
     }
@@ -373,7 +552,7 @@ impl StructuredLogParser for LinkParser {
     }
 }
 
-fn format_stack(stack: &StackSummary, caption: &str, open: bool) -> String {
+pub(crate) fn format_stack(stack: &StackSummary, caption: &str, open: bool) -> String {
     let mut trie = StackTrieNode::default();
     trie.insert_no_terminal(stack.to_vec());
     trie.fmt(None, caption, open).unwrap()
@@ -391,6 +570,9 @@ impl StructuredLogParser for CompilationMetricsParser<'_> {
     fn name(&self) -> &'static str {
         "compilation_metrics"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["compilation_metrics"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.compilation_metrics
             .as_ref()
@@ -525,6 +707,9 @@ impl StructuredLogParser for AOTAutogradBackwardCompilationMetricsParser<'_> {
     fn name(&self) -> &'static str {
         "aot_autograd_backward_compilation_metrics"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["aot_autograd_backward_compilation_metrics"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.aot_autograd_backward_compilation_metrics
             .as_ref()
@@ -566,6 +751,9 @@ impl StructuredLogParser for BwdCompilationMetricsParser<'_> {
     fn name(&self) -> &'static str {
         "bwd_compilation_metrics"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["bwd_compilation_metrics"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.bwd_compilation_metrics
             .as_ref()
@@ -603,6 +791,9 @@ impl StructuredLogParser for DumpFileParser {
     fn name(&self) -> &'static str {
         "dump_file"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["dump_file"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.dump_file.as_ref().map(|m| Metadata::DumpFile(m))
     }
@@ -960,11 +1151,23 @@ fn read_artifacts<T>(
     Ok(results)
 }
 
-pub struct ArtifactParser;
+pub struct ArtifactParser {
+    plain_text: bool,
+}
+impl ArtifactParser {
+    pub fn new(config: &ParseConfig) -> Self {
+        ArtifactParser {
+            plain_text: graphs_as_plain_text(config),
+        }
+    }
+}
 impl StructuredLogParser for ArtifactParser {
     fn name(&self) -> &'static str {
         "artifact"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["artifact"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.artifact.as_ref().map(|m| Metadata::Artifact(m))
     }
@@ -974,10 +1177,25 @@ impl StructuredLogParser for ArtifactParser {
         metadata: Metadata<'e>,
         _rank: Option<u32>,
         compile_id: &Option<CompileId>,
-        _payload: &str,
+        payload: &str,
     ) -> anyhow::Result<ParserResults> {
         if let Metadata::Artifact(metadata) = metadata {
             match metadata.encoding.as_str() {
+                "string" if !self.plain_text && is_graph_or_code_artifact(&metadata.name) => {
+                    let view = CodeView {
+                        title: metadata.name.clone(),
+                        source: payload.to_string(),
+                        language: detect_language(None, payload),
+                        // Generated code isn't a graph, don't link its variables
+                        link_nodes: !metadata.name.ends_with("_code"),
+                    };
+                    highlighted_file_output(
+                        &format!("{}.html", metadata.name),
+                        lineno,
+                        compile_id,
+                        view,
+                    )
+                }
                 "string" => {
                     let filename = format!("{}.txt", metadata.name);
                     payload_file_output(&filename, lineno, compile_id)
@@ -1066,12 +1284,15 @@ fn render_sym_expr_trie(
 
 pub struct PropagateRealTensorsParser<'t> {
     pub tt: &'t TinyTemplate<'t>,
-    pub sym_expr_info_index: &'t SymExprInfoIndex,
+    pub sym_expr_info_index: &'t RefCell<SymExprInfoIndex>,
 }
 impl StructuredLogParser for PropagateRealTensorsParser<'_> {
     fn name(&self) -> &'static str {
         "guard_added"
     }
+    fn envelope_types(&self) -> Option<&[&'static str]> {
+        Some(&["propagate_real_tensors_provenance", "guard_added"])
+    }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         if let Some(m) = e.propagate_real_tensors_provenance.as_ref() {
             return Some(Metadata::SymbolicShapePropagateRealTensor(m));
@@ -1107,17 +1328,22 @@ impl StructuredLogParser for PropagateRealTensorsParser<'_> {
             );
 
             let mut visited = HashSet::new();
-            let sym_expr_trie_html = render_sym_expr_trie(
-                m.expr_node_id.unwrap(),
-                self.sym_expr_info_index,
-                0,
-                &mut visited,
-            )
-            .unwrap_or("".to_string());
+            // Guards from torch.compile logs may not have an expression tree
+            let sym_expr_trie_html = m
+                .expr_node_id
+                .and_then(|expr_node_id| {
+                    render_sym_expr_trie(
+                        expr_node_id,
+                        &self.sym_expr_info_index.borrow(),
+                        0,
+                        &mut visited,
+                    )
+                })
+                .unwrap_or("".to_string());
 
             let context = SymbolicGuardContext {
                 css: crate::CSS,
-                expr: m.expr.clone().unwrap(),
+                expr: m.expr.clone().unwrap_or_default(),
                 user_stack_html: user_stack_html,
                 framework_stack_html: framework_stack_html,
                 sym_expr_trie_html: sym_expr_trie_html,
@@ -1146,44 +1372,75 @@ pub fn default_parsers<'t>(
     }
 
     let result: Vec<Box<dyn StructuredLogParser>> = vec![
-        Box::new(SentinelFileParser::new("optimize_ddp_split_graph", |e| {
-            e.optimize_ddp_split_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("compiled_autograd_graph", |e| {
-            e.compiled_autograd_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("aot_forward_graph", |e| {
-            e.aot_forward_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("aot_backward_graph", |e| {
-            e.aot_backward_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("aot_inference_graph", |e| {
-            e.aot_inference_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("aot_joint_graph", |e| {
-            e.aot_joint_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("inductor_post_grad_graph", |e| {
-            e.inductor_post_grad_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("inductor_pre_grad_graph", |e| {
-            e.inductor_pre_grad_graph.as_ref()
-        })),
-        Box::new(SentinelFileParser::new("dynamo_cpp_guards_str", |e| {
-            e.dynamo_cpp_guards_str.as_ref()
-        })),
-        Box::new(GraphDumpParser),
-        Box::new(DynamoOutputGraphParser),
+        Box::new(SentinelFileParser::graph(
+            "optimize_ddp_split_graph",
+            |e| e.optimize_ddp_split_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "compiled_autograd_graph",
+            |e| e.compiled_autograd_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "aot_forward_graph",
+            |e| e.aot_forward_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "aot_backward_graph",
+            |e| e.aot_backward_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "aot_inference_graph",
+            |e| e.aot_inference_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "aot_joint_graph",
+            |e| e.aot_joint_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "inductor_post_grad_graph",
+            |e| e.inductor_post_grad_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(SentinelFileParser::graph(
+            "inductor_pre_grad_graph",
+            |e| e.inductor_pre_grad_graph.as_ref(),
+            parser_config,
+        )),
+        Box::new(GraphDumpParser::new(parser_config)),
+        Box::new(DynamoOutputGraphParser::new(parser_config)),
         Box::new(DynamoGuardParser { tt }),
         Box::new(InductorOutputCodeParser::new(parser_config)),
-        Box::new(OptimizeDdpSplitChildParser),
+        Box::new(OptimizeDdpSplitChildParser::new(parser_config)),
         Box::new(AOTAutogradBackwardCompilationMetricsParser { tt }), // TODO: use own tt instances
         Box::new(BwdCompilationMetricsParser { tt }),                 // TODO: use own tt instances
         Box::new(LinkParser),
-        Box::new(ArtifactParser),
+        Box::new(ArtifactParser::new(parser_config)),
         Box::new(DumpFileParser),
     ];
 
     result
 }
+
+/// The default parsers, plus the symbolic guard provenance parser, which renders the expression
+/// trees `sym_expr_info_index` collects as the log is read
+pub(crate) fn default_parsers_with_sym_exprs<'t>(
+    tt: &'t TinyTemplate<'t>,
+    parser_config: &ParseConfig,
+    sym_expr_info_index: &'t RefCell<SymExprInfoIndex>,
+) -> Vec<Box<dyn StructuredLogParser + 't>> {
+    let mut parsers = default_parsers(tt, parser_config);
+    // Export mode renders these guards as export failures instead
+    if !parser_config.export {
+        parsers.push(Box::new(PropagateRealTensorsParser {
+            tt,
+            sym_expr_info_index,
+        }));
+    }
+    parsers
+}
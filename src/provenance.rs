@@ -0,0 +1,582 @@
+//! Inputs of the provenance tracking highlighter.
+//!
+//! Collects the graphs, generated code and node mappings that Inductor logs for each compile id,
+//! so the highlighter pages can be built without searching the output for them. The graphs and
+//! code of a compile id are dropped when it finishes without node mappings. A compile id
+//! that ran Inductor more than once, e.g. for a forward and a backward graph, logs a node
+//! mapping and a code file per run, and gets a page for each.
+//!
+//! The node mappings name FX nodes and kernels; the highlighter needs line numbers. Graphs and
+//! wrapper code are split into identifiers, so `triton_poi_fused_mul_1` doesn't match
+//! `triton_poi_fused_mul_10`, and a kernel name with a debug handle such as
+//! `triton_poi_fused_mul_1:2` is resolved through the `[Provenance debug handles]` comment
+//! Inductor writes above each launch.
+
+use std::collections::BTreeSet;
+
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use crate::types::*;
+
+// A node definition in a printed FX graph, e.g. `mul_1: "f32[8][1]cuda:0" = ...` or `add = ...`
+static NODE_DEFINITION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*(?<name>[A-Za-z_]\w*)\s*(?::\s*"[^"]*"\s*)?=(?:[^=]|$)"#).unwrap()
+});
+
+const DEBUG_HANDLES: &str = "[Provenance debug handles]";
+
+#[derive(Default)]
+struct CompileProvenance {
+    pre_grad_graphs: Vec<String>,
+    // Older logs only have inductor_pre_grad_graph / inductor_post_grad_graph
+    fallback_pre_grad_graphs: Vec<String>,
+    post_grad_graphs: Vec<String>,
+    fallback_post_grad_graphs: Vec<String>,
+    output_code: Vec<String>,
+    aot_code: Vec<String>,
+    node_mappings: Vec<String>,
+}
+
+/// What one provenance page shows
+pub struct ProvenanceInputs<'a> {
+    pub pre_grad_graph: &'a str,
+    pub post_grad_graph: &'a str,
+    pub output_code: &'a str,
+    pub aot_code: &'a str,
+    pub node_mappings: &'a str,
+}
+
+// The nth of `contents`, or the last if there are fewer; a backward graph has no pre-grad graph
+// of its own, for example
+fn nth_or_last<'a>(contents: &'a [String], fallback: &'a [String], n: usize) -> &'a str {
+    let contents = if contents.is_empty() {
+        fallback
+    } else {
+        contents
+    };
+    contents
+        .get(n)
+        .or(contents.last())
+        .map_or("", String::as_str)
+}
+
+#[derive(Default)]
+pub struct ProvenanceIndex {
+    compiles: FxIndexMap<Option<CompileId>, CompileProvenance>,
+}
+
+impl ProvenanceIndex {
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str) {
+        if e.compilation_metrics.is_some() || e.bwd_compilation_metrics.is_some() {
+            // A compile without node mappings gets no page
+            if self
+                .compiles
+                .get(&e.compile_id)
+                .is_some_and(|c| c.node_mappings.is_empty())
+            {
+                self.compiles.shift_remove(&e.compile_id);
+            }
+            return;
+        }
+        let artifact = e.artifact.as_ref().map(|a| a.name.as_str());
+        let graph_dump = e.graph_dump.as_ref().map(|g| g.name.as_str());
+        let field: fn(&mut CompileProvenance) -> &mut Vec<String> =
+            if e.inductor_output_code.is_some() {
+                |c| &mut c.output_code
+            } else if e.inductor_pre_grad_graph.is_some() {
+                |c| &mut c.fallback_pre_grad_graphs
+            } else if e.inductor_post_grad_graph.is_some() {
+                |c| &mut c.fallback_post_grad_graphs
+            } else if graph_dump == Some("inductor_aot_wrapper_code") {
+                |c| &mut c.aot_code
+            } else {
+                match artifact {
+                    Some("before_pre_grad_graph") => |c| &mut c.pre_grad_graphs,
+                    Some("after_post_grad_graph") => |c| &mut c.post_grad_graphs,
+                    Some("inductor_provenance_tracking_node_mappings") => |c| &mut c.node_mappings,
+                    _ => return,
+                }
+            };
+        field(self.compiles.entry(e.compile_id.clone()).or_default()).push(payload.to_string());
+    }
+
+    /// The filenames of a compile id's provenance pages, if it has node mappings; the first is
+    /// `provenance_tracking_{directory}.html`, later runs of Inductor get a numeric suffix
+    pub fn page_names(&self, compile_id: &Option<CompileId>) -> Vec<String> {
+        let Some(c) = self
+            .compiles
+            .get(compile_id)
+            .filter(|c| !c.node_mappings.is_empty())
+        else {
+            return Vec::new();
+        };
+        let directory = compile_id
+            .as_ref()
+            .map_or("(unknown)".to_string(), |c| c.as_directory_name());
+        let runs = c
+            .node_mappings
+            .len()
+            .max(c.output_code.len())
+            .max(c.aot_code.len())
+            .max(1);
+        (0..runs)
+            .map(|n| match n {
+                0 => format!("provenance_tracking_{}.html", directory),
+                n => format!("provenance_tracking_{}_{}.html", directory, n),
+            })
+            .collect()
+    }
+
+    /// The inputs of each of a compile id's pages, in the order of `page_names`
+    pub fn pages(&self, compile_id: &Option<CompileId>) -> Vec<ProvenanceInputs<'_>> {
+        let Some(c) = self.compiles.get(compile_id) else {
+            return Vec::new();
+        };
+        (0..self.page_names(compile_id).len())
+            .map(|n| ProvenanceInputs {
+                pre_grad_graph: nth_or_last(&c.pre_grad_graphs, &c.fallback_pre_grad_graphs, n),
+                post_grad_graph: nth_or_last(&c.post_grad_graphs, &c.fallback_post_grad_graphs, n),
+                output_code: nth_or_last(&c.output_code, &[], n),
+                aot_code: nth_or_last(&c.aot_code, &[], n),
+                node_mappings: nth_or_last(&c.node_mappings, &[], n),
+            })
+            .collect()
+    }
+
+    pub fn compile_ids(&self) -> impl Iterator<Item = &Option<CompileId>> {
+        self.compiles.keys()
+    }
+}
+
+// Name -> 1-based line numbers
+type Lines = FxHashMap<String, Vec<usize>>;
+
+/// The node mappings of a provenance page, converted to line numbers
+pub struct LineMappings {
+    pub mappings: Value,
+    /// Nodes and kernels named by the node mappings that aren't in the graphs or code
+    pub unresolved: Vec<String>,
+}
+
+// The identifiers of a line of Python or C++ code, and its comment. Dotted and qualified names
+// such as `extern_kernels.mm` are one identifier; string literals are skipped.
+struct Tokens<'a> {
+    identifiers: Vec<&'a str>,
+    comment: Option<&'a str>,
+}
+
+fn tokenize<'a>(line: &'a str, comment: &str) -> Tokens<'a> {
+    let bytes = line.as_bytes();
+    let is_start = |b: u8| b.is_ascii_alphabetic() || b == b'_';
+    let is_continue = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut identifiers = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if bytes[i..].starts_with(comment.as_bytes()) {
+            return Tokens {
+                identifiers,
+                comment: Some(&line[i + comment.len()..]),
+            };
+        } else if b == b'"' || b == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if is_start(b) {
+            let start = i;
+            loop {
+                while i < bytes.len() && is_continue(bytes[i]) {
+                    i += 1;
+                }
+                let separator = if bytes[i..].starts_with(b"::") {
+                    2
+                } else if bytes[i..].starts_with(b".") {
+                    1
+                } else {
+                    break;
+                };
+                if !bytes.get(i + separator).is_some_and(|&b| is_start(b)) {
+                    break;
+                }
+                i += separator;
+            }
+            identifiers.push(&line[start..i]);
+        } else if b.is_ascii_digit() {
+            // Numbers, including literals like 200L and 1e-05
+            while i < bytes.len() && (is_continue(bytes[i]) || bytes[i] == b'.') {
+                i += 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    Tokens {
+        identifiers,
+        comment: None,
+    }
+}
+
+// Whether `identifier` refers to `kernel`: the kernel itself, an attribute of it such as
+// `triton_poi_fused_mul_1.run`, or the `call_` function the C++ wrapper launches it with
+fn refers_to(identifier: &str, kernel: &str) -> bool {
+    [identifier, identifier.strip_prefix("call_").unwrap_or("")]
+        .iter()
+        .any(|i| {
+            i.strip_prefix(kernel)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+}
+
+// `triton_poi_fused_mul_1:2` -> (`triton_poi_fused_mul_1`, true)
+fn split_debug_handle(kernel: &str) -> (&str, bool) {
+    match kernel.rsplit_once(':') {
+        Some((name, handle))
+            if !name.is_empty()
+                && !handle.is_empty()
+                && handle.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (name, true)
+        }
+        _ => (kernel, false),
+    }
+}
+
+impl Tokens<'_> {
+    fn refers_to(&self, kernel: &str) -> bool {
+        self.identifiers.iter().any(|i| refers_to(i, kernel))
+    }
+
+    fn has_debug_handle(&self, kernel: &str) -> bool {
+        self.comment
+            .and_then(|c| c.trim().strip_prefix(DEBUG_HANDLES))
+            .is_some_and(|handles| handles.split(',').any(|h| h.trim() == kernel))
+    }
+}
+
+// The lines of a wrapper, from which kernel launches are looked up
+struct Code<'a> {
+    lines: Vec<Tokens<'a>>,
+    // The line where the wrapper's entry point starts; launches are after it
+    start: usize,
+    // Subtracted from line numbers
+    offset: usize,
+}
+
+impl<'a> Code<'a> {
+    // Leading empty lines are skipped, as they are not shown in the page's <pre>
+    fn new(content: &'a str, comment: &str) -> Self {
+        let lines = content
+            .lines()
+            .skip_while(|line| line.is_empty())
+            .map(|line| tokenize(line, comment))
+            .collect();
+        Code {
+            lines,
+            start: 0,
+            offset: 0,
+        }
+    }
+
+    // Python wrapper code, which runs its kernels in `def call(args)`
+    fn python(content: &'a str) -> Self {
+        let mut code = Code::new(content, "#");
+        code.start = code
+            .lines
+            .iter()
+            .position(|l| l.identifiers.starts_with(&["def", "call"]))
+            .unwrap_or(0);
+        code.offset = code
+            .lines
+            .iter()
+            .position(|l| l.comment.is_some_and(|c| c.trim().starts_with("AOT ID:")))
+            .unwrap_or(0);
+        code
+    }
+
+    // A C++ AOTInductor wrapper, which runs its kernels in `AOTInductorModel::run_impl`
+    fn cpp(content: &'a str) -> Self {
+        let mut code = Code::new(content, "//");
+        code.start = code
+            .lines
+            .iter()
+            .position(|l| l.identifiers.iter().any(|i| i.ends_with("::run_impl")))
+            .unwrap_or(0);
+        code
+    }
+
+    // The lines that launch `kernel`. With a debug handle, that's the launch after the comment
+    // naming the handle; without one, or in code that has no such comments, every launch.
+    fn kernel_lines(&self, kernel: &str) -> Vec<usize> {
+        let (name, has_handle) = split_debug_handle(kernel);
+        let body = self.lines.iter().enumerate().skip(self.start);
+        let line_number = |(i, _): (usize, _)| (i + 1).saturating_sub(self.offset);
+        if has_handle {
+            let launch = body
+                .clone()
+                .skip_while(|(_, l)| !l.has_debug_handle(kernel))
+                .find(|(_, l)| l.refers_to(name));
+            if let Some(launch) = launch {
+                return vec![line_number(launch)];
+            }
+        }
+        body.filter(|(_, l)| l.refers_to(name))
+            .map(line_number)
+            .collect()
+    }
+}
+
+/// The node a line of a printed FX graph defines, if any
+pub fn node_name(line: &str) -> Option<&str> {
+    NODE_DEFINITION
+        .captures(line)
+        .and_then(|m| m.name("name"))
+        .map(|m| m.as_str())
+}
+
+// The line each node of a printed FX graph is defined on
+fn node_lines(graph: &str) -> Lines {
+    let mut lines = Lines::default();
+    for (i, line) in graph.lines().enumerate() {
+        if let Some(name) = node_name(line) {
+            lines.insert(name.to_string(), vec![i + 1]);
+        }
+    }
+    lines
+}
+
+// Convert one direction of the node mappings, e.g. preToPost, to line numbers
+fn map_lines(mappings: Option<&Map<String, Value>>, from: &Lines, to: &Lines) -> Value {
+    let mut result = Map::new();
+    for (source, targets) in mappings.into_iter().flatten() {
+        let target_lines: Vec<usize> = targets
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter_map(|t| to.get(t))
+            .flatten()
+            .copied()
+            .collect();
+        if target_lines.is_empty() {
+            continue;
+        }
+        for line in from.get(source).into_iter().flatten() {
+            result.insert(line.to_string(), json!(target_lines));
+        }
+    }
+    Value::Object(result)
+}
+
+impl ProvenanceInputs<'_> {
+    pub fn line_mappings(&self) -> LineMappings {
+        let Ok(Value::Object(node_mappings)) = serde_json::from_str(self.node_mappings) else {
+            return LineMappings {
+                mappings: json!({}),
+                unresolved: Vec::new(),
+            };
+        };
+        let mapping = |key: &str| node_mappings.get(key).and_then(Value::as_object);
+        let keys = |key: &str| -> Vec<&str> {
+            mapping(key)
+                .into_iter()
+                .flat_map(|m| m.keys().map(String::as_str))
+                .collect()
+        };
+        let values = |key: &str| -> Vec<&str> {
+            mapping(key)
+                .into_iter()
+                .flat_map(|m| m.values())
+                .filter_map(Value::as_array)
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect()
+        };
+
+        let pre = node_lines(self.pre_grad_graph);
+        let post = node_lines(self.post_grad_graph);
+        let kernels: BTreeSet<&str> = keys("cppCodeToPost")
+            .into_iter()
+            .chain(values("postToCppCode"))
+            .collect();
+        let py_code = Code::python(self.output_code);
+        let cpp_code = Code::cpp(self.aot_code);
+        let kernel_lines = |code: &Code| -> Lines {
+            kernels
+                .iter()
+                .map(|k| (k.to_string(), code.kernel_lines(k)))
+                .filter(|(_, lines)| !lines.is_empty())
+                .collect()
+        };
+        let py = kernel_lines(&py_code);
+        let cpp = kernel_lines(&cpp_code);
+
+        let mut unresolved = Vec::new();
+        let pre_nodes: BTreeSet<&str> = keys("preToPost")
+            .into_iter()
+            .chain(values("postToPre"))
+            .collect();
+        let post_nodes: BTreeSet<&str> = [
+            keys("postToPre"),
+            keys("postToCppCode"),
+            values("preToPost"),
+            values("cppCodeToPost"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        for (kind, names, found) in [
+            ("pre-grad node", &pre_nodes, vec![&pre]),
+            ("post-grad node", &post_nodes, vec![&post]),
+            ("kernel", &kernels, vec![&py, &cpp]),
+        ] {
+            unresolved.extend(
+                names
+                    .iter()
+                    .filter(|name| !found.iter().any(|lines| lines.contains_key(**name)))
+                    .map(|name| format!("{} {}", kind, name)),
+            );
+        }
+
+        LineMappings {
+            mappings: json!({
+                "preToPost": map_lines(mapping("preToPost"), &pre, &post),
+                "postToPre": map_lines(mapping("postToPre"), &post, &pre),
+                "pyCodeToPost": map_lines(mapping("cppCodeToPost"), &py, &post),
+                "postToPyCode": map_lines(mapping("postToCppCode"), &post, &py),
+                "cppCodeToPost": map_lines(mapping("cppCodeToPost"), &cpp, &post),
+                "postToCppCode": map_lines(mapping("postToCppCode"), &post, &cpp),
+            }),
+            unresolved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward_and_backward() {
+        let mut index = ProvenanceIndex::default();
+        let artifact = |name: &str| {
+            Envelope::for_test(
+                0,
+                &format!(r#"{{"artifact": {{"name": "{name}", "encoding": "string"}}}}"#),
+            )
+        };
+        index.add_envelope(&artifact("before_pre_grad_graph"), "pre");
+        for run in ["fwd", "bwd"] {
+            index.add_envelope(&artifact("after_post_grad_graph"), &format!("{run} post"));
+            index.add_envelope(
+                &Envelope::for_test(0, r#"{"inductor_output_code": {"filename": "a.py"}}"#),
+                &format!("{run} code"),
+            );
+            index.add_envelope(
+                &artifact("inductor_provenance_tracking_node_mappings"),
+                &format!("{run} mappings"),
+            );
+        }
+
+        let compile_id = index.compile_ids().next().unwrap().clone();
+        assert_eq!(
+            index.page_names(&compile_id),
+            vec![
+                "provenance_tracking_-_0_0_0.html",
+                "provenance_tracking_-_0_0_0_1.html"
+            ]
+        );
+        let pages = index.pages(&compile_id);
+        let bwd = &pages[1];
+        assert_eq!(
+            (
+                bwd.pre_grad_graph,
+                bwd.post_grad_graph,
+                bwd.output_code,
+                bwd.node_mappings
+            ),
+            ("pre", "bwd post", "bwd code", "bwd mappings")
+        );
+        assert_eq!(bwd.aot_code, "");
+    }
+
+    #[test]
+    fn test_compiles_without_mappings_are_dropped() {
+        let mut index = ProvenanceIndex::default();
+        let code = Envelope::for_test(0, r#"{"inductor_output_code": {"filename": "a.py"}}"#);
+        let metrics = Envelope::for_test(0, r#"{"compilation_metrics": {}}"#);
+        index.add_envelope(&code, "code");
+        index.add_envelope(&metrics, "");
+        assert_eq!(index.compile_ids().count(), 0);
+
+        index.add_envelope(&code, "code");
+        index.add_envelope(
+            &Envelope::for_test(
+                0,
+                r#"{"artifact": {"name": "inductor_provenance_tracking_node_mappings", "encoding": "json"}}"#,
+            ),
+            "{}",
+        );
+        index.add_envelope(&metrics, "");
+        let compile_id = index.compile_ids().next().unwrap().clone();
+        assert_eq!(index.pages(&compile_id)[0].output_code, "code");
+    }
+
+    #[test]
+    fn test_kernel_names_are_identifiers() {
+        let post = "mul: \"f32[8]\" = torch.ops.aten.mul.Tensor(x, 2)\n\
+                    mul_10: \"f32[8]\" = torch.ops.aten.mul.Tensor(mul, 2)";
+        let code = "# AOT ID: ['0_inference']\n\
+                    def call(args):\n    \
+                    triton_poi_fused_mul_10.run(buf0, 8)\n    \
+                    # [Provenance debug handles] triton_poi_fused_mul_1:20\n    \
+                    triton_poi_fused_mul_1.run(buf0, 8)\n    \
+                    # [Provenance debug handles] triton_poi_fused_mul_1:2\n    \
+                    triton_poi_fused_mul_1.run(buf1, 8)";
+        let node_mappings = r#"{
+            "cppCodeToPost": {
+                "triton_poi_fused_mul_1:2": ["mul"],
+                "triton_poi_fused_mul_10": ["mul_10"],
+                "triton_poi_fused_mul_2": ["mul"]
+            },
+            "postToCppCode": {"mul_1": ["triton_poi_fused_mul_1:2"]}
+        }"#;
+        let inputs = ProvenanceInputs {
+            pre_grad_graph: "",
+            post_grad_graph: post,
+            output_code: code,
+            aot_code: "",
+            node_mappings,
+        };
+        let line_mappings = inputs.line_mappings();
+        assert_eq!(
+            line_mappings.mappings["pyCodeToPost"],
+            json!({"3": [2], "7": [1]})
+        );
+        assert_eq!(
+            line_mappings.unresolved,
+            vec!["post-grad node mul_1", "kernel triton_poi_fused_mul_2"]
+        );
+    }
+
+    #[test]
+    fn test_cpp_launches() {
+        let code = "void AOTInductorModel::const_run_impl() {}\n\
+                    void AOTInductorModel::run_impl() {\n    \
+                    int64_t triton_poi_fused_mul_1_xnumel = 200L;\n    \
+                    call_triton_poi_fused_mul_1(buf0, 200L); // \"triton_poi_fused_mul_10\"\n    \
+                    AOTI_TORCH_ERROR_CODE_CHECK(aoti_torch_cuda_mm_out(buf1, buf0));\n\
+                    }";
+        let code = Code::cpp(code);
+        assert_eq!(code.kernel_lines("triton_poi_fused_mul_1"), vec![4]);
+        assert_eq!(
+            code.kernel_lines("triton_poi_fused_mul_10"),
+            Vec::<usize>::new()
+        );
+        assert_eq!(code.kernel_lines("aoti_torch_cuda_mm_out:4"), vec![5]);
+    }
+}
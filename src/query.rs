@@ -0,0 +1,587 @@
+//! Ad-hoc filtering over parsed logs, backing `tlparse query`.
+//!
+//! The input is either a raw TORCH_TRACE log or a directory written by `--intermediate-only`;
+//! both are read as `IntermediateEntry` values, so a query behaves the same on either. Chromium
+//! trace events are not envelopes with metadata and are never returned.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDateTime, NaiveTime};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::intermediate::{
+    parse_compile_id, IntermediateEntry, IntermediateFileType, IntermediateManifest, LogEntries,
+    LogRecord,
+};
+
+/// How matching entries are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryFormat {
+    /// One `IntermediateEntry` JSON object per line
+    Jsonl,
+    /// Aligned columns: timestamp, rank, compile id, type, name, payload size
+    Table,
+    /// Only the payloads, one after another
+    Payload,
+}
+
+impl FromStr for QueryFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" => Ok(QueryFormat::Jsonl),
+            "table" => Ok(QueryFormat::Table),
+            "payload" | "payloads" => Ok(QueryFormat::Payload),
+            _ => bail!("Unknown format '{}', expected jsonl, table or payload", s),
+        }
+    }
+}
+
+/// A compile id pattern in the `CompileId` display format, e.g. `[0/1]`, `[!3/0/1_2]` or
+/// `0/*`. `-` matches a missing id and `*` matches anything. An elided attempt matches attempt
+/// 0 (as in the display format), unless the frame compile id is `*`, in which case it matches
+/// every attempt so that `[3/*]` selects everything compiled for frame 3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileIdPattern {
+    compiled_autograd_id: Option<Option<u32>>,
+    frame_id: Option<Option<u32>>,
+    frame_compile_id: Option<Option<u32>>,
+    attempt: Option<Option<u32>>,
+}
+
+impl FromStr for CompileIdPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            anyhow!(
+                "Invalid compile id '{}', expected e.g. [0/1] or [!0/1/2_1]",
+                s
+            )
+        };
+        // None is a wildcard, Some(None) a missing id
+        let component = |v: &str| -> Result<Option<Option<u32>>> {
+            match v {
+                "*" => Ok(None),
+                "-" => Ok(Some(None)),
+                v => v.parse().map(|n| Some(Some(n))).map_err(|_| invalid()),
+            }
+        };
+
+        let inner = s.trim();
+        let inner = inner
+            .strip_prefix('[')
+            .and_then(|i| i.strip_suffix(']'))
+            .unwrap_or(inner);
+        let (compiled_autograd_id, rest) = match inner.strip_prefix('!') {
+            Some(rest) => {
+                let (ca, rest) = rest.split_once('/').ok_or_else(invalid)?;
+                (component(ca)?, rest)
+            }
+            None => (Some(None), inner),
+        };
+        let (frame_id, rest) = rest.split_once('/').ok_or_else(invalid)?;
+        let (frame_compile_id, attempt) = match rest.split_once('_') {
+            Some((fcid, attempt)) => (component(fcid)?, component(attempt)?),
+            None if rest == "*" => (None, None),
+            None => (component(rest)?, Some(Some(0))),
+        };
+        Ok(CompileIdPattern {
+            compiled_autograd_id,
+            frame_id: component(frame_id)?,
+            frame_compile_id,
+            attempt,
+        })
+    }
+}
+
+impl CompileIdPattern {
+    /// Whether an intermediate compile id string (see `format_compile_id`) matches
+    pub fn matches(&self, compile_id: Option<&str>) -> bool {
+        let Some(cid) = compile_id.and_then(parse_compile_id) else {
+            return false;
+        };
+        let component = |pattern: Option<Option<u32>>, actual: Option<u32>| match pattern {
+            None => true,
+            Some(expected) => expected == actual,
+        };
+        component(self.compiled_autograd_id, cid.compiled_autograd_id)
+            && component(self.frame_id, cid.frame_id)
+            && component(self.frame_compile_id, cid.frame_compile_id)
+            && component(self.attempt, Some(cid.attempt.unwrap_or(0)))
+    }
+}
+
+/// One end of a `--since`/`--until` range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// A full timestamp, e.g. `2024-11-28T12:00:00` (RFC 3339 offsets are converted to UTC)
+    At(NaiveDateTime),
+    /// A time of day, e.g. `12:00:00.5`, compared against the time of day of each entry
+    TimeOfDay(NaiveTime),
+}
+
+impl FromStr for TimeBound {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeBound::At(dt.naive_utc()));
+        }
+        for fmt in [
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M",
+        ] {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+                return Ok(TimeBound::At(dt));
+            }
+        }
+        for fmt in ["%H:%M:%S%.f", "%H:%M"] {
+            if let Ok(t) = NaiveTime::parse_from_str(s, fmt) {
+                return Ok(TimeBound::TimeOfDay(t));
+            }
+        }
+        bail!(
+            "Invalid time '{}', expected e.g. 2024-11-28T12:00:00 or 12:00:00",
+            s
+        )
+    }
+}
+
+impl TimeBound {
+    /// Compare an entry timestamp against this bound; `None` if the timestamp doesn't parse
+    fn cmp_entry(&self, timestamp: &str) -> Option<std::cmp::Ordering> {
+        let ts = chrono::DateTime::parse_from_rfc3339(timestamp)
+            .ok()?
+            .naive_utc();
+        Some(match self {
+            TimeBound::At(bound) => ts.cmp(bound),
+            TimeBound::TimeOfDay(bound) => ts.time().cmp(bound),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// A predicate on entry metadata: `PATH [OP VALUE]`.
+///
+/// `PATH` is a JSON path relative to the metadata object, such as `fail_type`,
+/// `$.restart_reasons[0]` or `stack[0].name`. `OP` is one of `==`, `!=`, `<`, `<=`, `>`, `>=`
+/// or `~=` (substring / array membership), and `VALUE` is parsed as JSON, falling back to a
+/// plain string. Without an operator, the predicate holds if the value exists and is neither
+/// `null` nor `false`. Missing values compare as `null`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    path: Vec<PathSegment>,
+    op: Option<(Op, Value)>,
+}
+
+impl FromStr for Predicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Longest operators first so that `>=` isn't read as `>`
+        const OPS: &[(&str, Op)] = &[
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("~=", Op::Contains),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+            ("=", Op::Eq),
+        ];
+        let found = OPS
+            .iter()
+            .filter_map(|(token, op)| s.find(token).map(|pos| (pos, *token, *op)))
+            .min_by_key(|(pos, token, _)| (*pos, std::cmp::Reverse(token.len())));
+        let (path, op) = match found {
+            Some((pos, token, op)) => {
+                let raw = s[pos + token.len()..].trim();
+                let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::from(raw));
+                (&s[..pos], Some((op, value)))
+            }
+            None => (s, None),
+        };
+        Ok(Predicate {
+            path: parse_path(path.trim()).with_context(|| format!("Invalid predicate '{}'", s))?,
+            op,
+        })
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<PathSegment>> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let path = path.strip_prefix('.').unwrap_or(path);
+    let path = path.strip_prefix("metadata.").unwrap_or(path);
+    if path.is_empty() {
+        bail!("empty path");
+    }
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indexes) = match part.find('[') {
+            Some(i) => (&part[..i], &part[i..]),
+            None => (part, ""),
+        };
+        if !key.is_empty() {
+            segments.push(PathSegment::Key(key.to_string()));
+        } else if indexes.is_empty() {
+            bail!("empty path component");
+        }
+        while let Some(rest) = indexes.strip_prefix('[') {
+            let (index, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| anyhow!("unclosed '['"))?;
+            segments.push(PathSegment::Index(index.trim().parse()?));
+            indexes = rest;
+        }
+        if !indexes.is_empty() {
+            bail!("unexpected '{}'", indexes);
+        }
+    }
+    Ok(segments)
+}
+
+impl Predicate {
+    pub fn matches(&self, metadata: &Value) -> bool {
+        let mut current = metadata;
+        for segment in &self.path {
+            let next = match segment {
+                PathSegment::Key(key) => current.get(key),
+                PathSegment::Index(i) => current.get(i),
+            };
+            current = next.unwrap_or(&Value::Null);
+        }
+        let Some((op, expected)) = &self.op else {
+            return !matches!(current, Value::Null | Value::Bool(false));
+        };
+        let ordering = match (current, expected) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match op {
+            Op::Eq => current == expected || ordering == Some(std::cmp::Ordering::Equal),
+            Op::Ne => !(current == expected || ordering == Some(std::cmp::Ordering::Equal)),
+            Op::Lt => ordering.is_some_and(|o| o.is_lt()),
+            Op::Le => ordering.is_some_and(|o| o.is_le()),
+            Op::Gt => ordering.is_some_and(|o| o.is_gt()),
+            Op::Ge => ordering.is_some_and(|o| o.is_ge()),
+            Op::Contains => {
+                let needle = match expected {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                match current {
+                    Value::String(s) => s.contains(&needle),
+                    Value::Array(items) => items.iter().any(|item| {
+                        item == expected || item.as_str().is_some_and(|s| s.contains(&needle))
+                    }),
+                    Value::Object(map) => map.contains_key(&needle),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Which entries a query selects. Empty lists match everything; all criteria must hold.
+#[derive(Debug, Default, Clone)]
+pub struct QueryFilter {
+    /// Any of these compile ids
+    pub compile_ids: Vec<CompileIdPattern>,
+    /// Any of these envelope types, e.g. `compilation_metrics`
+    pub types: Vec<String>,
+    /// Any of these artifact names (the `name` metadata field of artifacts, dump files, graph
+    /// dumps and links)
+    pub names: Vec<String>,
+    pub rank: Option<u32>,
+    /// Inclusive lower bound on the entry timestamp
+    pub since: Option<TimeBound>,
+    /// Inclusive upper bound on the entry timestamp
+    pub until: Option<TimeBound>,
+    /// All of these metadata predicates
+    pub predicates: Vec<Predicate>,
+}
+
+impl QueryFilter {
+    pub fn matches(&self, entry: &IntermediateEntry) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&entry.entry_type) {
+            return false;
+        }
+        if !self.names.is_empty() {
+            let name = entry.metadata.get("name").and_then(|n| n.as_str());
+            if !name.is_some_and(|name| self.names.iter().any(|n| n == name)) {
+                return false;
+            }
+        }
+        if !self.compile_ids.is_empty()
+            && !self
+                .compile_ids
+                .iter()
+                .any(|p| p.matches(entry.compile_id.as_deref()))
+        {
+            return false;
+        }
+        if self.rank.is_some() && entry.rank != self.rank {
+            return false;
+        }
+        if let Some(since) = &self.since {
+            if !since.cmp_entry(&entry.timestamp).is_some_and(|o| o.is_ge()) {
+                return false;
+            }
+        }
+        if let Some(until) = &self.until {
+            if !until.cmp_entry(&entry.timestamp).is_some_and(|o| o.is_le()) {
+                return false;
+            }
+        }
+        self.predicates.iter().all(|p| p.matches(&entry.metadata))
+    }
+}
+
+/// Call `visit` for every entry of a raw log or intermediate directory, in file order. A log
+/// holding several ranks is read for `rank`, or else for the first rank it has.
+fn for_each_entry(
+    input: &Path,
+    rank: Option<u32>,
+    mut visit: impl FnMut(IntermediateEntry) -> Result<()>,
+) -> Result<()> {
+    if !input.is_dir() {
+        let file =
+            File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+        let mut entries = LogEntries::new(BufReader::new(file))?.with_timestamps_from(input);
+        if let Some(rank) = rank {
+            entries = entries.with_rank(rank);
+        }
+        for record in entries.by_ref() {
+            if let LogRecord::Entry(entry, _) = record {
+                visit(entry)?;
+            }
+        }
+        // Without --rank, only the first rank of a multi-rank log is read
+        if rank.is_none() && entries.stats.other_rank > 0 {
+            eprintln!(
+                "warning: {} holds several ranks; showing rank {}, the first one logged. \
+                 Pass --rank to choose another",
+                input.display(),
+                entries.rank().unwrap_or_default()
+            );
+        }
+        return Ok(());
+    }
+
+    let manifest_path = input.join("manifest.json");
+    let manifest: IntermediateManifest =
+        serde_json::from_str(&fs::read_to_string(&manifest_path).with_context(|| {
+            format!(
+                "{} is neither a log file nor an intermediate directory",
+                input.display()
+            )
+        })?)?;
+    manifest.check_compatible()?;
+    for file_type in IntermediateFileType::all() {
+        let path = input.join(file_type.filename());
+        if *file_type == IntermediateFileType::ChromiumEvents || !path.exists() {
+            continue;
+        }
+        for line in BufReader::new(File::open(&path)?).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            visit(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("Invalid entry in {}", path.display()))?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print a single entry in the JSONL or payload format
+fn write_entry(entry: &IntermediateEntry, format: QueryFormat, out: &mut dyn Write) -> Result<()> {
+    match format {
+        QueryFormat::Jsonl => writeln!(out, "{}", serde_json::to_string(entry)?)?,
+        QueryFormat::Payload => {
+            if let Some(payload) = &entry.payload {
+                writeln!(out, "{}", payload)?;
+            }
+        }
+        QueryFormat::Table => unreachable!("tables are written by write_table"),
+    }
+    Ok(())
+}
+
+/// Run a query against `input` and print the matches to `out`. Returns the number of matches.
+pub fn run_query(
+    input: &Path,
+    filter: &QueryFilter,
+    format: QueryFormat,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    // Logs are streamed; intermediate files are grouped by category, so their matches are
+    // buffered and put back into log order. Tables need every row to size their columns.
+    let is_dir = input.is_dir();
+    let mut count = 0;
+    let mut buffered = Vec::new();
+    for_each_entry(input, filter.rank, |entry| {
+        if !filter.matches(&entry) {
+            return Ok(());
+        }
+        count += 1;
+        if is_dir || format == QueryFormat::Table {
+            buffered.push(entry);
+            Ok(())
+        } else {
+            write_entry(&entry, format, out)
+        }
+    })?;
+
+    if is_dir {
+        buffered.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    }
+    if format == QueryFormat::Table {
+        write_table(&buffered, out)?;
+    } else {
+        for entry in &buffered {
+            write_entry(entry, format, out)?;
+        }
+    }
+    Ok(count)
+}
+
+fn write_table(entries: &[IntermediateEntry], out: &mut dyn Write) -> Result<()> {
+    let header = ["TIMESTAMP", "RANK", "COMPILE ID", "TYPE", "NAME", "PAYLOAD"];
+    let rows: Vec<[String; 6]> = entries
+        .iter()
+        .map(|e| {
+            let name = ["name", "filename"]
+                .iter()
+                .find_map(|k| e.metadata.get(*k).and_then(|v| v.as_str()))
+                .unwrap_or("");
+            [
+                e.timestamp.clone(),
+                e.rank.map_or("-".to_string(), |r| r.to_string()),
+                e.compile_id
+                    .as_deref()
+                    .and_then(parse_compile_id)
+                    .map_or("-".to_string(), |cid| cid.to_string()),
+                e.entry_type.clone(),
+                name.to_string(),
+                e.payload
+                    .as_ref()
+                    .map_or("-".to_string(), |p| format!("{} B", p.len())),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compile_id_pattern() {
+        let p: CompileIdPattern = "[0/1]".parse().unwrap();
+        assert!(p.matches(Some("0_1_0")));
+        assert!(p.matches(Some("0_1")));
+        assert!(!p.matches(Some("0_1_1")));
+        assert!(!p.matches(Some("!2_0_1_0")));
+        assert!(!p.matches(None));
+
+        let p: CompileIdPattern = "!2/0/1_1".parse().unwrap();
+        assert!(p.matches(Some("!2_0_1_1")));
+        assert!(!p.matches(Some("0_1_1")));
+
+        // A wildcard frame compile id also covers restarts
+        let p: CompileIdPattern = "[3/*]".parse().unwrap();
+        assert!(p.matches(Some("3_0_0")));
+        assert!(p.matches(Some("3_5_1")));
+        assert!(!p.matches(Some("4_0_0")));
+
+        let p: CompileIdPattern = "[-/-]".parse().unwrap();
+        assert!(p.matches(Some("__0")));
+
+        assert!("0".parse::<CompileIdPattern>().is_err());
+        assert!("[a/b]".parse::<CompileIdPattern>().is_err());
+    }
+
+    #[test]
+    fn test_predicate() {
+        let metadata = json!({
+            "fail_type": "BackendCompilerFailed",
+            "cache_size": 3,
+            "restart_reasons": ["graph break in foo"],
+            "stack": [{"name": "forward", "line": 12}],
+            "co_name": null
+        });
+        let holds = |s: &str| s.parse::<Predicate>().unwrap().matches(&metadata);
+
+        assert!(holds("fail_type"));
+        assert!(!holds("co_name"));
+        assert!(!holds("missing"));
+        assert!(holds("fail_type == BackendCompilerFailed"));
+        assert!(holds("$.fail_type != \"Other\""));
+        assert!(holds("co_name == null"));
+        assert!(holds("cache_size>=3"));
+        assert!(holds("cache_size > 2.5"));
+        assert!(!holds("cache_size < 3"));
+        assert!(holds("restart_reasons ~= graph break"));
+        assert!(holds("restart_reasons[0] ~= foo"));
+        assert!(holds("metadata.stack[0].name == forward"));
+        assert!(holds("stack[0].line <= 12"));
+
+        assert!("".parse::<Predicate>().is_err());
+        assert!("stack[0".parse::<Predicate>().is_err());
+    }
+
+    #[test]
+    fn test_time_bounds() {
+        let entry_ts = "2024-11-28T12:30:00.000000Z";
+        let at: TimeBound = "2024-11-28T12:00:00".parse().unwrap();
+        assert_eq!(at.cmp_entry(entry_ts), Some(std::cmp::Ordering::Greater));
+        let tod: TimeBound = "12:45".parse().unwrap();
+        assert_eq!(tod.cmp_entry(entry_ts), Some(std::cmp::Ordering::Less));
+        assert!("yesterday".parse::<TimeBound>().is_err());
+    }
+}
@@ -0,0 +1,690 @@
+//! Side-by-side comparison of divergent ranks.
+//!
+//! When the multi-rank landing page flags divergence, ranks are partitioned by every signature
+//! it compares (compile ids, cache hit/miss sequence, tensor metadata, collective schedule), and
+//! the lowest rank of each partition stands in for it. For every compile id, the Dynamo output
+//! graph, guards, Inductor output code and tensor metadata of each representative are line
+//! diffed against the first representative.
+//!
+//! The texts diffed are the payloads of each representative's copy of its log (`raw.log`), not
+//! the rendered pages, which only provide the links. Values that differ between any two
+//! processes, the device index of a rank, the object ids guards check and the cache paths of
+//! kernels (which hash the device), are masked before diffing.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use fxhash::FxHashMap;
+use html_escape::encode_text;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tinytemplate::TinyTemplate;
+
+use crate::guards::{parse_cpp_guard_tree, CppGuardNode};
+use crate::intermediate::{parse_compile_id, IntermediateEntry, LogEntries, LogRecord};
+use crate::templates::*;
+use crate::types::*;
+
+static PER_PROCESS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?<prefix>\b(?:cuda|xpu|hpu|mtia|npu):|\bdevice=|\(type='\w+', index=|\b(?:_DeviceGuard|set_device|get_raw_stream)\(|\bstream|___check_(?:obj|type)_id\(.*, )\d+\b|(?<path># kernel path: )\S+",
+    )
+    .unwrap()
+});
+
+/// Lines of unchanged context shown around each change
+const CONTEXT_LINES: usize = 3;
+/// Past this many edits, the remainder of a diff is shown as a single replacement
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy)]
+enum ArtifactKind {
+    DynamoGraph,
+    Guards,
+    OutputCode,
+    TensorMeta,
+}
+
+const ARTIFACT_KINDS: [ArtifactKind; 4] = [
+    ArtifactKind::DynamoGraph,
+    ArtifactKind::Guards,
+    ArtifactKind::OutputCode,
+    ArtifactKind::TensorMeta,
+];
+
+impl ArtifactKind {
+    fn name(self) -> &'static str {
+        match self {
+            ArtifactKind::DynamoGraph => "Dynamo output graph",
+            ArtifactKind::Guards => "Guards",
+            ArtifactKind::OutputCode => "Inductor output code",
+            ArtifactKind::TensorMeta => "Tensor metadata",
+        }
+    }
+
+    fn matches(self, artifact: &str) -> bool {
+        match self {
+            ArtifactKind::DynamoGraph => artifact.starts_with("dynamo_output_graph"),
+            ArtifactKind::Guards => {
+                artifact.starts_with("dynamo_guards")
+                    || artifact.starts_with("dynamo_cpp_guards_str")
+            }
+            ArtifactKind::OutputCode => artifact.starts_with("inductor_output_code"),
+            ArtifactKind::TensorMeta => {
+                artifact.starts_with("inductor_runtime_and_tensor_meta")
+                    && artifact.ends_with(".json")
+            }
+        }
+    }
+
+    /// The text to diff of an envelope of a rank's log, if it is of this kind
+    fn text(self, entry: &IntermediateEntry) -> Option<String> {
+        let payload = entry.payload.as_deref()?;
+        let text = match (self, entry.entry_type.as_str()) {
+            (ArtifactKind::DynamoGraph, "dynamo_output_graph")
+            | (ArtifactKind::OutputCode, "inductor_output_code") => payload.to_string(),
+            (ArtifactKind::Guards, "dynamo_guards") => {
+                serde_json::from_str::<Vec<DynamoGuard>>(payload)
+                    .ok()?
+                    .into_iter()
+                    .map(|guard| guard.code)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            // Without the header and the footer, which has the guard latency of the process
+            (ArtifactKind::Guards, "dynamo_cpp_guards_str") => {
+                let mut lines = Vec::new();
+                guard_tree_lines(&parse_cpp_guard_tree(payload).roots, 0, &mut lines);
+                lines.join("\n")
+            }
+            (ArtifactKind::TensorMeta, "artifact")
+                if entry.metadata.get("name").and_then(|name| name.as_str())
+                    == Some("inductor_runtime_and_tensor_meta") =>
+            {
+                serde_json::from_str::<serde_json::Value>(payload)
+                    .and_then(|v| serde_json::to_string_pretty(&v))
+                    .unwrap_or_else(|_| payload.to_string())
+            }
+            _ => return None,
+        };
+        Some(mask_per_process(&text))
+    }
+}
+
+/// One line per node of a C++ guard tree, indented by its depth
+fn guard_tree_lines(nodes: &[CppGuardNode], depth: usize, lines: &mut Vec<String>) {
+    for node in nodes {
+        lines.push(format!("{}{}", "  ".repeat(depth), node.text));
+        guard_tree_lines(&node.children, depth + 1, lines);
+    }
+}
+
+/// Replace the values of `text` that differ between processes anyway with `N`
+pub fn mask_per_process(text: &str) -> String {
+    PER_PROCESS
+        .replace_all(text, "${prefix}${path}N")
+        .into_owned()
+}
+
+/// The comparison page of a compile id, relative to the output directory
+pub fn comparison_page(compile_id: &str) -> String {
+    format!(
+        "rank_comparison/{}.html",
+        compile_id.trim_matches(['[', ']']).replace('/', "_")
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// The edits turning `old` into `new`, by Myers' algorithm
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    edits.extend(myers(a, b).into_iter().map(|edit| match edit {
+        Edit::Equal(i, j) => Edit::Equal(i + prefix, j + prefix),
+        Edit::Delete(i) => Edit::Delete(i + prefix),
+        Edit::Insert(j) => Edit::Insert(j + prefix),
+    }));
+    edits.extend((0..suffix).map(|i| Edit::Equal(old.len() - suffix + i, new.len() - suffix + i)));
+    edits
+}
+
+fn myers(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // The furthest reaching x on each diagonal k in -d..=d, before round d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+    'rounds: for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'rounds;
+            }
+        }
+    }
+    if !found {
+        return (0..a.len())
+            .map(Edit::Delete)
+            .chain((0..b.len()).map(Edit::Insert))
+            .collect();
+    }
+
+    let (mut x, mut y) = (n, m);
+    let mut edits = Vec::new();
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if x == prev_x {
+            edits.push(Edit::Insert(prev_y as usize));
+        } else {
+            edits.push(Edit::Delete(prev_x as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        edits.push(Edit::Equal(x as usize, y as usize));
+    }
+    edits.reverse();
+    edits
+}
+
+/// A unified diff of `old` and `new` as an html table, with the number of deleted and inserted
+/// lines
+fn diff_html(old: &str, new: &str) -> (String, usize, usize) {
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let edits = diff_lines(&old, &new);
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Group changes whose context overlaps into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let (start, end) = (
+            i.saturating_sub(CONTEXT_LINES),
+            (i + CONTEXT_LINES + 1).min(edits.len()),
+        );
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut html = String::from("<table class='diff'>\n");
+    let (mut deleted, mut inserted) = (0, 0);
+    for (start, end) in hunks {
+        let (old_start, new_start) =
+            edits[start..end]
+                .iter()
+                .fold((None, None), |(o, n), edit| match *edit {
+                    Edit::Equal(i, j) => (o.or(Some(i)), n.or(Some(j))),
+                    Edit::Delete(i) => (o.or(Some(i)), n),
+                    Edit::Insert(j) => (o, n.or(Some(j))),
+                });
+        html.push_str(&format!(
+            "<tr class='hunk'><td colspan='3'>@@ -{} +{} @@</td></tr>\n",
+            old_start.map_or(0, |i| i + 1),
+            new_start.map_or(0, |j| j + 1)
+        ));
+        for edit in &edits[start..end] {
+            let (class, old_line, new_line, sign, text) = match *edit {
+                Edit::Equal(i, j) => ("", (i + 1).to_string(), (j + 1).to_string(), ' ', old[i]),
+                Edit::Delete(i) => {
+                    deleted += 1;
+                    ("del", (i + 1).to_string(), String::new(), '-', old[i])
+                }
+                Edit::Insert(j) => {
+                    inserted += 1;
+                    ("ins", String::new(), (j + 1).to_string(), '+', new[j])
+                }
+            };
+            html.push_str(&format!(
+                "<tr class='{class}'><td class='lineno'>{old_line}</td><td class='lineno'>{new_line}</td><td><pre>{sign} {}</pre></td></tr>\n",
+                encode_text(text)
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+    (html, deleted, inserted)
+}
+
+/// Partition ranks by every grouping of the landing page, keeping the lowest rank of each
+/// partition as its representative
+pub fn representatives(
+    groupings: &[&FxHashMap<String, Vec<u32>>],
+    rank_nums: &[u32],
+) -> Vec<(u32, Vec<u32>)> {
+    let mut partitions: BTreeMap<Vec<Option<&str>>, Vec<u32>> = BTreeMap::new();
+    for &rank in rank_nums {
+        let signature = groupings
+            .iter()
+            .map(|groups| {
+                groups
+                    .iter()
+                    .find(|(_, ranks)| ranks.contains(&rank))
+                    .map(|(key, _)| key.as_str())
+            })
+            .collect();
+        partitions.entry(signature).or_default().push(rank);
+    }
+    let mut representatives: Vec<(u32, Vec<u32>)> = partitions
+        .into_values()
+        .map(|mut ranks| {
+            ranks.sort_unstable();
+            (ranks[0], ranks)
+        })
+        .collect();
+    representatives.sort_unstable();
+    representatives
+}
+
+/// A rank's artifacts for one compile id, by kind: the url relative to the output directory,
+/// if the artifact was written, and the text to diff
+type CompileArtifacts = [Option<(Option<String>, String)>; 4];
+
+/// The texts of each kind by compile id from the copy of a rank's log, with the compile ids in
+/// the order the rank logged them
+fn read_log_texts(rank_dir: &Path) -> FxIndexMap<String, [Vec<String>; 4]> {
+    let mut texts: FxIndexMap<String, [Vec<String>; 4]> = FxIndexMap::default();
+    let Ok(entries) = File::open(rank_dir.join("raw.log"))
+        .map_err(anyhow::Error::from)
+        .and_then(|file| LogEntries::new(BufReader::new(file)))
+    else {
+        return texts;
+    };
+    for record in entries {
+        let LogRecord::Entry(entry, _) = record else {
+            continue;
+        };
+        let Some(compile_id) = entry.compile_id.as_deref().and_then(parse_compile_id) else {
+            continue;
+        };
+        let found = texts.entry(compile_id.to_string()).or_default();
+        for (found, kind) in found.iter_mut().zip(ARTIFACT_KINDS) {
+            found.extend(kind.text(&entry));
+        }
+    }
+    texts
+}
+
+/// The artifacts of a rank by compile id, with the compile ids in the order the rank compiled them
+fn read_rank(out_path: &Path, rank: u32) -> Vec<(String, CompileArtifacts)> {
+    let rank_dir = out_path.join(format!("rank_{rank}"));
+    let Some(directory) = fs::read_to_string(rank_dir.join("compile_directory.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(directory) = directory.as_object() else {
+        return Vec::new();
+    };
+    let log_texts = read_log_texts(&rank_dir);
+
+    let mut compiles = Vec::new();
+    for (compile_id, entry) in directory {
+        if compile_id == "unknown" || compile_id.starts_with("unknown_") {
+            continue;
+        }
+        let mut artifacts: Vec<(u64, &str, &str)> = entry
+            .get("artifacts")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| {
+                Some((
+                    a.get("number")?.as_u64()?,
+                    a.get("name")?.as_str()?,
+                    a.get("url")?.as_str()?,
+                ))
+            })
+            .collect();
+        artifacts.sort_unstable();
+
+        let mut texts: CompileArtifacts = Default::default();
+        if let Some(found) = log_texts.get(compile_id) {
+            for ((slot, kind), found) in texts.iter_mut().zip(ARTIFACT_KINDS).zip(found) {
+                if found.is_empty() {
+                    continue;
+                }
+                let url = artifacts
+                    .iter()
+                    .find(|(_, name, _)| kind.matches(name))
+                    .map(|(_, _, url)| format!("rank_{rank}/{url}"));
+                // A compile may have several, e.g. forward and backward output code
+                *slot = Some((url, found.join("\n")));
+            }
+        }
+        let order = log_texts.get_index_of(compile_id).unwrap_or(usize::MAX);
+        compiles.push((order, compile_id.clone(), texts));
+    }
+    compiles.sort_by_key(|(order, ..)| *order);
+    compiles
+        .into_iter()
+        .map(|(_, compile_id, texts)| (compile_id, texts))
+        .collect()
+}
+
+/// Write `rank_comparison.html` and a page per compile id under `rank_comparison/`. Returns
+/// whether they were written, which needs at least two representatives.
+pub fn write_rank_comparison(
+    out_path: &Path,
+    representatives: &[(u32, Vec<u32>)],
+) -> anyhow::Result<bool> {
+    if representatives.len() < 2 {
+        return Ok(false);
+    }
+
+    let mut tt = TinyTemplate::new();
+    tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+    tt.add_template("rank_comparison.html", TEMPLATE_RANK_COMPARISON)?;
+    tt.add_template(
+        "rank_comparison_compile.html",
+        TEMPLATE_RANK_COMPARISON_COMPILE,
+    )?;
+
+    let groups: Vec<RankGroupContext> = representatives
+        .iter()
+        .map(|(rank, ranks)| RankGroupContext {
+            rank: *rank,
+            ranks: ranks
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+        .collect();
+
+    // Compile ids in the order the representatives compiled them
+    let mut order: Vec<String> = Vec::new();
+    let mut by_rank: Vec<FxHashMap<String, CompileArtifacts>> = Vec::new();
+    for (rank, _) in representatives {
+        let mut compiles = FxHashMap::default();
+        for (compile_id, texts) in read_rank(out_path, *rank) {
+            if !order.contains(&compile_id) {
+                order.push(compile_id.clone());
+            }
+            compiles.insert(compile_id, texts);
+        }
+        by_rank.push(compiles);
+    }
+
+    let (baseline, others) = (representatives[0].0, &representatives[1..]);
+    fs::create_dir_all(out_path.join("rank_comparison"))?;
+    let mut rows = Vec::new();
+    for compile_id in order {
+        let artifacts_of = |i: usize| by_rank[i].get(&compile_id);
+        let mut differs = false;
+        let mut statuses = Vec::new();
+        let mut artifacts = Vec::new();
+        for (k, kind) in ARTIFACT_KINDS.iter().enumerate() {
+            let text_of = |i: usize| artifacts_of(i).and_then(|texts| texts[k].as_ref());
+            let old = text_of(0);
+            let mut diffs = Vec::new();
+            for (i, (rank, _)) in others.iter().enumerate() {
+                let new = text_of(i + 1);
+                let (status, diff_html) = match (old, new) {
+                    (None, None) => continue,
+                    (Some((_, old)), Some((_, new))) if old == new => {
+                        ("identical".to_string(), String::new())
+                    }
+                    _ => {
+                        let (html, deleted, inserted) = diff_html(
+                            old.map_or("", |(_, text)| text),
+                            new.map_or("", |(_, text)| text),
+                        );
+                        let status = match (old, new) {
+                            (None, _) => format!("only on rank {rank}"),
+                            (_, None) => format!("missing on rank {rank}"),
+                            _ => format!("-{deleted} +{inserted} line(s)"),
+                        };
+                        (status, html)
+                    }
+                };
+                diffs.push(RankDiffContext {
+                    rank: *rank,
+                    differs: !diff_html.is_empty(),
+                    status,
+                    diff_html,
+                    baseline_url: old
+                        .and_then(|(url, _)| url.as_ref())
+                        .map(|url| format!("../{url}")),
+                    url: new
+                        .and_then(|(url, _)| url.as_ref())
+                        .map(|url| format!("../{url}")),
+                });
+            }
+            let kind_differs = diffs.iter().any(|diff| diff.differs);
+            differs |= kind_differs;
+            statuses.push(if diffs.is_empty() {
+                "-"
+            } else if kind_differs {
+                "differs"
+            } else {
+                "identical"
+            });
+            if !diffs.is_empty() {
+                artifacts.push(RankArtifactComparisonContext {
+                    name: kind.name(),
+                    diffs,
+                });
+            }
+        }
+        let missing_on: Vec<String> = representatives
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| artifacts_of(*i).is_none())
+            .map(|(_, (rank, _))| rank.to_string())
+            .collect();
+        differs |= !missing_on.is_empty();
+
+        let page = comparison_page(&compile_id);
+        fs::write(
+            out_path.join(&page),
+            tt.render(
+                "rank_comparison_compile.html",
+                &RankComparisonCompileContext {
+                    css: CSS,
+                    qps: TEMPLATE_QUERY_PARAM_SCRIPT,
+                    compile_id: compile_id.clone(),
+                    baseline,
+                    groups: &groups,
+                    missing_on: missing_on.join(", "),
+                    artifacts,
+                },
+            )?,
+        )?;
+        rows.push(RankComparisonRowContext {
+            compile_id,
+            url: page,
+            differs,
+            missing_on: missing_on.join(", "),
+            statuses,
+        });
+    }
+
+    fs::write(
+        out_path.join("rank_comparison.html"),
+        tt.render(
+            "rank_comparison.html",
+            &RankComparisonContext {
+                css: CSS,
+                qps: TEMPLATE_QUERY_PARAM_SCRIPT,
+                baseline,
+                groups: &groups,
+                num_differing: rows.iter().filter(|row| row.differs).count(),
+                rows,
+            },
+        )?,
+    )?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &[&str], new: &[&str]) -> Vec<String> {
+        diff_lines(old, new)
+            .into_iter()
+            .filter_map(|edit| match edit {
+                Edit::Equal(i, j) => {
+                    assert_eq!(old[i], new[j]);
+                    Some(old[i].to_string())
+                }
+                Edit::Delete(_) => None,
+                Edit::Insert(j) => Some(new[j].to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        assert_eq!(apply(&old, &new), new);
+        // The shortest edit script of the example in Myers' paper has five edits
+        let edits = diff_lines(&old, &new);
+        assert_eq!(
+            edits
+                .iter()
+                .filter(|e| !matches!(e, Edit::Equal(..)))
+                .count(),
+            5
+        );
+        assert_eq!(apply(&[], &new), new);
+        assert_eq!(apply(&old, &[]), Vec::<String>::new());
+
+        let (html, deleted, inserted) = diff_html(
+            "x\n1\n2\n3\n4\n5\n6\n7\n8\ny",
+            "x\n1\n2\n3\n4\n5\n6\n7\n8\nz",
+        );
+        assert_eq!((deleted, inserted), (1, 1));
+        assert!(html.contains("@@ -7 +7 @@"));
+        assert!(!html.contains(">  5<"));
+    }
+
+    #[test]
+    fn test_representatives() {
+        let compile_ids: FxHashMap<String, Vec<u32>> =
+            [("a".to_string(), vec![0, 1, 2, 3])].into_iter().collect();
+        let cache: FxHashMap<String, Vec<u32>> = [
+            ("hit".to_string(), vec![0, 2]),
+            ("miss".to_string(), vec![3, 1]),
+        ]
+        .into_iter()
+        .collect();
+        let tensor_meta: FxHashMap<String, Vec<u32>> =
+            [("x".to_string(), vec![0, 1, 2])].into_iter().collect();
+        assert_eq!(
+            representatives(&[&compile_ids, &cache, &tensor_meta], &[0, 1, 2, 3]),
+            vec![(0, vec![0, 2]), (1, vec![1]), (3, vec![3])]
+        );
+    }
+
+    #[test]
+    fn test_payload_text() {
+        let entry =
+            |entry_type: &str, metadata: serde_json::Value, payload: &str| IntermediateEntry {
+                entry_type: entry_type.to_string(),
+                compile_id: Some("0_0_0".to_string()),
+                rank: Some(1),
+                timestamp: String::new(),
+                thread: 0,
+                pathname: String::new(),
+                lineno: 0,
+                metadata,
+                payload: Some(payload.to_string()),
+            };
+        let guards = entry(
+            "dynamo_guards",
+            serde_json::Value::Null,
+            r#"[{"code": "ID_MATCH: ___check_obj_id(L['m'], 1404)"}, {"code": "x.to('cuda:3')"}]"#,
+        );
+        assert_eq!(
+            ArtifactKind::Guards.text(&guards).as_deref(),
+            Some("ID_MATCH: ___check_obj_id(L['m'], N)\nx.to('cuda:N')")
+        );
+        assert_eq!(ArtifactKind::DynamoGraph.text(&guards), None);
+
+        let cpp_guards = entry(
+            "dynamo_cpp_guards_str",
+            serde_json::Value::Null,
+            "\nTREE_GUARD_MANAGER:\n+- RootGuardManager\n| +- TENSOR_MATCH: check_tensor(L['x'])  # f.py:1 in f\n\nGuard latency = 15.88 us",
+        );
+        assert_eq!(
+            ArtifactKind::Guards.text(&cpp_guards).as_deref(),
+            Some("RootGuardManager\n  TENSOR_MATCH: check_tensor(L['x'])")
+        );
+
+        let tensor_meta = |name: &str| {
+            entry(
+                "artifact",
+                serde_json::json!({"name": name, "encoding": "json"}),
+                r#"[{"name": "buf0", "device": "cuda:2"}]"#,
+            )
+        };
+        assert_eq!(
+            ArtifactKind::TensorMeta
+                .text(&tensor_meta("inductor_runtime_and_tensor_meta"))
+                .as_deref(),
+            Some("[\n  {\n    \"device\": \"cuda:N\",\n    \"name\": \"buf0\"\n  }\n]")
+        );
+        assert_eq!(
+            ArtifactKind::TensorMeta.text(&tensor_meta("fx_graph_cache_hit")),
+            None
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! Recompilation explainer.
+//!
+//! When a frame recompiles, working out why means reading its `recompile_reasons` artifacts and
+//! diffing guard payloads by eye. This collects everything about each compilation of a frame and
+//! renders one page per recompiled frame, walking through the compilations in order.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use fxhash::{FxHashMap, FxHashSet};
+use serde_json::Value;
+
+use crate::graph_breaks::normalize_reason;
+use crate::guards::parse_cpp_guard_tree;
+use crate::parsers::format_stack;
+use crate::types::*;
+
+/// Dynamo's default `torch._dynamo.config.recompile_limit`, the number of compilations of a
+/// frame after which it stops recompiling and falls back to eager
+pub const RECOMPILE_LIMIT: u64 = 8;
+/// Dynamo's default `torch._dynamo.config.accumulated_recompile_limit`
+pub const ACCUMULATED_RECOMPILE_LIMIT: u64 = 256;
+
+// The parts of a describe_tensor that are worth comparing; the rest are ids that change on
+// every compilation
+const TENSOR_FIELDS: &[&str] = &[
+    "dtype",
+    "device",
+    "ndim",
+    "size",
+    "stride",
+    "storage_offset",
+    "layout",
+    "dynamo_dynamic_indices",
+    "requires_grad",
+    "is_leaf",
+    "is_view",
+    "is_parameter",
+    "is_inference",
+    "is_nested",
+    "is_sparse",
+    "is_functional",
+];
+
+#[derive(Default)]
+struct Compilation {
+    // The latest attempt seen
+    compile_id: Option<CompileId>,
+    // Links to the metrics page, once compilation_metrics has been seen
+    compile_id_html: Option<String>,
+    recompile_reasons: Vec<String>,
+    guards: Option<Vec<String>>,
+    cpp_guards: Option<Vec<String>>,
+    // (describer_id, id) -> compared fields
+    tensors: FxHashMap<(u64, u64), Vec<(&'static str, Value)>>,
+    // Input source -> (describer_id, id)
+    sources: Vec<(String, (u64, u64))>,
+    cache_size: Option<u64>,
+    accumulated_cache_size: Option<u64>,
+}
+
+impl Compilation {
+    fn guards(&self) -> Option<&Vec<String>> {
+        self.guards.as_ref().or(self.cpp_guards.as_ref())
+    }
+
+    fn inputs(&self) -> Vec<(&str, &[(&'static str, Value)])> {
+        self.sources
+            .iter()
+            .filter_map(|(source, key)| Some((source.as_str(), self.tensors.get(key)?.as_slice())))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Frame {
+    stack: Option<StackSummary>,
+    // By frame_compile_id
+    compilations: BTreeMap<u32, Compilation>,
+}
+
+/// Everything about each compilation of each frame, keyed by (compiled_autograd_id, frame_id)
+#[derive(Default)]
+pub struct RecompileIndex {
+    frames: BTreeMap<(Option<u32>, u32), Frame>,
+}
+
+pub fn frame_key(compile_id: &Option<CompileId>) -> Option<((Option<u32>, u32), u32)> {
+    let c = compile_id.as_ref()?;
+    Some(((c.compiled_autograd_id, c.frame_id?), c.frame_compile_id?))
+}
+
+/// The leaf guards of a `dynamo_cpp_guards_str` tree, without tree drawing or source comments
+fn cpp_guard_lines(payload: &str) -> Vec<String> {
+    parse_cpp_guard_tree(payload)
+        .leaves()
+        .into_iter()
+        .map(|leaf| leaf.text.clone())
+        .collect()
+}
+
+fn dedup(guards: Vec<String>) -> Vec<String> {
+    let mut seen = FxHashSet::default();
+    guards
+        .into_iter()
+        .filter(|g| seen.insert(g.clone()))
+        .collect()
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "None".to_string(),
+        v => v.to_string(),
+    }
+}
+
+/// Guards removed, added, and changed (a removed and an added guard that only differ in numbers)
+/// going from `before` to `after`
+fn diff_guards(before: &[String], after: &[String]) -> GuardDiffContext {
+    let before_set: FxHashSet<&String> = before.iter().collect();
+    let after_set: FxHashSet<&String> = after.iter().collect();
+    let removed: Vec<&String> = before.iter().filter(|g| !after_set.contains(g)).collect();
+    // Removed guards by normalized form, each normalized once, in order of removal
+    let mut by_normalized: FxHashMap<String, VecDeque<usize>> = FxHashMap::default();
+    for (i, guard) in removed.iter().enumerate() {
+        by_normalized
+            .entry(normalize_reason(guard))
+            .or_default()
+            .push_back(i);
+    }
+    let mut matched = vec![false; removed.len()];
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for guard in after.iter().filter(|g| !before_set.contains(g)) {
+        match by_normalized
+            .get_mut(&normalize_reason(guard))
+            .and_then(|indices| indices.pop_front())
+        {
+            Some(i) => {
+                matched[i] = true;
+                changed.push(GuardChangeContext {
+                    before: removed[i].clone(),
+                    after: guard.clone(),
+                });
+            }
+            None => added.push(guard.clone()),
+        }
+    }
+    GuardDiffContext {
+        unchanged: after.len() - added.len() - changed.len(),
+        removed: removed
+            .into_iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(guard, _)| guard.clone())
+            .collect(),
+        added,
+        changed,
+    }
+}
+
+fn diff_inputs(before: &Compilation, after: &Compilation) -> Vec<TensorChangeContext> {
+    let before = before.inputs();
+    let after = after.inputs();
+    let mut changes = Vec::new();
+    for (source, fields) in &after {
+        match before.iter().find(|(s, _)| s == source) {
+            Some((_, old_fields)) => {
+                for (field, value) in fields.iter() {
+                    let old = old_fields
+                        .iter()
+                        .find(|(f, _)| f == field)
+                        .map(|(_, v)| v)
+                        .unwrap_or(&Value::Null);
+                    if old != value {
+                        changes.push(TensorChangeContext {
+                            source: source.to_string(),
+                            field: field.to_string(),
+                            before: format_value(old),
+                            after: format_value(value),
+                        });
+                    }
+                }
+            }
+            None => changes.push(TensorChangeContext {
+                source: source.to_string(),
+                field: "input".to_string(),
+                before: "(absent)".to_string(),
+                after: "(present)".to_string(),
+            }),
+        }
+    }
+    for (source, _) in before
+        .iter()
+        .filter(|(s, _)| !after.iter().any(|(a, _)| a == s))
+    {
+        changes.push(TensorChangeContext {
+            source: source.to_string(),
+            field: "input".to_string(),
+            before: "(present)".to_string(),
+            after: "(absent)".to_string(),
+        });
+    }
+    changes
+}
+
+/// A warning once `size` gets within a quarter of `limit`
+fn limit_warning(name: &str, size: Option<u64>, limit: u64, config: &str) -> Option<String> {
+    let size = size?;
+    if size >= limit {
+        Some(format!(
+            "{name} {size} has reached the default {config} ({limit}); unless the limit was raised, Dynamo stops recompiling and runs this frame in eager"
+        ))
+    } else if size * 4 >= limit * 3 {
+        Some(format!(
+            "{name} {size} is approaching the default {config} ({limit})"
+        ))
+    } else {
+        None
+    }
+}
+
+impl RecompileIndex {
+    /// Record the parts of an envelope that describe its compilation: recompile reasons, guards
+    /// and input tensors
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str) {
+        let Some((frame, frame_compile_id)) = frame_key(&e.compile_id) else {
+            return;
+        };
+        let is_recompile_reasons = e
+            .artifact
+            .as_ref()
+            .is_some_and(|a| a.name == "recompile_reasons");
+        if !is_recompile_reasons
+            && e.dynamo_guards.is_none()
+            && e.dynamo_cpp_guards_str.is_none()
+            && e.describe_tensor.is_none()
+            && e.describe_source.is_none()
+        {
+            return;
+        }
+        let c = self.compilation(frame, frame_compile_id, &e.compile_id);
+
+        if is_recompile_reasons {
+            if let Ok(reasons) = serde_json::from_str::<Vec<String>>(payload) {
+                c.recompile_reasons.extend(reasons);
+            }
+        }
+        if e.dynamo_guards.is_some() {
+            if let Ok(guards) = serde_json::from_str::<Vec<DynamoGuard>>(payload) {
+                c.guards = Some(dedup(guards.into_iter().map(|g| g.code).collect()));
+            }
+        }
+        if e.dynamo_cpp_guards_str.is_some() {
+            c.cpp_guards = Some(dedup(cpp_guard_lines(payload)));
+        }
+        if let Some(tensor) = &e.describe_tensor {
+            if let Ok(Value::Object(desc)) = serde_json::to_value(tensor) {
+                let fields = TENSOR_FIELDS
+                    .iter()
+                    .filter_map(|&f| Some((f, desc.get(f)?.clone())))
+                    .collect();
+                c.tensors.insert((tensor.describer_id, tensor.id), fields);
+            }
+        }
+        if let Some(source) = &e.describe_source {
+            c.sources
+                .push((source.source.clone(), (source.describer_id, source.id)));
+        }
+    }
+
+    /// Record where the frame is, for marking it in the stack trie
+    pub fn add_stack(&mut self, compile_id: &Option<CompileId>, stack: &StackSummary) {
+        if let Some((frame, _)) = frame_key(compile_id) {
+            let frame = self.frames.entry(frame).or_default();
+            if frame.stack.is_none() {
+                frame.stack = Some(stack.clone());
+            }
+        }
+    }
+
+    /// Record the cache sizes of a compilation. `compile_id_html` links to its metrics page.
+    pub fn add_metrics(
+        &mut self,
+        compile_id: &Option<CompileId>,
+        compile_id_html: &str,
+        m: &CompilationMetricsMetadata,
+    ) {
+        if let Some((frame, frame_compile_id)) = frame_key(compile_id) {
+            let c = self.compilation(frame, frame_compile_id, compile_id);
+            c.compile_id_html = Some(compile_id_html.to_string());
+            c.cache_size = m.cache_size;
+            c.accumulated_cache_size = m.accumulated_cache_size;
+        }
+    }
+
+    // A later attempt (after a restart) replaces the guards and inputs of earlier ones
+    fn compilation(
+        &mut self,
+        frame: (Option<u32>, u32),
+        frame_compile_id: u32,
+        compile_id: &Option<CompileId>,
+    ) -> &mut Compilation {
+        let c = self
+            .frames
+            .entry(frame)
+            .or_default()
+            .compilations
+            .entry(frame_compile_id)
+            .or_default();
+        let attempt = |cid: &Option<CompileId>| cid.as_ref().and_then(|c| c.attempt).unwrap_or(0);
+        if c.compile_id.is_none() || attempt(compile_id) > attempt(&c.compile_id) {
+            if c.compile_id.is_some() {
+                c.guards = None;
+                c.cpp_guards = None;
+                c.tensors.clear();
+                c.sources.clear();
+            }
+            c.compile_id = compile_id.clone();
+        }
+        c
+    }
+
+    /// Name of the recompiles page for the frame of `compile_id`, if that frame recompiled
+    pub fn page_name(&self, compile_id: &Option<CompileId>) -> Option<String> {
+        let (frame, _) = frame_key(compile_id)?;
+        self.frames
+            .get(&frame)
+            .filter(|f| f.compilations.len() > 1)
+            .map(|_| page_name(frame))
+    }
+
+    /// Build a page context for each frame that recompiled, and mark the frames in `stack_trie`
+    pub fn finish(
+        &self,
+        stack_trie: &mut StackTrieNode,
+        css: &'static str,
+        qps: &'static str,
+    ) -> Vec<(String, RecompilesContext)> {
+        let mut pages = Vec::new();
+        for (&frame, f) in self.frames.iter().filter(|(_, f)| f.compilations.len() > 1) {
+            let filename = page_name(frame);
+            let num_compilations = f.compilations.len();
+            if let Some(stack) = &f.stack {
+                stack_trie.mark(
+                    stack,
+                    format!(
+                        "<a href='{filename}' class='recompile-marker' title='{num_compilations} compilations of this frame'>↻{num_compilations}</a>"
+                    ),
+                );
+            }
+
+            let mut compilations = Vec::new();
+            let mut prev: Option<&Compilation> = None;
+            for (frame_compile_id, c) in &f.compilations {
+                let compile_id_html = c.compile_id_html.clone().unwrap_or_else(|| {
+                    c.compile_id
+                        .as_ref()
+                        .map_or(format!("[{}/{}]", frame.1, frame_compile_id), |cid| {
+                            cid.to_string()
+                        })
+                });
+                let guard_diff = match (prev.and_then(|p| p.guards()), c.guards()) {
+                    (Some(before), Some(after)) => Some(diff_guards(before, after)),
+                    _ => None,
+                };
+                let warnings: Vec<String> = [
+                    limit_warning(
+                        "cache_size",
+                        c.cache_size,
+                        RECOMPILE_LIMIT,
+                        "recompile_limit",
+                    ),
+                    limit_warning(
+                        "accumulated_cache_size",
+                        c.accumulated_cache_size,
+                        ACCUMULATED_RECOMPILE_LIMIT,
+                        "accumulated_recompile_limit",
+                    ),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                compilations.push(RecompileContext {
+                    compile_id: compile_id_html,
+                    reasons: c.recompile_reasons.clone(),
+                    num_guards: c.guards().map_or(0, Vec::len),
+                    has_guard_diff: guard_diff.is_some(),
+                    guard_diff: guard_diff.unwrap_or_default(),
+                    tensor_changes: prev.map_or(Vec::new(), |p| diff_inputs(p, c)),
+                    cache_size: c
+                        .cache_size
+                        .map_or("?".to_string(), |s| format!("{s} / {RECOMPILE_LIMIT}")),
+                    accumulated_cache_size: c.accumulated_cache_size.map_or("?".to_string(), |s| {
+                        format!("{s} / {ACCUMULATED_RECOMPILE_LIMIT}")
+                    }),
+                    warnings,
+                });
+                prev = Some(c);
+            }
+
+            pages.push((
+                filename,
+                RecompilesContext {
+                    css,
+                    qps,
+                    frame: match frame.0 {
+                        Some(caid) => format!("!{}/{}", caid, frame.1),
+                        None => frame.1.to_string(),
+                    },
+                    stack_html: f
+                        .stack
+                        .as_ref()
+                        .map_or(String::new(), |s| format_stack(s, "Stack", true)),
+                    num_compilations,
+                    compilations,
+                },
+            ));
+        }
+        pages
+    }
+}
+
+fn page_name((compiled_autograd_id, frame_id): (Option<u32>, u32)) -> String {
+    format!(
+        "recompiles_{}_{}.html",
+        compiled_autograd_id.map_or("-".to_string(), |v| v.to_string()),
+        frame_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpp_guard_lines() {
+        let payload = "\nTREE_GUARD_MANAGER:\n+- RootGuardManager\n| +- GLOBAL_STATE: ___check_global_state()\n| +- GuardManager: source=L['x'], accessed_by=FrameLocalsGuardAccessor(key='x', framelocals_idx=0)\n| | +- TENSOR_MATCH: check_tensor(L['x'], size=[2, 2])  # x.sum() at model.py:3\n\nGuard latency = 15.88 us";
+        assert_eq!(
+            cpp_guard_lines(payload),
+            vec![
+                "GLOBAL_STATE: ___check_global_state()",
+                "TENSOR_MATCH: check_tensor(L['x'], size=[2, 2])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_guards() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let diff = diff_guards(
+            &strings(&["a", "L['x'].size()[0] == 2", "gone"]),
+            &strings(&["a", "L['x'].size()[0] == 3", "new"]),
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.removed, vec!["gone"]);
+        assert_eq!(diff.added, vec!["new"]);
+        assert_eq!(diff.changed[0].before, "L['x'].size()[0] == 2");
+        assert_eq!(diff.changed[0].after, "L['x'].size()[0] == 3");
+
+        // Guards that normalize alike are paired in order, and the rest stay removed
+        let diff = diff_guards(
+            &strings(&["x == 1", "y", "x == 2", "x == 3"]),
+            &strings(&["y", "x == 4", "x == 5"]),
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.removed, vec!["x == 3"]);
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.changed
+                .iter()
+                .map(|c| (c.before.as_str(), c.after.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("x == 1", "x == 4"), ("x == 2", "x == 5")]
+        );
+    }
+
+    #[test]
+    fn test_limit_warning() {
+        assert_eq!(
+            limit_warning("cache_size", Some(5), 8, "recompile_limit"),
+            None
+        );
+        assert!(limit_warning("cache_size", Some(6), 8, "recompile_limit")
+            .unwrap()
+            .contains("approaching"));
+        assert!(limit_warning("cache_size", Some(8), 8, "recompile_limit")
+            .unwrap()
+            .contains("has reached"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Full-text search index for a rendered report.
+//!
+//! The index is built while parsing and written to `search_index.js` as a call to
+//! `tlparseSearchIndex(...)`, so index.html can load it with a script tag (fetch() does not
+//! work from file:// URLs). The JSON payload is deliberately terse:
+//!
+//! ```json
+//! {"cids": ["[0/0]"], "docs": [[0, "-_0_0_0/dynamo_output_graph_1.txt", "dynamo_output_graph"]],
+//!  "terms": {"add": [0, 12, 0, 15]}}
+//! ```
+//!
+//! `docs` entries are `[compile id index, url, name]` and each term maps to a flat list of
+//! `(doc, line)` pairs. Lines are 1-based and shown with the hit; in highlighted HTML pages they
+//! link to the `#L<line>` anchor, while text and JSON files, which have no anchors, are linked
+//! as a whole. Line 0 means the hit has no meaningful line.
+
+use fxhash::{FxHashMap, FxHashSet};
+use serde_json::json;
+
+/// Tokens shorter than this are too common to be useful and are not indexed
+const MIN_TOKEN_LEN: usize = 2;
+/// Longer tokens are almost always hashes or base64 blobs; keep the index small
+const MAX_TOKEN_LEN: usize = 64;
+
+#[derive(Default)]
+pub struct SearchIndex {
+    compile_ids: Vec<String>,
+    compile_id_lookup: FxHashMap<String, u32>,
+    docs: Vec<(u32, String, String)>,
+    terms: FxHashMap<String, Vec<u32>>,
+}
+
+/// Split text into lowercased identifier-like tokens. `torch.ops.aten.add` yields `torch`,
+/// `ops`, `aten` and `add`; underscores are kept so `buf0_size` stays one token.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| (MIN_TOKEN_LEN..=MAX_TOKEN_LEN).contains(&t.chars().count()))
+        .map(|t| t.to_lowercase())
+}
+
+impl SearchIndex {
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    fn compile_id_index(&mut self, compile_id: &str) -> u32 {
+        if let Some(&i) = self.compile_id_lookup.get(compile_id) {
+            return i;
+        }
+        let i = self.compile_ids.len() as u32;
+        self.compile_ids.push(compile_id.to_string());
+        self.compile_id_lookup.insert(compile_id.to_string(), i);
+        i
+    }
+
+    fn add_doc(&mut self, compile_id: &str, url: &str, name: &str) -> u32 {
+        let cid = self.compile_id_index(compile_id);
+        self.docs.push((cid, url.to_string(), name.to_string()));
+        (self.docs.len() - 1) as u32
+    }
+
+    fn add_line(&mut self, doc: u32, lineno: u32, line: &str, seen: &mut FxHashSet<String>) {
+        seen.clear();
+        for token in tokenize(line) {
+            if seen.insert(token.clone()) {
+                let postings = self.terms.entry(token).or_default();
+                postings.push(doc);
+                postings.push(lineno);
+            }
+        }
+    }
+
+    /// Index `text` line by line as the contents of `url`
+    pub fn add_document(&mut self, compile_id: &str, url: &str, name: &str, text: &str) {
+        let doc = self.add_doc(compile_id, url, name);
+        let mut seen = FxHashSet::default();
+        for (i, line) in text.lines().enumerate() {
+            self.add_line(doc, i as u32 + 1, line, &mut seen);
+        }
+    }
+
+    /// Index `text` as a whole, for documents whose line numbers don't correspond to `text`
+    /// (e.g. an HTML page rendered from a JSON payload)
+    pub fn add_unanchored_document(&mut self, compile_id: &str, url: &str, name: &str, text: &str) {
+        let doc = self.add_doc(compile_id, url, name);
+        let mut seen = FxHashSet::default();
+        self.add_line(doc, 0, text, &mut seen);
+    }
+
+    /// Render the index as the contents of `search_index.js`
+    pub fn to_javascript(&self) -> anyhow::Result<String> {
+        // Sort terms so that the output is deterministic
+        let mut terms: Vec<(&String, &Vec<u32>)> = self.terms.iter().collect();
+        terms.sort_by(|a, b| a.0.cmp(b.0));
+        let terms: serde_json::Map<String, serde_json::Value> = terms
+            .into_iter()
+            .map(|(t, p)| (t.clone(), json!(p)))
+            .collect();
+        let docs: Vec<serde_json::Value> = self
+            .docs
+            .iter()
+            .map(|(cid, url, name)| json!([cid, url, name]))
+            .collect();
+        let index = json!({
+            "cids": self.compile_ids,
+            "docs": docs,
+            "terms": terms,
+        });
+        Ok(format!(
+            "tlparseSearchIndex({});\n",
+            serde_json::to_string(&index)?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<String> = tokenize("torch.ops.aten.add.Tensor(buf0_size, x) # a").collect();
+        assert_eq!(
+            tokens,
+            vec!["torch", "ops", "aten", "add", "tensor", "buf0_size"]
+        );
+    }
+
+    #[test]
+    fn test_postings() {
+        let mut index = SearchIndex::default();
+        index.add_document("[0/0]", "a.txt", "a", "add add\nmul\nadd");
+        index.add_unanchored_document("[0/0]", "b.html", "b", "x\nadd");
+        index.add_document("[1/0]", "c.txt", "c", "mul");
+
+        assert_eq!(index.compile_ids, vec!["[0/0]", "[1/0]"]);
+        assert_eq!(index.docs[2].0, 1);
+        // one posting per (doc, line), even when the token repeats on a line
+        assert_eq!(index.terms["add"], vec![0, 1, 0, 3, 1, 0]);
+        assert_eq!(index.terms["mul"], vec![0, 2, 2, 1]);
+
+        let js = index.to_javascript().unwrap();
+        assert!(js.starts_with("tlparseSearchIndex({"));
+        assert!(js.contains(r#""docs":[[0,"a.txt","a"],[0,"b.html","b"],[1,"c.txt","c"]]"#));
+    }
+}
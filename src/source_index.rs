@@ -0,0 +1,506 @@
+//! User source index.
+//!
+//! Model authors think in terms of their own code, not compile ids. This indexes the run by user
+//! source location instead: for each `file:line`, the compile ids and FX graph nodes traced from
+//! it, the kernels Inductor generated from it, and the graph breaks and guards attributed to it.
+//!
+//! Graph nodes come from the `# File: path:line in fn, code: ...` comments `print_readable`
+//! writes above them, which name the innermost frame only. Kernels come from the stacks of
+//! `inductor_provenance_tracking_kernel_stack_traces`, and are listed under every frame of them.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use html_escape::encode_text;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::graph_breaks::GraphBreakLeaderboard;
+use crate::graph_stats::graph_kind;
+use crate::highlight::{is_graph_or_code_artifact, FILE_COMMENT};
+use crate::provenance::node_name;
+use crate::types::*;
+
+// A frame of a Python traceback, followed by a line with its code
+static TRACEBACK_FRAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*File "(?<file>[^"]+)", line (?<line>\d+)(?:, in (?<function>.+))?$"#).unwrap()
+});
+
+#[derive(Default, Clone)]
+struct Location {
+    function: String,
+    code: String,
+    compile_ids: Vec<String>,
+    // (compile id, graph kind, graph file) -> node names
+    nodes: FxIndexMap<(String, &'static str, String), Vec<String>>,
+    kernels: Vec<(Option<CompileId>, String)>,
+    // (compile id link, kind, reason)
+    graph_breaks: Vec<(String, String, String)>,
+    // (compile id, guard code)
+    guards: Vec<(String, String)>,
+}
+
+impl Location {
+    fn add_compile_id(&mut self, compile_id: &str) {
+        if !self.compile_ids.iter().any(|c| c == compile_id) {
+            self.compile_ids.push(compile_id.to_string());
+        }
+    }
+
+    fn merge(&mut self, other: &Location) {
+        if self.function.is_empty() {
+            self.function = other.function.clone();
+        }
+        if self.code.is_empty() {
+            self.code = other.code.clone();
+        }
+        for compile_id in &other.compile_ids {
+            self.add_compile_id(compile_id);
+        }
+        for (key, nodes) in &other.nodes {
+            self.nodes
+                .entry(key.clone())
+                .or_default()
+                .extend_from_slice(nodes);
+        }
+        self.kernels.extend_from_slice(&other.kernels);
+        self.graph_breaks.extend_from_slice(&other.graph_breaks);
+        self.guards.extend_from_slice(&other.guards);
+    }
+}
+
+/// The anchor of a line of user code in the user source index, which highlighted graphs and
+/// code link their `# File:` frames to
+pub fn source_anchor(file: &str, line: u32) -> String {
+    format!(
+        "src-{:016x}-L{}",
+        fxhash::hash64(simplify_filename(file)),
+        line
+    )
+}
+
+fn compile_id_string(compile_id: &Option<CompileId>) -> String {
+    compile_id
+        .as_ref()
+        .map_or("(unknown)".to_string(), |c| c.to_string())
+}
+
+#[derive(Default)]
+pub struct SourceIndex {
+    // simplified filename -> line -> location
+    files: BTreeMap<String, BTreeMap<u32, Location>>,
+}
+
+impl SourceIndex {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn location(&mut self, file: &str, line: u32, function: &str, code: &str) -> &mut Location {
+        let location = self
+            .files
+            .entry(simplify_filename(file).to_string())
+            .or_default()
+            .entry(line)
+            .or_default();
+        if location.function.is_empty() {
+            location.function = function.to_string();
+        }
+        if location.code.is_empty() {
+            location.code = code.trim().to_string();
+        }
+        location
+    }
+
+    /// Record the nodes of an FX graph envelope, whose payload was written to `graph_file`, the
+    /// frames of other graphs and code, and the kernels of
+    /// `inductor_provenance_tracking_kernel_stack_traces`
+    pub fn add_envelope(&mut self, e: &Envelope, payload: &str, graph_file: Option<&Path>) {
+        let compile_id = compile_id_string(&e.compile_id);
+        let artifact = e.artifact.as_ref();
+        let is_code = e.inductor_output_code.is_some()
+            || artifact
+                .is_some_and(|a| a.encoding == "string" && is_graph_or_code_artifact(&a.name));
+        if is_code && graph_kind(e).is_none() {
+            // Their `# File:` frames link here from the highlighted code
+            for m in payload
+                .lines()
+                .filter_map(|line| FILE_COMMENT.captures(line))
+            {
+                if let Ok(lineno) = m["line"].parse() {
+                    let field = |name: &str| m.name(name).map_or("", |f| f.as_str());
+                    self.location(&m["path"], lineno, field("function"), field("code"))
+                        .add_compile_id(&compile_id);
+                }
+            }
+            return;
+        }
+        if let Some(kind) = graph_kind(e) {
+            let graph_file = graph_file.map_or(String::new(), |p| p.to_string_lossy().to_string());
+            let mut current: Option<(String, u32)> = None;
+            for line in payload.lines() {
+                if let Some(m) = FILE_COMMENT.captures(line) {
+                    let file = &m["path"];
+                    let Ok(lineno) = m["line"].parse() else {
+                        current = None;
+                        continue;
+                    };
+                    let field = |name: &str| m.name(name).map_or("", |f| f.as_str());
+                    self.location(file, lineno, field("function"), field("code"))
+                        .add_compile_id(&compile_id);
+                    current = Some((file.to_string(), lineno));
+                } else if line.trim_start().starts_with('#') {
+                    // e.g. "# No stacktrace found for following nodes"
+                    current = None;
+                } else if let (Some((file, lineno)), Some(node)) = (&current, node_name(line)) {
+                    self.location(file, *lineno, "", "")
+                        .nodes
+                        .entry((compile_id.clone(), kind, graph_file.clone()))
+                        .or_default()
+                        .push(node.to_string());
+                }
+            }
+            return;
+        }
+
+        if artifact.map(|a| a.name.as_str())
+            != Some("inductor_provenance_tracking_kernel_stack_traces")
+        {
+            return;
+        }
+        let Ok(Value::Object(stacks)) = serde_json::from_str::<Value>(payload) else {
+            return;
+        };
+        for (kernel, stacks) in stacks {
+            let mut frames: Vec<(String, u32, String, String)> = Vec::new();
+            for stack in stacks
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                // Some logs have the newlines of the stacks escaped
+                let stack = stack.replace("\\n", "\n");
+                let mut lines = stack.lines().peekable();
+                while let Some(line) = lines.next() {
+                    let Some(m) = TRACEBACK_FRAME.captures(line) else {
+                        continue;
+                    };
+                    let Ok(lineno) = m["line"].parse() else {
+                        continue;
+                    };
+                    let code = lines
+                        .next_if(|l| !TRACEBACK_FRAME.is_match(l))
+                        .unwrap_or("");
+                    let frame = (
+                        m["file"].to_string(),
+                        lineno,
+                        m.name("function").map_or("", |f| f.as_str()).to_string(),
+                        code.to_string(),
+                    );
+                    if !frames.iter().any(|f| f.0 == frame.0 && f.1 == frame.1) {
+                        frames.push(frame);
+                    }
+                }
+            }
+            for (file, lineno, function, code) in frames {
+                let location = self.location(&file, lineno, &function, &code);
+                location.add_compile_id(&compile_id);
+                location
+                    .kernels
+                    .push((e.compile_id.clone(), kernel.clone()));
+            }
+        }
+    }
+
+    /// Record the guards of a `dynamo_guards` envelope under the innermost frame of their user
+    /// stacks
+    pub fn add_guards(&mut self, compile_id: &Option<CompileId>, guards: &[DynamoGuard]) {
+        let compile_id = compile_id_string(compile_id);
+        for guard in guards {
+            let Some(frame) = guard.user_stack.as_ref().and_then(|s| s.last()) else {
+                continue;
+            };
+            let filename = frame
+                .uninterned_filename
+                .clone()
+                .unwrap_or_else(|| unintern_str(frame.filename));
+            let Ok(lineno) = u32::try_from(frame.line) else {
+                continue;
+            };
+            let location = self.location(
+                &filename,
+                lineno,
+                &frame.name,
+                frame.loc.as_deref().unwrap_or(""),
+            );
+            location.add_compile_id(&compile_id);
+            location
+                .guards
+                .push((compile_id.clone(), guard.code.clone()));
+        }
+    }
+
+    /// Record the graph breaks of the leaderboard that have a user source location
+    pub fn add_graph_breaks(&mut self, graph_breaks: &GraphBreakLeaderboard) {
+        for (location, compile_id, compile_id_html, kind, reason) in graph_breaks.located() {
+            let Some((file, lineno)) = location
+                .rsplit_once(':')
+                .and_then(|(file, line)| Some((file, line.parse().ok()?)))
+            else {
+                continue;
+            };
+            let location = self.location(file, lineno, "", "");
+            location.add_compile_id(compile_id);
+            location.graph_breaks.push((
+                compile_id_html.to_string(),
+                kind.to_string(),
+                reason.to_string(),
+            ));
+        }
+    }
+
+    // Some sources name files relative to the repository they are in, others absolutely; a file
+    // is merged into the only other file whose path ends with it. Returns the lines of each file
+    // and the files merged into it.
+    fn merged_files(&self) -> BTreeMap<&str, (BTreeMap<u32, Location>, Vec<&str>)> {
+        let mut merged: BTreeMap<&str, (BTreeMap<u32, Location>, Vec<&str>)> = BTreeMap::new();
+        for (file, lines) in &self.files {
+            let suffix = format!("/{}", file);
+            let mut longer = self.files.keys().filter(|f| f.ends_with(&suffix));
+            let target = match (longer.next(), longer.next()) {
+                (Some(f), None) => f,
+                _ => file,
+            };
+            let (target_lines, aliases) = merged.entry(target.as_str()).or_default();
+            if target != file {
+                aliases.push(file);
+            }
+            for (line, location) in lines {
+                target_lines
+                    .entry(*line)
+                    .and_modify(|l| l.merge(location))
+                    .or_insert_with(|| location.clone());
+            }
+        }
+        merged
+    }
+
+    pub fn finish(
+        &self,
+        kernels_page: impl Fn(&Option<CompileId>) -> Option<String>,
+        css: &'static str,
+        qps: &'static str,
+    ) -> SourceIndexContext {
+        let files: Vec<SourceFileContext> = self
+            .merged_files()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (path, (lines, aliases)))| SourceFileContext {
+                anchor: format!("file-{}", i),
+                path: path.to_string(),
+                lines: lines
+                    .into_iter()
+                    .map(|(line, l)| SourceLineContext {
+                        anchor: source_anchor(path, line),
+                        alias_anchors: aliases
+                            .iter()
+                            .map(|alias| source_anchor(alias, line))
+                            .collect(),
+                        line,
+                        function: l.function,
+                        code: l.code,
+                        compile_ids: l.compile_ids.join(" "),
+                        nodes: l
+                            .nodes
+                            .into_iter()
+                            .map(|((compile_id, graph, page), nodes)| SourceNodesContext {
+                                compile_id,
+                                graph,
+                                page,
+                                nodes: nodes.join(", "),
+                            })
+                            .collect(),
+                        kernels: l
+                            .kernels
+                            .iter()
+                            .map(|(compile_id, name)| SourceKernelContext {
+                                compile_id: compile_id_string(compile_id),
+                                name: name.clone(),
+                                page: kernels_page(compile_id).unwrap_or_default(),
+                            })
+                            .collect(),
+                        graph_breaks: l
+                            .graph_breaks
+                            .into_iter()
+                            .map(|(compile_id_html, kind, reason)| SourceGraphBreakContext {
+                                compile_id_html,
+                                kind,
+                                reason,
+                            })
+                            .collect(),
+                        num_guards: l.guards.len(),
+                        guards: l
+                            .guards
+                            .into_iter()
+                            .map(|(compile_id, code)| SourceGuardContext { compile_id, code })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        SourceIndexContext {
+            css,
+            qps,
+            num_files: files.len(),
+            num_lines: files.iter().map(|f| f.lines.len()).sum(),
+            tree_html: file_tree_html(&files),
+            files,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Directory<'a> {
+    directories: BTreeMap<&'a str, Directory<'a>>,
+    // (name, file)
+    files: Vec<(&'a str, &'a SourceFileContext)>,
+}
+
+/// Render the files as a tree of collapsible directories, linking to their sections of the page
+fn file_tree_html(files: &[SourceFileContext]) -> String {
+    let mut root = Directory::default();
+    for file in files {
+        let mut parts: Vec<&str> = file.path.split('/').filter(|p| !p.is_empty()).collect();
+        let name = parts.pop().unwrap_or(&file.path);
+        let mut directory = &mut root;
+        for part in parts {
+            directory = directory.directories.entry(part).or_default();
+        }
+        directory.files.push((name, file));
+    }
+
+    fn directory_html(directory: &Directory, html: &mut String) {
+        html.push_str("<ul class='source-tree'>");
+        for (name, mut child) in &directory.directories {
+            // Directories with a single subdirectory and no files are shown as one
+            let mut name = name.to_string();
+            while child.files.is_empty() && child.directories.len() == 1 {
+                let (grandchild_name, grandchild) = child.directories.iter().next().unwrap();
+                name = format!("{}/{}", name, grandchild_name);
+                child = grandchild;
+            }
+            html.push_str(&format!(
+                "<li><details open><summary>{}/</summary>",
+                encode_text(&name)
+            ));
+            directory_html(child, html);
+            html.push_str("</details></li>");
+        }
+        for (name, file) in &directory.files {
+            html.push_str(&format!(
+                "<li><a href='#{}'>{}</a> <span class='line-count'>({} line(s))</span></li>",
+                file.anchor,
+                encode_text(name),
+                file.lines.len()
+            ));
+        }
+        html.push_str("</ul>");
+    }
+
+    let mut html = String::new();
+    directory_html(&root, &mut html);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_and_kernels() {
+        let mut index = SourceIndex::default();
+        let graph = r#"class GraphModule(torch.nn.Module):
+    def forward(self, L_x_: "f32[8][1]cpu"):
+        l_x_ = L_x_
+
+         # File: /repo#link-tree/pkg/model.py:10 in forward, code: y = x.sin()
+        y: "f32[8][1]cpu" = l_x_.sin();  l_x_ = None
+
+         # File: /repo#link-tree/pkg/model.py:11 in forward, code: return y * 2
+        mul: "f32[8][1]cpu" = y * 2;  y = None
+        mul_1: "f32[8][1]cpu" = mul + 1;  mul = None
+        return (mul_1,)
+"#;
+        index.add_envelope(
+            &Envelope::for_test(0, r#"{"dynamo_output_graph": {}}"#),
+            graph,
+            Some(Path::new("-_0_0_0/dynamo_output_graph_0.txt")),
+        );
+        index.add_envelope(
+            &Envelope::for_test(
+                0,
+                r#"{"artifact": {"name": "inductor_provenance_tracking_kernel_stack_traces", "encoding": "json"}}"#,
+            ),
+            r#"{"triton_poi_fused_mul_0": ["  File \"/home/me/pkg/model.py\", line 11, in forward\n    return y * 2\n"]}"#,
+            None,
+        );
+        // Other highlighted code only records its frames
+        index.add_envelope(
+            &Envelope::for_test(
+                0,
+                r#"{"artifact": {"name": "fx_graph_runnable", "encoding": "string"}}"#,
+            ),
+            "    # File: /repo#link-tree/pkg/model.py:12 in forward, code: return y\n",
+            Some(Path::new("-_0_0_0/fx_graph_runnable_2.html")),
+        );
+        index.add_graph_breaks(&GraphBreakLeaderboard::default());
+
+        let context = index.finish(|_| Some("-_0_0_0/kernels.html".to_string()), "", "");
+        // pkg/model.py is /home/me/pkg/model.py
+        assert_eq!(context.num_files, 1);
+        let lines = &context.files[0].lines;
+        assert_eq!(
+            (
+                lines[0].line,
+                lines[0].code.as_str(),
+                lines[0].nodes[0].nodes.as_str()
+            ),
+            (10, "y = x.sin()", "y")
+        );
+        let line = &lines[1];
+        assert_eq!(line.compile_ids, "[0/0]");
+        assert_eq!(line.nodes[0].nodes, "mul, mul_1");
+        assert_eq!(line.nodes[0].page, "-_0_0_0/dynamo_output_graph_0.txt");
+        assert_eq!(
+            (line.kernels[0].name.as_str(), line.kernels[0].page.as_str()),
+            ("triton_poi_fused_mul_0", "-_0_0_0/kernels.html")
+        );
+        assert!(context
+            .tree_html
+            .contains("<summary>home/me/pkg/</summary>"));
+        // The graph links to the line by the path in its comment
+        assert_eq!(line.anchor, source_anchor("/home/me/pkg/model.py", 11));
+        assert_eq!(
+            line.alias_anchors,
+            vec![source_anchor("/repo#link-tree/pkg/model.py", 11)]
+        );
+        assert_eq!((lines[2].line, lines[2].nodes.len()), (12, 0));
+    }
+
+    #[test]
+    fn test_ambiguous_relative_paths() {
+        let mut index = SourceIndex::default();
+        index.location("/a/test/test_misc.py", 5, "fn", "x.cos()");
+        index.location("/b/test/test_misc.py", 5, "fn", "x.cos()");
+        index
+            .location("test/test_misc.py", 5, "", "")
+            .add_compile_id("[0/0]");
+        let context = index.finish(|_| None, "", "");
+        // Either file could be the relative one
+        assert_eq!(context.num_files, 3);
+        assert_eq!(context.files[2].path, "test/test_misc.py");
+    }
+}
@@ -15,6 +15,8 @@ table td { vertical-align: top; }
 }
 .stack-trie a { text-decoration: none; }
 .stack-trie a:hover { text-decoration: underline; }
+.stack-trie .break-marker { color: #b35900; font-weight: bold; }
+.stack-trie .recompile-marker { color: #0057b3; font-weight: bold; }
 .status-missing { background-color: purple; color: white; }
 .status-error { background-color: red; color: white; }
 .status-empty { background-color: white; color: black; }
@@ -36,6 +38,13 @@ details details summary { font-size: 16px; }
 pub static JAVASCRIPT: &str = r#"
   function toggleList(toggleItem) {
     const listItem = toggleItem.parentNode;
+    const template = listItem.querySelector(':scope > template');
+    if (template) {
+      // Collapsed subtree of a lazy stack trie: materialize it on first expansion
+      listItem.replaceChild(template.content.cloneNode(true), template);
+      toggleItem.classList.remove('collapsed');
+      return;
+    }
     const nestedList = listItem.querySelector('ul');
     if (nestedList) {
       nestedList.style.display = nestedList.style.display === 'none' ? 'block' : 'none';
@@ -46,6 +55,220 @@ pub static JAVASCRIPT: &str = r#"
   }
 "#;
 
+/// Drives the paginated build products list of the lazy index. Each compile id's artifacts
+/// live in `<compile id dir>/index_shard.js`, which calls `tlparseShard`; shards are loaded
+/// with script tags rather than fetch() so that the report also works from file:// URLs.
+pub static LAZY_DIRECTORY_JAVASCRIPT: &str = r#"
+(function() {
+  const PAGE_SIZE = 100;
+  const list = document.getElementById('lazy-directory-list');
+  const pager = document.getElementById('lazy-directory-pager');
+  const filterInput = document.getElementById('lazy-directory-filter');
+  const pending = {};
+  let filtered = COMPILE_DIRECTORY;
+  let page = 0;
+
+  window.tlparseShard = function(dir, artifacts) {
+    const callbacks = pending[dir] || [];
+    delete pending[dir];
+    callbacks.forEach(cb => cb(artifacts));
+  };
+
+  function loadShard(entry, cb) {
+    if (entry.artifacts) { cb(entry.artifacts); return; }
+    const first = !pending[entry.dir];
+    (pending[entry.dir] = pending[entry.dir] || []).push(artifacts => {
+      entry.artifacts = artifacts;
+      cb(artifacts);
+    });
+    if (first) {
+      const script = document.createElement('script');
+      script.src = entry.dir + '/index_shard.js';
+      script.onerror = () => window.tlparseShard(entry.dir, null);
+      document.head.appendChild(script);
+    }
+  }
+
+  function link(href, text) {
+    const a = document.createElement('a');
+    a.href = href;
+    a.textContent = text;
+    return a;
+  }
+
+  function renderArtifacts(ul, artifacts) {
+    ul.textContent = '';
+    if (artifacts === null) {
+      ul.appendChild(document.createElement('li')).textContent = 'Failed to load build products';
+      return;
+    }
+    for (const f of artifacts) {
+      const li = ul.appendChild(document.createElement('li'));
+      li.appendChild(link(f.url, f.name));
+      if (f.readable_url) {
+        li.append(' (');
+        li.appendChild(link(f.readable_url, 'readable_html'));
+        li.append(')');
+      }
+      li.append(' ' + f.suffix + ' (' + f.number + ')');
+    }
+  }
+
+  function renderPage() {
+    const pages = Math.max(1, Math.ceil(filtered.length / PAGE_SIZE));
+    page = Math.min(page, pages - 1);
+    list.textContent = '';
+    for (const entry of filtered.slice(page * PAGE_SIZE, (page + 1) * PAGE_SIZE)) {
+      const li = list.appendChild(document.createElement('li'));
+      const details = li.appendChild(document.createElement('details'));
+      details.id = entry.id;
+      const summary = details.appendChild(document.createElement('summary'));
+      summary.appendChild(link('#' + entry.id, entry.id)).className = entry.status;
+      summary.append(' (' + entry.count + ' files)');
+      const ul = details.appendChild(document.createElement('ul'));
+      details.addEventListener('toggle', () => {
+        if (details.open && !ul.hasChildNodes()) {
+          ul.appendChild(document.createElement('li')).textContent = 'Loading...';
+          loadShard(entry, artifacts => renderArtifacts(ul, artifacts));
+        }
+      });
+    }
+    pager.textContent = '';
+    const button = (label, target, disabled) => {
+      const b = pager.appendChild(document.createElement('button'));
+      b.textContent = label;
+      b.disabled = disabled;
+      b.onclick = () => { page = target; renderPage(); };
+    };
+    button('< Prev', page - 1, page === 0);
+    pager.append(' Page ' + (page + 1) + ' of ' + pages + ' (' + filtered.length + ' compile ids) ');
+    button('Next >', page + 1, page >= pages - 1);
+  }
+
+  // Stack trie links point at #<compile id>; bring that compile id into view and expand it
+  function showHash() {
+    const id = decodeURIComponent(location.hash.slice(1));
+    let index = filtered.findIndex(e => e.id === id);
+    if (index < 0) {
+      filterInput.value = '';
+      filtered = COMPILE_DIRECTORY;
+      index = filtered.findIndex(e => e.id === id);
+      if (index < 0) return;
+    }
+    page = Math.floor(index / PAGE_SIZE);
+    renderPage();
+    const details = document.getElementById(id);
+    details.open = true;
+    details.scrollIntoView();
+  }
+
+  filterInput.addEventListener('input', () => {
+    const needle = filterInput.value.trim();
+    filtered = COMPILE_DIRECTORY.filter(e => e.id.includes(needle));
+    page = 0;
+    renderPage();
+  });
+  window.addEventListener('hashchange', showHash);
+  renderPage();
+  if (location.hash) showHash();
+})();
+"#;
+
+/// Search box of index.html. The index lives in `search_index.js` (see `search.rs`) and is only
+/// loaded on first use; like the lazy index shards it is loaded with a script tag so that it
+/// works from file:// URLs.
+pub static SEARCH_JAVASCRIPT: &str = r#"
+(function() {
+  const MAX_HITS = 500;
+  const input = document.getElementById('search-input');
+  const status = document.getElementById('search-status');
+  const results = document.getElementById('search-results');
+  let index = null;
+  let terms = null;
+  let loading = false;
+  let timer = null;
+
+  window.tlparseSearchIndex = function(data) {
+    index = data;
+    terms = Object.keys(data.terms);
+    search();
+  };
+
+  function load() {
+    if (loading) return;
+    loading = true;
+    status.textContent = 'Loading search index...';
+    const script = document.createElement('script');
+    script.src = 'search_index.js';
+    script.onerror = () => { status.textContent = 'Failed to load search_index.js'; };
+    document.head.appendChild(script);
+  }
+
+  // Must agree with search::tokenize
+  function tokenize(text) {
+    return text.toLowerCase().split(/[^\p{L}\p{N}_]+/u).filter(t => t.length >= 2);
+  }
+
+  // Every query token matches as a prefix; a hit is a (doc, line) where all tokens match
+  function lookup(tokens) {
+    let hits = null;
+    for (const token of tokens) {
+      const found = new Set();
+      for (const term of terms) {
+        if (!term.startsWith(token)) continue;
+        const postings = index.terms[term];
+        for (let i = 0; i < postings.length; i += 2) {
+          found.add(postings[i] * 1048576 + postings[i + 1]);
+        }
+      }
+      hits = hits === null ? found : new Set([...hits].filter(h => found.has(h)));
+      if (hits.size === 0) break;
+    }
+    return [...(hits || [])].sort((a, b) => a - b)
+      .map(h => ({doc: Math.floor(h / 1048576), line: h % 1048576}));
+  }
+
+  function search() {
+    const tokens = tokenize(input.value);
+    results.textContent = '';
+    if (tokens.length === 0) { status.textContent = ''; return; }
+    if (index === null) { load(); return; }
+    const hits = lookup(tokens);
+    status.textContent = hits.length > MAX_HITS
+      ? hits.length + ' matches, showing the first ' + MAX_HITS
+      : hits.length + ' matches';
+    const groups = new Map();
+    for (const hit of hits.slice(0, MAX_HITS)) {
+      const cid = index.docs[hit.doc][0];
+      if (!groups.has(cid)) groups.set(cid, []);
+      groups.get(cid).push(hit);
+    }
+    for (const cid of [...groups.keys()].sort((a, b) => a - b)) {
+      const li = results.appendChild(document.createElement('li'));
+      const name = index.cids[cid];
+      const header = li.appendChild(document.createElement('a'));
+      header.href = '#' + name;
+      header.textContent = name;
+      li.append(' (' + groups.get(cid).length + ')');
+      const ul = li.appendChild(document.createElement('ul'));
+      for (const hit of groups.get(cid)) {
+        const [, url, file] = index.docs[hit.doc];
+        const a = ul.appendChild(document.createElement('li')).appendChild(document.createElement('a'));
+        // Only highlighted pages have line anchors
+        a.href = hit.line > 0 && url.endsWith('.html') ? url + '#L' + hit.line : url;
+        a.textContent = hit.line > 0 ? file + ':' + hit.line : file;
+      }
+    }
+  }
+
+  input.addEventListener('focus', load);
+  input.addEventListener('input', () => {
+    clearTimeout(timer);
+    timer = setTimeout(search, 150);
+  });
+})();
+"#;
+
 pub static EXPORT_CSS: &str = r#"
 table {
     width: 90%;
@@ -80,20 +303,98 @@ td:first-child {
 }
 "#;
 
-pub static TEMPLATE_DYNAMO_GUARDS: &str = r#"
+pub static TEMPLATE_DYNAMO_GUARDS: &str = r##"
 <html>
 <body>
 <h2>Guards</h2>
+<p>{num_guards} guard(s), by category. See <a href="../guards.html">guards.html</a> for guards across the whole run.</p>
 <ul>
-{{ for guard in guards }}
-    <li><code>{guard.code}</code></li>
+{{ for category in categories }}
+    <li><a href="#{category.id}">{category.name}</a>: {category.count}</li>
 {{ endfor }}
 </ul>
+{{ for category in categories }}
+<h3 id="{category.id}">{category.name} ({category.count})</h3>
+<ul>
+{{ for guard in category.guards }}
+    <li><code>{guard}</code></li>
+{{ endfor }}
+</ul>
+{{ endfor }}
+{qps | format_unescaped}
+</body>
+</html>
+"##;
+
+pub static TEMPLATE_CPP_GUARDS: &str = r#"
+<html>
+<head>
+<meta charset="UTF-8">
+<style>
+#guard-tree, #guard-tree ul \{ list-style-type: none; padding-left: 1.5em; margin: 0; }
+#guard-tree li \{ margin: 2px 0; }
+#guard-tree code \{ white-space: pre-wrap; }
+.guard-count \{ color: #666; }
+.guard-comment \{ color: #888; font-size: 90%; }
+</style>
+<title>C++ Guards</title>
+</head>
+<body>
+<h2>C++ Guards</h2>
+<p>
+{num_guards} guard(s). Guard managers show how many guards their subtree holds; hover over a guard for its category.
+Search by source expression, e.g. <code>L['x']</code>, or by guard text.
+</p>
+<p>
+<input id="guard-search" type="search" size="60" placeholder="L['x']">
+<span id="guard-search-status"></span>
+</p>
+<pre>{header}</pre>
+{tree_html | format_unescaped}
+<pre>{footer}</pre>
+{{ if stacks }}
+<h3>User stacks</h3>
+{{ for stack in stacks }}
+<div id="{stack.anchor}">{stack.html | format_unescaped}</div>
+{{ endfor }}
+{{ endif }}
+<script>
+{javascript | format_unescaped}
+</script>
 {qps | format_unescaped}
 </body>
 </html>
 "#;
 
+pub static CPP_GUARDS_JAVASCRIPT: &str = r#"
+(function () {
+  const input = document.getElementById('guard-search');
+  const status = document.getElementById('guard-search-status');
+  // Children come before their parents in reverse document order
+  const items = Array.from(document.querySelectorAll('#guard-tree li')).reverse();
+  function filter() {
+    const query = input.value.trim();
+    let shown = 0;
+    for (const li of items) {
+      const text = li.querySelector('.guard-text').textContent;
+      const matches = !query || li.dataset.source.includes(query) || text.includes(query);
+      const details = li.querySelector(':scope > details');
+      const children = details ? Array.from(details.querySelector(':scope > ul').children) : [];
+      const childShown = children.some((c) => c.style.display !== 'none');
+      li.style.display = matches || childShown ? '' : 'none';
+      if (details && query && childShown) {
+        details.open = true;
+      }
+      if (!details && matches) {
+        shown++;
+      }
+    }
+    status.textContent = query ? shown + ' matching guard(s)' : '';
+  }
+  input.addEventListener('input', filter);
+})();
+"#;
+
 pub static TEMPLATE_INDEX: &str = r#"
 <html>
 <head>
@@ -108,6 +409,64 @@ pub static TEMPLATE_INDEX: &str = r#"
 <body>
 <div>
 {custom_header_html | format_unescaped}
+{{ if has_search_index }}
+<h2>Search</h2>
+<p>
+Search graphs, generated code, guards, restart and failure reasons and other artifacts.  Hits are
+grouped by compile id; words match by prefix, and all words must appear on the same line.
+</p>
+<div id="search">
+<input id="search-input" type="search" size="60" placeholder="e.g. aten.mm or triton_poi">
+<span id="search-status"></span>
+<ul id="search-results"></ul>
+</div>
+<script>
+{search_javascript | format_unescaped}
+</script>
+{{ endif }}
+{{ if has_compile_time }}
+<h2>Compile time</h2>
+<p>
+The <a href="compile_time.html">compile time profile</a> totals each compilation phase over the run, lists the
+slowest compile ids, and breaks the <code>dynamo_timed</code> events of every compile id down, including the time
+spent looking up caches.
+</p>
+{{ endif }}
+{{ if has_timeline }}
+<p>
+The <a href="timeline.html">timeline</a> lays the compile ids out over wall time. The gaps between them are time spent
+running compiled code, so it shows when recompiles happen during training.
+</p>
+{{ endif }}
+{{ if has_graph_stats }}
+<h2>Graph sizes</h2>
+<p>
+The <a href="graph_stats.html">graph sizes</a> count the nodes of every FX graph by opcode and call target, showing which
+ops dominate the graphs of the run.
+</p>
+{{ endif }}
+{{ if has_autotuning }}
+<h2>Autotuning</h2>
+<p>
+The <a href="autotuning.html">autotuning report</a> totals the time spent compiling and benchmarking the configs of
+every Triton kernel, and lists the kernels that took longest to autotune.
+</p>
+{{ endif }}
+{{ if has_user_source }}
+<h2>User source</h2>
+<p>
+The <a href="user_source.html">user source index</a> lists, for each line of user code, the compile ids, graph nodes and
+kernels that came from it, and the graph breaks and guards attributed to it, organized as a file tree.
+</p>
+{{ endif }}
+{{ if has_dynamic_shapes }}
+<h2>Dynamic shapes</h2>
+<p>
+The <a href="dynamic_shapes.html">dynamic shapes report</a> lists the symbols, specializations and shape guards of
+every compile id, and suggests <code>mark_dynamic</code> or <code>mark_static</code> for inputs whose size changes
+made a frame recompile.
+</p>
+{{ endif }}
 <h2>Stack trie</h2>
 <p>
 The <strong>stack trie</strong> is a way of getting a quick orientation on where all the
@@ -133,6 +492,15 @@ Links to particular compilation are color coded by status:
 <p>
 Various issues may cause Dynamo to restart its analysis or give up on compilation entirely, causing graph breaks and fallbacks to eager mode.
 This run had <strong><a href="failures_and_restarts.html">{num_breaks} restart(s) and/or compilation failure(s)</a></strong>.
+The <a href="graph_breaks.html">graph break leaderboard</a> groups them by reason and user source location, and ranks them by how often they happen.
+</p>
+{{ endif }}
+{{ if has_guard_analytics }}
+<h2> Guards </h2>
+<p>
+Guards are checked every time a compiled frame runs, so large guard sets add runtime overhead.
+The <a href="guards.html">guard analytics</a> break the guards of each compile down by category, flag
+compile ids with unusually many guards, and show which guards are shared across compiles and which user code they come from.
 </p>
 {{ endif }}
 <h2>IR dumps</h2>
@@ -152,7 +520,8 @@ distinct code cache, which we are compiling next (perhaps because of a graph bre
 Dynamo treats distinct frames as completely unrelated, a frame compilation could overlap with another
 frame; for example, if you graph break in an inlined function, Dynamo will typically try to compile
 the nested frame again on an inner frame.  You can identify the hierarchical relationship between
-frames by looking at the stack trie above.
+frames by looking at the stack trie above.  Frames that recompiled are marked with ↻ in the stack
+trie, linking to a page that explains each recompilation.
 </p>
 <p>
 In some situations, the compile id will have an extra signifier <code>[x/y_z]</code>, where z is the
@@ -190,6 +559,21 @@ You can download and view them in a tool like <a href='https://ui.perfetto.dev/'
 <p>
 Build products below:
 </p>
+{{ if lazy_directory }}
+<p>
+This run has {num_compile_ids} compile ids, so build products are loaded one compile id at a time
+when you expand it.
+</p>
+<div id="lazy-directory">
+<input id="lazy-directory-filter" type="search" placeholder="Filter compile ids, e.g. [3/">
+<span id="lazy-directory-pager"></span>
+<ul id="lazy-directory-list"></ul>
+</div>
+<script>
+const COMPILE_DIRECTORY = {lazy_directory_json | format_unescaped};
+{lazy_directory_javascript | format_unescaped}
+</script>
+{{ else }}
 <ul>
 {{ for compile_directory in directory }}
     <li><a id="{compile_directory.0}">{compile_directory.0}</a>
@@ -201,78 +585,913 @@ Build products below:
     </li>
 {{ endfor }}
 </ul>
+{{ endif }}
+</div>
+
+
+{{ if has_inductor_provenance }}
+<h2>Provenance Tracking</h2>
+<div>
+    <p>View detailed provenance tracking information for each rank and frame:</p>
+    <ul>
+    {{ for page in provenance_pages }}
+        <li><a href='{page}'>{page}</a></li>
+    {{ endfor }}
+    </ul>
+</div>
+{{ endif }}
+
+{{ if has_unknown_stack_trie }}
+<div>
+<h2>Unknown stacks</h2>
+<p>
+  Sometimes, logs are made without a compile id.  This makes it difficult to correlate related
+  logs.  This stack trie shows all places where log entries occurred without compile context; to
+  fix, look an appropriate place in the stack where compile id should have been specified.
+</p>
+{unknown_stack_trie_html | format_unescaped}
 </div>
+{{ endif }}
+{qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_FAILURES_CSS: &str = r#"
+table {
+    width: 90%;
+    border-collapse: collapse;
+    margin: 20px 0;
+}
+table, th, td {
+    border: 1px solid #999;
+    padding: 10px;
+    text-align: left;
+}
+th {
+    background-color: #d3d3d3;
+    font-weight: bold;
+}
+tr:nth-child(odd) {
+    background-color: #f2f2f2;
+}
+a {
+    color: #0066cc;
+    text-decoration: none;
+}
+a:hover {
+    text-decoration: underline;
+}
+"#;
+
+pub static TEMPLATE_FAILURES_AND_RESTARTS: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+</head>
+<body>
+    <h1>Failures and Restarts</h1>
+    <table>
+    <tr> <th> Compile Id </th> <th> Failure Type </th> <th> Failure Description </th> <th> Failure Source (compilation failures only) </th> </tr>
+    {{ for failure in failures }}
+    <tr> <td> {failure.0 | format_unescaped} </td>{failure.1 | format_unescaped}</tr>
+    {{ endfor }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_GRAPH_BREAKS: &str = r##"
+<html>
+<head>
+    <style>
+    {css}
+    td pre \{ white-space: pre-wrap; margin: 0; }
+    tr:target \{ background-color: #ffffcc; }
+    </style>
+    <title>Graph Breaks</title>
+</head>
+<body>
+    <h1>Graph Breaks</h1>
+    <p>
+    The {num_breaks} restart(s) and compilation failure(s) from <a href="failures_and_restarts.html">failures_and_restarts.html</a>,
+    grouped by reason and by the user code they happened in. Numbers, sizes and addresses are ignored when comparing reasons.
+    Wasted time is the time Dynamo spent on a frame before restarting its analysis, counted once per compile against its first restart.
+    The same locations are marked with ⚠ in the stack trie on the index page.
+    </p>
+    <h2>By reason and location</h2>
+    <table>
+    <tr> <th> # </th> <th> Count </th> <th> Wasted time (s) </th> <th> Type </th> <th> Reason </th> <th> User source location </th> <th> Compile Ids </th> </tr>
+    {{ for group in groups }}
+    <tr id="break-{group.rank}">
+        <td> {group.rank} </td>
+        <td> {group.count} </td>
+        <td> {group.wasted_time_s} </td>
+        <td> {group.kind} </td>
+        <td>
+            <pre>{group.reason}</pre>
+            <details><summary>Example</summary><pre>{group.example}</pre></details>
+        </td>
+        <td> {group.location} </td>
+        <td> {{ for cid in group.compile_ids }}{cid | format_unescaped}{{ endfor }} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    <h2>By user source location</h2>
+    <table>
+    <tr> <th> User source location </th> <th> Count </th> <th> Wasted time (s) </th> <th> Reasons </th> </tr>
+    {{ for location in locations }}
+    <tr>
+        <td> {location.location} </td>
+        <td> {location.count} </td>
+        <td> {location.wasted_time_s} </td>
+        <td> {{ for rank in location.groups }}<a href="#break-{rank}">#{rank}</a> {{ endfor }} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {qps | format_unescaped}
+</body>
+</html>
+"##;
+
+pub static TEMPLATE_RECOMPILES: &str = r##"
+<html>
+<head>
+    <style>
+    {css}
+    td pre \{ white-space: pre-wrap; margin: 0; }
+    .warning \{ color: #b35900; font-weight: bold; }
+    .removed \{ background-color: #ffeef0; }
+    .added \{ background-color: #e6ffed; }
+    </style>
+    <title>Recompilations of frame {frame}</title>
+</head>
+<body>
+    <h1>Recompilations of frame {frame}</h1>
+    <p>
+    This frame was compiled {num_compilations} times. Each compilation below lists why Dynamo recompiled
+    (the guards of the earlier compilations that failed), how its guards and input tensors differ from the
+    previous compilation, and how close the frame's cache is to the default recompile limits.
+    </p>
+    {stack_html | format_unescaped}
+    <table>
+    <tr> <th> Compile Id </th> <th> Recompile reasons </th> <th> Guards </th> <th> Input tensor changes </th> <th> Cache size </th> <th> Accumulated cache size </th> </tr>
+    {{ for c in compilations }}
+    <tr>
+        <td> {c.compile_id | format_unescaped} </td>
+        <td> {{ for reason in c.reasons }}<pre>{reason}</pre>{{ endfor }} </td>
+        <td>
+            {{ if c.has_guard_diff }}
+            {c.guard_diff.unchanged} unchanged
+            {{ for g in c.guard_diff.changed }}<pre class="removed">- {g.before}</pre><pre class="added">+ {g.after}</pre>{{ endfor }}
+            {{ for g in c.guard_diff.removed }}<pre class="removed">- {g}</pre>{{ endfor }}
+            {{ for g in c.guard_diff.added }}<pre class="added">+ {g}</pre>{{ endfor }}
+            {{ else }}
+            {c.num_guards} guard(s)
+            {{ endif }}
+        </td>
+        <td>
+            {{ for t in c.tensor_changes }}<pre>{t.source} {t.field}: {t.before} → {t.after}</pre>{{ endfor }}
+        </td>
+        <td> {c.cache_size} </td>
+        <td> {c.accumulated_cache_size} </td>
+    </tr>
+    {{ for warning in c.warnings }}
+    <tr> <td></td> <td colspan="5" class="warning"> ⚠ {warning} </td> </tr>
+    {{ endfor }}
+    {{ endfor }}
+    </table>
+    {qps | format_unescaped}
+</body>
+</html>
+"##;
+
+pub static TEMPLATE_GUARD_ANALYTICS: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    td pre \{ white-space: pre-wrap; margin: 0; }
+    .unusual \{ background-color: #fff0e0; }
+    </style>
+    <title>Guards</title>
+</head>
+<body>
+    <h1>Guards</h1>
+    <p>
+    {num_guards} guard(s) over {num_compiles} compile(s). Counts per category come from the <code>dynamo_guards</code> payloads;
+    guard counts come from compilation metrics.
+    {{ if num_unusual }}<strong>{num_unusual} compile(s) have unusually many guards</strong>: more than twice the run's median, or simply a lot.{{ endif }}
+    </p>
+    <h2>By category</h2>
+    <table>
+    <tr> {{ for name in category_names }}<th> {name} </th>{{ endfor }} </tr>
+    <tr> {{ for count in totals }}<td> {count} </td>{{ endfor }} </tr>
+    </table>
+    <h2>By compile id</h2>
+    <table>
+    <tr> <th> Compile Id </th> <th> Guards </th> {{ for name in category_names }}<th> {name} </th>{{ endfor }} <th> Guard count </th> <th> Shape env guard count </th> <th> Unusual </th> </tr>
+    {{ for compile in compiles }}
+    <tr{{ if compile.unusual }} class="unusual"{{ endif }}>
+        <td> {compile.compile_id | format_unescaped} </td>
+        <td> {compile.num_guards} </td>
+        {{ for count in compile.counts }}<td> {count} </td>{{ endfor }}
+        <td> {compile.guard_count} </td>
+        <td> {compile.shape_env_guard_count} </td>
+        <td> {compile.unusual} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    <h2>Shared guards</h2>
+    <p>{num_identical} guard(s) are installed by more than one compile. The most shared are listed here.</p>
+    <table>
+    <tr> <th> Compiles </th> <th> Category </th> <th> Guard </th> <th> Compile Ids </th> </tr>
+    {{ for guard in identical }}
+    <tr>
+        <td> {guard.num_compiles} </td>
+        <td> {guard.category} </td>
+        <td> <pre>{guard.code}</pre> </td>
+        <td> {guard.compile_ids} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    <h2>By user source location</h2>
+    {{ if locations }}
+    <table>
+    <tr> <th> User source location </th> <th> Guards </th> <th> Categories </th> </tr>
+    {{ for location in locations }}
+    <tr>
+        <td> {location.location} </td>
+        <td> {location.count} </td>
+        <td> {location.categories} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No guard in this run recorded a user stack.</p>
+    {{ endif }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_COMPILE_TIME: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    td.number \{ text-align: right; }
+    </style>
+    <title>Compile Time</title>
+</head>
+<body>
+    <h1>Compile Time</h1>
+    <p>
+    Phase totals and the slowest compiles come from the compilation metrics of {num_compiles} compile(s). The per compile id
+    breakdown comes from matching the begin and end <code>dynamo_timed</code> events in <a href="chromium_events.json">chromium_events.json</a>.
+    </p>
+    <h2>Phases</h2>
+    {{ if phases }}
+    <table>
+    <tr> <th> Phase </th> <th> Total (s) </th> <th> Compiles </th> </tr>
+    {{ for phase in phases }}
+    <tr> <td> <code>{phase.name}</code> </td> <td class="number"> {phase.total_s} </td> <td class="number"> {phase.num_compiles} </td> </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No compilation metrics reported phase times.</p>
+    {{ endif }}
+    <h2>Slowest compiles</h2>
+    {{ if slowest }}
+    <p>The (at most) {slowest_limit} compiles with the largest <code>entire_frame_compile_time_s</code>, in seconds.</p>
+    <table>
+    <tr> <th> Compile Id </th> <th> Entire frame </th> <th> Backend </th> <th> Inductor </th> <th> Code gen </th> <th> Bwd inductor </th> </tr>
+    {{ for compile in slowest }}
+    <tr>
+        <td> {compile.compile_id | format_unescaped} </td>
+        <td class="number"> {compile.entire_frame_compile_time_s} </td>
+        <td class="number"> {compile.backend_compile_time_s} </td>
+        <td class="number"> {compile.inductor_compile_time_s} </td>
+        <td class="number"> {compile.code_gen_time_s} </td>
+        <td class="number"> {compile.bwd_inductor_compile_time_s} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No compilation metrics reported <code>entire_frame_compile_time_s</code>.</p>
+    {{ endif }}
+    <h2>Cache lookups</h2>
+    <p>
+    {cache_ms} ms of {total_ms} ms of <code>dynamo_timed</code> events ({cache_percent}%) were spent looking up caches:
+    the self time of events with "cache" in their name, so compiling on a cache miss counts as compilation.
+    </p>
+    {{ if cache_outcomes }}
+    <table>
+    <tr> <th> Event </th> <th> Count </th> <th> Time saved (ms) </th> </tr>
+    {{ for outcome in cache_outcomes }}
+    <tr> <td> {outcome.name} </td> <td class="number"> {outcome.count} </td> <td class="number"> {outcome.time_saved_ms} </td> </tr>
+    {{ endfor }}
+    </table>
+    {{ endif }}
+    <h2>By compile id</h2>
+    {{ if compiles }}
+    <p>Self time excludes the time spent in events nested in an event on the same thread.</p>
+    {{ for compile in compiles }}
+    <h3>{compile.compile_id | format_unescaped}</h3>
+    <p>{compile.total_ms} ms in total, {compile.cache_ms} ms looking up caches.</p>
+    <table>
+    <tr> <th> Event </th> <th> Count </th> <th> Total (ms) </th> <th> Self (ms) </th> </tr>
+    {{ for event in compile.events }}
+    <tr> <td> {event.name} </td> <td class="number"> {event.count} </td> <td class="number"> {event.total_ms} </td> <td class="number"> {event.self_ms} </td> </tr>
+    {{ endfor }}
+    </table>
+    {{ endfor }}
+    {{ else }}
+    <p>This log has no <code>dynamo_timed</code> chromium events.</p>
+    {{ endif }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_COMPILE_GRAPH_STATS: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>Graphs of {compile_id}</title>
+    <base href="..">
+</head>
+<body>
+    <h1>Graphs of {compile_id}</h1>
+    <p>
+    Node counts of the FX graphs compiled for this compile id, read from the logged graph code. Nodes of nested
+    graphs, like the bodies of higher order ops, are not counted. See the <a href="graph_stats.html">graph sizes</a>
+    of the whole run.
+    </p>
+    <table>
+    <tr> <th> Graph </th> {{ for opcode in opcodes }}<th> {opcode} </th> {{ endfor }}<th> Nodes </th> <th> Inputs </th> <th> Outputs </th> <th> Nested graphs </th> <th> Most called </th> </tr>
+    {{ for graph in graphs }}
+    <tr>
+        <td> {graph.kind} </td>
+        {{ for count in graph.opcodes }}<td> {count} </td> {{ endfor }}
+        <td> {graph.num_nodes} </td>
+        <td> {graph.inputs} </td>
+        <td> {graph.outputs} </td>
+        <td> {graph.subgraphs} </td>
+        <td> {graph.top_targets} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ for graph in graphs }}
+    <h2>{graph.kind}</h2>
+    {{ if graph.targets }}
+    <details>
+    <summary>Call targets</summary>
+    <table>
+    <tr> <th> Target </th> <th> Calls </th> <th> Share </th> </tr>
+    {{ for op in graph.targets }}
+    <tr> <td> <code>{op.target}</code> </td> <td> {op.count} </td> <td> {op.share} </td> </tr>
+    {{ endfor }}
+    </table>
+    </details>
+    {{ else }}
+    <p>No calls.</p>
+    {{ endif }}
+    {{ if graph.sizes }}
+    <details>
+    <summary>Recorded sizes</summary>
+    <ul>
+    {{ for size in graph.sizes }}
+    <li><code>{size}</code></li>
+    {{ endfor }}
+    </ul>
+    </details>
+    {{ endif }}
+    {{ endfor }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_GRAPH_STATS: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>Graph sizes</title>
+</head>
+<body>
+    <h1>Graph sizes</h1>
+    <p>
+    Node counts of the FX graphs compiled during the run, read from the logged graph code, and the (at most)
+    {run_top_targets} call targets that dominate each kind of graph.
+    </p>
+    <table>
+    <tr> <th> Graph </th> <th> Graphs </th> <th> Nodes </th> <th> Mean nodes </th> <th> Most nodes </th> </tr>
+    {{ for kind in kinds }}
+    <tr>
+        <td> <a href="graph_stats.html#{kind.kind}">{kind.kind}</a> </td>
+        <td> {kind.num_graphs} </td>
+        <td> {kind.num_nodes} </td>
+        <td> {kind.mean_nodes} </td>
+        <td> {kind.max_nodes} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ for kind in kinds }}
+    <h2 id="{kind.kind}">{kind.kind}</h2>
+    <table>
+    <tr> <th> Target </th> <th> Calls </th> <th> Share </th> <th> Graphs </th> </tr>
+    {{ for op in kind.targets }}
+    <tr> <td> <code>{op.target}</code> </td> <td> {op.count} </td> <td> {op.share} </td> <td> {op.num_graphs} </td> </tr>
+    {{ endfor }}
+    </table>
+    {{ endfor }}
+    <h2>Compile ids</h2>
+    <p>Nodes of each graph of each compile id.</p>
+    <table>
+    <tr> <th> Compile Id </th> {{ for kind in graph_kinds }}<th> {kind} </th> {{ endfor }}</tr>
+    {{ for compile in compiles }}
+    <tr>
+        <td> <a href="{compile.page}">{compile.compile_id}</a> </td>
+        {{ for num_nodes in compile.num_nodes }}<td> {num_nodes} </td> {{ endfor }}
+    </tr>
+    {{ endfor }}
+    </table>
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_COMPILE_KERNELS: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>Kernels of {compile_id}</title>
+    <base href="..">
+</head>
+<body>
+    <h1>Kernels of {compile_id}</h1>
+    <p>
+    The kernels Inductor generated for this compile id, and the extern kernels and fallback ops its wrapper code
+    calls. Autotuning and compile time information comes from <code>triton_kernel_info</code>, and post-grad nodes
+    from <code>inductor_triton_kernel_to_post_grad_nodes</code>, when they were logged.
+    </p>
+    <ul>
+    {{ for count in counts }}
+    <li>{count.kind}: {count.count}</li>
+    {{ endfor }}
+    </ul>
+    <table>
+    <tr> <th> Kernel </th> <th> Type </th> <th> Autotune cache </th> <th> Configs </th> <th> Only config </th> <th> Compile time (ms) </th> <th> Post-grad nodes </th> <th> Calls </th> </tr>
+    {{ for kernel in kernels }}
+    <tr>
+        <td> <a href="{kernel.page}">{kernel.name}</a> </td>
+        <td> {kernel.kind} </td>
+        <td> {kernel.cache_state} </td>
+        <td> {kernel.num_configs} </td>
+        <td> {kernel.only_config} </td>
+        <td> {kernel.compile_time} </td>
+        <td> {kernel.num_post_grad_nodes} </td>
+        <td> {kernel.num_launches} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ if slowest }}
+    <h2>Slowest to compile</h2>
+    <p>The (at most) {slowest_limit} kernels that took longest to compile.</p>
+    <table>
+    <tr> <th> Kernel </th> <th> Compile time (ms) </th> <th> Autotune cache </th> <th> Configs </th> </tr>
+    {{ for kernel in slowest }}
+    <tr>
+        <td> <a href="{kernel.page}">{kernel.name}</a> </td>
+        <td> {kernel.compile_time} </td>
+        <td> {kernel.cache_state} </td>
+        <td> {kernel.num_configs} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ endif }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_KERNEL: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>{name}</title>
+    <base href="..">
+</head>
+<body>
+    <h1>{name}</h1>
+    <p>
+    A {kind} kernel of <a href="{kernels_page}">{compile_id}</a>.
+    {{ if line }}Defined on <a href="{output_code}#L{line}">line {line}</a> of the output code.{{ endif }}
+    </p>
+    {{ if source_nodes }}
+    <p>Source nodes: <code>{source_nodes}</code></p>
+    {{ endif }}
+    {{ if post_grad_nodes }}
+    <p>Post-grad nodes: <code>{post_grad_nodes}</code></p>
+    {{ endif }}
+    {{ if info }}
+    <h2>Kernel info</h2>
+    <table>
+    {{ for field in info }}
+    <tr> <th> {field.key} </th> <td> <code>{field.value}</code> </td> </tr>
+    {{ endfor }}
+    </table>
+    {{ endif }}
+    <h2>Calls</h2>
+    {{ if launches }}
+    <ul>
+    {{ for launch in launches }}
+    <li><a href="{output_code}#L{launch.line}">line {launch.line}</a>: <code>{launch.code}</code></li>
+    {{ endfor }}
+    </ul>
+    {{ else }}
+    <p>The wrapper code doesn't call this kernel.</p>
+    {{ endif }}
+    {{ if source }}
+    <h2>Source</h2>
+    <pre>{source}</pre>
+    {{ endif }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_AUTOTUNING: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>Autotuning</title>
+</head>
+<body>
+    <h1>Autotuning</h1>
+    <p>
+    Time spent autotuning the {num_kernels} Triton kernels of the run: compiling their candidate configs, as
+    logged in <code>triton_kernel_info</code>, and benchmarking the configs to choose one, as timed by
+    <code>CachingAutotuner.benchmark_all_configs</code> events. Other benchmarking, like choosing between
+    matmul implementations, is counted separately. Benchmarks that ran outside of a compile are attributed to the
+    compile id of the event they ran in, usually the first run of the compiled graph.
+    </p>
+    <table>
+    <tr> <th> Compiling configs (ms) </th> <td> {compile_ms} </td> </tr>
+    <tr> <th> Benchmarking configs (ms) </th> <td> {benchmark_ms} </td> </tr>
+    <tr> <th> Other benchmarking (ms) </th> <td> {other_benchmark_ms} </td> </tr>
+    <tr> <th> Total (ms) </th> <td> {total_ms} </td> </tr>
+    {{ if compile_time_autotune_ms }}
+    <tr> <th> <code>compile_time_autotune_time_us</code> of compilation metrics (ms) </th> <td> {compile_time_autotune_ms} </td> </tr>
+    {{ endif }}
+    {{ if runtime_autotune_ms }}
+    <tr> <th> <code>runtime_triton_autotune_time_us</code> of runtime compilation metrics (ms) </th> <td> {runtime_autotune_ms} </td> </tr>
+    {{ endif }}
+    </table>
+    {{ if cache_states }}
+    <h2>Autotune cache</h2>
+    <ul>
+    {{ for state in cache_states }}
+    <li>{state.state}: {state.count}</li>
+    {{ endfor }}
+    </ul>
+    {{ endif }}
+    {{ if slowest }}
+    <h2>Slowest kernels to autotune</h2>
+    <p>The (at most) {slowest_limit} kernels that took longest to compile and benchmark.</p>
+    <table>
+    <tr> <th> Compile Id </th> <th> Kernel </th> <th> Autotune cache </th> <th> Candidates </th> <th> Compiling (ms) </th> <th> Benchmarking (ms) </th> <th> Total (ms) </th> </tr>
+    {{ for kernel in slowest }}
+    <tr>
+        <td> {kernel.compile_id} </td>
+        <td> {kernel.name} </td>
+        <td> {kernel.cache_state} </td>
+        <td> {kernel.candidates} </td>
+        <td> {kernel.compile_ms} </td>
+        <td> {kernel.benchmark_ms} </td>
+        <td> {kernel.total_ms} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ endif }}
+    <h2>Compile ids</h2>
+    <p>
+    The chosen config is logged for kernels with a single candidate and for autotune cache hits; kernels autotuned
+    in this run don't log which of their candidates won.
+    </p>
+    {{ for compile in compiles }}
+    <h3>{{ if compile.kernels_page }}<a href="{compile.kernels_page}">{compile.compile_id}</a>{{ else }}{compile.compile_id}{{ endif }}</h3>
+    <ul>
+    {{ if compile.compile_time_autotune_ms }}<li>Autotuning while compiling (compilation metrics): {compile.compile_time_autotune_ms} ms</li>{{ endif }}
+    {{ if compile.runtime_autotune_ms }}<li>Autotuning at runtime (runtime compilation metrics): {compile.runtime_autotune_ms} ms</li>{{ endif }}
+    <li>Other benchmarking: {compile.other_benchmark_ms} ms</li>
+    </ul>
+    {{ if compile.kernels }}
+    <table>
+    <tr> <th> Kernel </th> <th> Autotune cache </th> <th> Candidates </th> <th> Chosen config </th> <th> Compiling (ms) </th> <th> Benchmarking (ms) </th> <th> Benchmark runs </th> <th> Total (ms) </th> </tr>
+    {{ for kernel in compile.kernels }}
+    <tr>
+        <td> {kernel.name} </td>
+        <td> {kernel.cache_state} </td>
+        <td> {kernel.candidates} </td>
+        <td> {kernel.chosen_config} </td>
+        <td> {kernel.compile_ms} </td>
+        <td> {kernel.benchmark_ms} </td>
+        <td> {kernel.num_benchmarks} </td>
+        <td> {kernel.total_ms} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ endif }}
+    {{ endfor }}
+    {qps | format_unescaped}
+</body>
+</html>
+"#;
 
+pub static TEMPLATE_USER_SOURCE: &str = r##"
+<html>
+<head>
+    <style>
+    {css}
+    td pre \{ white-space: pre-wrap; margin: 0; }
+    tr:target \{ background-color: #ffffcc; }
+    .source-tree \{ list-style-type: none; padding-left: 1.5em; }
+    .line-count \{ color: #666; }
+    </style>
+    <title>User Source</title>
+</head>
+<body>
+    <h1>User Source</h1>
+    <p>
+    The {num_lines} line(s) of source code in {num_files} file(s) that the run's graphs, kernels, graph breaks and guards came from.
+    Graph nodes are listed under the line they were traced from, as recorded in the <code># File:</code> comments of the FX graphs.
+    Kernels are listed under every line on the stacks that <code>inductor_provenance_tracking_kernel_stack_traces</code> records for them.
+    Guards and graph breaks are listed under the innermost user frame they were attributed to.
+    </p>
+    <h2>Files</h2>
+    {tree_html | format_unescaped}
+    {{ for file in files }}
+    <h2 id="{file.anchor}">{file.path}</h2>
+    <table>
+    <tr> <th> Line </th> <th> Code </th> <th> Compile Ids </th> <th> Graph nodes </th> <th> Kernels </th> <th> Graph breaks </th> <th> Guards </th> </tr>
+    {{ for line in file.lines }}
+    <tr id="{line.anchor}">
+        <td> {{ for alias in line.alias_anchors }}<span id="{alias}"></span>{{ endfor }}<a href="#{line.anchor}">{line.line}</a> </td>
+        <td> {{ if line.function }}<em>{line.function}</em>{{ endif }}<pre>{line.code}</pre> </td>
+        <td> {line.compile_ids} </td>
+        <td> {{ for nodes in line.nodes }}<div>{{ if nodes.page }}<a href="{nodes.page}">{nodes.compile_id} {nodes.graph}</a>{{ else }}{nodes.compile_id} {nodes.graph}{{ endif }}: {nodes.nodes}</div>{{ endfor }} </td>
+        <td> {{ for kernel in line.kernels }}<div>{kernel.compile_id} {{ if kernel.page }}<a href="{kernel.page}">{kernel.name}</a>{{ else }}{kernel.name}{{ endif }}</div>{{ endfor }} </td>
+        <td> {{ for break in line.graph_breaks }}<div>{break.compile_id_html | format_unescaped} {break.kind}: <pre>{break.reason}</pre></div>{{ endfor }} </td>
+        <td>
+        {{ if line.guards }}
+        <details><summary>{line.num_guards} guard(s)</summary>
+        {{ for guard in line.guards }}<div>{guard.compile_id} <code>{guard.code}</code></div>{{ endfor }}
+        </details>
+        {{ endif }}
+        </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ endfor }}
+    {qps | format_unescaped}
+</body>
+</html>
+"##;
 
-{{ if has_inductor_provenance }}
-<h2>Provenance Tracking</h2>
-<div>
-    <p>View detailed provenance tracking information for each rank and frame:</p>
+pub static TEMPLATE_TENSOR_METADATA: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    td.changed, tr.changed th \{ background-color: #ffe08a; }
+    </style>
+    <title>Input tensors of {compile_id}</title>
+    <base href="..">
+</head>
+<body>
+    <h1>Input tensors of {compile_id}</h1>
+    <p>
+    The tensors Dynamo fakeified for this compile id, joined with the sources they came from and the storages
+    they use. Tensors without a source are bases, gradients and other tensors reachable from the inputs.
+    </p>
+    {{ if has_previous }}
+    <p>
+    Compared with the previous compilation of the frame, <a href="{previous_page}">{previous_compile_id}</a>:
+    {{ if changed_inputs }}
+    the metadata of these inputs changed, highlighted below; hover over a highlighted cell for its previous value.
+    </p>
     <ul>
-    {{ for directory_name in directory_names }}
-        <li><a href='provenance_tracking_{directory_name}.html'>provenance_tracking_{directory_name}</a></li>
+    {{ for input in changed_inputs }}
+    <li><code>{input}</code></li>
     {{ endfor }}
     </ul>
-</div>
-{{ endif }}
-
-{{ if has_unknown_stack_trie }}
-<div>
-<h2>Unknown stacks</h2>
-<p>
-  Sometimes, logs are made without a compile id.  This makes it difficult to correlate related
-  logs.  This stack trie shows all places where log entries occurred without compile context; to
-  fix, look an appropriate place in the stack where compile id should have been specified.
-</p>
-{unknown_stack_trie_html | format_unescaped}
-</div>
-{{ endif }}
-{qps | format_unescaped}
+    {{ else }}
+    the metadata of the inputs didn't change.
+    </p>
+    {{ endif }}
+    {{ endif }}
+    <table>
+    <tr> <th> Tensor </th> {{ for column in columns }}<th> {column} </th> {{ endfor }}</tr>
+    {{ for tensor in tensors }}
+    <tr{{ if tensor.changed }} class="changed"{{ endif }}>
+        <th> {{ if tensor.has_source }}<code>{tensor.name}</code>{{ else }}{tensor.name}{{ endif }} </th>
+        {{ for cell in tensor.cells }}<td{{ if cell.changed }} class="changed" title="was {cell.before}"{{ endif }}> {cell.value} </td>
+        {{ endfor }}
+    </tr>
+    {{ endfor }}
+    </table>
+    {qps | format_unescaped}
 </body>
 </html>
 "#;
 
-pub static TEMPLATE_FAILURES_CSS: &str = r#"
-table {
-    width: 90%;
-    border-collapse: collapse;
-    margin: 20px 0;
-}
-table, th, td {
-    border: 1px solid #999;
-    padding: 10px;
-    text-align: left;
-}
-th {
-    background-color: #d3d3d3;
-    font-weight: bold;
-}
-tr:nth-child(odd) {
-    background-color: #f2f2f2;
-}
-a {
-    color: #0066cc;
-    text-decoration: none;
-}
-a:hover {
-    text-decoration: underline;
-}
+pub static TEMPLATE_DYNAMIC_SHAPES: &str = r#"
+<html>
+<head>
+    <style>
+    {css}
+    </style>
+    <title>{title}</title>
+    {{ if not run_wide }}<base href="..">{{ endif }}
+</head>
+<body>
+    <h1>{title}</h1>
+    {{ if run_wide }}
+    <h2>Suggestions</h2>
+    {{ if suggestions }}
+    <p>
+    These inputs changed size between calls, which made their frame recompile. Marking the dimension
+    before the first call avoids the recompile.
+    </p>
+    <table>
+    <tr> <th> First recompile </th> <th> Input </th> <th> Dimension </th> <th> Sizes </th> <th> Suggestion </th> </tr>
+    {{ for suggestion in suggestions }}
+    <tr>
+        <td> {suggestion.compile_id} </td>
+        <td> <code>{suggestion.source}</code> </td>
+        <td> {suggestion.dim} </td>
+        <td> {suggestion.sizes} </td>
+        <td> <code>{suggestion.action}</code><br>{suggestion.why} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No frame recompiled because the size of an input changed.</p>
+    {{ endif }}
+    <h2>Specializations</h2>
+    {{ if specializations }}
+    <table>
+    <tr> <th> Compile Id </th> <th> Symbol </th> <th> Sources </th> <th> Value </th> <th> Reason </th> <th> User code </th> </tr>
+    {{ for spec in specializations }}
+    <tr>
+        <td> {spec.compile_id} </td>
+        <td> {spec.symbol} </td>
+        <td> <code>{spec.sources}</code> </td>
+        <td> {spec.value} </td>
+        <td> <code>{spec.reason}</code> </td>
+        <td> {spec.location}{spec.user_stack_html | format_unescaped} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No symbol was specialized.</p>
+    {{ endif }}
+    <h2>Compile ids</h2>
+    <table>
+    <tr> <th> Compile Id </th> <th> Symbols </th> <th> Specializations </th> <th> Shape guards </th> </tr>
+    {{ for compile in compiles }}
+    <tr>
+        <td> <a href="{compile.page}">{compile.compile_id}</a> </td>
+        <td> {compile.num_symbols} </td>
+        <td> {compile.num_specializations} </td>
+        <td> {compile.num_guards} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>See <a href="dynamic_shapes.html">dynamic shapes</a> for the whole run.</p>
+    {{ for compile in compiles }}
+    <h2>Symbols</h2>
+    {{ if compile.symbols }}
+    <table>
+    <tr> <th> Symbol </th> <th> Kind </th> <th> Value </th> <th> Value range </th> <th> Sources </th> <th> User code </th> </tr>
+    {{ for symbol in compile.symbols }}
+    <tr>
+        <td> {symbol.symbol} </td>
+        <td> {symbol.kind} </td>
+        <td> {symbol.value} </td>
+        <td> {symbol.range} </td>
+        <td> <code>{symbol.sources}</code> </td>
+        <td> {symbol.location} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No symbols were created.</p>
+    {{ endif }}
+    <h2>Specializations</h2>
+    {{ if compile.specializations }}
+    <table>
+    <tr> <th> Symbol </th> <th> Sources </th> <th> Value </th> <th> Reason </th> <th> User code </th> </tr>
+    {{ for spec in compile.specializations }}
+    <tr>
+        <td> {spec.symbol} </td>
+        <td> <code>{spec.sources}</code> </td>
+        <td> {spec.value} </td>
+        <td> <code>{spec.reason}</code> </td>
+        <td> {spec.location}{spec.user_stack_html | format_unescaped} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No symbol was specialized.</p>
+    {{ endif }}
+    <h2>Shape guards</h2>
+    {{ if compile.guards }}
+    <table>
+    <tr> <th> Guard </th> <th> Kind </th> <th> User code </th> </tr>
+    {{ for guard in compile.guards }}
+    <tr>
+        <td> <code>{guard.expr}</code> </td>
+        <td> {guard.kind} </td>
+        <td> {guard.location} </td>
+    </tr>
+    {{ endfor }}
+    </table>
+    {{ else }}
+    <p>No shape guards were added.</p>
+    {{ endif }}
+    {{ endfor }}
+    {{ endif }}
+    {qps | format_unescaped}
+</body>
+</html>
 "#;
 
-pub static TEMPLATE_FAILURES_AND_RESTARTS: &str = r#"
+pub static TEMPLATE_TIMELINE: &str = r#"
 <html>
 <head>
     <style>
-    {css}
+    {css | format_unescaped}
+    .timeline \{ position: relative; margin-left: 8em; }
+    .row \{ position: relative; height: 1.2em; border-bottom: 1px solid #eee; }
+    .row .label \{ position: absolute; left: -8em; width: 7.5em; font-family: monospace; font-size: small; overflow: hidden; }
+    .bar \{ position: absolute; top: 0.15em; height: 0.9em; min-width: 1px; }
+    .bar.recompile \{ outline: 2px solid #0057b3; }
+    .bar.runtime \{ background-color: #ddd; }
+    .axis \{ position: relative; height: 1.5em; }
+    .axis span \{ position: absolute; transform: translateX(-50%); font-size: small; }
     </style>
+    <title>Timeline</title>
 </head>
 <body>
-    <h1>Failures and Restarts</h1>
+    <h1>Timeline</h1>
+    <p>
+    The run logged from {start} to {end} ({duration}), in {timezone}; the year comes from {year_source}.
+    Compile ids were compiling for {compile_time} and the run spent {runtime} between compilations, over {num_gaps} gap(s).
+    </p>
+    <p>
+    Each compile id spans from the first to the last line logged for it, colored by status like the
+    <a href="index.html">stack trie</a>. Recompiles are outlined. The <em>runtime</em> row shows the gaps between compilations.
+    </p>
+    <div class="timeline">
+    <div class="axis">{{ for tick in ticks }}<span style="left: {tick.left}%">{tick.label}</span>{{ endfor }}</div>
+    <div class="row"><span class="label">runtime</span>{{ for bar in runtime_bars }}<span class="bar runtime" style="left: {bar.left}%; width: {bar.width}%" title="{bar.title}"></span>{{ endfor }}</div>
+    {{ for compile in compiles }}
+    <div class="row"><span class="label">{compile.compile_id}</span><a href="index.html#{compile.compile_id}" class="bar {compile.status}{{ if compile.recompile }} recompile{{ endif }}" style="left: {compile.left}%; width: {compile.width}%" title="{compile.compile_id} at {compile.start}, {compile.duration}"></a></div>
+    {{ endfor }}
+    </div>
+    <h2>Longest gaps between compilations</h2>
+    {{ if longest_gaps }}
+    <p>The (at most) {gaps_limit} longest stretches of time during which no compile id was compiling.</p>
     <table>
-    <tr> <th> Compile Id </th> <th> Failure Type </th> <th> Failure Description </th> <th> Failure Source (compilation failures only) </th> </tr>
-    {{ for failure in failures }}
-    <tr> <td> {failure.0 | format_unescaped} </td>{failure.1 | format_unescaped}</tr>
+    <tr> <th> Start </th> <th> Duration </th> <th> After </th> <th> Before </th> </tr>
+    {{ for gap in longest_gaps }}
+    <tr>
+        <td> {gap.start} </td>
+        <td> {gap.duration} </td>
+        <td> {gap.after} </td>
+        <td> {gap.before}{{ if gap.recompile }} (recompile){{ endif }} </td>
+    </tr>
     {{ endfor }}
+    </table>
+    {{ else }}
+    <p>The run never stopped compiling.</p>
+    {{ endif }}
     {qps | format_unescaped}
 </body>
 </html>
@@ -539,7 +1758,7 @@ pub static PROVENANCE_CSS: &str = include_str!("provenance.css");
 pub static PROVENANCE_JS: &str = include_str!("provenance.js");
 pub static TEMPLATE_PROVENANCE_TRACKING: &str = include_str!("provenance.html");
 
-pub static TEMPLATE_MULTI_RANK_INDEX: &str = r#"
+pub static TEMPLATE_MULTI_RANK_INDEX: &str = r##"
 <html>
 <head>
   <meta charset="UTF-8">
@@ -572,6 +1791,12 @@ pub static TEMPLATE_MULTI_RANK_INDEX: &str = r#"
         {{ endfor }}
     </ul>
     {{ endif }}
+    {{ if diagnostics.rank_comparison }}
+    <p>Compare the graphs, guards, output code and tensor metadata of these ranks in the <a href="rank_comparison.html">rank comparison</a>.</p>
+    {{ endif }}
+    {{ if diagnostics.first_divergence }}
+    <p>See the <a href="#first-divergence">first divergence</a> for where the compile events of the ranks first disagree.</p>
+    {{ endif }}
 </div>
 {{ endif }}
 <h2>Multi-Rank TLParse Report</h2>
@@ -603,6 +1828,43 @@ Individual rank reports:
     <li><a href="rank_{rank}/index.html">Rank {rank}</a></li>
 {{ endfor }}
 </ul>
+{{ if diagnostics.first_divergence }}
+<h3 id="first-divergence">First Divergence</h3>
+<p>
+Each rank's compile events (compile ids, cache hits and misses, recompile reasons and collective schedules) are lined up
+in the order the rank logged them. The ranks first disagree at event <strong>{diagnostics.first_divergence.number}</strong>.
+A hang usually starts here: the ranks after it compile different graphs or issue different collectives.
+</p>
+{{ for group in diagnostics.first_divergence.groups }}
+<h4>Rank(s) {group.ranks}: {group.event}</h4>
+<p>Details from <a href="rank_{group.rank}/index.html">rank {group.rank}</a>{{ if group.compile_id }}, compile id {group.compile_id}{{ endif }}:</p>
+<ul>
+    {{ if group.cache_status }}<li>Cache status: {group.cache_status}</li>{{ endif }}
+    {{ if group.recompile_reasons }}
+    <li>Recompile reasons:
+        <ul>
+        {{ for reason in group.recompile_reasons }}
+            <li><code>{reason}</code></li>
+        {{ endfor }}
+        </ul>
+    </li>
+    {{ endif }}
+    {{ if group.guards_url }}<li><a href="{group.guards_url}">Guards</a></li>{{ endif }}
+    {{ if group.comparison_url }}<li><a href="{group.comparison_url}">Rank comparison of this compile id</a></li>{{ endif }}
+</ul>
+{{ if group.stack }}
+<p>Stack:</p>
+<pre>{group.stack}</pre>
+{{ endif }}
+<p>Events around the divergence:</p>
+<ol>
+    {{ for event in group.context }}
+    <li value="{event.number}">{{ if event.divergent }}<strong>{event.description}</strong>{{ else }}{event.description}{{ endif }}</li>
+    {{ endfor }}
+    {{ if group.context }}{{ else }}<li>No compile events</li>{{ endif }}
+</ol>
+{{ endfor }}
+{{ endif }}
 {{ if diagnostics.analysis }}
 {{ if diagnostics.analysis.has_mismatched_graph_counts }}
 <h3>Graph Runtime Analysis</h3>
@@ -621,6 +1883,16 @@ desync issues on specific ranks.
 {{ endfor }}
 {{ endif }}
 {{ endif }}
+{{ if diagnostics.compile_time }}
+<h3>Compile Time Analysis</h3>
+<p>
+The <a href="compile_stragglers.html">compile time stragglers</a> report compares the total compile time and cache hit
+ratio of each rank, and the spread of each compile id across ranks.
+</p>
+{{ if diagnostics.compile_time.has_flagged }}
+<p><strong>Warning:</strong> Rank(s) {diagnostics.compile_time.flagged_ranks} compiled for longer than percentile {diagnostics.compile_time.percentile} of all ranks ({diagnostics.compile_time.threshold_s} s). Compile stragglers hold the other ranks up at their next collective, which can lead to timeouts.</p>
+{{ endif }}
+{{ endif }}
 <h3>Graph Execution-Order Diagnostics</h3>
 <p>Note: To enable this feature, wrap your code with torch._inductor.debug.record_and_log_graph_execution_order()</p>
 {{ if diagnostics.exec_order }}
@@ -655,6 +1927,9 @@ Ranks exhibit divergent inductor tensor metadata across graphs. Groups with iden
         <li>Ranks: {group.ranks}</li>
     {{ endfor }}
     </ul>
+{{ if diagnostics.rank_comparison }}
+<p>See the <a href="rank_comparison.html">rank comparison</a> for the differences.</p>
+{{ endif }}
 {{ else }}
 <p>
 All ranks have matching tensor meta signatures across graphs.
@@ -664,4 +1939,201 @@ All ranks have matching tensor meta signatures across graphs.
 {qps | format_unescaped}
 </body>
 </html>
+"##;
+
+pub static SORTABLE_TABLE_JAVASCRIPT: &str = r#"
+(function () {
+  // Click a column header of a table.sortable to sort its rows by that column; cells sort by
+  // their data-value if they have one, numerically where possible
+  for (const table of document.querySelectorAll('table.sortable')) {
+    const headers = Array.from(table.querySelectorAll('th'));
+    headers.forEach((th, column) => {
+      th.style.cursor = 'pointer';
+      th.title = 'Click to sort';
+      th.addEventListener('click', () => {
+        const descending = th.dataset.order !== 'desc';
+        headers.forEach((h) => delete h.dataset.order);
+        th.dataset.order = descending ? 'desc' : 'asc';
+        const rows = Array.from(table.querySelectorAll('tr')).filter((tr) => !tr.querySelector('th'));
+        const key = (row) => {
+          const cell = row.children[column];
+          const value = cell.dataset.value ?? cell.textContent.trim();
+          const number = parseFloat(value);
+          return isNaN(number) ? value : number;
+        };
+        rows.sort((a, b) => {
+          const [x, y] = [key(a), key(b)];
+          const order = typeof x === 'number' && typeof y === 'number' ? x - y : String(x).localeCompare(String(y));
+          return descending ? -order : order;
+        });
+        for (const row of rows) {
+          row.parentNode.appendChild(row);
+        }
+      });
+    });
+  }
+})();
+"#;
+
+pub static TEMPLATE_COMPILE_STRAGGLERS: &str = r#"
+<html>
+<head>
+<meta charset="UTF-8">
+<title>Compile Time Stragglers</title>
+</head>
+<style>
+{css | format_unescaped}
+tr.flagged td \{ background-color: #fff0f0; }
+table.heatmap td \{ text-align: right; font-family: monospace; }
+</style>
+<body>
+<h2>Compile Time Stragglers</h2>
+<p>
+Compile time of every rank, from the <code>compilation_metrics</code> of each rank's log. A compile takes its
+<code>entire_frame_compile_time_s</code> plus the Inductor compile time of its backward. Ranks that compile slower than
+the others hold them up at the next collective, which can end in a timeout. Click a column header to sort.
+</p>
+<h3>Ranks</h3>
+<p>
+The median rank compiled for {median_s} s.
+Ranks above percentile {percentile} of all ranks ({threshold_s} s) and more than 10% above the median are flagged{{ if flagged_ranks }}: <strong>rank(s) {flagged_ranks}</strong>{{ endif }}.
+</p>
+<table class="sortable">
+<tr> <th> Rank </th> <th> Total compile time (s) </th> <th> Over median (s) </th> <th> Compiles </th> <th> Cache hit ratio </th> <th> Slowest compile id </th> <th> Flagged </th> </tr>
+{{ for rank in ranks }}
+<tr{{ if rank.flagged }} class="flagged"{{ endif }}>
+    <td> <a href="rank_{rank.rank}/compile_time.html">{rank.rank}</a> </td>
+    <td> {rank.total_s} </td>
+    <td> {rank.over_median_s} </td>
+    <td> {rank.num_compiles} </td>
+    <td> {rank.cache_hit_ratio} ({rank.cache_hits}/{rank.cache_lookups}) </td>
+    <td> {rank.slowest_compile_id} </td>
+    <td> {{ if rank.flagged }}yes{{ endif }} </td>
+</tr>
+{{ endfor }}
+</table>
+<h3>Compile ids</h3>
+<p>
+The spread of each of the {num_compiles} compile id(s) across the ranks that compiled it.
+</p>
+<table class="sortable">
+<tr> <th> Compile Id </th> <th> Ranks </th> <th> Min (s) </th> <th> Median (s) </th> <th> Max (s) </th> <th> Max - min (s) </th> <th> Slowest rank </th> </tr>
+{{ for compile in compiles }}
+<tr>
+    <td data-value="{compile.order}"> {compile.compile_id} </td>
+    <td> {compile.num_ranks} </td>
+    <td> {compile.min_s} </td>
+    <td> {compile.median_s} </td>
+    <td> {compile.max_s} </td>
+    <td> {compile.spread_s} </td>
+    <td> {compile.slowest_rank} </td>
+</tr>
+{{ endfor }}
+</table>
+<h3>Heatmap</h3>
+<p>
+Compile time in seconds of the first {num_heatmap_compiles} compile id(s) on each rank, from white for the fastest rank
+of a compile id to red for the slowest.
+</p>
+<table class="heatmap">
+<tr> <th> Compile Id </th> {{ for rank in rank_headers }}<th> Rank {rank} </th> {{ endfor }}</tr>
+{{ for row in heatmap }}
+<tr>
+    <td> {row.compile_id} </td>
+    {{ for cell in row.cells }}<td style="{cell.style}"> {cell.time_s} </td> {{ endfor }}
+</tr>
+{{ endfor }}
+</table>
+<script>
+{javascript | format_unescaped}
+</script>
+{qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_RANK_COMPARISON: &str = r#"
+<html>
+<head>
+<meta charset="UTF-8">
+<title>Rank Comparison</title>
+</head>
+<style>
+{css | format_unescaped}
+tr.differs td \{ background-color: #fff0f0; }
+</style>
+<body>
+<h2>Rank Comparison</h2>
+<p>
+Ranks are grouped by everything the <a href="index.html">landing page</a> compares: compile ids, cache hit/miss sequence,
+tensor metadata and collective schedule. The lowest rank of each group represents it, and is compared against
+rank {baseline} for every compile id. {num_differing} compile id(s) differ.
+</p>
+<ul>
+{{ for group in groups }}
+    <li>Rank {group.rank}: ranks {group.ranks}</li>
+{{ endfor }}
+</ul>
+<table>
+<tr> <th> Compile Id </th> <th> Dynamo output graph </th> <th> Guards </th> <th> Inductor output code </th> <th> Tensor metadata </th> <th> Not compiled on </th> </tr>
+{{ for row in rows }}
+<tr{{ if row.differs }} class="differs"{{ endif }}>
+    <td> <a href="{row.url}">{row.compile_id}</a> </td>
+    {{ for status in row.statuses }}<td> {status} </td> {{ endfor }}
+    <td> {{ if row.missing_on }}rank(s) {row.missing_on}{{ endif }} </td>
+</tr>
+{{ endfor }}
+</table>
+{qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_RANK_COMPARISON_COMPILE: &str = r#"
+<html>
+<head>
+<meta charset="UTF-8">
+<title>Rank Comparison {compile_id}</title>
+</head>
+<style>
+{css | format_unescaped}
+table.diff \{ border-collapse: collapse; font-family: monospace; }
+table.diff td \{ padding: 0 0.5em; vertical-align: top; }
+table.diff td pre \{ margin: 0; white-space: pre-wrap; }
+table.diff td.lineno \{ color: #999; text-align: right; }
+table.diff tr.del \{ background-color: #ffebe9; }
+table.diff tr.ins \{ background-color: #e6ffec; }
+table.diff tr.hunk td \{ color: #666; background-color: #f0f4ff; }
+</style>
+<body>
+<h2>Rank Comparison for {compile_id}</h2>
+<p>
+Artifacts of {compile_id} on the representative of each rank group, diffed against rank {baseline}.
+Device indices, the object ids checked by guards and kernel cache paths differ between processes, and are shown as <code>N</code>.
+See <a href="../rank_comparison.html">all compile ids</a>.
+</p>
+<ul>
+{{ for group in groups }}
+    <li>Rank {group.rank}: ranks {group.ranks}</li>
+{{ endfor }}
+</ul>
+{{ if missing_on }}
+<p><strong>Not compiled on rank(s) {missing_on}.</strong></p>
+{{ endif }}
+{{ for artifact in artifacts }}
+<h3>{artifact.name}</h3>
+{{ for diff in artifact.diffs }}
+<h4>Rank {baseline} &rarr; rank {diff.rank}: {diff.status}</h4>
+<p>
+{{ if diff.baseline_url }}<a href="{diff.baseline_url}">rank {baseline}</a>{{ endif }}
+{{ if diff.url }}<a href="{diff.url}">rank {diff.rank}</a>{{ endif }}
+</p>
+{{ if diff.differs }}
+{diff.diff_html | format_unescaped}
+{{ endif }}
+{{ endfor }}
+{{ endfor }}
+{qps | format_unescaped}
+</body>
+</html>
 "#;
@@ -0,0 +1,356 @@
+//! Input tensor metadata.
+//!
+//! Dynamo describes every tensor it fakeifies with `describe_storage`, `describe_tensor` and
+//! `describe_source` envelopes, which refer to each other by `describer_id` and id. This joins
+//! them into one page per compile id, and when a frame recompiles, compares its inputs with the
+//! previous compilation of the frame.
+
+use fxhash::FxHashMap;
+use serde_json::Value;
+
+use crate::recompiles::frame_key;
+use crate::types::*;
+
+const COLUMNS: &[&str] = &[
+    "Size",
+    "Stride",
+    "Storage offset",
+    "Dtype",
+    "Device",
+    "Layout",
+    "Dynamic dims",
+    "Requires grad",
+    "View of",
+    "Storage",
+    "Flags",
+];
+
+// Flags shown when set; mostly tensor subclasses and wrappers
+const FLAGS: &[&str] = &[
+    "is_parameter",
+    "is_inference",
+    "is_nested",
+    "is_sparse",
+    "is_mkldnn",
+    "is_functional",
+    "is_traceable_wrapper_subclass",
+    "is_functorch_wrapped",
+    "is_batchedtensor",
+    "is_legacy_batchedtensor",
+    "is_gradtrackingtensor",
+    "is_conj",
+    "is_neg",
+];
+
+type TensorKey = (u64, u64);
+
+#[derive(Default)]
+struct CompileTensors {
+    compile_id: String,
+    // (describer_id, id) -> describe_tensor
+    tensors: FxIndexMap<TensorKey, serde_json::Map<String, Value>>,
+    // (describer_id, id) -> size in bytes
+    storages: FxHashMap<TensorKey, u64>,
+    // (describer_id, storage id) -> the tensors viewing that storage
+    storage_tensors: FxHashMap<TensorKey, Vec<TensorKey>>,
+    // Sources of each tensor, e.g. L['x']
+    sources: FxHashMap<TensorKey, Vec<String>>,
+}
+
+#[derive(Default)]
+pub struct TensorMetadataIndex {
+    compiles: FxIndexMap<Option<CompileId>, CompileTensors>,
+}
+
+fn format_json(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(format_json)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        v => v.to_string(),
+    }
+}
+
+impl CompileTensors {
+    fn name(&self, key: &TensorKey) -> String {
+        match self.sources.get(key) {
+            Some(sources) => sources.join(", "),
+            None => format!("tensor {}", key.1),
+        }
+    }
+
+    // The cells of a tensor's row, in the order of COLUMNS
+    fn cells(&self, key: &TensorKey, desc: &serde_json::Map<String, Value>) -> Vec<String> {
+        let field = |name: &str| desc.get(name).map_or(String::new(), format_json);
+        let flag = |name: &str| desc.get(name).and_then(Value::as_bool).unwrap_or(false);
+        let id = |name: &str| desc.get(name).and_then(Value::as_u64);
+
+        let view_of = match id("base") {
+            Some(base) if flag("is_view") => self.name(&(key.0, base)),
+            _ => String::new(),
+        };
+        let storage = id("storage").map_or(String::new(), |storage| {
+            let mut cell = self
+                .storages
+                .get(&(key.0, storage))
+                .map_or("unknown size".to_string(), |size| format!("{} bytes", size));
+            let mut shared: Vec<&TensorKey> = self
+                .storage_tensors
+                .get(&(key.0, storage))
+                .into_iter()
+                .flatten()
+                .filter(|k| *k != key)
+                .collect();
+            shared.sort_by_key(|k| self.tensors.get_index_of(*k));
+            let shared: Vec<String> = shared.into_iter().map(|k| self.name(k)).collect();
+            if !shared.is_empty() {
+                cell.push_str(&format!(", shared with {}", shared.join(", ")));
+            }
+            cell
+        });
+        let flags: Vec<&str> = FLAGS.iter().copied().filter(|&f| flag(f)).collect();
+
+        vec![
+            field("size"),
+            field("stride"),
+            desc.get("storage_offset")
+                .map_or("0".to_string(), format_json),
+            field("dtype"),
+            field("device"),
+            desc.get("layout")
+                .map_or("torch.strided".to_string(), format_json),
+            field("dynamo_dynamic_indices"),
+            flag("requires_grad").to_string(),
+            view_of,
+            storage,
+            flags.join(", "),
+        ]
+    }
+
+    // The cells of the tensor with `source`, if there is one
+    fn cells_of(&self, source: &str) -> Option<Vec<String>> {
+        let (key, _) = self
+            .sources
+            .iter()
+            .find(|(_, sources)| sources.iter().any(|s| s == source))?;
+        Some(self.cells(key, self.tensors.get(key)?))
+    }
+}
+
+impl TensorMetadataIndex {
+    pub fn add_envelope(&mut self, e: &Envelope) {
+        if e.describe_tensor.is_none()
+            && e.describe_storage.is_none()
+            && e.describe_source.is_none()
+        {
+            return;
+        }
+        let c = self
+            .compiles
+            .entry(e.compile_id.clone())
+            .or_insert_with(|| CompileTensors {
+                compile_id: e
+                    .compile_id
+                    .as_ref()
+                    .map_or("(unknown)".to_string(), |c| c.to_string()),
+                ..Default::default()
+            });
+        if let Some(tensor) = &e.describe_tensor {
+            if let Ok(Value::Object(desc)) = serde_json::to_value(tensor) {
+                let key = (tensor.describer_id, tensor.id);
+                let storage = |desc: &serde_json::Map<String, Value>| {
+                    desc.get("storage").and_then(Value::as_u64)
+                };
+                // A tensor described again may have moved to another storage
+                if let Some(old) = c.tensors.get(&key).and_then(storage) {
+                    if let Some(tensors) = c.storage_tensors.get_mut(&(key.0, old)) {
+                        tensors.retain(|k| *k != key);
+                    }
+                }
+                if let Some(new) = storage(&desc) {
+                    c.storage_tensors.entry((key.0, new)).or_default().push(key);
+                }
+                c.tensors.insert(key, desc);
+            }
+        }
+        if let Some(storage) = &e.describe_storage {
+            if let Ok(Value::Object(desc)) = serde_json::to_value(storage) {
+                let field = |name: &str| desc.get(name).and_then(Value::as_u64);
+                if let (Some(describer_id), Some(id), Some(size)) =
+                    (field("describer_id"), field("id"), field("size"))
+                {
+                    c.storages.insert((describer_id, id), size);
+                }
+            }
+        }
+        if let Some(source) = &e.describe_source {
+            let sources = c
+                .sources
+                .entry((source.describer_id, source.id))
+                .or_default();
+            if !sources.contains(&source.source) {
+                sources.push(source.source.clone());
+            }
+        }
+    }
+
+    /// The tensor metadata page of a compile id, if any of its tensors were described
+    pub fn page_name(&self, compile_id: &Option<CompileId>) -> Option<String> {
+        let cid = compile_id.as_ref()?;
+        self.compiles
+            .get(compile_id)
+            .filter(|c| !c.tensors.is_empty())
+            .map(|_| format!("{}/tensor_metadata.html", cid.as_directory_name()))
+    }
+
+    // The last compilation of the same frame before `compile_id`
+    fn previous(&self, compile_id: &Option<CompileId>) -> Option<&Option<CompileId>> {
+        let (frame, frame_compile_id) = frame_key(compile_id)?;
+        self.compiles.keys().rev().find(|c| {
+            frame_key(c).is_some_and(|(f, n)| f == frame && n < frame_compile_id)
+                && self.page_name(c).is_some()
+        })
+    }
+
+    /// Build a page context for each compile id with a page, keyed by the page's filename
+    pub fn finish(
+        &self,
+        css: &'static str,
+        qps: &'static str,
+    ) -> Vec<(String, TensorMetadataContext)> {
+        self.compiles
+            .iter()
+            .filter_map(|(compile_id, c)| {
+                let page = self.page_name(compile_id)?;
+                let previous = self
+                    .previous(compile_id)
+                    .and_then(|p| Some((self.page_name(p)?, self.compiles.get(p)?)));
+
+                let mut changed_inputs = Vec::new();
+                let tensors = c
+                    .tensors
+                    .iter()
+                    .map(|(key, desc)| {
+                        let cells = c.cells(key, desc);
+                        let source = c.sources.get(key).and_then(|s| s.first());
+                        let before = previous
+                            .as_ref()
+                            .zip(source)
+                            .map(|((_, p), source)| p.cells_of(source));
+                        let cells: Vec<TensorCellContext> = cells
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, value)| {
+                                let before = before
+                                    .as_ref()
+                                    .and_then(|b| b.as_ref())
+                                    .map(|b| b[i].clone());
+                                TensorCellContext {
+                                    changed: before.as_ref().is_some_and(|b| *b != value),
+                                    before: before.unwrap_or_default(),
+                                    value,
+                                }
+                            })
+                            .collect();
+                        let changed: Vec<&str> = COLUMNS
+                            .iter()
+                            .zip(&cells)
+                            .filter(|(_, cell)| cell.changed)
+                            .map(|(column, _)| *column)
+                            .collect();
+                        // An input that the previous compilation didn't have
+                        let new = matches!(before, Some(None));
+                        if let Some(source) = source {
+                            if new {
+                                changed_inputs.push(format!("{} (new input)", source));
+                            } else if !changed.is_empty() {
+                                changed_inputs.push(format!(
+                                    "{} ({})",
+                                    source,
+                                    changed.join(", ").to_lowercase()
+                                ));
+                            }
+                        }
+                        TensorContext {
+                            name: c.name(key),
+                            has_source: source.is_some(),
+                            changed: new || !changed.is_empty(),
+                            cells,
+                        }
+                    })
+                    .collect();
+
+                Some((
+                    page,
+                    TensorMetadataContext {
+                        css,
+                        qps,
+                        compile_id: c.compile_id.clone(),
+                        columns: COLUMNS,
+                        tensors,
+                        has_previous: previous.is_some(),
+                        previous_compile_id: previous
+                            .as_ref()
+                            .map_or(String::new(), |(_, p)| p.compile_id.clone()),
+                        previous_page: previous.map_or(String::new(), |(page, _)| page),
+                        changed_inputs,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(index: &mut TensorMetadataIndex, frame_compile_id: u32, size: &str) {
+        for json in [
+            r#"{"describe_storage": {"id": 0, "describer_id": 0, "size": 64}}"#.to_string(),
+            format!(
+                r#"{{"describe_tensor": {{"id": 0, "describer_id": 0, "ndim": 1, "dtype": "torch.float32", "device": "device(type='cpu')", "size": {size}, "stride": [1], "storage": 0, "is_leaf": true}}}}"#
+            ),
+            r#"{"describe_source": {"describer_id": 0, "id": 0, "source": "L['x']"}}"#.to_string(),
+            r#"{"describe_tensor": {"id": 1, "describer_id": 0, "ndim": 1, "dtype": "torch.float32", "device": "device(type='cpu')", "size": [4], "stride": [1], "storage": 0, "is_view": true, "base": 0}}"#.to_string(),
+            r#"{"describe_source": {"describer_id": 0, "id": 1, "source": "L['y']"}}"#.to_string(),
+        ] {
+            index.add_envelope(&Envelope::for_test(frame_compile_id, &json));
+        }
+    }
+
+    #[test]
+    fn test_recompile_changes() {
+        let mut index = TensorMetadataIndex::default();
+        describe(&mut index, 0, "[16]");
+        describe(&mut index, 1, r#"["s0"]"#);
+        let pages = index.finish("", "");
+        assert_eq!(pages.len(), 2);
+
+        let (page, first) = &pages[0];
+        assert_eq!(page, "-_0_0_0/tensor_metadata.html");
+        assert!(!first.has_previous);
+        let y = &first.tensors[1];
+        assert_eq!(y.name, "L['y']");
+        assert_eq!(y.cells[8].value, "L['x']");
+        assert_eq!(y.cells[9].value, "64 bytes, shared with L['x']");
+
+        let (_, second) = &pages[1];
+        assert_eq!(second.previous_page, "-_0_0_0/tensor_metadata.html");
+        assert_eq!(second.changed_inputs, vec!["L['x'] (size)"]);
+        let x = &second.tensors[0];
+        assert!(x.changed);
+        assert_eq!(
+            (x.cells[0].before.as_str(), x.cells[0].value.as_str()),
+            ("[16]", "[s0]")
+        );
+        assert!(!second.tensors[1].changed);
+    }
+}
@@ -0,0 +1,243 @@
+//! Wall-clock timeline of compile ids.
+//!
+//! Each compile id spans from the first to the last line logged for it. Laid out over wall
+//! time, the gaps between compilations are the time spent running compiled code, which shows
+//! when recompiles happen during a training run.
+
+use chrono::NaiveDateTime;
+
+use crate::types::*;
+
+/// How many of the longest gaps between compilations are listed
+const GAPS_LIMIT: usize = 20;
+
+/// Width in % of the timeline given to the shortest bars, so that they stay visible
+const MIN_BAR_WIDTH: f64 = 0.2;
+
+struct Span {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+#[derive(Default)]
+pub struct CompileTimeline {
+    spans: FxIndexMap<Option<CompileId>, Span>,
+}
+
+fn seconds(from: NaiveDateTime, to: NaiveDateTime) -> f64 {
+    (to - from).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+}
+
+fn format_duration(s: f64) -> String {
+    if s >= 60.0 {
+        format!("{}m {:.1}s", (s / 60.0).floor(), s % 60.0)
+    } else {
+        format!("{:.3}s", s)
+    }
+}
+
+impl CompileTimeline {
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Record a line logged for `compile_id` at local time `at`
+    pub fn add(&mut self, compile_id: &Option<CompileId>, at: NaiveDateTime) {
+        // Lines logged outside of any frame have a compile id of [-/-]
+        if compile_id
+            .as_ref()
+            .is_none_or(|c| c.frame_id.is_none() && c.compiled_autograd_id.is_none())
+        {
+            return;
+        }
+        // Some envelopes leave out the attempt
+        let mut compile_id = compile_id.clone();
+        if let Some(c) = compile_id.as_mut() {
+            if c.frame_compile_id.is_some() {
+                c.attempt.get_or_insert(0);
+            }
+        }
+        let span = self
+            .spans
+            .entry(compile_id)
+            .or_insert(Span { start: at, end: at });
+        span.start = span.start.min(at);
+        span.end = span.end.max(at);
+    }
+
+    pub fn finish(
+        &self,
+        metrics_index: &CompilationMetricsIndex,
+        timezone: Option<String>,
+        year_source: &'static str,
+        css: &'static str,
+        qps: &'static str,
+    ) -> TimelineContext {
+        let mut spans: Vec<(&Option<CompileId>, &Span)> = self.spans.iter().collect();
+        spans.sort_by_key(|(_, span)| span.start);
+        let start = spans.first().map(|(_, s)| s.start).unwrap_or_default();
+        let end = spans.iter().map(|(_, s)| s.end).max().unwrap_or_default();
+        let total = seconds(start, end).max(f64::EPSILON);
+        let position = |from: NaiveDateTime, to: NaiveDateTime| {
+            let width = (100.0 * seconds(from, to) / total).max(MIN_BAR_WIDTH);
+            // Bars at the very end are moved left to fit
+            let left = (100.0 * seconds(start, from) / total).min(100.0 - width);
+            (format!("{:.3}", left), format!("{:.3}", width))
+        };
+
+        let compiles = spans
+            .iter()
+            .map(|(compile_id, span)| {
+                let (left, width) = position(span.start, span.end);
+                let cid = compile_id.as_ref().unwrap();
+                CompileSpanContext {
+                    compile_id: cid.to_string(),
+                    status: compile_status_class(metrics_index, compile_id),
+                    recompile: cid.frame_compile_id.is_some_and(|n| n > 0),
+                    start: span.start.format("%H:%M:%S%.3f").to_string(),
+                    duration: format_duration(seconds(span.start, span.end)),
+                    left,
+                    width,
+                }
+            })
+            .collect();
+
+        // Time between compilations, during which none was running
+        let mut gaps = Vec::new();
+        let mut compiling_until = start;
+        let mut previous: Option<&CompileId> = None;
+        for (compile_id, span) in &spans {
+            if span.start > compiling_until {
+                gaps.push((compiling_until, span.start, previous, compile_id.as_ref()));
+            }
+            if span.end >= compiling_until {
+                compiling_until = span.end;
+                previous = compile_id.as_ref();
+            }
+        }
+        let runtime = gaps
+            .iter()
+            .fold(0.0, |sum, (from, to, _, _)| sum + seconds(*from, *to));
+        let runtime_bars = gaps
+            .iter()
+            .map(|(from, to, _, _)| {
+                let (left, width) = position(*from, *to);
+                GapBarContext {
+                    title: format!(
+                        "{} to {}, {}",
+                        from.format("%H:%M:%S%.3f"),
+                        to.format("%H:%M:%S%.3f"),
+                        format_duration(seconds(*from, *to))
+                    ),
+                    left,
+                    width,
+                }
+            })
+            .collect();
+        let num_gaps = gaps.len();
+        gaps.sort_by(|a, b| {
+            seconds(b.0, b.1)
+                .partial_cmp(&seconds(a.0, a.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let longest_gaps = gaps
+            .into_iter()
+            .take(GAPS_LIMIT)
+            .map(|(from, to, after, before)| GapContext {
+                start: from.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+                duration: format_duration(seconds(from, to)),
+                after: after.map_or(String::new(), |c| c.to_string()),
+                before: before.map_or(String::new(), |c| c.to_string()),
+                recompile: before.is_some_and(|c| c.frame_compile_id.is_some_and(|n| n > 0)),
+            })
+            .collect();
+
+        let ticks = (0..=4)
+            .map(|i| {
+                let offset = chrono::Duration::microseconds((total * 1e6 * i as f64 / 4.0) as i64);
+                TimelineTickContext {
+                    left: format!("{}", i * 25),
+                    label: (start + offset).format("%H:%M:%S").to_string(),
+                }
+            })
+            .collect();
+
+        TimelineContext {
+            css,
+            qps,
+            timezone: timezone.unwrap_or_else(|| "the log's local time".to_string()),
+            year_source,
+            start: start.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            end: end.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            duration: format_duration(total),
+            compile_time: format_duration((total - runtime).max(0.0)),
+            runtime: format_duration(runtime),
+            num_gaps,
+            ticks,
+            runtime_bars,
+            compiles,
+            gaps_limit: GAPS_LIMIT,
+            longest_gaps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps() {
+        let compile_id = |frame_id, frame_compile_id| {
+            Some(CompileId {
+                compiled_autograd_id: None,
+                frame_id: Some(frame_id),
+                frame_compile_id: Some(frame_compile_id),
+                attempt: Some(0),
+            })
+        };
+        let at = |s: &str| {
+            NaiveDateTime::parse_from_str(&format!("2024-12-31 {}", s), "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+        };
+        let mut timeline = CompileTimeline::default();
+        timeline.add(&compile_id(0, 0), at("10:00:00"));
+        timeline.add(&compile_id(0, 0), at("10:00:10"));
+        // Overlaps with [0/0], so there is no gap between them
+        timeline.add(&compile_id(1, 0), at("10:00:05"));
+        timeline.add(&compile_id(1, 0), at("10:00:20"));
+        timeline.add(&compile_id(0, 1), at("10:01:20"));
+        timeline.add(&compile_id(0, 1), at("10:01:40"));
+        timeline.add(&None, at("12:00:00"));
+
+        let context = timeline.finish(
+            &CompilationMetricsIndex::default(),
+            None,
+            "the current year",
+            "",
+            "",
+        );
+        assert_eq!(context.duration, "1m 40.0s");
+        assert_eq!(context.runtime, "1m 0.0s");
+        assert_eq!(context.num_gaps, 1);
+        let gap = &context.longest_gaps[0];
+        assert_eq!(
+            (gap.after.as_str(), gap.before.as_str()),
+            ("[1/0]", "[0/1]")
+        );
+        assert!(gap.recompile);
+        let bars: Vec<(&str, &str)> = context
+            .compiles
+            .iter()
+            .map(|c| (c.left.as_str(), c.width.as_str()))
+            .collect();
+        assert_eq!(
+            bars,
+            vec![
+                ("0.000", "10.000"),
+                ("5.000", "15.000"),
+                ("80.000", "20.000")
+            ]
+        );
+    }
+}
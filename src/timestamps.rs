@@ -0,0 +1,320 @@
+//! Timestamps of glog lines.
+//!
+//! glog prefixes every line with the month, day and local time, but no year or timezone. The
+//! year is inferred from a chromium event near the start of the log, whose `ts` is in epoch
+//! microseconds, falling back to the modification time of the log file. The chromium event
+//! also gives the offset of the log's local time from UTC. While reading, the year advances
+//! when the log crosses New Year.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// How many lines at the start of a log are searched for a chromium event
+const PRESCAN_LINES: usize = 10_000;
+
+static GLOG_PREFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"^[VIWEC](?<month>\d{2})(?<day>\d{2}) ",
+        r"(?<hour>\d{2}):(?<minute>\d{2}):(?<second>\d{2}).(?<millisecond>\d{6}) "
+    ))
+    .unwrap()
+});
+
+/// Where the year of the log came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum YearSource {
+    ChromiumEvent,
+    FileModificationTime,
+    CurrentYear,
+}
+
+/// Month, day and time of day of a glog line
+#[derive(Debug, Clone, Copy)]
+struct GlogTime {
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    microsecond: u32,
+}
+
+impl GlogTime {
+    fn from_captures(caps: &regex::Captures) -> Option<Self> {
+        let field = |name: &str| -> Option<u32> { caps.name(name)?.as_str().parse().ok() };
+        Some(GlogTime {
+            month: field("month")?,
+            day: field("day")?,
+            hour: field("hour")?,
+            minute: field("minute")?,
+            second: field("second")?,
+            microsecond: field("millisecond")?,
+        })
+    }
+
+    fn in_year(&self, year: i32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)?.and_hms_micro_opt(
+            self.hour,
+            self.minute,
+            self.second,
+            self.microsecond,
+        )
+    }
+}
+
+/// Turns the glog prefixes of a log's lines into full timestamps
+#[derive(Debug, Clone)]
+pub struct GlogClock {
+    // Year of the most recent line
+    year: i32,
+    last_month: Option<u32>,
+    // Offset of the log's local time from UTC, if known
+    utc_offset: Option<FixedOffset>,
+    source: YearSource,
+}
+
+impl Default for GlogClock {
+    fn default() -> Self {
+        GlogClock {
+            year: Utc::now().year(),
+            last_month: None,
+            utc_offset: None,
+            source: YearSource::CurrentYear,
+        }
+    }
+}
+
+/// The year of the local time `local`, logged at `utc`, and the UTC offset of the local time
+/// rounded to 15 minutes
+fn year_and_offset(local: &GlogTime, utc: DateTime<Utc>) -> Option<(i32, FixedOffset)> {
+    (utc.year() - 1..=utc.year() + 1).find_map(|year| {
+        let offset = (local.in_year(year)? - utc.naive_utc()).num_seconds();
+        if offset.abs() > 14 * 3600 + 900 {
+            return None;
+        }
+        let offset = ((offset as f64 / 900.0).round() as i32) * 900;
+        Some((year, FixedOffset::east_opt(offset)?))
+    })
+}
+
+impl GlogClock {
+    /// Infer the year (and UTC offset) of the log at `path`. Falls back to the current year if
+    /// the file can't be read.
+    pub fn for_file(path: &Path) -> Self {
+        let Ok(file) = File::open(path) else {
+            return GlogClock::default();
+        };
+        let mtime = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+
+        let mut first: Option<GlogTime> = None;
+        let mut chromium: Option<(GlogTime, DateTime<Utc>)> = None;
+        let mut lines = BufReader::new(file)
+            .lines()
+            .take(PRESCAN_LINES)
+            .map_while(Result::ok)
+            .peekable();
+        while let Some(line) = lines.next() {
+            let Some(time) = GLOG_PREFIX
+                .captures(&line)
+                .and_then(|caps| GlogTime::from_captures(&caps))
+            else {
+                continue;
+            };
+            first.get_or_insert(time);
+            if !line.contains(r#""chromium_event": "#) {
+                continue;
+            }
+            let mut payload = String::new();
+            while let Some(payload_line) = lines.next_if(|l| l.starts_with('\t')) {
+                payload.push_str(&payload_line[1..]);
+                payload.push('\n');
+            }
+            let ts = serde_json::from_str::<Value>(&payload)
+                .ok()
+                .and_then(|event| event.get("ts")?.as_f64());
+            if let Some(utc) = ts.and_then(|ts| DateTime::from_timestamp_micros(ts as i64)) {
+                chromium = Some((time, utc));
+                break;
+            }
+        }
+        match first {
+            Some(first) => GlogClock::from_anchors(&first, chromium, mtime),
+            None => GlogClock::default(),
+        }
+    }
+
+    /// Infer the year of the first line of a log from a chromium event logged at `utc` on a
+    /// line with local time `local`, or else from the file modification time
+    fn from_anchors(
+        first: &GlogTime,
+        chromium: Option<(GlogTime, DateTime<Utc>)>,
+        mtime: Option<DateTime<Utc>>,
+    ) -> Self {
+        let mut clock = GlogClock::default();
+        if let Some((year, offset)) = chromium
+            .as_ref()
+            .and_then(|(local, utc)| Some((local, year_and_offset(local, *utc)?)))
+            .map(|(local, (year, offset))| {
+                // The log crossed New Year before the chromium event
+                if first.month >= local.month + 6 {
+                    (year - 1, offset)
+                } else {
+                    (year, offset)
+                }
+            })
+        {
+            clock.year = year;
+            clock.utc_offset = Some(offset);
+            clock.source = YearSource::ChromiumEvent;
+        } else if let Some(mtime) = mtime {
+            // The file was last written after its first line; allow a day for timezones
+            let latest = mtime.date_naive() + Duration::days(1);
+            let year = mtime.year();
+            clock.year = (year - 1..=year + 1)
+                .rev()
+                .find(|&y| first.in_year(y).is_some_and(|first| first.date() <= latest))
+                .unwrap_or(year);
+            clock.source = YearSource::FileModificationTime;
+        }
+        clock
+    }
+
+    /// The local date and time of a line, given the captures of a glog prefix regex with
+    /// `month`, `day`, `hour`, `minute`, `second` and `millisecond` groups. Lines must be
+    /// passed in log order so that the clock notices when the log crosses New Year.
+    pub fn datetime(&mut self, caps: &regex::Captures) -> Option<NaiveDateTime> {
+        let time = GlogTime::from_captures(caps)?;
+        let year = match self.last_month {
+            // Threads can log slightly out of order around midnight on New Year's Eve
+            Some(last) if time.month >= last + 6 => self.year - 1,
+            Some(last) if last >= time.month + 6 => {
+                self.year += 1;
+                self.last_month = Some(time.month);
+                self.year
+            }
+            _ => {
+                self.last_month = Some(time.month);
+                self.year
+            }
+        };
+        time.in_year(year)
+    }
+
+    /// Format a local time as ISO-8601. Converted to UTC when the offset is known; otherwise the
+    /// local time is written as is.
+    pub fn format(&self, datetime: &NaiveDateTime) -> String {
+        let utc = match self.utc_offset {
+            Some(offset) => *datetime - Duration::seconds(offset.local_minus_utc() as i64),
+            None => *datetime,
+        };
+        utc.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+    }
+
+    /// The timestamp of a line as ISO-8601; see `datetime` and `format`
+    pub fn timestamp(&mut self, caps: &regex::Captures) -> String {
+        self.datetime(caps)
+            .map_or(String::new(), |datetime| self.format(&datetime))
+    }
+
+    /// The timezone local times are in, e.g. "UTC-08:00", if known
+    pub fn timezone(&self) -> Option<String> {
+        self.utc_offset.map(|offset| format!("UTC{}", offset))
+    }
+
+    /// How the year of the log was determined, for display
+    pub fn year_source(&self) -> &'static str {
+        match self.source {
+            YearSource::ChromiumEvent => "the timestamp of a chromium event",
+            YearSource::FileModificationTime => "the modification time of the log file",
+            YearSource::CurrentYear => "the current year",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glog_time(month: u32, day: u32, hour: u32) -> GlogTime {
+        GlogTime {
+            month,
+            day,
+            hour,
+            minute: 0,
+            second: 0,
+            microsecond: 0,
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().into()
+    }
+
+    fn timestamps(clock: &mut GlogClock, lines: &[&str]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| clock.timestamp(&GLOG_PREFIX.captures(line).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_chromium_event_anchor() {
+        // 15:24 local is 23:24 UTC, so the log is in UTC-08:00
+        let mut clock = GlogClock::from_anchors(
+            &glog_time(12, 6, 15),
+            Some((
+                glog_time(12, 6, 15),
+                utc("2024-12-06T23:00:00.524020+00:00"),
+            )),
+            Some(utc("2026-03-01T00:00:00+00:00")),
+        );
+        assert_eq!(clock.timezone().as_deref(), Some("UTC-08:00"));
+        assert_eq!(
+            timestamps(&mut clock, &["V1206 15:24:50.600000 1 a.py:1] {}"]),
+            vec!["2024-12-06T23:24:50.600000Z"]
+        );
+
+        // A chromium event logged on January 1st local time, on December 31st UTC
+        let (year, offset) =
+            year_and_offset(&glog_time(1, 1, 1), utc("2024-12-31T16:00:00+00:00")).unwrap();
+        assert_eq!((year, offset.local_minus_utc()), (2025, 9 * 3600));
+    }
+
+    #[test]
+    fn test_new_year_rollover() {
+        // Started in December, the file was last written in January
+        let mut clock = GlogClock::from_anchors(
+            &glog_time(12, 31, 23),
+            None,
+            Some(utc("2025-01-01T02:00:00+00:00")),
+        );
+        assert_eq!(clock.year_source(), "the modification time of the log file");
+        assert_eq!(clock.timezone(), None);
+        assert_eq!(
+            timestamps(
+                &mut clock,
+                &[
+                    "V1231 23:59:59.000000 1 a.py:1] {}",
+                    "V0101 00:00:00.100000 1 a.py:1] {}",
+                    "V1231 23:59:59.900000 2 a.py:1] {}",
+                    "V0101 00:00:01.000000 1 a.py:1] {}",
+                ]
+            ),
+            vec![
+                "2024-12-31T23:59:59.000000Z",
+                "2025-01-01T00:00:00.100000Z",
+                "2024-12-31T23:59:59.900000Z",
+                "2025-01-01T00:00:01.000000Z",
+            ]
+        );
+    }
+}
@@ -19,6 +19,7 @@ pub type StackIndex = FxHashMap<Option<CompileId>, StackSummary>; // NB: attempt
 pub type SymbolicShapeSpecializationIndex =
     FxHashMap<Option<CompileId>, Vec<SymbolicShapeSpecializationMetadata>>;
 pub type GuardAddedFastIndex = FxHashMap<Option<CompileId>, Vec<GuardAddedFastMetadata>>;
+pub type DynamoGuardsIndex = FxHashMap<Option<CompileId>, Vec<DynamoGuard>>;
 pub type SymExprInfoIndex = FxHashMap<u64, SymExprInfoMetadata>;
 
 pub type FxIndexMap<K, V> = IndexMap<K, V, BuildHasherDefault<FxHasher>>;
@@ -105,9 +106,11 @@ pub struct RuntimeAnalysis {
     pub has_mismatched_graph_counts: bool,
 }
 
+static EVAL_WITH_KEY: Lazy<Regex> = Lazy::new(|| Regex::new(r"<eval_with_key>\.([0-9]+)").unwrap());
+
 pub fn extract_eval_with_key_id(filename: &str) -> Option<u64> {
-    let re = Regex::new(r"<eval_with_key>\.([0-9]+)").unwrap();
-    re.captures(filename)
+    EVAL_WITH_KEY
+        .captures(filename)
         .and_then(|caps| caps.get(1))
         .and_then(|m| m.as_str().parse::<u64>().ok())
 }
@@ -120,6 +123,8 @@ pub struct StackTrieNode {
     terminal: Vec<Option<CompileId>>,
     // Ordered map so that when we print we roughly print in chronological order
     children: FxIndexMap<FrameSummary, StackTrieNode>,
+    // Extra HTML shown next to the frame, e.g. graph break markers
+    markers: Vec<String>,
 }
 
 impl StackTrieNode {
@@ -142,18 +147,54 @@ impl StackTrieNode {
         return self.children.is_empty() && self.terminal.is_empty();
     }
 
+    /// Show `marker_html` next to the node for the last frame of `stack`, if the trie has it.
+    /// Identical markers are only shown once.
+    pub fn mark(&mut self, stack: &[FrameSummary], marker_html: String) {
+        let mut cur = self;
+        for frame in stack {
+            match cur.children.get_mut(frame) {
+                Some(child) => cur = child,
+                None => return,
+            }
+        }
+        if !cur.markers.contains(&marker_html) {
+            cur.markers.push(marker_html);
+        }
+    }
+
     pub fn fmt(
         &self,
         metrics_index: Option<&CompilationMetricsIndex>,
         caption: &str,
         open: bool,
+    ) -> Result<String, fmt::Error> {
+        self.fmt_with(metrics_index, caption, open, false)
+    }
+
+    /// Like `fmt`, but every subtree starts out collapsed and is only materialized into the
+    /// DOM (from a `<template>`) when first expanded. Used by the lazy index for huge tries.
+    pub fn fmt_collapsed(
+        &self,
+        metrics_index: Option<&CompilationMetricsIndex>,
+        caption: &str,
+        open: bool,
+    ) -> Result<String, fmt::Error> {
+        self.fmt_with(metrics_index, caption, open, true)
+    }
+
+    fn fmt_with(
+        &self,
+        metrics_index: Option<&CompilationMetricsIndex>,
+        caption: &str,
+        open: bool,
+        collapse: bool,
     ) -> Result<String, fmt::Error> {
         let mut f = String::new();
         write!(f, "<details{}>", if open { " open" } else { "" })?;
         write!(f, "<summary>{}</summary>", caption)?;
         write!(f, "<div class='stack-trie'>")?;
         write!(f, "<ul>")?;
-        self.fmt_inner(&mut f, metrics_index)?;
+        self.fmt_inner(&mut f, metrics_index, collapse)?;
         write!(f, "</ul>")?;
         write!(f, "</div>")?;
         write!(f, "</details>")?;
@@ -164,25 +205,14 @@ impl StackTrieNode {
         &self,
         f: &mut String,
         mb_metrics_index: Option<&CompilationMetricsIndex>,
+        collapse: bool,
     ) -> fmt::Result {
         for (frame, node) in self.children.iter() {
             let mut star = String::new();
             for t in &node.terminal {
                 if let Some(c) = t {
                     let ok_class = mb_metrics_index.map_or("status-missing", |metrics_index| {
-                        metrics_index.get(t).map_or("status-missing", |m| {
-                            if m.iter().any(|n| n.fail_type.is_some()) {
-                                "status-error"
-                            } else if m.iter().any(|n| n.graph_op_count.unwrap_or(0) == 0) {
-                                "status-empty"
-                            } else if m.iter().any(|n| {
-                                !n.restart_reasons.as_ref().map_or(false, |o| o.is_empty())
-                            }) {
-                                "status-break"
-                            } else {
-                                "status-ok"
-                            }
-                        })
+                        compile_status_class(metrics_index, t)
                     });
                     write!(
                         star,
@@ -194,27 +224,58 @@ impl StackTrieNode {
                     write!(star, "(unknown) ")?;
                 }
             }
+            for marker in &node.markers {
+                write!(star, "{} ", marker)?;
+            }
 
             if self.children.len() > 1 {
                 // If the node has multiple children, increase the indent and print a hyphen
                 writeln!(
                     f,
-                    "<li><span onclick='toggleList(this)' class='marker'></span>{star}",
+                    "<li><span onclick='toggleList(this)' class='marker{}'></span>{star}",
+                    if collapse { " collapsed" } else { "" },
                     star = star
                 )?;
-                writeln!(f, "{}<ul>", frame)?;
-                node.fmt_inner(f, mb_metrics_index)?;
-                write!(f, "</ul></li>")?;
+                if collapse {
+                    writeln!(f, "{}<template><ul>", frame)?;
+                    node.fmt_inner(f, mb_metrics_index, collapse)?;
+                    write!(f, "</ul></template></li>")?;
+                } else {
+                    writeln!(f, "{}<ul>", frame)?;
+                    node.fmt_inner(f, mb_metrics_index, collapse)?;
+                    write!(f, "</ul></li>")?;
+                }
             } else {
                 // If the node has only one child, don't increase the indent and don't print a hyphen
                 writeln!(f, "<li>{star}{}</li>", frame, star = star)?;
-                node.fmt_inner(f, mb_metrics_index)?;
+                node.fmt_inner(f, mb_metrics_index, collapse)?;
             }
         }
         Ok(())
     }
 }
 
+/// CSS class summarizing how a compilation went, as shown in the stack trie legend
+pub fn compile_status_class(
+    metrics_index: &CompilationMetricsIndex,
+    compile_id: &Option<CompileId>,
+) -> &'static str {
+    metrics_index.get(compile_id).map_or("status-missing", |m| {
+        if m.iter().any(|n| n.fail_type.is_some()) {
+            "status-error"
+        } else if m.iter().any(|n| n.graph_op_count.unwrap_or(0) == 0) {
+            "status-empty"
+        } else if m
+            .iter()
+            .any(|n| n.restart_reasons.as_ref().is_none_or(|o| !o.is_empty()))
+        {
+            "status-break"
+        } else {
+            "status-ok"
+        }
+    })
+}
+
 #[derive(Eq, PartialEq, Hash, Deserialize, Serialize, Debug, Clone)]
 pub struct CompileId {
     pub compiled_autograd_id: Option<u32>,
@@ -324,6 +385,46 @@ impl std::fmt::Display for Stats {
     }
 }
 
+/// Time spent per envelope type, reported by --profile. Each envelope is charged from the
+/// start of its glog line to the start of the next one, so payload lines are included.
+#[derive(Default, Debug)]
+pub struct EnvelopeProfile {
+    pub by_type: FxHashMap<&'static str, (u64, std::time::Duration)>,
+}
+
+impl EnvelopeProfile {
+    pub fn record(&mut self, envelope_type: &'static str, elapsed: std::time::Duration) {
+        let entry = self.by_type.entry(envelope_type).or_default();
+        entry.0 += 1;
+        entry.1 += elapsed;
+    }
+}
+
+impl Display for EnvelopeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows: Vec<_> = self.by_type.iter().collect();
+        rows.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+        let total: std::time::Duration = rows.iter().map(|(_, (_, t))| *t).sum();
+        writeln!(
+            f,
+            "{:<45} {:>10} {:>12} {:>7} {:>10}",
+            "envelope type", "count", "total ms", "%", "us/each"
+        )?;
+        for (envelope_type, (count, time)) in rows {
+            writeln!(
+                f,
+                "{:<45} {:>10} {:>12.1} {:>6.1}% {:>10.1}",
+                envelope_type,
+                count,
+                time.as_secs_f64() * 1e3,
+                100.0 * time.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON),
+                time.as_secs_f64() * 1e6 / *count as f64,
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Hash, Eq, PartialEq, Deserialize, Serialize, Clone)]
 pub struct FrameSummary {
     pub filename: u32,
@@ -333,13 +434,14 @@ pub struct FrameSummary {
     pub uninterned_filename: Option<String>,
 }
 
+static SEED_NSPID: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^/]+-seed-nspid[^/]+/").unwrap());
+
 pub fn simplify_filename<'a>(filename: &'a str) -> &'a str {
     let parts: Vec<&'a str> = filename.split("#link-tree/").collect();
     if parts.len() > 1 {
         return parts[1];
     }
-    let re = Regex::new(r"[^/]+-seed-nspid[^/]+/").unwrap();
-    if let Some(captures) = re.captures(filename) {
+    if let Some(captures) = SEED_NSPID.captures(filename) {
         if let Some(capture) = captures.get(0) {
             return &filename[capture.end()..];
         }
@@ -422,7 +524,8 @@ pub struct GraphDumpMetadata {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DynamoOutputGraphMetadata {
-    _sizes: Option<FxHashMap<String, Vec<SymInt>>>,
+    // Sizes of the graph's tensors, usually a list of ints and symbols
+    pub sizes: Option<serde_json::Map<String, Value>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -447,7 +550,7 @@ pub struct ArtifactMetadata {
     pub encoding: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct CompilationMetricsMetadata {
     // Other information like frame_key are already in envelope
     pub co_name: Option<String>,
@@ -465,6 +568,13 @@ pub struct CompilationMetricsMetadata {
     pub backend_compile_time_s: Option<f64>,
     pub inductor_compile_time_s: Option<f64>,
     pub code_gen_time_s: Option<f64>,
+    // Newer PyTorch logs the phase times above under these names instead
+    pub dynamo_cumulative_compile_time_us: Option<u64>,
+    pub aot_autograd_cumulative_compile_time_us: Option<u64>,
+    pub inductor_cumulative_compile_time_us: Option<u64>,
+    pub inductor_code_gen_cumulative_compile_time_us: Option<u64>,
+    // Time spent autotuning while compiling
+    pub compile_time_autotune_time_us: Option<u64>,
     pub fail_type: Option<String>,
     pub fail_reason: Option<String>,
     pub fail_user_frame_filename: Option<String>,
@@ -544,6 +654,16 @@ pub struct SymbolicShapePropagateRealTensorMetadata {
     pub prefix: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateSymbolMetadata {
+    pub symbol: Option<String>,
+    pub val: Option<String>,
+    pub vr: Option<String>,
+    pub source: Option<String>,
+    pub user_stack: Option<StackSummary>,
+    pub stack: Option<StackSummary>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UnbackedSymbolMetadata {
     pub symbol: Option<String>,
@@ -677,6 +797,88 @@ impl Display for ExportFailure {
     }
 }
 
+/// One row of the graph break leaderboard: breaks with the same kind, normalised reason and
+/// user source location
+#[derive(Debug, Serialize)]
+pub struct GraphBreakGroupContext {
+    pub rank: usize,
+    pub kind: String,
+    pub reason: String,
+    // The first break's reason, before normalisation
+    pub example: String,
+    pub location: String,
+    pub count: usize,
+    // Links to the metrics pages of the affected compile ids
+    pub compile_ids: Vec<String>,
+    pub wasted_time_s: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphBreakLocationContext {
+    pub location: String,
+    pub count: usize,
+    // Ranks of the leaderboard rows at this location
+    pub groups: Vec<usize>,
+    pub wasted_time_s: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphBreaksContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub num_breaks: usize,
+    pub groups: Vec<GraphBreakGroupContext>,
+    pub locations: Vec<GraphBreakLocationContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardChangeContext {
+    pub before: String,
+    pub after: String,
+}
+
+/// Guards of a compilation compared to the previous compilation of the same frame
+#[derive(Debug, Default, Serialize)]
+pub struct GuardDiffContext {
+    pub unchanged: usize,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+    // Guards that only differ in numbers, e.g. a size that changed
+    pub changed: Vec<GuardChangeContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TensorChangeContext {
+    pub source: String,
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecompileContext {
+    // Links to the compilation's metrics page
+    pub compile_id: String,
+    pub reasons: Vec<String>,
+    pub num_guards: usize,
+    pub has_guard_diff: bool,
+    pub guard_diff: GuardDiffContext,
+    pub tensor_changes: Vec<TensorChangeContext>,
+    pub cache_size: String,
+    pub accumulated_cache_size: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecompilesContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub frame: String,
+    pub stack_html: String,
+    pub num_compilations: usize,
+    pub compilations: Vec<RecompileContext>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct RestartsAndFailuresContext {
     // Serialized versions of (CompileId, FailureReason)
@@ -749,6 +951,7 @@ pub struct Envelope {
     pub symbolic_shape_specialization: Option<SymbolicShapeSpecializationMetadata>,
     pub propagate_real_tensors_provenance: Option<SymbolicShapePropagateRealTensorMetadata>,
     pub guard_added: Option<SymbolicShapePropagateRealTensorMetadata>,
+    pub create_symbol: Option<CreateSymbolMetadata>,
     pub create_unbacked_symbol: Option<UnbackedSymbolMetadata>,
     pub expression_created: Option<SymExprInfoMetadata>,
     pub missing_fake_kernel: Option<FakeKernelMetadata>,
@@ -765,13 +968,26 @@ pub struct Envelope {
     pub _other: FxHashMap<String, Value>,
 }
 
+#[cfg(test)]
+impl Envelope {
+    /// The envelope with the fields in `json`, logged by compile `[0/frame_compile_id]`
+    pub fn for_test(frame_compile_id: u32, json: &str) -> Self {
+        let mut value: serde_json::Value = serde_json::from_str(json).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        obj.insert("frame_id".to_string(), 0.into());
+        obj.insert("frame_compile_id".to_string(), frame_compile_id.into());
+        obj.insert("attempt".to_string(), 0.into());
+        serde_json::from_value(value).unwrap()
+    }
+}
+
 type MetaTensorId = u64;
 type MetaStorageId = u64;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TensorDesc {
-    id: MetaTensorId,
-    describer_id: u64,
+    pub id: MetaTensorId,
+    pub describer_id: u64,
     ndim: u64,
     dtype: String,
     device: String,
@@ -843,9 +1059,9 @@ pub struct StorageDesc {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SourceDesc {
-    describer_id: u64,
-    id: MetaTensorId,
-    source: String,
+    pub describer_id: u64,
+    pub id: MetaTensorId,
+    pub source: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -857,8 +1073,520 @@ pub struct DynamoGuard {
 
 #[derive(Debug, Serialize)]
 pub struct DynamoGuardsContext {
-    pub guards: Vec<DynamoGuard>,
+    pub num_guards: usize,
+    pub categories: Vec<GuardCategoryContext>,
+    pub qps: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserStackContext {
+    pub anchor: String,
+    pub html: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CppGuardsContext {
+    pub javascript: &'static str,
+    pub qps: &'static str,
+    pub num_guards: usize,
+    pub header: String,
+    pub footer: String,
+    pub tree_html: String,
+    // User stacks of the leaf guards, from dynamo_guards
+    pub stacks: Vec<UserStackContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardCategoryContext {
+    pub name: &'static str,
+    // Anchor on the page
+    pub id: String,
+    pub count: usize,
+    pub guards: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileGuardsContext {
+    // Links to the compile's metrics page when it has one
+    pub compile_id: String,
+    pub num_guards: usize,
+    // Per category, in the order of GuardAnalyticsContext::category_names
+    pub counts: Vec<usize>,
+    pub guard_count: String,
+    pub shape_env_guard_count: String,
+    // Why the compile has unusually many guards, empty if it doesn't
+    pub unusual: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdenticalGuardContext {
+    pub code: String,
+    pub category: &'static str,
+    pub num_compiles: usize,
+    pub compile_ids: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardLocationContext {
+    pub location: String,
+    pub count: usize,
+    pub categories: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardAnalyticsContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub num_guards: usize,
+    pub num_compiles: usize,
+    pub num_unusual: usize,
+    pub category_names: Vec<&'static str>,
+    pub totals: Vec<usize>,
+    pub compiles: Vec<CompileGuardsContext>,
+    // Number of guards installed by more than one compile; only the most shared are listed
+    pub num_identical: usize,
+    pub identical: Vec<IdenticalGuardContext>,
+    pub locations: Vec<GuardLocationContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileTimePhaseContext {
+    pub name: &'static str,
+    pub total_s: String,
+    // Number of compiles that reported the phase
+    pub num_compiles: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowCompileContext {
+    pub compile_id: String,
+    pub entire_frame_compile_time_s: String,
+    pub backend_compile_time_s: String,
+    pub inductor_compile_time_s: String,
+    pub code_gen_time_s: String,
+    pub bwd_inductor_compile_time_s: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheOutcomeContext {
+    pub name: String,
+    pub count: usize,
+    pub time_saved_ms: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimedEventContext {
+    pub name: String,
+    pub count: usize,
+    pub total_ms: String,
+    pub self_ms: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileEventsContext {
+    pub compile_id: String,
+    pub total_ms: String,
+    pub cache_ms: String,
+    pub events: Vec<TimedEventContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileTimeContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub num_compiles: usize,
+    pub phases: Vec<CompileTimePhaseContext>,
+    pub slowest_limit: usize,
+    pub slowest: Vec<SlowCompileContext>,
+    // Summed over the outermost dynamo_timed events of every compile id
+    pub total_ms: String,
+    pub cache_ms: String,
+    pub cache_percent: String,
+    pub cache_outcomes: Vec<CacheOutcomeContext>,
+    pub compiles: Vec<CompileEventsContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileSpanContext {
+    pub compile_id: String,
+    pub status: &'static str,
+    pub recompile: bool,
+    pub start: String,
+    pub duration: String,
+    // Position and width of the bar, in % of the timeline
+    pub left: String,
+    pub width: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GapBarContext {
+    pub title: String,
+    pub left: String,
+    pub width: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GapContext {
+    pub start: String,
+    pub duration: String,
+    // The compile ids that ran before and after the gap
+    pub after: String,
+    pub before: String,
+    pub recompile: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineTickContext {
+    pub left: String,
+    pub label: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub timezone: String,
+    pub year_source: &'static str,
+    pub start: String,
+    pub end: String,
+    pub duration: String,
+    pub compile_time: String,
+    pub runtime: String,
+    pub num_gaps: usize,
+    pub ticks: Vec<TimelineTickContext>,
+    pub runtime_bars: Vec<GapBarContext>,
+    pub compiles: Vec<CompileSpanContext>,
+    pub gaps_limit: usize,
+    pub longest_gaps: Vec<GapContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SymbolContext {
+    pub symbol: String,
+    pub kind: &'static str,
+    pub value: String,
+    pub range: String,
+    pub sources: String,
+    pub location: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpecializationContext {
+    pub compile_id: String,
+    pub symbol: String,
+    pub sources: String,
+    pub value: String,
+    pub reason: String,
+    pub location: String,
+    pub user_stack_html: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShapeGuardContext {
+    pub expr: String,
+    pub kind: &'static str,
+    pub location: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileShapesContext {
+    pub compile_id: String,
+    /// Path of the compile id's dynamic shapes page, relative to the output directory
+    pub page: String,
+    pub num_symbols: usize,
+    pub num_specializations: usize,
+    pub num_guards: usize,
+    pub symbols: Vec<SymbolContext>,
+    pub specializations: Vec<SpecializationContext>,
+    pub guards: Vec<ShapeGuardContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShapeSuggestionContext {
+    pub compile_id: String,
+    pub source: String,
+    pub dim: u32,
+    pub sizes: String,
+    pub action: String,
+    pub why: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DynamicShapesContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub title: String,
+    /// Set for the page of the whole run, which links to the page of each compile id
+    pub run_wide: bool,
+    pub compiles: Vec<CompileShapesContext>,
+    pub suggestions: Vec<ShapeSuggestionContext>,
+    pub specializations: Vec<SpecializationContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TensorCellContext {
+    pub value: String,
+    // The value in the previous compilation of the frame, if it changed
+    pub before: String,
+    pub changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TensorContext {
+    // Sources of the tensor, or its id if it has none
+    pub name: String,
+    pub has_source: bool,
+    pub changed: bool,
+    pub cells: Vec<TensorCellContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TensorMetadataContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub compile_id: String,
+    pub columns: &'static [&'static str],
+    pub tensors: Vec<TensorContext>,
+    pub has_previous: bool,
+    pub previous_compile_id: String,
+    pub previous_page: String,
+    // Inputs whose metadata changed since the previous compilation, with what changed
+    pub changed_inputs: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpCountContext {
+    pub target: String,
+    pub count: usize,
+    // How many of the graphs call the target
+    pub num_graphs: usize,
+    // Share of the call nodes
+    pub share: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphSummaryContext {
+    pub kind: &'static str,
+    // Node counts by opcode, in the order of CompileGraphStatsContext::opcodes
+    pub opcodes: Vec<usize>,
+    pub num_nodes: usize,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub subgraphs: usize,
+    pub top_targets: String,
+    pub targets: Vec<OpCountContext>,
+    // Tensor sizes recorded with the graph, only for dynamo_output_graph
+    pub sizes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileGraphStatsContext {
+    pub css: &'static str,
     pub qps: &'static str,
+    pub compile_id: String,
+    pub opcodes: &'static [&'static str],
+    pub graphs: Vec<GraphSummaryContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphKindContext {
+    pub kind: &'static str,
+    pub num_graphs: usize,
+    pub num_nodes: usize,
+    pub mean_nodes: String,
+    pub max_nodes: usize,
+    pub targets: Vec<OpCountContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileGraphSizesContext {
+    pub compile_id: String,
+    pub page: String,
+    // Node counts of the compile id's graphs, per graph kind in the order of
+    // GraphStatsContext::graph_kinds
+    pub num_nodes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphStatsContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub graph_kinds: &'static [&'static str],
+    pub run_top_targets: usize,
+    pub kinds: Vec<GraphKindContext>,
+    pub compiles: Vec<CompileGraphSizesContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KernelKindContext {
+    pub kind: &'static str,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelRowContext {
+    pub name: String,
+    pub page: String,
+    pub kind: &'static str,
+    // From triton_kernel_info, empty when the kernel has none
+    pub cache_state: String,
+    pub num_configs: String,
+    pub compile_time: String,
+    pub only_config: String,
+    pub num_post_grad_nodes: String,
+    pub num_launches: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileKernelsContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub compile_id: String,
+    pub counts: Vec<KernelKindContext>,
+    pub kernels: Vec<KernelRowContext>,
+    pub slowest_limit: usize,
+    pub slowest: Vec<KernelRowContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KernelInfoContext {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KernelLaunchContext {
+    pub line: usize,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KernelContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub compile_id: String,
+    pub kernels_page: String,
+    pub name: String,
+    pub kind: &'static str,
+    // Output code page, and the line the kernel is defined on (empty for extern kernels)
+    pub output_code: String,
+    pub line: String,
+    pub info: Vec<KernelInfoContext>,
+    pub post_grad_nodes: String,
+    pub source_nodes: String,
+    pub launches: Vec<KernelLaunchContext>,
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutotuneKernelContext {
+    pub compile_id: String,
+    pub name: String,
+    pub cache_state: String,
+    pub candidates: String,
+    // Only known when there was a single config to choose from
+    pub chosen_config: String,
+    pub compile_ms: String,
+    pub benchmark_ms: String,
+    pub num_benchmarks: usize,
+    pub total_ms: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompileAutotuningContext {
+    pub compile_id: String,
+    pub kernels_page: String,
+    pub compile_time_autotune_ms: String,
+    pub runtime_autotune_ms: String,
+    pub other_benchmark_ms: String,
+    pub kernels: Vec<AutotuneKernelContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutotuneCacheStateContext {
+    pub state: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutotuningContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub num_kernels: usize,
+    pub cache_states: Vec<AutotuneCacheStateContext>,
+    pub compile_ms: String,
+    pub benchmark_ms: String,
+    pub other_benchmark_ms: String,
+    pub total_ms: String,
+    // Totals of compilation metrics, empty when no compile reported them
+    pub compile_time_autotune_ms: String,
+    pub runtime_autotune_ms: String,
+    pub slowest_limit: usize,
+    pub slowest: Vec<AutotuneKernelContext>,
+    pub compiles: Vec<CompileAutotuningContext>,
+}
+
+/// FX graph nodes of one graph that were traced from a user source line
+#[derive(Debug, Serialize)]
+pub struct SourceNodesContext {
+    pub compile_id: String,
+    pub graph: &'static str,
+    pub page: String,
+    pub nodes: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceKernelContext {
+    pub compile_id: String,
+    pub name: String,
+    /// The compile id's kernel table, if it has one
+    pub page: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceGraphBreakContext {
+    pub compile_id_html: String,
+    pub kind: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceGuardContext {
+    pub compile_id: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceLineContext {
+    pub anchor: String,
+    /// Anchors of the same line in files merged into this one
+    pub alias_anchors: Vec<String>,
+    pub line: u32,
+    pub function: String,
+    pub code: String,
+    pub compile_ids: String,
+    pub nodes: Vec<SourceNodesContext>,
+    pub kernels: Vec<SourceKernelContext>,
+    pub graph_breaks: Vec<SourceGraphBreakContext>,
+    pub num_guards: usize,
+    pub guards: Vec<SourceGuardContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceFileContext {
+    pub anchor: String,
+    pub path: String,
+    pub lines: Vec<SourceLineContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceIndexContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub num_files: usize,
+    pub num_lines: usize,
+    pub tree_html: String,
+    pub files: Vec<SourceFileContext>,
 }
 
 #[derive(Debug, Serialize)]
@@ -874,7 +1602,28 @@ pub struct IndexContext {
     pub has_chromium_events: bool,
     pub qps: &'static str,
     pub has_inductor_provenance: bool,
-    pub directory_names: Vec<String>,
+    pub provenance_pages: Vec<String>,
+    pub num_compile_ids: usize,
+    /// If set, `directory` is empty and build products are loaded per compile id on demand
+    pub lazy_directory: bool,
+    pub lazy_directory_json: String,
+    pub lazy_directory_javascript: &'static str,
+    /// If set, search_index.js was written and index.html gets a search box
+    pub has_search_index: bool,
+    pub search_javascript: &'static str,
+    /// If set, guards.html was written
+    pub has_guard_analytics: bool,
+    /// If set, compile_time.html was written
+    pub has_compile_time: bool,
+    /// If set, timeline.html was written
+    pub has_timeline: bool,
+    /// If set, dynamic_shapes.html was written
+    pub has_dynamic_shapes: bool,
+    /// If set, graph_stats.html was written
+    pub has_graph_stats: bool,
+    pub has_autotuning: bool,
+    /// If set, user_source.html was written
+    pub has_user_source: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -915,6 +1664,8 @@ pub struct ProvenanceContext<'a> {
     pub output_code_content: String,
     pub aot_code_content: String,
     pub line_mappings_content: String,
+    pub num_unresolved_mappings: usize,
+    pub unresolved_mappings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
@@ -947,6 +1698,49 @@ pub struct ExecOrderSummary {
     pub ranks_cache_str: String,
 }
 
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct CompileTimeSummary {
+    pub percentile: String,
+    pub threshold_s: String,
+    pub has_flagged: bool,
+    /// Ranks whose total compile time is beyond the percentile, e.g. "3, 5"
+    pub flagged_ranks: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DivergenceEventContext {
+    /// Position in the rank's event stream, starting at 1
+    pub number: usize,
+    pub description: String,
+    pub divergent: bool,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DivergenceGroupContext {
+    /// Ranks that logged the same event at the divergence, e.g. "0, 2"
+    pub ranks: String,
+    /// The representative of the group
+    pub rank: u32,
+    pub event: String,
+    /// The compile id of the event, or the last compile id if the rank has no more events
+    pub compile_id: String,
+    pub stack: String,
+    pub recompile_reasons: Vec<String>,
+    pub cache_status: String,
+    pub guards_url: Option<String>,
+    pub comparison_url: Option<String>,
+    /// Events of the representative around the divergence
+    pub context: Vec<DivergenceEventContext>,
+}
+
+/// The first compile event the ranks disagree on
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FirstDivergence {
+    /// Position in the event streams, starting at 1
+    pub number: usize,
+    pub groups: Vec<DivergenceGroupContext>,
+}
+
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Diagnostics {
     pub divergence: DivergenceFlags,
@@ -956,6 +1750,11 @@ pub struct Diagnostics {
     pub collective_groups: Vec<DivergenceGroup>,
     pub tensor_meta_groups: Vec<DivergenceGroup>,
     pub exec_order: Option<ExecOrderSummary>,
+    /// If set, rank_comparison.html was written
+    pub rank_comparison: bool,
+    /// If set, compile_stragglers.html was written
+    pub compile_time: Option<CompileTimeSummary>,
+    pub first_divergence: Option<FirstDivergence>,
 }
 
 #[derive(Serialize)]
@@ -970,3 +1769,113 @@ pub struct MultiRankContext<'a> {
     pub compile_id_divergence: bool,
     pub diagnostics: Diagnostics,
 }
+
+#[derive(Serialize)]
+pub struct RankGroupContext {
+    /// The representative of the group
+    pub rank: u32,
+    pub ranks: String,
+}
+
+#[derive(Serialize)]
+pub struct RankComparisonRowContext {
+    pub compile_id: String,
+    pub url: String,
+    pub differs: bool,
+    /// Representatives that did not compile this compile id
+    pub missing_on: String,
+    /// Per artifact kind: "identical", "differs" or "-" if no representative has it
+    pub statuses: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct RankComparisonContext<'a> {
+    pub css: &'a str,
+    pub qps: &'a str,
+    pub baseline: u32,
+    pub groups: &'a [RankGroupContext],
+    pub num_differing: usize,
+    pub rows: Vec<RankComparisonRowContext>,
+}
+
+#[derive(Serialize)]
+pub struct RankDiffContext {
+    pub rank: u32,
+    pub differs: bool,
+    pub status: String,
+    pub diff_html: String,
+    pub baseline_url: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RankArtifactComparisonContext {
+    pub name: &'static str,
+    pub diffs: Vec<RankDiffContext>,
+}
+
+#[derive(Serialize)]
+pub struct RankComparisonCompileContext<'a> {
+    pub css: &'a str,
+    pub qps: &'a str,
+    pub compile_id: String,
+    pub baseline: u32,
+    pub groups: &'a [RankGroupContext],
+    pub missing_on: String,
+    pub artifacts: Vec<RankArtifactComparisonContext>,
+}
+
+#[derive(Serialize)]
+pub struct RankCompileTimeContext {
+    pub rank: u32,
+    pub total_s: String,
+    pub over_median_s: String,
+    pub num_compiles: usize,
+    pub cache_hits: usize,
+    pub cache_lookups: usize,
+    pub cache_hit_ratio: String,
+    pub slowest_compile_id: String,
+    pub flagged: bool,
+}
+
+#[derive(Serialize)]
+pub struct CompileIdSpreadContext {
+    /// Position in compile order, for sorting
+    pub order: usize,
+    pub compile_id: String,
+    pub num_ranks: usize,
+    pub min_s: String,
+    pub median_s: String,
+    pub max_s: String,
+    pub spread_s: String,
+    pub slowest_rank: u32,
+}
+
+#[derive(Serialize)]
+pub struct HeatmapCellContext {
+    pub time_s: String,
+    pub style: String,
+}
+
+#[derive(Serialize)]
+pub struct HeatmapRowContext {
+    pub compile_id: String,
+    pub cells: Vec<HeatmapCellContext>,
+}
+
+#[derive(Serialize)]
+pub struct CompileStragglersContext {
+    pub css: &'static str,
+    pub qps: &'static str,
+    pub javascript: &'static str,
+    pub percentile: String,
+    pub threshold_s: String,
+    pub median_s: String,
+    pub flagged_ranks: String,
+    pub rank_headers: Vec<u32>,
+    pub num_compiles: usize,
+    pub num_heatmap_compiles: usize,
+    pub ranks: Vec<RankCompileTimeContext>,
+    pub compiles: Vec<CompileIdSpreadContext>,
+    pub heatmap: Vec<HeatmapRowContext>,
+}
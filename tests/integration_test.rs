@@ -174,11 +174,15 @@ fn test_parse_compilation_metrics() {
     );
 
     // Check that exactly the expected payload files exist (no more, no less)
-    // With conditional payload writing, only payloads not handled by parsers are written
+    // With conditional payload writing, only payloads not handled by parsers are written,
+    // plus the raw payloads of graphs and code rendered as html
     let expected_payload_hashes: std::collections::HashSet<&str> = [
         "11726d08889974e57b12edee2812504e",
         "29e35548d59d0e446f0c8a3f3010cc72",
         "e18e1bcb67140c0a67427a6119556f7a",
+        "2cbc38baafc04c3a903e8d659e78c854",
+        "2a35823c4e6ce78bcc3a8557f11f8a52",
+        "b02b7e74ec144d0daf4087e58131a444",
     ]
     .iter()
     .cloned()
@@ -454,8 +458,8 @@ fn test_export_guard_report() {
 #[test]
 fn test_provenance_tracking_aot_cuda() {
     let expected_files = [
-        "-_-_-_-/before_pre_grad_graph_0.txt",
-        "-_-_-_-/after_post_grad_graph_6.txt",
+        "-_-_-_-/before_pre_grad_graph_0.html",
+        "-_-_-_-/after_post_grad_graph_6.html",
         "provenance_tracking_-_-_-_-.html",
         "-_-_-_-/inductor_provenance_tracking_node_mappings_12.json",
     ];
@@ -554,8 +558,8 @@ fn test_provenance_tracking_aot_cuda() {
 #[test]
 fn test_provenance_tracking_aot_debug_handle() {
     let expected_files = [
-        "-_-_-_-/before_pre_grad_graph_0.txt",
-        "-_-_-_-/after_post_grad_graph_6.txt",
+        "-_-_-_-/before_pre_grad_graph_0.html",
+        "-_-_-_-/after_post_grad_graph_6.html",
         "provenance_tracking_-_-_-_-.html",
         "-_-_-_-/inductor_provenance_tracking_node_mappings_10.json",
     ];
@@ -740,8 +744,8 @@ fn test_provenance_tracking_aot_debug_handle() {
 #[test]
 fn test_provenance_tracking_aot_log() {
     let expected_files = [
-        "-_-_-_-/before_pre_grad_graph_0.txt",
-        "-_-_-_-/after_post_grad_graph_6.txt",
+        "-_-_-_-/before_pre_grad_graph_0.html",
+        "-_-_-_-/after_post_grad_graph_6.html",
         "provenance_tracking_-_-_-_-.html",
         "-_-_-_-/inductor_provenance_tracking_node_mappings_11.json",
     ];
@@ -916,8 +920,8 @@ fn test_provenance_tracking_aot_log() {
 #[test]
 fn test_provenance_tracking_aot_log_old() {
     let expected_files = [
-        "-_-_-_-/inductor_pre_grad_graph_0.txt",
-        "-_-_-_-/inductor_post_grad_graph_8.txt",
+        "-_-_-_-/inductor_pre_grad_graph_0.html",
+        "-_-_-_-/inductor_post_grad_graph_8.html",
         "provenance_tracking_-_-_-_-.html",
         "-_-_-_-/inductor_provenance_tracking_node_mappings_11.json",
     ];
@@ -1064,8 +1068,8 @@ fn test_provenance_tracking_aot_log_old() {
 #[test]
 fn test_provenance_tracking_jit_cuda() {
     let expected_files = [
-        "-_0_0_0/before_pre_grad_graph_1.txt",
-        "-_0_0_0/after_post_grad_graph_8.txt",
+        "-_0_0_0/before_pre_grad_graph_1.html",
+        "-_0_0_0/after_post_grad_graph_8.html",
         "provenance_tracking_-_0_0_0.html",
         "-_0_0_0/inductor_provenance_tracking_node_mappings_14.json",
     ];
@@ -1258,8 +1262,8 @@ fn test_provenance_tracking_jit_cuda() {
 #[test]
 fn test_provenance_tracking_jit_log() {
     let expected_files = [
-        "-_0_0_0/before_pre_grad_graph_1.txt",
-        "-_0_0_0/after_post_grad_graph_8.txt",
+        "-_0_0_0/before_pre_grad_graph_1.html",
+        "-_0_0_0/after_post_grad_graph_8.html",
         "provenance_tracking_-_0_0_0.html",
         "-_0_0_0/inductor_provenance_tracking_node_mappings_13.json",
     ];
@@ -1434,8 +1438,8 @@ fn test_provenance_tracking_jit_log() {
 #[test]
 fn test_provenance_tracking_jit_debug_handle() {
     let expected_files = [
-        "-_0_0_0/before_pre_grad_graph_1.txt",
-        "-_0_0_0/after_post_grad_graph_11.txt",
+        "-_0_0_0/before_pre_grad_graph_1.html",
+        "-_0_0_0/after_post_grad_graph_11.html",
         "provenance_tracking_-_0_0_0.html",
         "-_0_0_0/inductor_provenance_tracking_node_mappings_14.json",
     ];
@@ -1624,8 +1628,8 @@ fn test_provenance_tracking_jit_debug_handle() {
 #[test]
 fn test_provenance_tracking_multiple_colons_in_kernel_name() {
     let expected_files = [
-        "-_-_-_-/before_pre_grad_graph_0.txt",
-        "-_-_-_-/after_post_grad_graph_8.txt",
+        "-_-_-_-/before_pre_grad_graph_0.html",
+        "-_-_-_-/after_post_grad_graph_8.html",
         "provenance_tracking_-_-_-_-.html",
         "-_-_-_-/inductor_provenance_tracking_node_mappings_12.json",
     ];
@@ -2327,6 +2331,126 @@ fn test_runtime_estimation_parsing() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_rank_comparison() -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = PathBuf::from("tests/inputs/multi_rank_runtime");
+    let temp_out = tempdir()?;
+    let out_dir = temp_out.path();
+
+    Command::cargo_bin("tlparse")?
+        .arg(&input_dir)
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(out_dir)
+        .arg("--no-browser")
+        .assert()
+        .success();
+
+    let landing = fs::read_to_string(out_dir.join("index.html"))?;
+    assert!(landing.contains(r#"<a href="rank_comparison.html">rank comparison</a>"#));
+
+    // Ranks 5 and 6 traced different source lines, and did not compile [0/2] and [0/3]
+    let overview = fs::read_to_string(out_dir.join("rank_comparison.html"))?;
+    assert!(overview.contains("<li>Rank 5: ranks 5, 6</li>"));
+    assert!(overview.contains(r#"<a href="rank_comparison/0_2.html">[0/2]</a>"#));
+    assert!(overview.contains("rank(s) 5"));
+
+    let page = fs::read_to_string(out_dir.join("rank_comparison/0_0.html"))?;
+    // Device indices are masked, so only rank 5's graph differs from rank 0's
+    assert!(page.contains("Rank 0 &rarr; rank 1: identical"));
+    assert!(page.contains("Rank 0 &rarr; rank 5: -6 +6 line(s)"));
+    assert!(page.contains("test2.py:105 in graph_two"));
+    assert!(page.contains("test2.py:88 in graph_two"));
+    assert!(!page.contains("cuda:1"));
+    assert!(page.contains(r#"<a href="../rank_5/-_0_0_0/dynamo_output_graph_0.html">rank 5</a>"#));
+    Ok(())
+}
+
+#[test]
+fn test_compile_stragglers() -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = PathBuf::from("tests/inputs/multi_rank_runtime");
+    let temp_out = tempdir()?;
+    let out_dir = temp_out.path();
+
+    Command::cargo_bin("tlparse")?
+        .arg(&input_dir)
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(out_dir)
+        .args(&["--no-browser", "--straggler-percentile", "20"])
+        .assert()
+        .success();
+
+    let landing = fs::read_to_string(out_dir.join("index.html"))?;
+    assert!(landing.contains(r#"<a href="compile_stragglers.html">compile time stragglers</a>"#));
+    // Ranks 0-4 are above percentile 20, but they are the median
+    assert!(!landing.contains("compiled for longer than percentile"));
+
+    let page = fs::read_to_string(out_dir.join("compile_stragglers.html"))?;
+    assert!(page.contains("<td> 6.661 </td>"));
+    assert!(page.contains("<td> 0% (0/8) </td>"));
+    assert!(page.contains("<th> Rank 6 </th>"));
+    assert!(page.contains(r#"<td data-value="3"> [0/3] </td>"#));
+
+    // Rank 0 compiled four frames, ranks 5 and 6 only two
+    let (subset_dir, subset_out) = setup_runtime_test_with_ranks(&[0, 5, 6])?;
+    Command::cargo_bin("tlparse")?
+        .arg(subset_dir.path())
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(subset_out.path())
+        .args(&["--no-browser", "--straggler-percentile", "20"])
+        .assert()
+        .success();
+    let landing = fs::read_to_string(subset_out.path().join("index.html"))?;
+    assert!(landing.contains("Rank(s) 0 compiled for longer than percentile 20"));
+
+    Command::cargo_bin("tlparse")?
+        .arg(&input_dir)
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(out_dir)
+        .args(&["--no-browser", "--straggler-percentile", "101"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_first_divergence() -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = PathBuf::from("tests/inputs/multi_rank_runtime");
+    let temp_out = tempdir()?;
+    let out_dir = temp_out.path();
+
+    Command::cargo_bin("tlparse")?
+        .arg(&input_dir)
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(out_dir)
+        .arg("--no-browser")
+        .assert()
+        .success();
+
+    let landing = fs::read_to_string(out_dir.join("index.html"))?;
+    assert!(landing.contains(r##"<a href="#first-divergence">first divergence</a>"##));
+    // Ranks 5 and 6 stop compiling after [0/1], the other ranks go on to [0/2]
+    assert!(landing.contains("The ranks first disagree at event <strong>10</strong>."));
+    assert!(landing.contains("<h4>Rank(s) 0, 1, 2, 3, 4: Compile [0/2] started</h4>"));
+    assert!(landing.contains("<h4>Rank(s) 5, 6: No more compile events</h4>"));
+    assert!(landing.contains(r#"<a href="rank_comparison/0_2.html">"#));
+    assert!(landing.contains("<li><code>0/0: ___check_obj_id(fn, N)</code></li>"));
+    assert!(landing.contains("test2.py:172 in &lt;module&gt;"));
+    assert!(landing.contains(r#"<li value="10"><strong>Compile [0/2] started</strong></li>"#));
+
+    // Ranks that logged the same compile events have no first divergence
+    let (input_dir, output_dir) = setup_runtime_test_with_ranks(&[0, 1])?;
+    Command::cargo_bin("tlparse")?
+        .arg(input_dir.path())
+        .args(&["--all-ranks-html", "--overwrite", "-o"])
+        .arg(output_dir.path())
+        .arg("--no-browser")
+        .assert()
+        .success();
+    let landing = fs::read_to_string(output_dir.path().join("index.html"))?;
+    assert!(!landing.contains("first-divergence"));
+    Ok(())
+}
+
 fn setup_runtime_test_with_ranks(
     ranks: &[u32],
 ) -> Result<(tempfile::TempDir, tempfile::TempDir), Box<dyn std::error::Error>> {
@@ -2911,7 +3035,7 @@ fn test_intermediate_file_generation() {
     let manifest = manifest.unwrap();
 
     // Check manifest fields
-    assert_eq!(manifest.version, "2.0");
+    assert_eq!(manifest.version, "2.1");
     assert!(
         manifest.total_envelopes > 0,
         "Should have parsed some envelopes"
@@ -3076,3 +3200,796 @@ fn test_intermediate_cli_integration() {
     assert!(output_path.join("chromium_events.json").exists());
     assert!(output_path.join("compile_artifacts.jsonl").exists());
 }
+
+#[test]
+fn test_validate_intermediate_cli() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("intermediate_output");
+
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.arg("tests/inputs/comp_metrics.log")
+        .arg("--intermediate-only")
+        .arg(&output_path);
+    cmd.assert().success();
+
+    // Schemas are published next to the intermediate files
+    assert!(output_path.join("schemas/manifest.schema.json").exists());
+    assert!(output_path
+        .join("schemas/compilation_metrics.schema.json")
+        .exists());
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_path.join("manifest.json")).unwrap())
+            .unwrap();
+    assert!(manifest["source_file_hash"]
+        .as_str()
+        .is_some_and(|h| h.starts_with("md5:")));
+
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.arg("validate").arg(&output_path);
+    cmd.assert().success().stdout(str::contains("is valid"));
+
+    // Corrupt an entry and check that validation fails and names the offending line
+    let metrics_path = output_path.join("compilation_metrics.jsonl");
+    let content = fs::read_to_string(&metrics_path).unwrap();
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let mut entry: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    entry["lineno"] = serde_json::json!("not a number");
+    lines[0] = entry.to_string();
+    fs::write(&metrics_path, lines.join("\n") + "\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.arg("validate").arg(&output_path);
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("compilation_metrics.jsonl:1: /lineno"));
+}
+
+#[test]
+fn test_render_rejects_incompatible_intermediate_version() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let output_path = temp_dir.path();
+
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig::default();
+    tlparse::generate_intermediate_files(&path, output_path, &config).unwrap();
+
+    let manifest_path = output_path.join("manifest.json");
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+    manifest["version"] = serde_json::json!("99.0");
+    fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+    let result = tlparse::render_from_intermediate(
+        output_path,
+        &temp_dir.path().join("out"),
+        &tlparse::ModuleConfig::default(),
+    );
+    let err = result.expect_err("rendering a future format version should fail");
+    assert!(err.to_string().contains("format version 99.0"));
+}
+
+#[test]
+fn test_query_log_and_intermediate_dir() {
+    let log = "tests/inputs/cache_hit_miss.log";
+
+    // Table output over a raw log
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.args(["query", log, "-c", "[1/*]", "-t", "artifact"]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("COMPILE ID"))
+        .stdout(str::contains("fx_graph_cache_miss"))
+        .stdout(str::contains("[1/0]"));
+
+    // Artifacts reach the intermediate files, so the same query works on them
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let intermediate = temp_dir.path().join("intermediate");
+    Command::cargo_bin("tlparse")
+        .unwrap()
+        .arg(log)
+        .arg("--intermediate-only")
+        .arg(&intermediate)
+        .assert()
+        .success();
+
+    let run = |input: &Path| {
+        let output = Command::cargo_bin("tlparse")
+            .unwrap()
+            .arg("query")
+            .arg(input)
+            .args(["-t", "artifact", "-w", "name ~= cache_", "-f", "jsonl"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let from_log = run(Path::new(log));
+    let from_dir = run(&intermediate);
+    assert!(!from_log.is_empty());
+    assert_eq!(from_log, from_dir);
+    for line in from_log.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(entry["type"], "artifact");
+        assert!(entry["metadata"]["name"]
+            .as_str()
+            .unwrap()
+            .contains("cache_"));
+    }
+}
+
+#[test]
+fn test_query_rank_of_multi_rank_log() {
+    // A log holding ranks 3 and 4 is read for the queried rank, not only the first one
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let log = temp_dir.path().join("both_ranks.log");
+    let rank_log = |rank: u32| {
+        fs::read_to_string(format!(
+            "tests/inputs/multi_rank_runtime/dedicated_log_torch_trace_rank_{rank}.log"
+        ))
+        .unwrap()
+    };
+    fs::write(&log, rank_log(3) + &rank_log(4)).unwrap();
+
+    let run = |input: &Path, rank: &str| {
+        let output = Command::cargo_bin("tlparse")
+            .unwrap()
+            .arg("query")
+            .arg(input)
+            .args(["--rank", rank, "-f", "jsonl"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let from_combined = run(&log, "4");
+    assert!(!from_combined.is_empty());
+    assert_eq!(
+        from_combined,
+        run(
+            Path::new("tests/inputs/multi_rank_runtime/dedicated_log_torch_trace_rank_4.log"),
+            "4"
+        )
+    );
+    for line in from_combined.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(entry["rank"], 4);
+    }
+
+    // Without --rank, the first rank is read and the others are pointed out
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.arg("query").arg(&log).args(["-t", "dynamo_start"]);
+    cmd.assert().success().stderr(str::contains(
+        "holds several ranks; showing rank 3, the first one logged",
+    ));
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.args([
+        "query",
+        "tests/inputs/multi_rank_runtime/dedicated_log_torch_trace_rank_4.log",
+        "-t",
+        "dynamo_start",
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(str::contains("several ranks").not());
+}
+
+#[test]
+fn test_query_payload_and_predicates() {
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.args([
+        "query",
+        "tests/inputs/collectives_parity/dedicated_log_torch_trace_rank_0.log",
+        "--artifact",
+        "recompile_reasons",
+        "--format",
+        "payload",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("___check_obj_id"));
+
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.args([
+        "query",
+        "tests/inputs/comp_failure.log",
+        "--where",
+        "fail_type == BackendCompilerFailed",
+        "-f",
+        "jsonl",
+    ]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("\"type\":\"compilation_metrics\""));
+
+    let mut cmd = Command::cargo_bin("tlparse").unwrap();
+    cmd.args(["query", "tests/inputs/comp_failure.log", "-c", "nonsense"]);
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("Invalid compile id"));
+}
+
+#[test]
+fn test_lazy_index() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        lazy_index: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, &config).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    // Build products are no longer inlined, only the compile id list
+    let index = &map[&PathBuf::from("index.html")];
+    assert!(index.contains("const COMPILE_DIRECTORY = ["));
+    assert!(index.contains(r#""id":"[1/0]","status":"status-ok""#));
+    assert!(!index.contains("dynamo_output_graph_3.html"));
+    // Stack trie subtrees start collapsed
+    assert!(index.contains("class='marker collapsed'"));
+    assert!(index.contains("<template><ul>"));
+
+    // One shard per compile id, next to that compile id's artifacts
+    let shard = &map[&PathBuf::from("-_1_0_0/index_shard.js")];
+    let json = shard
+        .strip_prefix(r#"tlparseShard("-_1_0_0", "#)
+        .and_then(|s| s.strip_suffix(");\n"))
+        .expect("shard should call tlparseShard");
+    let artifacts: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+    assert!(artifacts
+        .iter()
+        .any(|a| a["url"] == "-_1_0_0/dynamo_output_graph_3.html"));
+
+    // The eager index is unchanged for small logs
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let index = &map[&PathBuf::from("index.html")];
+    assert!(index.contains("dynamo_output_graph_3.html"));
+    assert!(!index.contains("COMPILE_DIRECTORY"));
+    assert!(!map.contains_key(&PathBuf::from("-_1_0_0/index_shard.js")));
+}
+
+#[test]
+fn test_search_index() {
+    let path = Path::new("tests/inputs/comp_failure.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let index = &map[&PathBuf::from("index.html")];
+    assert!(index.contains(r#"<input id="search-input""#));
+    // Only highlighted pages have line anchors to link hits to
+    assert!(index.contains("hit.line > 0 && url.endsWith('.html')"));
+
+    let search = &map[&PathBuf::from("search_index.js")];
+    let json = search
+        .strip_prefix("tlparseSearchIndex(")
+        .and_then(|s| s.strip_suffix(");\n"))
+        .expect("search index should call tlparseSearchIndex");
+    let search: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(search["cids"], serde_json::json!(["[0/0]"]));
+
+    let docs = search["docs"].as_array().unwrap();
+    let doc_url = |posting: &serde_json::Value| docs[posting.as_u64().unwrap() as usize][1].clone();
+    // Graph dumps are indexed line by line
+    let postings = search["terms"]["forward"].as_array().unwrap();
+    assert!(postings
+        .chunks(2)
+        .any(|p| doc_url(&p[0]) == "-_0_0_0/dynamo_output_graph_0.html" && p[1] != 0));
+    // Failure reasons point at the compilation metrics page
+    let postings = search["terms"]["backendcompilerfailed"].as_array().unwrap();
+    assert!(postings
+        .chunks(2)
+        .any(|p| doc_url(&p[0]) == "-_0_0_0/compilation_metrics_2.html"));
+}
+
+#[test]
+fn test_highlighted_graphs_and_code() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let graph = &map[&PathBuf::from("-_0_0_0/inductor_post_grad_graph_4.html")];
+    assert!(graph.contains(r#"<span class="line" id="L1">"#));
+    assert!(graph.contains("<small>(Python)</small>"));
+    // arg0_1 is a parameter of forward, defined on line 2
+    assert!(graph.contains(r##"<a href="#L2">arg0_1</a>"##));
+    let code = map
+        .iter()
+        .find(|(p, _)| {
+            p.to_string_lossy()
+                .starts_with("-_0_0_0/inductor_output_code_")
+        })
+        .map(|(_, c)| c)
+        .unwrap();
+    assert!(code.contains("<small>(Triton)</small>"));
+    assert!(!prefix_exists(
+        &map,
+        "-_0_0_0/inductor_post_grad_graph_4.txt"
+    ));
+
+    let config = tlparse::ParseConfig {
+        plain_text: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, &config).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    assert!(map
+        .get(&PathBuf::from("-_0_0_0/inductor_post_grad_graph_4.txt"))
+        .unwrap()
+        .starts_with("class <lambda>(torch.nn.Module):"));
+    assert!(prefix_exists(
+        &map,
+        "-_0_0_0/inductor_output_code_cyjwx6x6efpuwt4dvr4ev42v4ghac5zo2uggvscht2otwnesito6_5.txt"
+    ));
+}
+
+#[test]
+fn test_profile_flag() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir().unwrap();
+    let out_dir = temp_dir.path().join("out");
+
+    let mut cmd = Command::cargo_bin("tlparse")?;
+    cmd.arg("tests/inputs/simple.log")
+        .arg("--profile")
+        .arg("--overwrite")
+        .arg("-o")
+        .arg(&out_dir)
+        .arg("--no-browser");
+    cmd.assert()
+        .success()
+        .stderr(str::contains("envelope type"))
+        .stderr(str::is_match(r"chromium_event\s+50\s").unwrap())
+        .stderr(str::is_match(r"inductor_output_code\s+1\s").unwrap());
+    Ok(())
+}
+
+#[test]
+fn test_highlight_size_limit() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        highlight_size_limit: 5000,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, &config).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    // The output code is over the limit and falls back to plain text with line anchors
+    let (_, code) = map
+        .iter()
+        .find(|(p, _)| {
+            p.to_string_lossy()
+                .starts_with("-_0_0_0/inductor_output_code_")
+        })
+        .unwrap();
+    assert!(code.contains("Shown as plain text because it is larger than 5000 bytes."));
+    assert!(code.contains(r#"<a href="?highlight">Highlight it</a>"#));
+    assert!(code.contains(r#"id="L2""#));
+    assert!(!code.contains("<span style=\"color:#"));
+
+    // Smaller graphs are still highlighted
+    let graph = map
+        .get(&PathBuf::from("-_0_0_0/inductor_post_grad_graph_4.html"))
+        .unwrap();
+    assert!(!graph.contains("Highlight it"));
+    assert!(graph.contains(r##"<a href="#L2">arg0_1</a>"##));
+}
+
+#[test]
+fn test_graph_break_leaderboard() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, &config).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let leaderboard = map.get(&PathBuf::from("graph_breaks.html")).unwrap();
+    // Both restarts have the same reason, but happened in different user frames
+    assert!(leaderboard.contains(r#"<tr id="break-2">"#));
+    assert!(!leaderboard.contains(r#"<tr id="break-3">"#));
+    assert!(leaderboard.contains("test/dynamo/test_misc.py:9559"));
+    assert!(leaderboard.contains("<a href='-_0_0_1/compilation_metrics_2.html'>[0/0_1]</a>"));
+    // The first restart wasted more time, so it ranks first
+    let first = leaderboard.split(r#"<tr id="break-1">"#).nth(1).unwrap();
+    assert!(first.split("</tr>").next().unwrap().contains("0.007"));
+
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="graph_breaks.html">graph break leaderboard</a>"#));
+    assert!(index.contains("<a href='graph_breaks.html#break-1' class='break-marker'"));
+
+    // No breaks, no leaderboard
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    assert!(!output
+        .iter()
+        .any(|(p, _)| p == &PathBuf::from("graph_breaks.html")));
+}
+
+#[test]
+fn test_user_source() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let page = map.get(&PathBuf::from("user_source.html")).unwrap();
+    // The graph breaks' relative path is merged into the graphs' absolute one
+    assert!(page.contains("The 5 line(s) of source code in 1 file(s)"));
+    assert!(page.contains("<summary>data/users/jjwu/a/pytorch/test/dynamo/</summary>"));
+    assert!(page.contains(
+        r#"<a href="-_1_0_1/dynamo_output_graph_3.html">[1/0_1] dynamo_output_graph</a>: sin"#
+    ));
+    assert!(
+        page.contains("<div><a href='-_0_0_1/compilation_metrics_2.html'>[0/0_1]</a> Restart: ")
+    );
+
+    // Graphs link the frames of their `# File:` comments to the index
+    let graph = map
+        .get(&PathBuf::from("-_1_0_1/dynamo_output_graph_3.html"))
+        .unwrap();
+    let anchor = graph
+        .split(r#"<a href="../user_source.html#"#)
+        .nth(1)
+        .unwrap()
+        .split('"')
+        .next()
+        .unwrap();
+    assert!(page.contains(&format!(r#"<tr id="{}">"#, anchor)));
+
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="user_source.html">user source index</a>"#));
+
+    // Kernels are listed under every frame of their stacks
+    let path = Path::new("tests/inputs/inductor_provenance_extended_log.txt").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let page = map.get(&PathBuf::from("user_source.html")).unwrap();
+    let rotate_half = page
+        .split("<tr id=")
+        .find(|row| row.contains("<pre>return torch.cat((-x2, x1), dim=-1)</pre>"))
+        .unwrap();
+    let rotate_half = rotate_half.split("</tr>").next().unwrap();
+    assert!(rotate_half.contains(r#"-L50">"#));
+    assert!(rotate_half.contains("triton_poi_fused_add_cat_mul_neg_slice_unsqueeze_0"));
+}
+
+#[test]
+fn test_recompiles_page() {
+    // Not the multi_rank_logs ranks, which other tests rewrite while they run
+    let path = Path::new("tests/inputs/collectives_parity/dedicated_log_torch_trace_rank_0.log")
+        .to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let page = map.get(&PathBuf::from("recompiles_-_0.html")).unwrap();
+    assert!(page.contains("This frame was compiled 4 times"));
+    assert!(page.contains("<a href='-_0_1_0/compilation_metrics_38.html'>[0/1]</a>"));
+    assert!(page.contains("<pre>0/0: ___check_obj_id(fn, 140439264606080)"));
+    // Guards that only differ in numbers are paired up
+    assert!(page.contains(
+        r#"<pre class="removed">- LENGTH_CHECK: len(L[&#39;args&#39;]) == 1</pre><pre class="added">+ LENGTH_CHECK: len(L[&#39;args&#39;]) == 3</pre>"#
+    ));
+    assert!(page.contains("L[&#39;args&#39;][1] input: (absent) → (present)"));
+    assert!(page.contains("<td> 1 / 8 </td>"));
+
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains("<a href='recompiles_-_0.html' class='recompile-marker'"));
+    assert!(index.contains(r#"<a href="recompiles_-_0.html">recompiles_-_0.html</a>"#));
+
+    // No recompiles, no page
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    assert!(!output
+        .iter()
+        .any(|(p, _)| p.to_string_lossy().starts_with("recompiles_")));
+}
+
+#[test]
+fn test_guard_analytics() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let guards = map
+        .get(&PathBuf::from("-_0_0_1/dynamo_guards_1.html"))
+        .unwrap();
+    assert!(guards.contains(r#"<h3 id="tensor-match">Tensor match (1)</h3>"#));
+    assert!(guards.contains(r#"<h3 id="global-state">Global state (1)</h3>"#));
+
+    let analytics = map.get(&PathBuf::from("guards.html")).unwrap();
+    assert!(analytics.contains("9 guard(s) over 3 compile(s)"));
+    assert!(analytics.contains("3 guard(s) are installed by more than one compile"));
+    assert!(analytics.contains("<td> [0/0_1] [1/0_1] [2/0] </td>"));
+    assert!(analytics.contains("<a href='-_2_0_0/compilation_metrics_8.html'>[2/0]</a>"));
+
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="guards.html">guard analytics</a>"#));
+}
+
+#[test]
+fn test_cpp_guard_tree() {
+    let path = Path::new("tests/inputs/links.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let (_, tree) = output
+        .iter()
+        .find(|(p, _)| p.to_string_lossy().contains("dynamo_cpp_guards_str_6.html"))
+        .unwrap();
+    assert!(tree.contains("13 guard(s)."));
+    assert!(tree.contains(r#"<input id="guard-search""#));
+    assert!(tree.contains("<code class='guard-text'>RootGuardManager</code> <span class='guard-count'>(13 guard(s))</span>"));
+    assert!(tree.contains(
+        r#"<li data-source="L['x']"><code class='guard-text' title='Tensor match'>TENSOR_MATCH: "#
+    ));
+
+    // Plain text mode keeps the payload as is
+    let config = tlparse::ParseConfig {
+        plain_text: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, &config).unwrap();
+    let (_, tree) = output
+        .iter()
+        .find(|(p, _)| p.to_string_lossy().contains("dynamo_cpp_guards_str_6.txt"))
+        .unwrap();
+    assert!(tree.starts_with("\nTREE_GUARD_MANAGER:\n+- RootGuardManager"));
+
+    // Dict guard managers nest their guards under key/value managers
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let tree = map
+        .get(&PathBuf::from("-_0_0_0/dynamo_cpp_guards_str_1.html"))
+        .unwrap();
+    assert!(tree.contains(
+        "KeyValueManager pair at index=1</code> <span class='guard-count'>(2 guard(s))</span>"
+    ));
+    assert!(tree.contains(
+        "ID_MATCH: ___check_obj_id(G['__import_torch_dot_utils_dot__pytree'].SUPPORTED_NODES[list("
+    ));
+}
+
+#[test]
+fn test_compile_time() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let compile_time = map.get(&PathBuf::from("compile_time.html")).unwrap();
+    // Newer logs only have the cumulative *_us metrics
+    assert!(compile_time.contains(
+        r#"<td> <code>entire_frame_compile_time_s</code> </td> <td class="number"> 8.297 </td> <td class="number"> 6 </td>"#
+    ));
+    // Slowest first, one row per compilation_metrics even when runs reuse a compile id
+    let slowest = compile_time
+        .find("<a href='-_1_0_0/compilation_metrics_12.html'>[1/0]</a>")
+        .unwrap();
+    assert!(slowest < compile_time.find("compilation_metrics_36.html").unwrap());
+    assert!(compile_time.contains("301.9 ms of 8319.3 ms"));
+    assert!(
+        compile_time.contains(r#"<tr> <td> fx_graph_cache_miss </td> <td class="number"> 2 </td>"#)
+    );
+    assert!(
+        compile_time.contains("<h3><a href='-_1_0_0/compilation_metrics_36.html'>[1/0]</a></h3>")
+    );
+
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="compile_time.html">compile time profile</a>"#));
+}
+
+#[test]
+fn test_timeline() {
+    let path = Path::new("tests/inputs/collectives_parity/dedicated_log_torch_trace_rank_0.log")
+        .to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    // The year and timezone come from the chromium events, and raw.jsonl is in UTC
+    let timeline = map.get(&PathBuf::from("timeline.html")).unwrap();
+    assert!(timeline.contains("The run logged from 2025-08-04 12:34:12.816 to 2025-08-04 12:34:17.635 (4.819s), in UTC-07:00; the year comes from the timestamp of a chromium event."));
+    assert!(timeline.contains("the run spent 0.785s between compilations, over 1 gap(s)"));
+    let raw = map.get(&PathBuf::from("raw.jsonl")).unwrap();
+    assert!(raw.contains(r#""timestamp":"2025-08-04T19:34:12.819000Z""#));
+
+    assert_eq!(
+        timeline
+            .matches(r#"<span class="label">[0/1]</span>"#)
+            .count(),
+        1
+    );
+    assert!(timeline.contains(r#"<a href="index.html#[0/1]" class="bar status-ok recompile""#));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="timeline.html">timeline</a>"#));
+}
+
+#[test]
+fn test_dynamic_shapes() {
+    // Outside of export mode, the symbolic shape envelopes of export.log go to the dynamic
+    // shapes report
+    let path = Path::new("tests/inputs/export.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let compile_page = map
+        .get(&PathBuf::from("-_-_-_-/dynamic_shapes.html"))
+        .unwrap();
+    assert!(compile_page.contains("<td> unbacked </td>"));
+    assert!(compile_page.contains("<td> [-int_oo, int_oo] </td>"));
+    assert!(compile_page.contains("<td> <code>L[&#39;args&#39;][0][1].size()[0]</code> </td>"));
+    assert_eq!(compile_page.matches("<td> guard_added </td>").count(), 7);
+
+    let run_page = map.get(&PathBuf::from("dynamic_shapes.html")).unwrap();
+    assert!(run_page.contains(r#"<a href="-_-_-_-/dynamic_shapes.html">[-/-]</a>"#));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="dynamic_shapes.html">dynamic shapes report</a>"#));
+}
+
+#[test]
+fn test_symbolic_guard_information_outside_export() {
+    let path = Path::new("tests/inputs/export.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    // Every guard_added envelope gets a page, not only the evaluated ones
+    let pages: Vec<&PathBuf> = map
+        .keys()
+        .filter(|p| {
+            p.to_string_lossy()
+                .starts_with("-_-_-_-/symbolic_guard_information")
+        })
+        .collect();
+    assert_eq!(pages.len(), 8);
+    // The expression tree is built from the expression_created envelopes
+    let guard = map
+        .get(&PathBuf::from("-_-_-_-/symbolic_guard_information_2.html"))
+        .unwrap();
+    assert!(guard.contains("<code>Eq((((-u0)//3)) + 5, 0)</code>"));
+    assert!(guard.contains(r#"<span style="font-weight: bold;">Method:</span> int_floordiv"#));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains("-_-_-_-/symbolic_guard_information_2.html"));
+}
+
+#[test]
+fn test_tensor_metadata() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let page = map
+        .get(&PathBuf::from("-_0_0_0/tensor_metadata.html"))
+        .unwrap();
+    assert!(page.contains("<h1>Input tensors of [0/0]</h1>"));
+    // describe_source gives the tensor's name and describe_storage its storage size
+    assert!(page.contains("<th> <code>L[&#39;args&#39;][4][0]</code> </th>"));
+    assert!(page.contains("<td> [1, 1, 16] </td>"));
+    assert!(page.contains("<td> 64 bytes </td>"));
+    assert!(map.contains_key(&PathBuf::from("-_1_0_0/tensor_metadata.html")));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains("-_0_0_0/tensor_metadata.html"));
+}
+
+#[test]
+fn test_graph_stats() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let run = map.get(&PathBuf::from("graph_stats.html")).unwrap();
+    // 6 dynamo graphs of 17 or 16 nodes
+    assert!(run.contains(
+        "<td> <a href=\"graph_stats.html#dynamo_output_graph\">dynamo_output_graph</a> </td>\n        <td> 6 </td>\n        <td> 99 </td>"
+    ));
+    assert!(run.contains(
+        "<td> <code>higher_order.flex_attention</code> </td> <td> 3 </td> <td> 50.0% </td> <td> 3 </td>"
+    ));
+    assert!(run.contains(r#"<a href="-_1_0_0/graph_stats.html">[1/0]</a>"#));
+
+    let compile = map.get(&PathBuf::from("-_0_0_0/graph_stats.html")).unwrap();
+    assert!(compile.contains("<td> operator.getitem (2), higher_order.flex_attention (1) </td>"));
+    // The sizes recorded with dynamo_output_graph
+    assert!(compile.contains("<li><code>l_args_4_1_: [1, 1, 16, 16]</code></li>"));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="graph_stats.html">graph sizes</a>"#));
+}
+
+#[test]
+fn test_kernels() {
+    let path = Path::new("tests/inputs/collectives_parity/dedicated_log_torch_trace_rank_0.log")
+        .to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let table = map.get(&PathBuf::from("-_0_0_0/kernels.html")).unwrap();
+    assert!(table.contains("<li>pointwise: 2</li>"));
+    // Joined with triton_kernel_info
+    assert!(table.contains(
+        "<td> <a href=\"-_0_0_0/kernel_triton_poi_fused_all_reduce_silu_0.html\">triton_poi_fused_all_reduce_silu_0</a> </td>\n        <td> 288.6 </td>\n        <td> miss </td>\n        <td> 2 </td>"
+    ));
+    assert!(
+        table.contains(r#"<a href="-_0_0_0/kernel_extern_kernels.mm.html">extern_kernels.mm</a>"#)
+    );
+
+    let kernel = map
+        .get(&PathBuf::from(
+            "-_0_0_0/kernel_triton_poi_fused_relu_1.html",
+        ))
+        .unwrap();
+    assert!(kernel.contains("#L90\">line 90</a> of the output code"));
+    assert!(kernel.contains("def triton_poi_fused_relu_1("));
+
+    let directory = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(directory.contains(r#"<a href="-_0_0_0/kernels.html">"#));
+
+    // Joined with inductor_triton_kernel_to_post_grad_nodes
+    let path = Path::new("tests/inputs/inductor_provenance_jit_log.txt").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let kernel = map
+        .get(&PathBuf::from(
+            "-_0_0_0/kernel_cpp_fused_mul_relu_sigmoid_0.html",
+        ))
+        .unwrap();
+    assert!(kernel.contains("<p>Post-grad nodes: <code>sigmoid, relu, mul</code></p>"));
+}
+
+#[test]
+fn test_autotuning() {
+    let path = Path::new("tests/inputs/collectives_parity/dedicated_log_torch_trace_rank_0.log")
+        .to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+
+    let report = map.get(&PathBuf::from("autotuning.html")).unwrap();
+    assert!(report.contains("<li>miss: 8</li>"));
+    assert!(report.contains("<li>only 1 config: 3</li>"));
+    // Benchmarking agrees with what the runtime compilation metrics report
+    assert!(report.contains("<tr> <th> Benchmarking configs (ms) </th> <td> 231.9 </td> </tr>"));
+    assert!(report.contains(
+        "<td> triton_poi_fused_all_reduce_silu_0 </td>\n        <td> miss </td>\n        <td> 2 </td>\n        <td>  </td>\n        <td> 288.6 </td>\n        <td> 47.7 </td>"
+    ));
+    assert!(report.contains("<td> XBLOCK=1, num_warps=8, num_stages=1 </td>"));
+    assert!(report.contains(r#"<h3><a href="-_0_0_0/kernels.html">[0/0]</a></h3>"#));
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="autotuning.html">autotuning report</a>"#));
+
+    // Benchmarks logged without a compile id are attributed to the compile that ran them
+    let path = Path::new("tests/inputs/autotune_with_compile_id.log").to_path_buf();
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let report = map.get(&PathBuf::from("autotuning.html")).unwrap();
+    assert!(report.contains("<li>Other benchmarking: 31.4 ms</li>"));
+    assert!(!report.contains("(unknown)"));
+}
+
+#[test]
+fn test_provenance_tracking_without_flag() {
+    let path = Path::new("tests/inputs/inductor_provenance_jit_log.txt").to_path_buf();
+    let page = PathBuf::from("provenance_tracking_-_0_0_0.html");
+    let output = tlparse::parse_path(&path, &tlparse::ParseConfig::default()).unwrap();
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    let config = tlparse::ParseConfig {
+        inductor_provenance: true,
+        ..Default::default()
+    };
+    let with_flag: HashMap<PathBuf, String> = tlparse::parse_path(&path, &config)
+        .unwrap()
+        .into_iter()
+        .collect();
+
+    // Turned on by the node mappings in the log, with graphs rendered as HTML, and the same
+    // line mappings as when asked for
+    assert!(map.contains_key(&PathBuf::from(
+        "-_0_0_0/inductor_output_code_celvaalwlz2ulme27hh3jttevphbo7cueohqpbbvxp4oigd66bqb_10.html"
+    )));
+    let line_mappings = |html: &str| {
+        let start = html.find(r#"<script id="lineMappings""#).unwrap();
+        let end = html[start..].find("</script>").unwrap() + start;
+        html[start..end].to_string()
+    };
+    assert!(line_mappings(&map[&page]).contains("\"postToPyCode\": {\n"));
+    assert_eq!(line_mappings(&map[&page]), line_mappings(&with_flag[&page]));
+
+    // Linked from the compile id's directory entry
+    let index = map.get(&PathBuf::from("index.html")).unwrap();
+    assert!(index.contains(r#"<a href="provenance_tracking_-_0_0_0.html">"#));
+}